@@ -13,5 +13,6 @@ fn main() {
         // the old version of `getrandom` we pin here is available on all targets, but
         // using it will panic if no suitable source of entropy is found
         with_getrandom: { any(web, not(target_arch = "wasm32")) },
+        with_encrypted_signer: { all(feature = "encrypted-signer", with_getrandom) },
     };
 }
@@ -41,6 +41,23 @@ impl CryptoHash {
         CryptoHash(hasher.0.finalize())
     }
 
+    /// Computes the hash of a value from the name of its type and its BCS serialization,
+    /// without deserializing it. The result is the same as [`CryptoHash::new`].
+    pub fn from_bcs_bytes(type_name: &str, bytes: &[u8]) -> Self {
+        use io::Write as _;
+
+        let mut hasher = Keccak256Ext(Keccak256::new());
+        write!(hasher, "{}::", type_name).expect("Hasher should not fail");
+        hasher.write_all(bytes).expect("Hasher should not fail");
+        CryptoHash(hasher.0.finalize())
+    }
+
+    /// Computes the plain Keccak256 hash of some bytes, without any type name prefix. This
+    /// makes it comparable with hashes computed outside of Linera.
+    pub fn keccak256(bytes: &[u8]) -> Self {
+        CryptoHash(alloy_primitives::keccak256(bytes))
+    }
+
     /// Reads the bytes of the hash value.
     pub fn as_bytes(&self) -> &B256 {
         &self.0
@@ -6,6 +6,7 @@
 
 mod ed25519;
 mod hash;
+mod multisig;
 #[allow(dead_code)]
 mod secp256k1;
 pub mod signer;
@@ -16,6 +17,7 @@ use custom_debug_derive::Debug;
 pub use ed25519::{Ed25519PublicKey, Ed25519SecretKey, Ed25519Signature};
 pub use hash::*;
 use linera_witty::{WitLoad, WitStore, WitType};
+pub use multisig::{MultisigPolicy, MAX_MULTISIG_MEMBERS};
 pub use secp256k1::{
     evm::{EvmPublicKey, EvmSecretKey, EvmSignature},
     Secp256k1PublicKey, Secp256k1SecretKey, Secp256k1Signature,
@@ -85,7 +87,7 @@ pub enum AccountSecretKey {
 }
 
 /// The signature of a chain owner.
-#[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub enum AccountSignature {
     /// Ed25519 signature.
     Ed25519 {
@@ -109,6 +111,14 @@ pub enum AccountSignature {
         #[debug(with = "hex_debug")]
         address: [u8; 20],
     },
+    /// Signatures by members of a [`MultisigPolicy`].
+    Multisig {
+        /// The policy whose owner signed the value.
+        policy: MultisigPolicy,
+        /// Signatures of the value by exactly `policy.threshold()` distinct members, sorted
+        /// by signer.
+        signatures: Vec<AccountSignature>,
+    },
 }
 
 impl AccountSecretKey {
@@ -259,9 +269,17 @@ impl AccountSignature {
                 signature.check_with_recover(value, *sender_address)?;
                 Ok(())
             }
+            AccountSignature::Multisig { policy, signatures } => policy.verify(signatures, value),
         }
     }
 
+    /// Combines the signatures of members of `policy` into a signature by the policy's
+    /// owner. The signatures are sorted by signer, as required for verification.
+    pub fn multisig(policy: MultisigPolicy, mut signatures: Vec<AccountSignature>) -> Self {
+        signatures.sort_by_key(AccountSignature::owner);
+        AccountSignature::Multisig { policy, signatures }
+    }
+
     /// Returns byte representation of the signatures.
     pub fn to_bytes(&self) -> Vec<u8> {
         bcs::to_bytes(&self).expect("serialization to bytes should not fail")
@@ -278,6 +296,7 @@ impl AccountSignature {
             AccountSignature::Ed25519 { public_key, .. } => AccountOwner::from(*public_key),
             AccountSignature::Secp256k1 { public_key, .. } => AccountOwner::from(*public_key),
             AccountSignature::EvmSecp256k1 { address, .. } => AccountOwner::Address20(*address),
+            AccountSignature::Multisig { policy, .. } => policy.owner(),
         }
     }
 }
@@ -346,6 +365,12 @@ pub enum CryptoError {
     PublicKeyParseError(bcs::Error),
     #[error("could not parse signature: {0}")]
     SignatureParseError(bcs::Error),
+    #[error("a multi-signature threshold of {threshold} is invalid for {members} members")]
+    InvalidMultisigPolicy { threshold: u32, members: usize },
+    #[error("the members of a multi-signature policy must be sorted and unique")]
+    UnsortedMultisigMembers,
+    #[error("a multi-signature policy has at most {max} members, not {members}")]
+    TooManyMultisigMembers { members: usize, max: usize },
 }
 
 #[cfg(with_getrandom)]
@@ -572,4 +597,20 @@ mod tests {
             Secp256k1KeyPair::generate().secret_key,
         ));
     }
+
+    #[test]
+    fn hash_from_bcs_bytes() {
+        let test_string = TestString::new("test");
+        let bytes = bcs::to_bytes(&test_string).unwrap();
+        assert_eq!(
+            CryptoHash::from_bcs_bytes("TestString", &bytes),
+            CryptoHash::new(&test_string)
+        );
+    }
+
+    #[test]
+    fn keccak256_hash() {
+        let expected = "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470";
+        assert_eq!(CryptoHash::keccak256(&[]), expected.parse().unwrap());
+    }
 }
@@ -0,0 +1,212 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Account owners requiring the approval of several members.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::{AccountSignature, BcsHashable, BcsSignable, CryptoError, CryptoHash, HasTypeName};
+use crate::identifiers::AccountOwner;
+
+/// The maximum number of members of a [`MultisigPolicy`], which also bounds the number of
+/// signatures in an [`AccountSignature::Multisig`].
+pub const MAX_MULTISIG_MEMBERS: usize = 32;
+
+/// An M-of-N policy: an account owned by a policy can only be used with the signatures of
+/// at least `threshold` distinct `members`.
+///
+/// The [`AccountOwner`] of the account is derived from the hash of the policy, so the
+/// policy itself is only revealed when it is first used, as part of an
+/// [`AccountSignature::Multisig`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct MultisigPolicy {
+    /// The minimum number of members whose signatures are required.
+    threshold: u32,
+    /// The members, sorted and without duplicates.
+    members: Vec<AccountOwner>,
+}
+
+impl BcsHashable<'_> for MultisigPolicy {}
+
+impl MultisigPolicy {
+    /// Creates a new policy requiring `threshold` signatures of the given `members`.
+    pub fn new(
+        threshold: u32,
+        members: impl IntoIterator<Item = AccountOwner>,
+    ) -> Result<Self, CryptoError> {
+        let members = members
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let policy = MultisigPolicy { threshold, members };
+        policy.check()?;
+        Ok(policy)
+    }
+
+    /// Returns the minimum number of member signatures.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Returns the members of the policy.
+    pub fn members(&self) -> &[AccountOwner] {
+        &self.members
+    }
+
+    /// Returns the [`AccountOwner`] controlled by this policy.
+    pub fn owner(&self) -> AccountOwner {
+        AccountOwner::Address32(CryptoHash::new(self))
+    }
+
+    /// Checks that the policy is well-formed. Deserialized policies are not checked
+    /// otherwise.
+    fn check(&self) -> Result<(), CryptoError> {
+        if self.members.len() > MAX_MULTISIG_MEMBERS {
+            return Err(CryptoError::TooManyMultisigMembers {
+                members: self.members.len(),
+                max: MAX_MULTISIG_MEMBERS,
+            });
+        }
+        if self.threshold == 0 || self.threshold as usize > self.members.len() {
+            return Err(CryptoError::InvalidMultisigPolicy {
+                threshold: self.threshold,
+                members: self.members.len(),
+            });
+        }
+        if !self.members.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Err(CryptoError::UnsortedMultisigMembers);
+        }
+        Ok(())
+    }
+
+    /// Verifies that `signatures` are exactly `threshold` valid signatures of `value` by
+    /// distinct members, sorted by signer. Any other set of signatures is rejected, so
+    /// that a signed proposal cannot be altered without invalidating it.
+    pub(crate) fn verify<'de, T>(
+        &self,
+        signatures: &[AccountSignature],
+        value: &T,
+    ) -> Result<(), CryptoError>
+    where
+        T: BcsSignable<'de> + std::fmt::Debug,
+    {
+        self.check()?;
+        let invalid = |error: String| CryptoError::InvalidSignature {
+            error,
+            type_name: T::type_name().to_string(),
+        };
+        if signatures.len() != self.threshold as usize {
+            return Err(invalid(format!(
+                "{} signatures instead of the {} required",
+                signatures.len(),
+                self.threshold
+            )));
+        }
+        let mut previous = None;
+        for signature in signatures {
+            if matches!(signature, AccountSignature::Multisig { .. }) {
+                return Err(invalid("nested multi-signatures are not supported".into()));
+            }
+            let signer = signature.owner();
+            if self.members.binary_search(&signer).is_err() {
+                return Err(invalid(format!("{signer} is not a member of the policy")));
+            }
+            if previous.is_some_and(|previous| previous >= signer) {
+                return Err(invalid(format!(
+                    "signature by {signer} is a duplicate or out of order"
+                )));
+            }
+            previous = Some(signer);
+            signature.verify(value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{AccountSecretKey, Ed25519SecretKey, TestString};
+
+    fn member_keys(count: usize) -> Vec<AccountSecretKey> {
+        (0..count)
+            .map(|_| AccountSecretKey::Ed25519(Ed25519SecretKey::generate()))
+            .collect()
+    }
+
+    #[test]
+    fn test_multisig_threshold() {
+        let keys = member_keys(3);
+        let policy =
+            MultisigPolicy::new(2, keys.iter().map(|key| AccountOwner::from(key.public())))
+                .unwrap();
+        let value = TestString::new("transfer");
+        let sign = |keys: &[&AccountSecretKey]| {
+            AccountSignature::multisig(
+                policy.clone(),
+                keys.iter().map(|key| key.sign(&value)).collect(),
+            )
+        };
+
+        let signature = sign(&[&keys[0], &keys[2]]);
+        assert_eq!(signature.owner(), policy.owner());
+        signature.verify(&value).unwrap();
+        let bytes = signature.to_bytes();
+        assert_eq!(AccountSignature::from_slice(&bytes).unwrap(), signature);
+
+        // Not enough signatures.
+        assert!(sign(&[&keys[1]]).verify(&value).is_err());
+        // The same member twice.
+        assert!(sign(&[&keys[1], &keys[1]]).verify(&value).is_err());
+        // A signature by a non-member.
+        let outsider = member_keys(1).pop().unwrap();
+        assert!(sign(&[&keys[0], &outsider]).verify(&value).is_err());
+        // Signatures of a different value.
+        assert!(signature.verify(&TestString::new("other")).is_err());
+        // Every signature is verified, even after the threshold is met.
+        assert!(sign(&[&keys[0], &keys[2], &outsider])
+            .verify(&value)
+            .is_err());
+        // There cannot be more signatures than the threshold, even valid ones.
+        assert!(sign(&[&keys[0], &keys[1], &keys[2]])
+            .verify(&value)
+            .is_err());
+        // The signatures are sorted by signer, so their order cannot be changed either.
+        let AccountSignature::Multisig { signatures, .. } = &signature else {
+            unreachable!()
+        };
+        let reordered = AccountSignature::Multisig {
+            policy: policy.clone(),
+            signatures: signatures.iter().rev().cloned().collect(),
+        };
+        assert!(reordered.verify(&value).is_err());
+    }
+
+    #[test]
+    fn test_multisig_policy_checks() {
+        let owners = member_keys(2)
+            .iter()
+            .map(|key| AccountOwner::from(key.public()))
+            .collect::<Vec<_>>();
+        assert!(MultisigPolicy::new(0, owners.clone()).is_err());
+        assert!(MultisigPolicy::new(3, owners.clone()).is_err());
+        // Duplicates are removed and do not count towards the threshold.
+        assert!(MultisigPolicy::new(2, [owners[0], owners[0]]).is_err());
+        // The order of the members does not change the owner.
+        let policy = MultisigPolicy::new(2, owners.clone()).unwrap();
+        let reversed = MultisigPolicy::new(2, owners.into_iter().rev()).unwrap();
+        assert_eq!(policy.owner(), reversed.owner());
+
+        let owners = member_keys(MAX_MULTISIG_MEMBERS + 1)
+            .iter()
+            .map(|key| AccountOwner::from(key.public()))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            MultisigPolicy::new(1, owners),
+            Err(CryptoError::TooManyMultisigMembers { .. })
+        ));
+    }
+}
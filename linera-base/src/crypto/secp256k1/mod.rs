@@ -63,6 +63,12 @@ pub struct Secp256k1KeyPair {
 #[derive(Eq, PartialEq, Copy, Clone)]
 pub struct Secp256k1Signature(pub Signature);
 
+impl Hash for Secp256k1Signature {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
 impl Secp256k1PublicKey {
     /// A fake public key used for testing.
     #[cfg(all(with_testing, not(target_arch = "wasm32")))]
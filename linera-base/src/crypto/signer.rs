@@ -7,6 +7,8 @@ An interface for cryptographic signers that can be used by the Linera client to
 
 use std::error::Error as StdError;
 
+#[cfg(with_encrypted_signer)]
+pub use encrypted::EncryptedSigner;
 pub use in_mem::InMemorySigner;
 
 use super::CryptoHash;
@@ -54,6 +56,9 @@ pub trait Signer {
     async fn contains_key(&self, owner: &AccountOwner) -> Result<bool, Self::Error>;
 }
 
+#[cfg(with_encrypted_signer)]
+pub mod encrypted;
+
 /// In-memory implementation of the [`Signer`] trait.
 mod in_mem {
     use std::{
@@ -124,6 +129,11 @@ mod in_mem {
             let inner = self.0.read().unwrap();
             inner.keys()
         }
+
+        /// Removes the key of the given `owner`. Returns whether there was one.
+        pub fn remove_key(&mut self, owner: &AccountOwner) -> bool {
+            self.0.write().unwrap().keys.remove(owner).is_some()
+        }
     }
 
     #[derive(Debug, Deserialize, Serialize)]
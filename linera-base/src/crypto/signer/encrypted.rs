@@ -0,0 +1,506 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A signer whose secret keys are encrypted at rest with a passphrase.
+//!
+//! The encryption key is derived from the passphrase with Argon2id and every secret key is
+//! sealed with XChaCha20-Poly1305, using the owner as associated data. The passphrase is
+//! only used (and the key derivation only performed) the first time a secret key is
+//! actually needed, so that commands that never sign do not pay for it. The derivation
+//! is deliberately slow, so it never runs while holding the signer's lock and, when
+//! signing, it runs on a blocking thread rather than on the async executor.
+//!
+//! The passphrase, the derived key and the serialized secret keys are zeroed when
+//! dropped; the decrypted secret keys themselves are zeroed by their own types.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::RngCore as _;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{
+        AccountPublicKey, AccountSecretKey, AccountSignature, CryptoHash, InMemorySigner, Signer,
+    },
+    identifiers::AccountOwner,
+};
+
+/// The length of the salt used for the key derivation, in bytes.
+const SALT_LENGTH: usize = 16;
+/// The length of the XChaCha20 nonces, in bytes.
+const NONCE_LENGTH: usize = 24;
+/// The length of the derived encryption key, in bytes.
+const KEY_LENGTH: usize = 32;
+/// The plaintext of the entry used to check that a passphrase is correct.
+const CHECK_PLAINTEXT: &[u8] = b"linera-encrypted-signer";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no key found for the given owner")]
+    NoSuchOwner,
+    #[error("the keystore is encrypted and no passphrase was provided")]
+    Locked,
+    #[error("invalid passphrase or corrupted keystore")]
+    InvalidPassphrase,
+    #[error("key derivation failed: {0}")]
+    KeyDerivation(String),
+    #[error("malformed keystore entry: {0}")]
+    Malformed(String),
+}
+
+/// Parameters of the Argon2id key derivation.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct KdfParams {
+    /// The hex-encoded salt.
+    salt: String,
+    /// Memory size in KiB.
+    m_cost: u32,
+    /// Number of iterations.
+    t_cost: u32,
+    /// Degree of parallelism.
+    p_cost: u32,
+}
+
+impl KdfParams {
+    fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        KdfParams {
+            salt: hex::encode(salt),
+            m_cost,
+            t_cost,
+            p_cost,
+        }
+    }
+
+    fn derive_cipher(&self, passphrase: &str) -> Result<XChaCha20Poly1305, Error> {
+        let salt = hex::decode(&self.salt).map_err(|error| Error::Malformed(error.to_string()))?;
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(KEY_LENGTH))
+            .map_err(|error| Error::KeyDerivation(error.to_string()))?;
+        let argon2 =
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+        argon2
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut *key)
+            .map_err(|error| Error::KeyDerivation(error.to_string()))?;
+        Ok(XChaCha20Poly1305::new(Key::from_slice(&*key)))
+    }
+}
+
+/// A hex-encoded nonce and ciphertext.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Sealed {
+    fn seal(cipher: &XChaCha20Poly1305, plaintext: &[u8], aad: &[u8]) -> Self {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("encryption into a vector should not fail");
+        Sealed {
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        }
+    }
+
+    fn open(&self, cipher: &XChaCha20Poly1305, aad: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let nonce =
+            hex::decode(&self.nonce).map_err(|error| Error::Malformed(error.to_string()))?;
+        if nonce.len() != NONCE_LENGTH {
+            return Err(Error::Malformed(format!(
+                "expected a nonce of {NONCE_LENGTH} bytes, got {}",
+                nonce.len()
+            )));
+        }
+        let ciphertext =
+            hex::decode(&self.ciphertext).map_err(|error| Error::Malformed(error.to_string()))?;
+        cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| Error::InvalidPassphrase)
+    }
+}
+
+/// Returns the associated data binding a sealed secret key to its owner.
+fn owner_aad(owner: &AccountOwner) -> Vec<u8> {
+    bcs::to_bytes(owner).expect("serialization of an owner should not fail")
+}
+
+/// The on-disk representation of an [`EncryptedSigner`].
+#[derive(Debug, Deserialize, Serialize)]
+struct Persisted {
+    kdf: KdfParams,
+    check: Sealed,
+    keys: Vec<(AccountOwner, Sealed)>,
+}
+
+/// A signer that keeps its secret keys encrypted with a passphrase-derived key.
+///
+/// Only the owners are stored in clear, so [`Signer::contains_key`] never needs the
+/// passphrase. The first operation that requires a secret key derives the encryption
+/// key from the passphrase set with [`EncryptedSigner::set_passphrase`].
+#[derive(Clone)]
+pub struct EncryptedSigner(Arc<RwLock<EncryptedSignerInner>>);
+
+struct EncryptedSignerInner {
+    kdf: KdfParams,
+    check: Sealed,
+    keys: BTreeMap<AccountOwner, Sealed>,
+    passphrase: Option<Zeroizing<String>>,
+    cipher: Option<XChaCha20Poly1305>,
+    /// Secret keys that have already been decrypted.
+    unlocked: BTreeMap<AccountOwner, AccountSecretKey>,
+}
+
+impl EncryptedSignerInner {
+    fn new(passphrase: Zeroizing<String>, kdf: KdfParams) -> Result<Self, Error> {
+        let cipher = kdf.derive_cipher(&passphrase)?;
+        let check = Sealed::seal(&cipher, CHECK_PLAINTEXT, &[]);
+        Ok(EncryptedSignerInner {
+            kdf,
+            check,
+            keys: BTreeMap::new(),
+            passphrase: Some(passphrase),
+            cipher: Some(cipher),
+            unlocked: BTreeMap::new(),
+        })
+    }
+
+    /// Checks that `cipher` was derived from the right passphrase, with the current salt.
+    fn check_cipher(&self, cipher: &XChaCha20Poly1305) -> Result<(), Error> {
+        if *self.check.open(cipher, &[])? != CHECK_PLAINTEXT {
+            return Err(Error::InvalidPassphrase);
+        }
+        Ok(())
+    }
+
+    fn secret(
+        &mut self,
+        owner: &AccountOwner,
+        cipher: &XChaCha20Poly1305,
+    ) -> Result<&AccountSecretKey, Error> {
+        if !self.unlocked.contains_key(owner) {
+            let sealed = self.keys.get(owner).ok_or(Error::NoSuchOwner)?;
+            let bytes = sealed.open(cipher, &owner_aad(owner))?;
+            let secret = serde_json::from_slice(&bytes)
+                .map_err(|error| Error::Malformed(error.to_string()))?;
+            self.unlocked.insert(*owner, secret);
+        }
+        Ok(self
+            .unlocked
+            .get(owner)
+            .expect("secret key was just inserted"))
+    }
+
+    fn add_key(
+        &mut self,
+        secret: AccountSecretKey,
+        cipher: &XChaCha20Poly1305,
+    ) -> Result<AccountOwner, Error> {
+        // The key must not be sealed with an outdated cipher, e.g. if the passphrase was
+        // changed since it was derived.
+        self.check_cipher(cipher)?;
+        let owner = AccountOwner::from(secret.public());
+        let bytes =
+            Zeroizing::new(serde_json::to_vec(&secret).expect("serialization should not fail"));
+        let sealed = Sealed::seal(cipher, &bytes, &owner_aad(&owner));
+        self.keys.insert(owner, sealed);
+        self.unlocked.insert(owner, secret);
+        Ok(owner)
+    }
+}
+
+impl EncryptedSigner {
+    /// Creates a new empty [`EncryptedSigner`] protected by `passphrase`, using the
+    /// default Argon2id parameters.
+    pub fn new(passphrase: impl Into<String>) -> Result<Self, Error> {
+        let kdf = KdfParams::new(
+            argon2::Params::DEFAULT_M_COST,
+            argon2::Params::DEFAULT_T_COST,
+            argon2::Params::DEFAULT_P_COST,
+        );
+        let inner = EncryptedSignerInner::new(Zeroizing::new(passphrase.into()), kdf)?;
+        Ok(EncryptedSigner(Arc::new(RwLock::new(inner))))
+    }
+
+    /// Creates a new empty [`EncryptedSigner`] with cheap key derivation parameters.
+    /// USE FOR TESTING ONLY.
+    #[cfg(with_testing)]
+    pub fn new_for_testing(passphrase: impl Into<String>) -> Result<Self, Error> {
+        let kdf = KdfParams::new(argon2::Params::MIN_M_COST, argon2::Params::MIN_T_COST, 1);
+        let inner = EncryptedSignerInner::new(Zeroizing::new(passphrase.into()), kdf)?;
+        Ok(EncryptedSigner(Arc::new(RwLock::new(inner))))
+    }
+
+    /// Sets the passphrase used to unlock the keys. The key derivation itself is
+    /// deferred until a secret key is needed.
+    pub fn set_passphrase(&self, passphrase: impl Into<String>) {
+        let mut inner = self.0.write().unwrap();
+        inner.passphrase = Some(Zeroizing::new(passphrase.into()));
+        inner.cipher = None;
+        inner.unlocked.clear();
+    }
+
+    /// Returns whether the encryption key has already been derived.
+    pub fn is_unlocked(&self) -> bool {
+        self.0.read().unwrap().cipher.is_some()
+    }
+
+    /// Checks the passphrase and derives the encryption key, if not done yet.
+    pub fn unlock(&self) -> Result<(), Error> {
+        self.cipher()?;
+        Ok(())
+    }
+
+    /// Returns the encryption key, deriving it from the passphrase if that was not done
+    /// yet. The lock is not held during the derivation.
+    fn cipher(&self) -> Result<XChaCha20Poly1305, Error> {
+        let (kdf, check, passphrase) = {
+            let inner = self.0.read().unwrap();
+            if let Some(cipher) = &inner.cipher {
+                return Ok(cipher.clone());
+            }
+            let passphrase = inner.passphrase.clone().ok_or(Error::Locked)?;
+            (inner.kdf.clone(), inner.check.clone(), passphrase)
+        };
+        let cipher = kdf.derive_cipher(&passphrase)?;
+        if *check.open(&cipher, &[])? != CHECK_PLAINTEXT {
+            return Err(Error::InvalidPassphrase);
+        }
+        let mut inner = self.0.write().unwrap();
+        // The passphrase or the keys may have changed during the derivation.
+        if inner.cipher.is_none()
+            && inner.kdf.salt == kdf.salt
+            && inner.passphrase.as_deref() == Some(&*passphrase)
+        {
+            inner.cipher = Some(cipher.clone());
+        }
+        Ok(cipher)
+    }
+
+    /// Same as [`EncryptedSigner::cipher`], but derives the key on a blocking thread so
+    /// that the async executor is not stalled.
+    async fn cipher_async(&self) -> Result<XChaCha20Poly1305, Error> {
+        let cached = self.0.read().unwrap().cipher.clone();
+        match cached {
+            Some(cipher) => Ok(cipher),
+            None => self.derive_cipher_blocking().await,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn derive_cipher_blocking(&self) -> Result<XChaCha20Poly1305, Error> {
+        let signer = self.clone();
+        tokio::task::spawn_blocking(move || signer.cipher())
+            .await
+            .map_err(|error| Error::KeyDerivation(error.to_string()))?
+    }
+
+    /// There are no blocking threads on the Web.
+    #[cfg(target_arch = "wasm32")]
+    async fn derive_cipher_blocking(&self) -> Result<XChaCha20Poly1305, Error> {
+        self.cipher()
+    }
+
+    /// Generates a new key pair using the operating system's RNG and stores it.
+    pub fn generate_new(&mut self) -> Result<AccountPublicKey, Error> {
+        let mut rng = rand::rngs::OsRng;
+        let secret = AccountSecretKey::generate_from(&mut rng);
+        let public = secret.public();
+        self.add_key(secret)?;
+        Ok(public)
+    }
+
+    /// Encrypts and stores the given secret key, returning its owner. An existing key for
+    /// the same owner is replaced.
+    pub fn add_key(&mut self, secret: AccountSecretKey) -> Result<AccountOwner, Error> {
+        let cipher = self.cipher()?;
+        self.0.write().unwrap().add_key(secret, &cipher)
+    }
+
+    /// Encrypts and stores all the keys of an [`InMemorySigner`].
+    pub fn import(&mut self, signer: &InMemorySigner) -> Result<(), Error> {
+        for (owner, bytes) in signer.keys() {
+            let bytes = Zeroizing::new(bytes);
+            let secret: AccountSecretKey = serde_json::from_slice(&bytes)
+                .map_err(|error| Error::Malformed(format!("key of {owner}: {error}")))?;
+            self.add_key(secret)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the key of the given `owner`. Returns whether there was one.
+    pub fn remove_key(&mut self, owner: &AccountOwner) -> bool {
+        let mut inner = self.0.write().unwrap();
+        inner.unlocked.remove(owner);
+        inner.keys.remove(owner).is_some()
+    }
+
+    /// Re-encrypts every key under a key derived from `new_passphrase`, with a fresh salt.
+    /// The current passphrase must be set and valid.
+    pub fn change_passphrase(&mut self, new_passphrase: impl Into<String>) -> Result<(), Error> {
+        let cipher = self.cipher()?;
+        let (kdf, secrets) = {
+            let mut inner = self.0.write().unwrap();
+            let owners = inner.keys.keys().copied().collect::<Vec<_>>();
+            let mut secrets = Vec::with_capacity(owners.len());
+            for owner in owners {
+                secrets.push(inner.secret(&owner, &cipher)?.copy());
+            }
+            let kdf = KdfParams::new(inner.kdf.m_cost, inner.kdf.t_cost, inner.kdf.p_cost);
+            (kdf, secrets)
+        };
+        let mut new_inner = EncryptedSignerInner::new(Zeroizing::new(new_passphrase.into()), kdf)?;
+        let new_cipher = new_inner.cipher.clone().expect("a new signer has a cipher");
+        for secret in secrets {
+            new_inner.add_key(secret, &new_cipher)?;
+        }
+        *self.0.write().unwrap() = new_inner;
+        Ok(())
+    }
+
+    /// Returns the owners of all the stored keys.
+    pub fn owners(&self) -> Vec<AccountOwner> {
+        self.0.read().unwrap().keys.keys().copied().collect()
+    }
+}
+
+impl Signer for EncryptedSigner {
+    type Error = Error;
+
+    /// Creates a signature for the given `value` using the provided `owner`.
+    async fn sign(
+        &self,
+        owner: &AccountOwner,
+        value: &CryptoHash,
+    ) -> Result<AccountSignature, Error> {
+        let cipher = self.cipher_async().await?;
+        let mut inner = self.0.write().unwrap();
+        let secret = inner.secret(owner, &cipher)?;
+        Ok(secret.sign_prehash(*value))
+    }
+
+    /// Returns whether the given `owner` is a known signer.
+    async fn contains_key(&self, owner: &AccountOwner) -> Result<bool, Error> {
+        Ok(self.0.read().unwrap().keys.contains_key(owner))
+    }
+}
+
+impl Serialize for EncryptedSigner {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let inner = self.0.read().unwrap();
+        let persisted = Persisted {
+            kdf: inner.kdf.clone(),
+            check: inner.check.clone(),
+            keys: inner
+                .keys
+                .iter()
+                .map(|(owner, sealed)| (*owner, sealed.clone()))
+                .collect(),
+        };
+        persisted.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EncryptedSigner {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let persisted = Persisted::deserialize(deserializer)?;
+        let inner = EncryptedSignerInner {
+            kdf: persisted.kdf,
+            check: persisted.check,
+            keys: persisted.keys.into_iter().collect(),
+            passphrase: None,
+            cipher: None,
+            unlocked: BTreeMap::new(),
+        };
+        Ok(EncryptedSigner(Arc::new(RwLock::new(inner))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Ed25519SecretKey, Secp256k1SecretKey};
+
+    #[tokio::test]
+    async fn test_sign_after_roundtrip() {
+        let mut signer = EncryptedSigner::new_for_testing("correct horse").unwrap();
+        let ed25519_owner = signer
+            .add_key(AccountSecretKey::Ed25519(Ed25519SecretKey::generate()))
+            .unwrap();
+        let secp256k1_owner = signer
+            .add_key(AccountSecretKey::Secp256k1(Secp256k1SecretKey::generate()))
+            .unwrap();
+
+        let json = serde_json::to_string(&signer).unwrap();
+        let reloaded: EncryptedSigner = serde_json::from_str(&json).unwrap();
+        assert!(!reloaded.is_unlocked());
+        assert!(reloaded.contains_key(&ed25519_owner).await.unwrap());
+
+        let value = CryptoHash::test_hash("value");
+        assert!(matches!(
+            reloaded.sign(&ed25519_owner, &value).await,
+            Err(Error::Locked)
+        ));
+
+        reloaded.set_passphrase("correct horse");
+        for owner in [ed25519_owner, secp256k1_owner] {
+            let signature = reloaded.sign(&owner, &value).await.unwrap();
+            let expected = signer.sign(&owner, &value).await.unwrap();
+            assert_eq!(signature, expected);
+        }
+        assert!(reloaded.is_unlocked());
+    }
+
+    #[tokio::test]
+    async fn test_wrong_passphrase_and_change() {
+        let mut signer = EncryptedSigner::new_for_testing("old").unwrap();
+        let owner = signer.generate_new().unwrap().into();
+        let json = serde_json::to_string(&signer).unwrap();
+        let reloaded: EncryptedSigner = serde_json::from_str(&json).unwrap();
+        reloaded.set_passphrase("wrong");
+        assert!(matches!(reloaded.unlock(), Err(Error::InvalidPassphrase)));
+
+        signer.change_passphrase("new").unwrap();
+        let json = serde_json::to_string(&signer).unwrap();
+        let mut reloaded: EncryptedSigner = serde_json::from_str(&json).unwrap();
+        reloaded.set_passphrase("old");
+        assert!(matches!(reloaded.unlock(), Err(Error::InvalidPassphrase)));
+        reloaded.set_passphrase("new");
+        let value = CryptoHash::test_hash("value");
+        reloaded.sign(&owner, &value).await.unwrap();
+
+        assert!(reloaded.remove_key(&owner));
+        assert!(!reloaded.contains_key(&owner).await.unwrap());
+    }
+}
@@ -29,7 +29,7 @@ use thiserror::Error;
 #[cfg(with_metrics)]
 use crate::prometheus_util::MeasureLatency as _;
 use crate::{
-    crypto::{BcsHashable, CryptoError, CryptoHash},
+    crypto::{BcsHashable, BcsSignable, CryptoError, CryptoHash},
     doc_scalar, hex_debug, http,
     identifiers::{
         ApplicationId, BlobId, BlobType, ChainId, EventId, GenericApplicationId, ModuleId, StreamId,
@@ -670,6 +670,22 @@ impl Round {
     }
 }
 
+/// The kind of value that validators vote for and certify.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[repr(u8)]
+pub enum CertificateKind {
+    Timeout = 0,
+    Validated = 1,
+    Confirmed = 2,
+}
+
+/// The statement signed by a validator when voting: the hash of the value, the round and the
+/// kind of value.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct VoteValue(pub CryptoHash, pub Round, pub CertificateKind);
+
+impl BcsSignable<'_> for VoteValue {}
+
 impl<'a> iter::Sum<&'a Amount> for Amount {
     fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
         iter.fold(Self::ZERO, |a, b| a.saturating_add(*b))
@@ -11,6 +11,7 @@ mod validated;
 use std::collections::BTreeSet;
 
 pub use generic::GenericCertificate;
+pub use linera_base::data_types::CertificateKind;
 use linera_base::{
     crypto::{CryptoHash, ValidatorPublicKey, ValidatorSignature},
     data_types::{BlockHeight, Epoch, Round},
@@ -84,14 +85,6 @@ impl Certificate {
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
-#[repr(u8)]
-pub enum CertificateKind {
-    Timeout = 0,
-    Validated = 1,
-    Confirmed = 2,
-}
-
 pub trait CertificateValue: Clone {
     const KIND: CertificateKind;
 
@@ -836,15 +836,24 @@ where
             }
         }
 
+        let (messages, oracle_responses, events, blobs, operation_results) =
+            block_execution_tracker.finalize();
+
+        chain
+            .system
+            .collect_fees(
+                resource_controller.tracker.fees,
+                resource_controller.policy().fee_sink,
+            )
+            .await
+            .with_execution_context(ChainExecutionContext::Block)?;
+
         let state_hash = {
             #[cfg(with_metrics)]
             let _hash_latency = metrics::STATE_HASH_COMPUTATION_LATENCY.measure_latency();
             chain.crypto_hash().await?
         };
 
-        let (messages, oracle_responses, events, blobs, operation_results) =
-            block_execution_tracker.finalize();
-
         Ok(BlockExecutionOutcome {
             messages,
             previous_message_blocks,
@@ -12,11 +12,17 @@ use linera_base::{
         AccountSignature, BcsHashable, BcsSignable, CryptoError, CryptoHash, Signer,
         ValidatorPublicKey, ValidatorSecretKey, ValidatorSignature,
     },
-    data_types::{Amount, Blob, BlockHeight, Epoch, Event, OracleResponse, Round, Timestamp},
+    data_types::{
+        Amount, Blob, BlockHeight, Epoch, Event, OracleResponse, Round, Timestamp, VoteValue,
+    },
     doc_scalar, ensure, hex, hex_debug,
     identifiers::{Account, AccountOwner, ApplicationId, BlobId, ChainId, StreamId},
 };
-use linera_execution::{committee::Committee, Message, MessageKind, Operation, OutgoingMessage};
+use linera_execution::{
+    committee::Committee,
+    evidence::{BlockVote, MisbehaviorEvidence},
+    Message, MessageKind, Operation, OutgoingMessage,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -397,9 +403,6 @@ impl LiteValue {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
-struct VoteValue(CryptoHash, Round, CertificateKind);
-
 /// A vote on a statement from a validator.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(bound(deserialize = "T: Deserialize<'de>"))]
@@ -468,6 +471,35 @@ impl LiteVote {
     pub fn kind(&self) -> CertificateKind {
         self.value.kind
     }
+
+    /// Returns the evidence that `validator` equivocated, if it signed both this vote for
+    /// `block` and the `other` vote for `other_block`, and they are conflicting: votes of the
+    /// same kind and in the same round for different blocks at the same height of a chain.
+    pub fn misbehavior_evidence(
+        &self,
+        block: &Block,
+        other: &LiteVote,
+        other_block: &Block,
+        validator: ValidatorPublicKey,
+    ) -> Option<MisbehaviorEvidence> {
+        if self.round != other.round || self.kind() != other.kind() {
+            return None;
+        }
+        let evidence = MisbehaviorEvidence {
+            validator,
+            round: self.round,
+            kind: self.kind(),
+            first: BlockVote {
+                block: bcs::to_bytes(block).ok()?,
+                signature: self.signature,
+            },
+            second: BlockVote {
+                block: bcs::to_bytes(other_block).ok()?,
+                signature: other.signature,
+            },
+        };
+        evidence.verify().is_ok().then_some(evidence)
+    }
 }
 
 impl MessageBundle {
@@ -612,11 +644,7 @@ impl BlockProposal {
 
     /// Returns the `AccountOwner` that proposed the block.
     pub fn owner(&self) -> AccountOwner {
-        match self.signature {
-            AccountSignature::Ed25519 { public_key, .. } => public_key.into(),
-            AccountSignature::Secp256k1 { public_key, .. } => public_key.into(),
-            AccountSignature::EvmSecp256k1 { address, .. } => AccountOwner::Address20(address),
-        }
+        self.signature.owner()
     }
 
     pub fn check_signature(&self) -> Result<(), CryptoError> {
@@ -787,8 +815,6 @@ pub(crate) fn check_signatures(
 
 impl BcsSignable<'_> for ProposalContent {}
 
-impl BcsSignable<'_> for VoteValue {}
-
 doc_scalar!(
     MessageAction,
     "Whether an incoming message is accepted or rejected."
@@ -6,5 +6,7 @@ fn main() {
         web: { all(target_arch = "wasm32", feature = "web") },
         with_testing: { any(test, feature = "test") },
         with_metrics: { all(not(target_arch = "wasm32"), feature = "metrics") },
+        // the encrypted signer of `linera-base` needs a source of entropy
+        with_encrypted_signer: { any(web, not(target_arch = "wasm32")) },
     };
 }
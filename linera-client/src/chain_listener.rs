@@ -14,7 +14,7 @@ use futures::{
 };
 use linera_base::{
     crypto::{CryptoHash, Signer},
-    data_types::{ChainDescription, Epoch, Timestamp},
+    data_types::{ChainDescription, Epoch, TimeDelta, Timestamp},
     identifiers::{AccountOwner, BlobType, ChainId},
     task::NonBlockingFuture,
 };
@@ -33,6 +33,10 @@ use crate::{
     Error,
 };
 
+/// How long to wait before retrying to execute scheduled operations that are still due after
+/// the inbox was processed, e.g. because of a network error.
+const SCHEDULED_OPERATIONS_RETRY_DELAY: TimeDelta = TimeDelta::from_secs(5);
+
 #[derive(Debug, Default, Clone, clap::Args, serde::Serialize)]
 pub struct ChainListenerConfig {
     /// Do not create blocks automatically to receive incoming messages. Instead, wait for
@@ -141,7 +145,8 @@ struct ListeningClient<C: ClientContext> {
     join_handle: NonBlockingFuture<()>,
     /// The stream of notifications from the local node.
     notification_stream: Arc<Mutex<NotificationStream>>,
-    /// This is only `< u64::MAX` when the client is waiting for a timeout to process the inbox.
+    /// This is only `< u64::MAX` when the client is waiting for a timeout to process the inbox,
+    /// or for a scheduled operation to become due.
     timeout: Timestamp,
     /// The mode of listening to this chain.
     listening_mode: ListeningMode,
@@ -267,6 +272,8 @@ impl<C: ClientContext> ChainListener<C> {
                     return Ok(());
                 }
                 self.update_wallet(notification.chain_id).await?;
+                self.update_scheduled_operations_timeout(notification.chain_id)
+                    .await;
                 self.add_new_chains(*hash).await?;
                 let publishers = self
                     .update_event_subscriptions(notification.chain_id)
@@ -551,6 +558,8 @@ impl<C: ClientContext> ChainListener<C> {
                 listening_client.timeout = new_timeout.timestamp;
             }
         }
+        self.update_scheduled_operations_timeout(chain_id).await;
+        let listening_client = self.listening.get(&chain_id).expect("missing client");
         let mut context_guard = self.context.lock().await;
         context_guard
             .update_wallet(&listening_client.client)
@@ -558,6 +567,29 @@ impl<C: ClientContext> ChainListener<C> {
         Ok(())
     }
 
+    /// Makes sure the inbox is processed when the next scheduled operation of the chain is due.
+    ///
+    /// Operations that are still due, because processing the inbox failed, are retried after
+    /// [`SCHEDULED_OPERATIONS_RETRY_DELAY`].
+    async fn update_scheduled_operations_timeout(&mut self, chain_id: ChainId) {
+        if self.config.skip_process_inbox {
+            return;
+        }
+        let local_time = self.storage.clock().current_time();
+        let listening_client = self.listening.get_mut(&chain_id).expect("missing client");
+        if !listening_client.client.is_tracked() {
+            return;
+        }
+        match listening_client.client.next_scheduled_operation_due().await {
+            Ok(Some(due)) => {
+                let retry = local_time.saturating_add(SCHEDULED_OPERATIONS_RETRY_DELAY);
+                listening_client.timeout = listening_client.timeout.min(due.max(retry));
+            }
+            Ok(None) => {}
+            Err(error) => warn!(%error, "Failed to read the scheduled operations."),
+        }
+    }
+
     /// Sleeps for the given number of milliseconds, if greater than 0.
     async fn sleep(delay_ms: u64) {
         if delay_ms > 0 {
@@ -493,6 +493,51 @@ impl<Env: Environment, W: Persist<Target = Wallet>> ClientContext<Env, W> {
         Ok(())
     }
 
+    /// Replaces the preferred owner of the chain by `new_owner` among the chain's owners,
+    /// keeping its weight, and makes `new_owner` the preferred owner. Returns the old one.
+    pub async fn rotate_key(
+        &mut self,
+        chain_id: Option<ChainId>,
+        new_owner: AccountOwner,
+    ) -> Result<AccountOwner, Error> {
+        let chain_id = chain_id.unwrap_or_else(|| self.default_chain());
+        let mut chain_client = self.make_chain_client(chain_id);
+        let old_owner = chain_client
+            .preferred_owner()
+            .ok_or(error::Inner::NonexistentKeypair(chain_id))?;
+        info!(%chain_id, %old_owner, %new_owner, "Rotating the key of a chain owner");
+        let time_start = Instant::now();
+
+        let certificate = self
+            .apply_client_command(&chain_client, |chain_client| {
+                let chain_client = chain_client.clone();
+                async move {
+                    let info = chain_client.prepare_chain().await?;
+                    let mut ownership = info.manager.ownership;
+                    if ownership.super_owners.remove(&old_owner) {
+                        ownership.super_owners.insert(new_owner);
+                    } else if let Some(weight) = ownership.owners.remove(&old_owner) {
+                        ownership.owners.insert(new_owner, weight);
+                    } else {
+                        return Err(error::Inner::ChainOwnership.into());
+                    }
+                    chain_client
+                        .change_ownership(ownership)
+                        .await
+                        .map_err(Error::from)
+                        .context("Failed to rotate the key")
+                }
+            })
+            .await?;
+        let time_total = time_start.elapsed();
+        info!("Operation confirmed after {} ms", time_total.as_millis());
+        debug!("{:?}", certificate);
+
+        chain_client.set_preferred_owner(new_owner);
+        self.update_wallet_from_client(&chain_client).await?;
+        Ok(old_owner)
+    }
+
     pub async fn check_compatible_version_info(
         &self,
         address: &str,
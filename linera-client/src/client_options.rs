@@ -50,6 +50,11 @@ pub struct ClientContextOptions {
     #[arg(long = "keystore")]
     pub keystore_path: Option<PathBuf>,
 
+    /// Sets the file containing the passphrase of an encrypted keystore. If missing, the
+    /// passphrase is read from the environment variable `LINERA_KEYSTORE_PASSPHRASE`.
+    #[arg(long = "keystore-passphrase-file")]
+    pub keystore_passphrase_file: Option<PathBuf>,
+
     /// Given an ASCII alphanumeric parameter `X`, read the wallet state and the wallet
     /// storage config from the environment variables `LINERA_WALLET_{X}` and
     /// `LINERA_STORAGE_{X}` instead of `LINERA_WALLET` and
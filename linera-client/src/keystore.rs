@@ -0,0 +1,164 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The keystore used by the client: either a plain [`InMemorySigner`], an
+//! [`EncryptedSigner`] or a [`RemoteSigner`], depending on the contents of the keystore
+//! file.
+
+#[cfg(not(web))]
+use std::path::PathBuf;
+
+#[cfg(with_encrypted_signer)]
+use linera_base::crypto::{encrypted, EncryptedSigner};
+use linera_base::{
+    crypto::{AccountPublicKey, AccountSignature, CryptoHash, InMemorySigner, Signer},
+    identifiers::AccountOwner,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(web))]
+use crate::remote_signer::{self, RemoteSigner};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    InMemory(<InMemorySigner as Signer>::Error),
+    #[cfg(with_encrypted_signer)]
+    #[error(transparent)]
+    Encrypted(#[from] encrypted::Error),
+    #[cfg(not(web))]
+    #[error(transparent)]
+    Remote(#[from] remote_signer::Error),
+    #[error("the keys are held by a remote signer")]
+    RemoteKeys,
+    #[error("the keystore is already encrypted")]
+    AlreadyEncrypted,
+    #[error("the keystore is not encrypted")]
+    NotEncrypted,
+}
+
+/// The keys of a client, possibly encrypted with a passphrase.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Keystore {
+    /// Secret keys encrypted with a passphrase-derived key.
+    #[cfg(with_encrypted_signer)]
+    Encrypted(EncryptedSigner),
+    /// Secret keys held by a separate signing daemon.
+    #[cfg(not(web))]
+    Remote(RemoteSigner),
+    /// Secret keys stored in plain text.
+    Plain(InMemorySigner),
+}
+
+impl Keystore {
+    /// Creates a new plain keystore. See [`InMemorySigner::new`].
+    pub fn new(prng_seed: Option<u64>) -> Self {
+        Keystore::Plain(InMemorySigner::new(prng_seed))
+    }
+
+    /// Creates a new empty keystore encrypted with `passphrase`.
+    #[cfg(with_encrypted_signer)]
+    pub fn new_encrypted(passphrase: impl Into<String>) -> Result<Self, Error> {
+        Ok(Keystore::Encrypted(EncryptedSigner::new(passphrase)?))
+    }
+
+    /// Creates a keystore delegating to the signing daemon at `address`, authenticated
+    /// with the token in `token_file`.
+    #[cfg(not(web))]
+    pub fn new_remote(address: remote_signer::SignerAddress, token_file: PathBuf) -> Self {
+        Keystore::Remote(RemoteSigner::new(address, token_file))
+    }
+
+    /// Returns whether the keys are encrypted.
+    #[cfg(with_encrypted_signer)]
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self, Keystore::Encrypted(_))
+    }
+
+    /// Sets the passphrase used to unlock an encrypted keystore. Does nothing for a plain
+    /// keystore.
+    #[cfg(with_encrypted_signer)]
+    pub fn set_passphrase(&self, passphrase: impl Into<String>) {
+        if let Keystore::Encrypted(signer) = self {
+            signer.set_passphrase(passphrase);
+        }
+    }
+
+    /// Generates a new key pair and stores it.
+    pub fn generate_new(&mut self) -> Result<AccountPublicKey, Error> {
+        match self {
+            #[cfg(with_encrypted_signer)]
+            Keystore::Encrypted(signer) => Ok(signer.generate_new()?),
+            #[cfg(not(web))]
+            Keystore::Remote(_) => Err(Error::RemoteKeys),
+            Keystore::Plain(signer) => Ok(signer.generate_new()),
+        }
+    }
+
+    /// Removes the secret key of `owner`. Returns whether there was one.
+    pub fn remove_key(&mut self, owner: &AccountOwner) -> Result<bool, Error> {
+        match self {
+            #[cfg(with_encrypted_signer)]
+            Keystore::Encrypted(signer) => Ok(signer.remove_key(owner)),
+            #[cfg(not(web))]
+            Keystore::Remote(_) => Err(Error::RemoteKeys),
+            Keystore::Plain(signer) => Ok(signer.remove_key(owner)),
+        }
+    }
+
+    /// Replaces a plain keystore with an encrypted one containing the same keys.
+    #[cfg(with_encrypted_signer)]
+    pub fn encrypt(&mut self, passphrase: impl Into<String>) -> Result<(), Error> {
+        let plain = match self {
+            Keystore::Plain(plain) => plain,
+            Keystore::Encrypted(_) => return Err(Error::AlreadyEncrypted),
+            #[cfg(not(web))]
+            Keystore::Remote(_) => return Err(Error::RemoteKeys),
+        };
+        let mut signer = EncryptedSigner::new(passphrase)?;
+        signer.import(plain)?;
+        *self = Keystore::Encrypted(signer);
+        Ok(())
+    }
+
+    /// Re-encrypts the keys of an encrypted keystore with `new_passphrase`. The current
+    /// passphrase must have been set.
+    #[cfg(with_encrypted_signer)]
+    pub fn change_passphrase(&mut self, new_passphrase: impl Into<String>) -> Result<(), Error> {
+        match self {
+            Keystore::Encrypted(signer) => Ok(signer.change_passphrase(new_passphrase)?),
+            #[cfg(not(web))]
+            Keystore::Remote(_) => Err(Error::RemoteKeys),
+            Keystore::Plain(_) => Err(Error::NotEncrypted),
+        }
+    }
+}
+
+impl Signer for Keystore {
+    type Error = Error;
+
+    async fn sign(
+        &self,
+        owner: &AccountOwner,
+        value: &CryptoHash,
+    ) -> Result<AccountSignature, Error> {
+        match self {
+            #[cfg(with_encrypted_signer)]
+            Keystore::Encrypted(signer) => Ok(signer.sign(owner, value).await?),
+            #[cfg(not(web))]
+            Keystore::Remote(signer) => Ok(signer.sign(owner, value).await?),
+            Keystore::Plain(signer) => signer.sign(owner, value).await.map_err(Error::InMemory),
+        }
+    }
+
+    async fn contains_key(&self, owner: &AccountOwner) -> Result<bool, Error> {
+        match self {
+            #[cfg(with_encrypted_signer)]
+            Keystore::Encrypted(signer) => Ok(signer.contains_key(owner).await?),
+            #[cfg(not(web))]
+            Keystore::Remote(signer) => Ok(signer.contains_key(owner).await?),
+            Keystore::Plain(signer) => signer.contains_key(owner).await.map_err(Error::InMemory),
+        }
+    }
+}
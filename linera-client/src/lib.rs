@@ -13,11 +13,14 @@ pub mod client_metrics;
 pub mod client_options;
 pub mod config;
 mod error;
+pub mod keystore;
 pub mod util;
 pub mod wallet;
 
 #[cfg(not(web))]
 pub mod benchmark;
+#[cfg(not(web))]
+pub mod remote_signer;
 
 #[cfg(test)]
 mod unit_tests;
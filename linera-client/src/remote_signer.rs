@@ -0,0 +1,545 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A [`Signer`] that forwards requests to a separate signing daemon, so that secret keys
+//! never have to be present in the client process.
+//!
+//! The protocol is deliberately small: every request and response is a BCS-serialized
+//! message prefixed by its length as a little-endian [`u32`], exchanged over a TCP or
+//! Unix socket connection.
+//!
+//! Clients are authenticated with a [`SignerToken`] shared with the daemon: each
+//! connection starts with a random challenge from the daemon, answered with the hash of
+//! the challenge and the token, so that the token itself is never sent. The requests are
+//! not encrypted, so TCP connections should only go through trusted networks, e.g.
+//! between containers on the same host. Unix sockets are only accessible to their owner.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use linera_base::{
+    crypto::{AccountSignature, CryptoHash, Signer},
+    identifiers::AccountOwner,
+};
+use rand::Rng as _;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+/// The maximum size of a message of the signing protocol.
+const MAX_MESSAGE_SIZE: u32 = 1 << 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("I/O error while talking to the signer at {address}: {error}")]
+    Io {
+        address: SignerAddress,
+        error: io::Error,
+    },
+    #[error("message of {0} bytes exceeds the maximum size")]
+    MessageTooBig(usize),
+    #[error("the signer returned an error: {0}")]
+    Remote(String),
+    #[error("unexpected response from the signer")]
+    UnexpectedResponse,
+    #[error("the signer returned a signature by {actual} instead of {expected}")]
+    WrongSigner {
+        expected: AccountOwner,
+        actual: AccountOwner,
+    },
+    #[error("invalid signer address {0:?}: expected `tcp:<host>:<port>` or `unix:<path>`")]
+    InvalidAddress(String),
+    #[error("failed to read the signer token from {path}: {error}")]
+    TokenFile { path: PathBuf, error: io::Error },
+    #[error("invalid signer token in {0}")]
+    InvalidToken(PathBuf),
+}
+
+/// The address of a signing daemon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignerAddress {
+    /// A TCP address, as `host:port`.
+    Tcp(String),
+    /// The path of a Unix socket.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for SignerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerAddress::Tcp(address) => write!(f, "tcp:{address}"),
+            SignerAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for SignerAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(address) = s.strip_prefix("tcp:") {
+            return Ok(SignerAddress::Tcp(address.to_string()));
+        }
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(SignerAddress::Unix(PathBuf::from(path)));
+        }
+        Err(Error::InvalidAddress(s.to_string()))
+    }
+}
+
+impl Serialize for SignerAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SignerAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The secret shared by the signing daemon and its clients.
+#[derive(Clone)]
+pub struct SignerToken(CryptoHash);
+
+impl fmt::Debug for SignerToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SignerToken(..)")
+    }
+}
+
+impl SignerToken {
+    /// Generates a random token.
+    pub fn generate() -> Self {
+        SignerToken(CryptoHash::from(rand::thread_rng().gen::<[u8; 32]>()))
+    }
+
+    /// Reads a token from a file, as written by [`SignerToken::write`].
+    pub async fn read(path: &Path) -> Result<Self, Error> {
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|error| Error::TokenFile {
+                path: path.to_path_buf(),
+                error,
+            })?;
+        let token = contents
+            .trim()
+            .parse()
+            .map_err(|_| Error::InvalidToken(path.to_path_buf()))?;
+        Ok(SignerToken(token))
+    }
+
+    /// Writes the token to a new file that only its owner can read.
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        use std::io::Write as _;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", self.0))
+            .map_err(|error| Error::TokenFile {
+                path: path.to_path_buf(),
+                error,
+            })
+    }
+
+    /// Returns the answer to the challenge `nonce` proving the knowledge of the token.
+    fn proof(&self, nonce: &CryptoHash) -> CryptoHash {
+        CryptoHash::keccak256(&[nonce.as_bytes().as_slice(), self.0.as_bytes().as_slice()].concat())
+    }
+}
+
+/// A request sent to the signing daemon.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SignerRequest {
+    /// Signs `value` with the key of `owner`.
+    Sign {
+        owner: AccountOwner,
+        value: CryptoHash,
+    },
+    /// Checks whether the daemon has a key for `owner`.
+    ContainsKey { owner: AccountOwner },
+    /// Answers the challenge of the daemon. Must be the first request of a connection.
+    Authenticate { proof: CryptoHash },
+}
+
+/// A response of the signing daemon.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SignerResponse {
+    Signature(AccountSignature),
+    ContainsKey(bool),
+    Error(String),
+    /// The challenge sent when a connection is opened.
+    Challenge(CryptoHash),
+    Authenticated,
+}
+
+/// Writes a length-prefixed BCS message.
+async fn write_message<W, T>(writer: &mut W, message: &T) -> Result<(), io::Error>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let bytes = bcs::to_bytes(message).map_err(io::Error::other)?;
+    let size = u32::try_from(bytes.len())
+        .ok()
+        .filter(|size| *size <= MAX_MESSAGE_SIZE)
+        .ok_or_else(|| io::Error::other(Error::MessageTooBig(bytes.len())))?;
+    writer.write_u32_le(size).await?;
+    writer.write_all(&bytes).await?;
+    writer.flush().await
+}
+
+/// Reads a length-prefixed BCS message, or returns `None` if the connection was closed.
+async fn read_message<R, T>(reader: &mut R) -> Result<Option<T>, io::Error>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let size = match reader.read_u32_le().await {
+        Ok(size) => size,
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    if size > MAX_MESSAGE_SIZE {
+        return Err(io::Error::other(Error::MessageTooBig(size as usize)));
+    }
+    let mut bytes = vec![0; size as usize];
+    reader.read_exact(&mut bytes).await?;
+    let message = bcs::from_bytes(&bytes).map_err(io::Error::other)?;
+    Ok(Some(message))
+}
+
+/// Reads a response, failing if the connection was closed.
+async fn read_response<S>(stream: &mut S) -> Result<SignerResponse, io::Error>
+where
+    S: AsyncRead + Unpin,
+{
+    read_message(stream)
+        .await?
+        .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+/// Authenticates with `token`, then sends a single request over `stream` and waits for
+/// the response.
+async fn exchange<S>(
+    mut stream: S,
+    token: &SignerToken,
+    request: &SignerRequest,
+) -> Result<SignerResponse, io::Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let SignerResponse::Challenge(nonce) = read_response(&mut stream).await? else {
+        return Err(io::Error::other(Error::UnexpectedResponse));
+    };
+    let proof = token.proof(&nonce);
+    write_message(&mut stream, &SignerRequest::Authenticate { proof }).await?;
+    match read_response(&mut stream).await? {
+        SignerResponse::Authenticated => {}
+        SignerResponse::Error(message) => return Ok(SignerResponse::Error(message)),
+        _ => return Err(io::Error::other(Error::UnexpectedResponse)),
+    }
+    write_message(&mut stream, request).await?;
+    read_response(&mut stream).await
+}
+
+/// A [`Signer`] delegating to a signing daemon.
+///
+/// A new connection is opened for every request: signing happens at most once per block
+/// proposal, and this keeps the client resilient to restarts of the daemon.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteSigner {
+    remote_signer: SignerAddress,
+    /// The file containing the [`SignerToken`] of the daemon.
+    token_file: PathBuf,
+}
+
+impl RemoteSigner {
+    /// Creates a [`RemoteSigner`] talking to the daemon at `address`, authenticated with
+    /// the token in `token_file`.
+    pub fn new(address: SignerAddress, token_file: PathBuf) -> Self {
+        RemoteSigner {
+            remote_signer: address,
+            token_file,
+        }
+    }
+
+    /// Returns the address of the signing daemon.
+    pub fn address(&self) -> &SignerAddress {
+        &self.remote_signer
+    }
+
+    async fn request(&self, request: SignerRequest) -> Result<SignerResponse, Error> {
+        let token = SignerToken::read(&self.token_file).await?;
+        let result = match &self.remote_signer {
+            SignerAddress::Tcp(address) => {
+                match tokio::net::TcpStream::connect(address.as_str()).await {
+                    Ok(stream) => exchange(stream, &token, &request).await,
+                    Err(error) => Err(error),
+                }
+            }
+            #[cfg(unix)]
+            SignerAddress::Unix(path) => match tokio::net::UnixStream::connect(path).await {
+                Ok(stream) => exchange(stream, &token, &request).await,
+                Err(error) => Err(error),
+            },
+            #[cfg(not(unix))]
+            SignerAddress::Unix(_) => Err(io::ErrorKind::Unsupported.into()),
+        };
+        match result.map_err(|error| Error::Io {
+            address: self.remote_signer.clone(),
+            error,
+        })? {
+            SignerResponse::Error(message) => Err(Error::Remote(message)),
+            response => Ok(response),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    type Error = Error;
+
+    async fn sign(
+        &self,
+        owner: &AccountOwner,
+        value: &CryptoHash,
+    ) -> Result<AccountSignature, Error> {
+        let request = SignerRequest::Sign {
+            owner: *owner,
+            value: *value,
+        };
+        let SignerResponse::Signature(signature) = self.request(request).await? else {
+            return Err(Error::UnexpectedResponse);
+        };
+        let actual = signature.owner();
+        if actual != *owner {
+            return Err(Error::WrongSigner {
+                expected: *owner,
+                actual,
+            });
+        }
+        Ok(signature)
+    }
+
+    async fn contains_key(&self, owner: &AccountOwner) -> Result<bool, Error> {
+        let request = SignerRequest::ContainsKey { owner: *owner };
+        let SignerResponse::ContainsKey(contains_key) = self.request(request).await? else {
+            return Err(Error::UnexpectedResponse);
+        };
+        Ok(contains_key)
+    }
+}
+
+/// Authenticates the client of a connection with `token`, then answers its requests until
+/// the connection is closed.
+async fn handle_connection<S, Si>(
+    mut stream: S,
+    signer: Si,
+    token: SignerToken,
+) -> Result<(), io::Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    Si: Signer,
+{
+    let nonce = CryptoHash::from(rand::thread_rng().gen::<[u8; 32]>());
+    write_message(&mut stream, &SignerResponse::Challenge(nonce)).await?;
+    match read_message::<_, SignerRequest>(&mut stream).await? {
+        Some(SignerRequest::Authenticate { proof }) if proof == token.proof(&nonce) => {
+            write_message(&mut stream, &SignerResponse::Authenticated).await?;
+        }
+        Some(_) => {
+            warn!("Rejected a connection that failed to authenticate");
+            let response = SignerResponse::Error("authentication failed".to_string());
+            return write_message(&mut stream, &response).await;
+        }
+        None => return Ok(()),
+    }
+    while let Some(request) = read_message::<_, SignerRequest>(&mut stream).await? {
+        debug!(?request, "Received signing request");
+        let response = match request {
+            SignerRequest::Sign { owner, value } => match signer.sign(&owner, &value).await {
+                Ok(signature) => SignerResponse::Signature(signature),
+                Err(error) => SignerResponse::Error(error.to_string()),
+            },
+            SignerRequest::ContainsKey { owner } => match signer.contains_key(&owner).await {
+                Ok(contains_key) => SignerResponse::ContainsKey(contains_key),
+                Err(error) => SignerResponse::Error(error.to_string()),
+            },
+            SignerRequest::Authenticate { .. } => {
+                SignerResponse::Error("already authenticated".to_string())
+            }
+        };
+        write_message(&mut stream, &response).await?;
+    }
+    Ok(())
+}
+
+/// The bound socket of a signing daemon.
+pub struct SignerListener {
+    address: SignerAddress,
+    token: SignerToken,
+    listener: Listener,
+}
+
+enum Listener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+/// Binds a Unix socket at `path` that only its owner can connect to. The socket is
+/// created in a new directory that only the owner can access, restricted, and then linked
+/// to `path`, so that no one else can connect to it in between.
+#[cfg(unix)]
+fn bind_private_socket(path: &Path) -> Result<tokio::net::UnixListener, io::Error> {
+    use std::os::unix::fs::{DirBuilderExt as _, PermissionsExt as _};
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let private_dir = parent.join(format!(".linera-signer-{:.16}", SignerToken::generate().0));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)?;
+    let private_path = private_dir.join("socket");
+    let result = tokio::net::UnixListener::bind(&private_path).and_then(|listener| {
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))?;
+        // Unlike a rename, a link fails instead of replacing an existing socket.
+        std::fs::hard_link(&private_path, path)?;
+        Ok(listener)
+    });
+    if let Err(error) = std::fs::remove_dir_all(&private_dir) {
+        error!("Failed to remove {}: {error}", private_dir.display());
+    }
+    result
+}
+
+impl SignerListener {
+    /// Binds the socket at `address`, for the clients knowing `token`. Unix sockets are
+    /// only accessible to their owner.
+    pub async fn bind(address: SignerAddress, token: SignerToken) -> Result<Self, Error> {
+        let io_error = |error| Error::Io {
+            address: address.clone(),
+            error,
+        };
+        let listener = match &address {
+            SignerAddress::Tcp(tcp_address) => {
+                let listener = tokio::net::TcpListener::bind(tcp_address.as_str())
+                    .await
+                    .map_err(io_error)?;
+                Listener::Tcp(listener)
+            }
+            #[cfg(unix)]
+            SignerAddress::Unix(path) => {
+                Listener::Unix(bind_private_socket(path).map_err(io_error)?)
+            }
+            #[cfg(not(unix))]
+            SignerAddress::Unix(_) => return Err(io_error(io::ErrorKind::Unsupported.into())),
+        };
+        Ok(SignerListener {
+            address,
+            token,
+            listener,
+        })
+    }
+
+    /// Returns the address the daemon listens on, e.g. with the port chosen by the
+    /// system.
+    pub fn local_address(&self) -> Result<SignerAddress, Error> {
+        match &self.listener {
+            Listener::Tcp(listener) => {
+                let address = listener.local_addr().map_err(|error| Error::Io {
+                    address: self.address.clone(),
+                    error,
+                })?;
+                Ok(SignerAddress::Tcp(address.to_string()))
+            }
+            #[cfg(unix)]
+            Listener::Unix(_) => Ok(self.address.clone()),
+        }
+    }
+
+    /// Answers requests with `signer` until `shutdown_signal` is cancelled.
+    pub async fn serve<Si>(
+        self,
+        signer: Si,
+        shutdown_signal: CancellationToken,
+    ) -> Result<(), Error>
+    where
+        Si: Signer + Clone + Send + Sync + 'static,
+    {
+        let address = self.address;
+        let token = self.token;
+        let io_error = |error| Error::Io {
+            address: address.clone(),
+            error,
+        };
+        info!("Signer listening on {address}");
+        match self.listener {
+            Listener::Tcp(listener) => loop {
+                let (stream, peer) = tokio::select! {
+                    result = listener.accept() => result.map_err(io_error)?,
+                    () = shutdown_signal.cancelled() => break,
+                };
+                debug!("Accepted connection from {peer}");
+                let signer = signer.clone();
+                let token = token.clone();
+                tokio::spawn(async move {
+                    if let Err(error) = handle_connection(stream, signer, token).await {
+                        error!("Error while serving signing requests: {error}");
+                    }
+                });
+            },
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                loop {
+                    let (stream, _) = tokio::select! {
+                        result = listener.accept() => result.map_err(io_error)?,
+                        () = shutdown_signal.cancelled() => break,
+                    };
+                    let signer = signer.clone();
+                    let token = token.clone();
+                    tokio::spawn(async move {
+                        if let Err(error) = handle_connection(stream, signer, token).await {
+                            error!("Error while serving signing requests: {error}");
+                        }
+                    });
+                }
+                if let SignerAddress::Unix(path) = &address {
+                    if let Err(error) = std::fs::remove_file(path) {
+                        error!("Failed to remove socket {}: {error}", path.display());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs a signing daemon answering the requests of the clients knowing `token` at
+/// `address` with `signer`, until `shutdown_signal` is cancelled.
+pub async fn serve<Si>(
+    signer: Si,
+    address: &SignerAddress,
+    token: SignerToken,
+    shutdown_signal: CancellationToken,
+) -> Result<(), Error>
+where
+    Si: Signer + Clone + Send + Sync + 'static,
+{
+    SignerListener::bind(address.clone(), token)
+        .await?
+        .serve(signer, shutdown_signal)
+        .await
+}
@@ -15,6 +15,7 @@ use linera_core::{
     environment,
     test_utils::{MemoryStorageBuilder, StorageBuilder as _, TestBuilder},
 };
+use linera_execution::system::SystemOperation;
 use linera_storage::Storage;
 use tokio_util::sync::CancellationToken;
 
@@ -181,6 +182,85 @@ async fn test_chain_listener() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Tests that the chain listener proposes a block to execute a scheduled operation once it is
+/// due.
+#[test_log::test(tokio::test)]
+async fn test_chain_listener_scheduled_operation() -> anyhow::Result<()> {
+    let mut signer = InMemorySigner::new(Some(42));
+    let owner: AccountOwner = signer.generate_new().into();
+    let config = ChainListenerConfig::default();
+    let storage_builder = MemoryStorageBuilder::default();
+    let clock = storage_builder.clock().clone();
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer.clone()).await?;
+    let mut client0 = builder.add_root_chain(0, Amount::from_tokens(2)).await?;
+    let chain_id0 = client0.chain_id();
+    let client1 = builder.add_root_chain(1, Amount::ZERO).await?;
+    let genesis_config = make_genesis_config(&builder);
+    let admin_id = genesis_config.admin_id();
+    let storage = builder.make_storage().await?;
+    let epoch0 = client0.chain_info().await?.epoch;
+
+    // Transfer ownership of chain 0 to the listener's key.
+    client0
+        .change_ownership(ChainOwnership::single(owner))
+        .await?;
+    client0.set_preferred_owner(owner);
+
+    let mut context = ClientContext {
+        wallet: Wallet::new(genesis_config),
+        client: Arc::new(Client::new(
+            environment::Impl {
+                storage: storage.clone(),
+                network: builder.make_node_provider(),
+                signer,
+            },
+            admin_id,
+            false,
+            [chain_id0],
+            format!("Client node for {:.8}", chain_id0),
+            Duration::from_secs(30),
+            ChainClientOptions::test_default(),
+        )),
+    };
+    context
+        .update_wallet_for_new_chain(chain_id0, Some(owner), clock.current_time(), epoch0)
+        .await?;
+    let context = Arc::new(Mutex::new(context));
+    let cancellation_token = CancellationToken::new();
+    let child_token = cancellation_token.child_token();
+    let chain_listener = ChainListener::new(config, context, storage, child_token)
+        .run()
+        .await
+        .unwrap();
+
+    let handle = linera_base::task::spawn(async move { chain_listener.await.unwrap() });
+    // Schedule a transfer. Once it is due, the listener should propose a block executing it.
+    let due = clock.current_time().saturating_add(TimeDelta::from_secs(5));
+    let transfer = SystemOperation::Transfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Account::chain(client1.chain_id()),
+        amount: Amount::ONE,
+    };
+    client0.schedule_operation(transfer, due, None).await?;
+    for i in 0.. {
+        client0.synchronize_from_validators().boxed().await?;
+        let balance = client0.local_balance().await?;
+        if balance == Amount::ONE {
+            break;
+        }
+        clock.add(TimeDelta::from_secs(1));
+        linera_base::time::timer::sleep(Duration::from_millis(100)).await;
+        if i == 30 {
+            panic!("Unexpected local balance: {}", balance);
+        }
+    }
+
+    cancellation_token.cancel();
+    handle.await?;
+
+    Ok(())
+}
+
 /// Tests that the chain listener always listens to the admin chain.
 #[test_log::test(tokio::test)]
 async fn test_chain_listener_admin_chain() -> anyhow::Result<()> {
@@ -0,0 +1,97 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{os::unix::fs::PermissionsExt as _, time::Duration};
+
+use linera_base::{
+    crypto::{CryptoHash, InMemorySigner, Signer},
+    identifiers::AccountOwner,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    keystore::Keystore,
+    remote_signer::{self, RemoteSigner, SignerAddress, SignerListener, SignerToken},
+};
+
+/// How long the test waits for each response of the daemon.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tests that a [`RemoteSigner`] produces the same signatures as the signer of the daemon.
+#[test_log::test(tokio::test)]
+async fn test_remote_signer_over_unix_socket() -> anyhow::Result<()> {
+    let mut signer = InMemorySigner::new(Some(42));
+    let owner = AccountOwner::from(signer.generate_new());
+    let unknown_owner = AccountOwner::from(InMemorySigner::new(Some(43)).generate_new());
+
+    let tmp_dir = tempfile::tempdir()?;
+    let address = SignerAddress::Unix(tmp_dir.path().join("signer.sock"));
+    let token_file = tmp_dir.path().join("token");
+    let token = SignerToken::generate();
+    token.write(&token_file)?;
+    let mode = std::fs::metadata(&token_file)?.permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // The socket is bound, hence ready to accept connections, before the daemon runs.
+    let listener = SignerListener::bind(address.clone(), token).await?;
+    let mode = std::fs::metadata(tmp_dir.path().join("signer.sock"))?
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // Only the socket and the token are left in the directory.
+    assert_eq!(std::fs::read_dir(tmp_dir.path())?.count(), 2);
+    let shutdown_signal = CancellationToken::new();
+    let server = tokio::spawn(listener.serve(signer.clone(), shutdown_signal.clone()));
+
+    let remote = RemoteSigner::new(address.clone(), token_file.clone());
+    let value = CryptoHash::test_hash("value");
+    let signature = tokio::time::timeout(TIMEOUT, remote.sign(&owner, &value)).await??;
+    assert_eq!(signature, signer.sign(&owner, &value).await?);
+    assert!(remote.contains_key(&owner).await?);
+    assert!(!remote.contains_key(&unknown_owner).await?);
+    assert!(matches!(
+        remote.sign(&unknown_owner, &value).await,
+        Err(remote_signer::Error::Remote(_))
+    ));
+
+    // A keystore file can point to the daemon instead of containing keys.
+    let keystore: Keystore = serde_json::from_str(&serde_json::to_string(&Keystore::new_remote(
+        address, token_file,
+    ))?)?;
+    assert!(matches!(keystore, Keystore::Remote(_)));
+    assert_eq!(signature, keystore.sign(&owner, &value).await?);
+
+    shutdown_signal.cancel();
+    server.await??;
+    Ok(())
+}
+
+/// Tests that the daemon only answers the clients knowing its token, over TCP.
+#[test_log::test(tokio::test)]
+async fn test_remote_signer_rejects_wrong_token() -> anyhow::Result<()> {
+    let mut signer = InMemorySigner::new(Some(42));
+    let owner = AccountOwner::from(signer.generate_new());
+
+    let listener =
+        SignerListener::bind("tcp:127.0.0.1:0".parse()?, SignerToken::generate()).await?;
+    let address = listener.local_address()?;
+    let shutdown_signal = CancellationToken::new();
+    let server = tokio::spawn(listener.serve(signer.clone(), shutdown_signal.clone()));
+
+    let tmp_dir = tempfile::tempdir()?;
+    let token_file = tmp_dir.path().join("token");
+    SignerToken::generate().write(&token_file)?;
+    let remote = RemoteSigner::new(address, token_file);
+    let value = CryptoHash::test_hash("value");
+    assert!(matches!(
+        tokio::time::timeout(TIMEOUT, remote.sign(&owner, &value)).await?,
+        Err(remote_signer::Error::Remote(_))
+    ));
+    assert!(matches!(
+        remote.contains_key(&owner).await,
+        Err(remote_signer::Error::Remote(_))
+    ));
+
+    shutdown_signal.cancel();
+    server.await??;
+    Ok(())
+}
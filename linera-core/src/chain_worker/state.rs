@@ -441,7 +441,10 @@ where
         let certificates = match ResultReadCertificates::new(certificates, hashes) {
             ResultReadCertificates::Certificates(certificates) => certificates,
             ResultReadCertificates::InvalidHashes(hashes) => {
-                return Err(WorkerError::ReadCertificatesError(hashes))
+                if let Some(height) = heights.first() {
+                    self.ensure_is_not_pruned(*height).await?;
+                }
+                return Err(WorkerError::ReadCertificatesError(hashes));
             }
         };
         let certificates = heights
@@ -835,7 +838,7 @@ where
             });
         }
         // Persist chain.
-        self.save().await?;
+        self.save_after_block(Some(height)).await?;
 
         self.block_values
             .insert(Cow::Owned(certificate.into_inner().into_inner()));
@@ -1049,14 +1052,23 @@ where
             Some(hash) => hash,
             None => return Ok(None),
         };
-        let certificate = self
-            .storage
-            .read_certificate(certificate_hash)
-            .await?
-            .ok_or_else(|| WorkerError::ReadCertificatesError(vec![certificate_hash]))?;
+        let Some(certificate) = self.storage.read_certificate(certificate_hash).await? else {
+            self.ensure_is_not_pruned(height).await?;
+            return Err(WorkerError::ReadCertificatesError(vec![certificate_hash]));
+        };
         Ok(Some(certificate))
     }
 
+    /// Returns an error if the certificate at `height` was pruned from the storage.
+    async fn ensure_is_not_pruned(&self, height: BlockHeight) -> Result<(), WorkerError> {
+        let chain_id = self.chain_id();
+        ensure!(
+            height >= self.storage.pruned_height(chain_id).await?,
+            WorkerError::CertificatePruned { height, chain_id }
+        );
+        Ok(())
+    }
+
     /// Queries an application's state on the chain.
     pub(super) async fn query_application(
         &mut self,
@@ -1160,7 +1172,7 @@ where
                         round: Round::Fast,
                         outcome: None,
                     },
-                    signature: *signature,
+                    signature: signature.clone(),
                     original_proposal: None,
                 };
                 let super_owner = original_proposal.owner();
@@ -1306,6 +1318,15 @@ where
 
             info.requested_pending_message_bundles = messages;
         }
+        // The hashes of pruned certificates are still in the log, but are useless to the
+        // caller.
+        if let Some(height) = query
+            .request_sent_certificate_hashes_by_heights
+            .iter()
+            .min()
+        {
+            self.ensure_is_not_pruned(*height).await?;
+        }
         let mut hashes = Vec::new();
         for height in query.request_sent_certificate_hashes_by_heights {
             hashes.extend(chain.block_hashes(height..=height).await?);
@@ -1364,8 +1385,14 @@ where
     ///
     /// Waits until the [`ChainStateView`] is no longer shared before persisting the changes.
     async fn save(&mut self) -> Result<(), WorkerError> {
+        self.save_after_block(None).await
+    }
+
+    /// Stores the chain state, as the one right after executing the block at `height` if
+    /// given.
+    async fn save_after_block(&mut self, height: Option<BlockHeight>) -> Result<(), WorkerError> {
         self.clear_shared_chain_view().await;
-        self.chain.save().await?;
+        self.storage.save_chain(&mut self.chain, height).await?;
         Ok(())
     }
 }
@@ -20,10 +20,12 @@ use futures::{
 use linera_base::prometheus_util::MeasureLatency as _;
 use linera_base::{
     abi::Abi,
-    crypto::{signer, AccountPublicKey, CryptoHash, Signer, ValidatorPublicKey},
+    crypto::{
+        signer, AccountPublicKey, CryptoHash, Signer, ValidatorPublicKey, ValidatorSignature,
+    },
     data_types::{
         Amount, ApplicationPermissions, ArithmeticError, Blob, BlobContent, BlockHeight,
-        ChainDescription, Epoch, Round, Timestamp,
+        ChainDescription, Epoch, Round, TimeDelta, Timestamp,
     },
     ensure,
     identifiers::{
@@ -50,7 +52,7 @@ use linera_chain::{
 use linera_execution::{
     committee::Committee,
     system::{
-        AdminOperation, OpenChainConfig, SystemOperation, EPOCH_STREAM_NAME,
+        AdminOperation, OpenChainConfig, SystemOperation, ValidatorRegistration, EPOCH_STREAM_NAME,
         REMOVED_EPOCH_STREAM_NAME,
     },
     ExecutionError, Operation, Query, QueryOutcome, QueryResponse, SystemQuery, SystemResponse,
@@ -1520,6 +1522,12 @@ pub enum ChainClientError {
     #[error("Missing confirmed block: {0:?}")]
     MissingConfirmedBlock(CryptoHash),
 
+    #[error("The certificate at height {height} of chain {chain_id:8} was pruned")]
+    CertificatePruned {
+        height: BlockHeight,
+        chain_id: ChainId,
+    },
+
     #[error("JSON (de)serialization error: {0}")]
     JsonError(#[from] serde_json::Error),
 
@@ -1596,6 +1604,12 @@ pub enum ChainClientError {
 
     #[error("Epoch is already revoked")]
     EpochAlreadyRevoked,
+
+    #[error("The state of chain {chain_id} at height {height} was not archived")]
+    MissingArchivedChainState {
+        chain_id: ChainId,
+        height: BlockHeight,
+    },
 }
 
 impl From<Infallible> for ChainClientError {
@@ -1711,6 +1725,22 @@ impl<Env: Environment> ChainClient<Env> {
         self.client.local_node.chain_state_view(self.chain_id).await
     }
 
+    /// Obtains the `ChainStateView` of this client's chain as it was right after the block
+    /// at `height` was executed. This requires the storage to archive chain states.
+    #[instrument(level = "trace")]
+    pub async fn chain_state_view_at(
+        &self,
+        height: BlockHeight,
+    ) -> Result<ChainStateView<Env::StorageContext>, ChainClientError> {
+        self.storage_client()
+            .load_chain_at_height(self.chain_id, height)
+            .await?
+            .ok_or(ChainClientError::MissingArchivedChainState {
+                chain_id: self.chain_id,
+                height,
+            })
+    }
+
     /// Returns chain IDs that this chain subscribes to.
     #[instrument(level = "trace", skip(self))]
     pub async fn event_stream_publishers(
@@ -2598,6 +2628,19 @@ impl<Env: Environment> ChainClient<Env> {
         }
     }
 
+    /// Queries an application against the state of the chain right after the block at
+    /// `height` was executed.
+    #[instrument(level = "trace", skip(query))]
+    pub async fn query_application_at(
+        &self,
+        query: Query,
+        height: BlockHeight,
+    ) -> Result<QueryOutcome, ChainClientError> {
+        let mut chain = self.chain_state_view_at(height).await?;
+        let local_time = self.storage_client().clock().current_time();
+        Ok(chain.query_application(local_time, query, None).await?)
+    }
+
     /// Queries a system application.
     #[instrument(level = "trace", skip(query))]
     pub async fn query_system_application(
@@ -3278,6 +3321,84 @@ impl<Env: Environment> ChainClient<Env> {
         }
     }
 
+    /// Schedules an operation to be executed on this chain once `due` is reached, and then
+    /// again after every `interval`, if any.
+    #[instrument(level = "trace", skip(operation))]
+    pub async fn schedule_operation(
+        &self,
+        operation: impl Into<Operation>,
+        due: Timestamp,
+        interval: Option<TimeDelta>,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::ScheduleOperation {
+            operation: Box::new(operation.into()),
+            due,
+            interval,
+        })
+        .await
+    }
+
+    /// Locks `amount` units of value from the given owner's account for the recipient. The
+    /// tokens stay on this chain: the recipient can claim them before the `deadline`, with the
+    /// preimage of the `hash_lock`, if any. After the deadline, they can be refunded.
+    #[instrument(level = "trace")]
+    pub async fn lock_transfer(
+        &self,
+        owner: AccountOwner,
+        recipient: Account,
+        amount: Amount,
+        hash_lock: Option<CryptoHash>,
+        deadline: Timestamp,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::LockTransfer {
+            owner,
+            recipient,
+            amount,
+            hash_lock,
+            deadline,
+        })
+        .await
+    }
+
+    /// Claims a transfer locked on chain `target_id` for the given owner's account on this
+    /// chain, with the preimage of its hash lock, if any.
+    #[instrument(level = "trace", skip(preimage))]
+    pub async fn claim_locked_transfer(
+        &self,
+        owner: AccountOwner,
+        target_id: ChainId,
+        id: u64,
+        preimage: Vec<u8>,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::ClaimLockedTransfer {
+            owner,
+            target_id,
+            id,
+            preimage,
+        })
+        .await
+    }
+
+    /// Refunds a transfer locked on this chain to its sender, after its deadline.
+    #[instrument(level = "trace")]
+    pub async fn refund_locked_transfer(
+        &self,
+        id: u64,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::RefundLockedTransfer { id })
+            .await
+    }
+
+    /// Cancels a scheduled operation.
+    #[instrument(level = "trace")]
+    pub async fn cancel_scheduled_operation(
+        &self,
+        id: u64,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::CancelScheduledOperation { id })
+            .await
+    }
+
     /// Publishes some module.
     #[cfg(not(target_arch = "wasm32"))]
     #[instrument(level = "trace", skip(contract, service))]
@@ -3441,8 +3562,75 @@ impl<Env: Environment> ChainClient<Env> {
         .await
     }
 
+    /// Elects a new committee from the tokens staked with the registered validators and
+    /// starts using it (admin chains only).
+    #[instrument(level = "trace")]
+    pub async fn elect_committee(
+        &self,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        let epoch = self.chain_info().await?.epoch.try_add_one()?;
+        self.execute_operation(SystemOperation::Admin(AdminOperation::ElectCommittee {
+            epoch,
+        }))
+        .await
+    }
+
+    /// Registers a validator for staking on the admin chain, or updates its network address.
+    /// The block must be signed by the owner of the validator's account, and the `signature`
+    /// is the validator's signature of the [`ValidatorRegistration`].
+    #[instrument(level = "trace")]
+    pub async fn register_validator(
+        &self,
+        public_key: ValidatorPublicKey,
+        registration: ValidatorRegistration,
+        signature: ValidatorSignature,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::RegisterValidator {
+            public_key,
+            account_public_key: registration.account_public_key,
+            network_address: registration.network_address,
+            signature,
+        })
+        .await
+    }
+
+    /// Stakes `amount` units of value from the given owner's account with a validator
+    /// registered on the admin chain.
+    #[instrument(level = "trace")]
+    pub async fn stake(
+        &self,
+        owner: AccountOwner,
+        validator: ValidatorPublicKey,
+        amount: Amount,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::Stake {
+            owner,
+            validator,
+            amount,
+        })
+        .await
+    }
+
+    /// Unstakes `amount` units of value staked by the given owner with a validator. The
+    /// tokens are credited back after the unbonding delay.
+    #[instrument(level = "trace")]
+    pub async fn unstake(
+        &self,
+        owner: AccountOwner,
+        validator: ValidatorPublicKey,
+        amount: Amount,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::Unstake {
+            owner,
+            validator,
+            amount,
+        })
+        .await
+    }
+
     /// Synchronizes the chain with the validators and creates blocks without any operations to
-    /// process all incoming messages. This may require several blocks.
+    /// process all incoming messages, and then a block executing the due scheduled operations.
+    /// This may require several blocks.
     ///
     /// If not all certificates could be processed due to a timeout, the timestamp for when to retry
     /// is returned, too.
@@ -3454,8 +3642,8 @@ impl<Env: Environment> ChainClient<Env> {
         self.process_inbox_without_prepare().await
     }
 
-    /// Creates blocks without any operations to process all incoming messages. This may require
-    /// several blocks.
+    /// Creates blocks without any operations to process all incoming messages, and then one
+    /// block for each due scheduled operation. This may require several blocks.
     ///
     /// If not all certificates could be processed due to a timeout, the timestamp for when to retry
     /// is returned, too.
@@ -3477,7 +3665,7 @@ impl<Env: Environment> ChainClient<Env> {
                 .chain(epoch_change_ops.next())
                 .collect::<Vec<_>>();
             if incoming_bundles.is_empty() && block_operations.is_empty() {
-                return Ok((certificates, None));
+                break;
             }
             match self.execute_block(block_operations, vec![]).await {
                 Ok(ExecuteBlockOutcome::Executed(certificate))
@@ -3488,6 +3676,69 @@ impl<Env: Environment> ChainClient<Env> {
                 Err(error) => return Err(error),
             };
         }
+
+        // Execute the due scheduled operations last, each in its own block, so that a failing
+        // one doesn't prevent the incoming messages or the other operations from being
+        // processed. An operation that fails is skipped, so that it doesn't block the chain.
+        for id in self.collect_due_scheduled_operations().await? {
+            let execute = SystemOperation::ExecuteScheduledOperation { id };
+            let outcome = match self.execute_block(vec![execute.into()], vec![]).await {
+                Err(ChainClientError::LocalNodeError(LocalNodeError::WorkerError(
+                    WorkerError::ChainError(chain_error),
+                ))) if matches!(*chain_error, ChainError::ExecutionError(..)) => {
+                    warn!(%id, %chain_error, "Skipping failed scheduled operation");
+                    let skip = SystemOperation::SkipScheduledOperation { id };
+                    self.execute_block(vec![skip.into()], vec![]).await
+                }
+                outcome => outcome,
+            };
+            match outcome {
+                Ok(ExecuteBlockOutcome::Executed(certificate))
+                | Ok(ExecuteBlockOutcome::Conflict(certificate)) => certificates.push(certificate),
+                Ok(ExecuteBlockOutcome::WaitForTimeout(timeout)) => {
+                    return Ok((certificates, Some(timeout)));
+                }
+                Err(error) => return Err(error),
+            };
+        }
+        Ok((certificates, None))
+    }
+
+    /// Returns the IDs of all scheduled operations that are due according to the local clock.
+    async fn collect_due_scheduled_operations(&self) -> Result<Vec<u64>, ChainClientError> {
+        let local_time = self.storage_client().clock().current_time();
+        let scheduled_operations = self
+            .chain_state_view()
+            .await?
+            .execution_state
+            .system
+            .scheduled_operations
+            .index_values()
+            .await?;
+        Ok(scheduled_operations
+            .into_iter()
+            .filter(|(_, scheduled)| scheduled.due <= local_time)
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// Returns the time at which the next scheduled operation on this chain is due, if any.
+    #[instrument(level = "trace")]
+    pub async fn next_scheduled_operation_due(
+        &self,
+    ) -> Result<Option<Timestamp>, ChainClientError> {
+        let scheduled_operations = self
+            .chain_state_view()
+            .await?
+            .execution_state
+            .system
+            .scheduled_operations
+            .index_values()
+            .await?;
+        Ok(scheduled_operations
+            .into_iter()
+            .map(|(_, scheduled)| scheduled.due)
+            .min())
     }
 
     /// Returns operations to process all pending epoch changes: first the new epochs, in order,
@@ -4042,6 +4293,19 @@ impl<Env: Environment> ChainClient<Env> {
             .expect("`usize` should be at least `u64`");
         let missing_certificates_start = missing_certificates_end
             - usize::try_from(missing_certificate_count).expect("`usize` should be at least `u64`");
+        // The validator cannot catch up from our storage if it pruned the certificates.
+        let pruned_height = self
+            .client
+            .storage_client()
+            .pruned_height(self.chain_id)
+            .await?;
+        ensure!(
+            validator_next_block_height >= pruned_height.0,
+            ChainClientError::CertificatePruned {
+                height: BlockHeight(validator_next_block_height),
+                chain_id: self.chain_id,
+            }
+        );
 
         let missing_certificate_hashes = self
             .chain_state_view()
@@ -792,6 +792,115 @@ where
     Ok(())
 }
 
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new(); "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_cross_chain_locked_transfers<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let clock = storage_builder.clock().clone();
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(2)).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    let owner = receiver.identity().await?;
+    let recipient = Account::new(receiver.chain_id(), owner);
+    let preimage = b"secret".to_vec();
+    let hash_lock = Some(CryptoHash::keccak256(&preimage));
+    let deadline = clock
+        .current_time()
+        .saturating_add(TimeDelta::from_secs(10));
+    for _ in 0..2 {
+        sender
+            .lock_transfer(
+                AccountOwner::CHAIN,
+                recipient,
+                Amount::ONE,
+                hash_lock,
+                deadline,
+            )
+            .await
+            .unwrap_ok_committed();
+    }
+    assert_eq!(sender.local_balance().await?, Amount::ZERO);
+
+    // The receiver claims the first transfer with the preimage.
+    receiver
+        .claim_locked_transfer(owner, sender.chain_id(), 0, preimage)
+        .await
+        .unwrap_ok_committed();
+    sender.synchronize_from_validators().await?;
+    sender.process_inbox().await?;
+    receiver.synchronize_from_validators().await?;
+    receiver.process_inbox().await?;
+    assert_eq!(receiver.local_owner_balance(owner).await?, Amount::ONE);
+
+    // The receiver doesn't claim the second transfer, and never creates a block again: the
+    // sender can still get a refund after the deadline.
+    clock.set(deadline);
+    sender.refund_locked_transfer(1).await.unwrap_ok_committed();
+    assert_eq!(sender.local_balance().await?, Amount::ONE);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new(); "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_failing_scheduled_operation_is_skipped<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let clock = storage_builder.clock().clone();
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::ONE).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    let recipient = Account::chain(receiver.chain_id());
+
+    // A recurring transfer that exceeds the balance, and a transfer that doesn't.
+    let due = clock.current_time().saturating_add(TimeDelta::from_secs(1));
+    let interval = TimeDelta::from_secs(10);
+    for (amount, interval) in [
+        (Amount::from_tokens(3), Some(interval)),
+        (Amount::ONE, None),
+    ] {
+        let transfer = SystemOperation::Transfer {
+            owner: AccountOwner::CHAIN,
+            recipient,
+            amount,
+        };
+        sender
+            .schedule_operation(transfer, due, interval)
+            .await
+            .unwrap_ok_committed();
+    }
+
+    // The failing transfer is skipped and the other one is executed.
+    clock.set(due);
+    let (certificates, _) = sender.process_inbox().await?;
+    assert_eq!(certificates.len(), 2);
+    assert_matches!(
+        &certificates[0].block().body.transactions[..],
+        [Transaction::ExecuteOperation(Operation::System(system_op))]
+            if matches!(**system_op, SystemOperation::SkipScheduledOperation { id: 0 })
+    );
+    assert_eq!(sender.local_balance().await?, Amount::ZERO);
+    receiver.synchronize_from_validators().await?;
+    receiver.process_inbox().await?;
+    assert_eq!(receiver.local_balance().await?, Amount::ONE);
+
+    // The recurring transfer remains scheduled for its next occurrence.
+    let chain = sender.chain_state_view().await?;
+    let scheduled_operations = &chain.execution_state.system.scheduled_operations;
+    assert_eq!(scheduled_operations.indices().await?, vec![0]);
+    assert_eq!(
+        scheduled_operations.get(&0).await?.unwrap().due,
+        due.saturating_add(interval)
+    );
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new(); "storage_service"))]
 #[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
@@ -8,7 +8,7 @@
 mod wasm;
 
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     iter,
     sync::{Arc, Mutex},
     time::Duration,
@@ -18,7 +18,7 @@ use assert_matches::assert_matches;
 use linera_base::{
     crypto::{
         AccountPublicKey, AccountSecretKey, AccountSignature, CryptoHash, InMemorySigner,
-        ValidatorKeypair,
+        MultisigPolicy, Signer as _, ValidatorKeypair, ValidatorSignature,
     },
     data_types::*,
     identifiers::{Account, AccountOwner, ChainId, EventId, StreamId},
@@ -41,17 +41,18 @@ use linera_chain::{
 use linera_execution::{
     committee::Committee,
     system::{
-        AdminOperation, OpenChainConfig, SystemMessage, SystemOperation,
+        AdminOperation, OpenChainConfig, SystemMessage, SystemOperation, ValidatorRegistration,
         EPOCH_STREAM_NAME as NEW_EPOCH_STREAM_NAME, REMOVED_EPOCH_STREAM_NAME,
     },
     test_utils::{
         dummy_chain_description, ExpectedCall, RegisterMockApplication, SystemExecutionState,
     },
-    ExecutionError, Message, MessageKind, OutgoingMessage, Query, QueryContext, QueryOutcome,
-    QueryResponse, SystemQuery, SystemResponse,
+    BlobState, ExecutionError, Message, MessageKind, Operation, OutgoingMessage, Query,
+    QueryContext, QueryOutcome, QueryResponse, SystemQuery, SystemResponse,
 };
-use linera_storage::{DbStorage, Storage, TestClock};
+use linera_storage::{DbStorage, PruningPolicy, PruningSummary, SnapshotError, Storage, TestClock};
 use linera_views::{
+    faulty::{Fault, FaultRule, FaultyMemoryDatabase, FaultyOperation},
     memory::MemoryDatabase,
     random::generate_test_namespace,
     store::TestKeyValueDatabase as _,
@@ -586,6 +587,61 @@ where
     Ok(())
 }
 
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_handle_block_proposal_multisig<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let mut signer = InMemorySigner::new(None);
+    let members = (0..3)
+        .map(|_| AccountOwner::from(signer.generate_new()))
+        .collect::<Vec<_>>();
+    let policy = MultisigPolicy::new(2, members.clone())?;
+    let treasury = policy.owner();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, treasury, Amount::from_tokens(5))
+        .await
+        .id();
+    let chain_2 = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+        .await
+        .id();
+    let mut proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::from_tokens(1))
+        .with_authenticated_signer(Some(treasury))
+        .into_first_proposal(members[0], &signer)
+        .await?;
+    let hash = CryptoHash::new(&proposal.content);
+
+    // A single member cannot approve the transfer.
+    proposal.signature =
+        AccountSignature::multisig(policy.clone(), vec![signer.sign(&members[0], &hash).await?]);
+    assert_eq!(proposal.owner(), treasury);
+    assert_matches!(
+        env.worker().handle_block_proposal(proposal.clone()).await,
+        Err(WorkerError::CryptoError(error))
+            if matches!(error, linera_base::crypto::CryptoError::InvalidSignature {..})
+    );
+
+    // Two members can.
+    proposal.signature = AccountSignature::multisig(
+        policy,
+        vec![
+            signer.sign(&members[0], &hash).await?,
+            signer.sign(&members[2], &hash).await?,
+        ],
+    );
+    env.worker().handle_block_proposal(proposal).await?;
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert!(chain.manager.validated_vote().is_some());
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
@@ -998,6 +1054,443 @@ where
     Ok(())
 }
 
+#[test_log::test(tokio::test)]
+async fn test_prune_storage() -> anyhow::Result<()> {
+    let mut signer = InMemorySigner::new(None);
+    let sender_public_key = signer.generate_new();
+    let sender_owner = sender_public_key.into();
+    let storage = MemoryStorageBuilder::default()
+        .build()
+        .await?
+        .with_archived_chain_states(true);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = env.add_root_chain(2, sender_owner, Amount::ZERO).await.id();
+
+    let mut certificates = Vec::new();
+    let mut requests = VecDeque::new();
+    for balance in [4, 3, 2] {
+        let certificate = env
+            .make_simple_transfer_certificate(
+                chain_1_desc.clone(),
+                sender_public_key,
+                chain_2,
+                Amount::ONE,
+                Vec::new(),
+                Amount::from_tokens(balance),
+                certificates.last().into_iter().collect(),
+            )
+            .await;
+        let (_, actions) = env
+            .worker()
+            .handle_confirmed_certificate(certificate.clone(), None)
+            .await?;
+        requests.extend(actions.cross_chain_requests);
+        certificates.push(certificate);
+    }
+    // A data blob last used by the first block, and one not used by any block yet.
+    let used_blob = Blob::new_data(b"used".to_vec());
+    let unused_blob = Blob::new_data(b"unused".to_vec());
+    let storage = env.worker().storage.clone();
+    storage
+        .write_blobs(&[used_blob.clone(), unused_blob.clone()])
+        .await?;
+    let blob_state = BlobState {
+        last_used_by: Some(certificates[0].hash()),
+        chain_id: chain_1,
+        block_height: BlockHeight(0),
+        epoch: Some(Epoch::ZERO),
+    };
+    storage
+        .maybe_write_blob_states(&[used_blob.id()], blob_state)
+        .await?;
+    let policy = PruningPolicy {
+        keep_heights: 1,
+        keep_for: Some(TimeDelta::from_secs(10)),
+        prune_blobs: true,
+    };
+
+    // The messages to chain 2 were not delivered yet, so every block is still needed.
+    storage.clock().set(Timestamp::from(20_000_000));
+    let summary = storage.prune(&[chain_1, chain_2], &policy).await?;
+    assert_eq!(summary, PruningSummary::default());
+
+    while let Some(request) = requests.pop_front() {
+        let actions = env.worker().handle_cross_chain_request(request).await?;
+        requests.extend(actions.cross_chain_requests);
+    }
+    // The blocks are too recent to be pruned.
+    storage.clock().set(Timestamp::from(5_000_000));
+    let summary = storage.prune(&[chain_1, chain_2], &policy).await?;
+    assert_eq!(summary, PruningSummary::default());
+
+    storage.clock().set(Timestamp::from(20_000_000));
+    let summary = storage.prune(&[chain_1, chain_2], &policy).await?;
+    assert_eq!(
+        summary,
+        PruningSummary {
+            certificates: 2,
+            archived_chain_states: 2,
+            blobs: 1,
+        }
+    );
+    assert_eq!(storage.pruned_height(chain_1).await?, BlockHeight(2));
+    assert!(!storage.contains_certificate(certificates[0].hash()).await?);
+    assert!(!storage.contains_certificate(certificates[1].hash()).await?);
+    assert!(storage.contains_certificate(certificates[2].hash()).await?);
+    assert!(!storage.contains_blob(used_blob.id()).await?);
+    assert!(storage.contains_blob(unused_blob.id()).await?);
+    // Chain descriptions are never pruned.
+    let description_blob = Blob::new_chain_description(&chain_1_desc);
+    assert!(storage.contains_blob(description_blob.id()).await?);
+    for height in [0, 1] {
+        assert!(storage
+            .load_chain_at_height(chain_1, BlockHeight(height))
+            .await?
+            .is_none());
+    }
+    let chain = storage
+        .load_chain_at_height(chain_1, BlockHeight(2))
+        .await?
+        .expect("the chain state should be archived");
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(2)
+    );
+
+    // The pruned certificates are reported as such.
+    assert_matches!(
+        env.worker().read_certificate(chain_1, BlockHeight(1)).await,
+        Err(WorkerError::CertificatePruned { height, .. }) if height == BlockHeight(1)
+    );
+    let query =
+        ChainInfoQuery::new(chain_1).with_sent_certificate_hashes_by_heights(vec![BlockHeight(1)]);
+    assert_matches!(
+        env.worker().handle_chain_info_query(query).await,
+        Err(WorkerError::CertificatePruned { .. })
+    );
+    let query =
+        ChainInfoQuery::new(chain_1).with_sent_certificate_hashes_by_heights(vec![BlockHeight(2)]);
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    assert_eq!(
+        response.info.requested_sent_certificate_hashes,
+        vec![certificates[2].hash()]
+    );
+
+    // Pruning again does not visit the deleted blocks.
+    let summary = storage.prune(&[chain_1, chain_2], &policy).await?;
+    assert_eq!(summary, PruningSummary::default());
+    let chain = storage.load_chain(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(3));
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn test_export_and_import_chain() -> anyhow::Result<()> {
+    let mut storage_builder = MemoryStorageBuilder::default();
+    let mut signer = InMemorySigner::new(None);
+    let sender_public_key = signer.generate_new();
+    let sender_owner = sender_public_key.into();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = env.add_root_chain(2, sender_owner, Amount::ZERO).await.id();
+
+    let certificate0 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_public_key,
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(4),
+            vec![],
+        )
+        .await;
+    let certificate1 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_public_key,
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(3),
+            vec![&certificate0],
+        )
+        .await;
+    let mut cross_chain_requests = Vec::new();
+    for certificate in [certificate0, certificate1.clone()] {
+        let (_, actions) = env
+            .worker()
+            .handle_confirmed_certificate(certificate, None)
+            .await?;
+        cross_chain_requests.extend(actions.cross_chain_requests);
+    }
+
+    let storage = &env.worker().storage;
+    let mut snapshot = Vec::new();
+    let header = storage.export_chain(chain_1, &mut snapshot).await?;
+    assert_eq!(header.next_block_height, BlockHeight(2));
+    storage.verify_chain_snapshot(&snapshot[..]).await?;
+
+    let mut tampered = snapshot.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_matches!(
+        storage.verify_chain_snapshot(&tampered[..]).await,
+        Err(SnapshotError::HashMismatch)
+    );
+    // Storages with a different committee reject the certificates.
+    let other_env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    assert_matches!(
+        other_env.worker().storage.verify_chain_snapshot(&snapshot[..]).await,
+        Err(SnapshotError::InvalidSignatures(height)) if height == BlockHeight(0)
+    );
+
+    // Restore the chain into a different namespace, knowing the same committee.
+    let target = storage_builder.build().await?;
+    let committee_blob = Blob::new_committee(bcs::to_bytes(env.committee())?);
+    target.write_blob(&committee_blob).await?;
+    let network_description = storage
+        .read_network_description()
+        .await?
+        .expect("the network description should exist");
+    target
+        .write_network_description(&network_description)
+        .await?;
+    // An interrupted import is retried from scratch.
+    assert_matches!(
+        target.import_chain(&snapshot[..snapshot.len() - 1]).await,
+        Err(SnapshotError::Io(_))
+    );
+    target.import_chain(&snapshot[..]).await?;
+    assert_matches!(
+        target.import_chain(&snapshot[..]).await,
+        Err(SnapshotError::ChainAlreadyExists(chain_id)) if chain_id == chain_1
+    );
+    let chain = target.load_chain(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(2));
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(3)
+    );
+    assert!(target.contains_certificate(certificate1.hash()).await?);
+
+    // A pruned chain is exported from its pruned height.
+    let mut requests = VecDeque::from(cross_chain_requests);
+    while let Some(request) = requests.pop_front() {
+        let actions = env.worker().handle_cross_chain_request(request).await?;
+        requests.extend(actions.cross_chain_requests);
+    }
+    let policy = PruningPolicy {
+        keep_heights: 1,
+        keep_for: None,
+        prune_blobs: false,
+    };
+    storage.prune(&[chain_1, chain_2], &policy).await?;
+    let mut snapshot = Vec::new();
+    let header = storage.export_chain(chain_1, &mut snapshot).await?;
+    assert_eq!(header.first_height, BlockHeight(1));
+    let target = storage_builder.build().await?;
+    target.write_blob(&committee_blob).await?;
+    target
+        .write_network_description(&network_description)
+        .await?;
+    target.import_chain(&snapshot[..]).await?;
+    assert_eq!(target.pruned_height(chain_1).await?, BlockHeight(1));
+    let chain = target.load_chain(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(2));
+    assert!(target.contains_certificate(certificate1.hash()).await?);
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn test_archived_chain_states() -> anyhow::Result<()> {
+    let mut storage_builder = MemoryStorageBuilder::default();
+    let mut signer = InMemorySigner::new(None);
+    let sender_public_key = signer.generate_new();
+    let sender_owner = sender_public_key.into();
+    let storage = storage_builder
+        .build()
+        .await?
+        .with_archived_chain_states(true);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = env.add_root_chain(2, sender_owner, Amount::ZERO).await.id();
+
+    let certificate0 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_public_key,
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(4),
+            vec![],
+        )
+        .await;
+    let certificate1 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_public_key,
+            chain_2,
+            Amount::from_tokens(2),
+            Vec::new(),
+            Amount::from_tokens(2),
+            vec![&certificate0],
+        )
+        .await;
+    env.worker()
+        .handle_confirmed_certificate(certificate0, None)
+        .await?;
+    env.worker()
+        .handle_confirmed_certificate(certificate1, None)
+        .await?;
+
+    let storage = &env.worker().storage;
+    for (height, balance) in [(0, 4), (1, 2)] {
+        let chain = storage
+            .load_chain_at_height(chain_1, BlockHeight(height))
+            .await?
+            .expect("the chain state should be archived");
+        assert_eq!(
+            chain.tip_state.get().next_block_height,
+            BlockHeight(height + 1)
+        );
+        assert_eq!(
+            *chain.execution_state.system.balance.get(),
+            Amount::from_tokens(balance)
+        );
+    }
+    // The state built when first loaded is loaded again from where it was kept.
+    let chain = storage
+        .load_chain_at_height(chain_1, BlockHeight(0))
+        .await?
+        .expect("the chain state should be archived");
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(4)
+    );
+    assert!(storage
+        .load_chain_at_height(chain_1, BlockHeight(2))
+        .await?
+        .is_none());
+    Ok(())
+}
+
+/// Injects a fault into the storage while a block is executed, then checks that the
+/// same chain worker can process the block and that the chain is saved consistently.
+#[test_case(
+    FaultRule::new(Fault::Error)
+        .with_operations([FaultyOperation::Write])
+        .with_key_prefix(vec![0, 2]),
+    true;
+    "error while writing the journal"
+)]
+#[test_case(
+    FaultRule::new(Fault::TornWrite)
+        .with_operations([FaultyOperation::Write])
+        .with_key_prefix(vec![0, 2]),
+    true;
+    "torn write of the journal"
+)]
+#[test_case(
+    FaultRule::new(Fault::Error).with_operations([FaultyOperation::Read]),
+    true;
+    "error while reading"
+)]
+#[test_case(
+    FaultRule::new(Fault::Latency(Duration::from_millis(10)))
+        .with_operations([FaultyOperation::Write]),
+    false;
+    "latency while writing"
+)]
+#[test_case(
+    FaultRule::new(Fault::Latency(Duration::from_millis(10)))
+        .with_operations([FaultyOperation::Read]),
+    false;
+    "latency while reading"
+)]
+#[test_log::test(tokio::test)]
+async fn test_recovery_from_storage_fault(rule: FaultRule, fails: bool) -> anyhow::Result<()> {
+    let config = FaultyMemoryDatabase::new_test_config().await?;
+    let injector = config.injector.clone();
+    let storage = DbStorage::<FaultyMemoryDatabase, _>::new_for_testing(
+        config,
+        &generate_test_namespace(),
+        None,
+        TestClock::new(),
+    )
+    .await?;
+    let mut signer = InMemorySigner::new(None);
+    let sender_public_key = signer.generate_new();
+    let sender_owner = sender_public_key.into();
+    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = env.add_root_chain(2, sender_owner, Amount::ZERO).await.id();
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_1_desc,
+            sender_public_key,
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(4),
+            vec![],
+        )
+        .await;
+
+    // The chain state is loaded by the worker before any fault is injected, so that the
+    // same `ChainWorkerState` handles the certificate twice. Only chain states are saved
+    // in batches large enough to be journaled. The keys of the journal's entries start
+    // with `[0, 2]`, and a journal whose entries are not all written has no effect.
+    drop(env.worker().chain_state_view(chain_1).await?);
+    injector.add_rule(rule.with_max_count(1));
+    let result = env
+        .worker()
+        .handle_confirmed_certificate(certificate.clone(), None)
+        .await;
+    assert_eq!(result.is_err(), fails);
+    assert_eq!(injector.injected_count(), 1);
+
+    if fails {
+        let chain = storage.load_chain(chain_1).await?;
+        assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(0));
+        assert_eq!(
+            *chain.execution_state.system.balance.get(),
+            Amount::from_tokens(5)
+        );
+        assert_eq!(chain.confirmed_log.count(), 0);
+        env.worker()
+            .handle_confirmed_certificate(certificate.clone(), None)
+            .await?;
+    }
+
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(1));
+    assert_eq!(chain.tip_state.get().block_hash, Some(certificate.hash()));
+    drop(chain);
+    let chain = storage.load_chain(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(1));
+    assert_eq!(chain.tip_state.get().block_hash, Some(certificate.hash()));
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(4)
+    );
+    assert_eq!(chain.confirmed_log.count(), 1);
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
@@ -3099,6 +3592,193 @@ where
     Ok(())
 }
 
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_report_misbehavior<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let owner = AccountSecretKey::generate().public().into();
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let admin_id = env.admin_id();
+    let admin_owner = AccountOwner::from(env.admin_public_key());
+    let validator = env.worker().public_key();
+    let key_pair = env.worker().chain_worker_config.key_pair().unwrap();
+
+    // The validator signs validated votes for two different blocks at the same height, in the
+    // same round.
+    let mut blocks = Vec::new();
+    let mut votes = Vec::new();
+    for amount in [Amount::ONE, Amount::from_tokens(2)] {
+        let proposed_block = make_first_block(chain_1)
+            .with_simple_transfer(admin_id, amount)
+            .with_authenticated_signer(Some(owner));
+        let (block, _) = env
+            .worker()
+            .stage_block_execution(proposed_block, None, vec![])
+            .await?;
+        let value = ValidatedBlock::new(block.clone());
+        votes.push(LiteVote::new(
+            LiteValue::new(&value),
+            Round::MultiLeader(0),
+            key_pair,
+        ));
+        blocks.push(block);
+    }
+    assert!(votes[0]
+        .misbehavior_evidence(&blocks[0], &votes[0], &blocks[0], validator)
+        .is_none());
+    let evidence = votes[0]
+        .misbehavior_evidence(&blocks[0], &votes[1], &blocks[1], validator)
+        .unwrap();
+    assert_eq!(evidence.verify()?, (chain_1, BlockHeight::ZERO));
+
+    // The admin chain registers the validator with some stake, and then receives the
+    // evidence.
+    let report = SystemOperation::ReportMisbehavior(Box::new(evidence));
+    let registration = ValidatorRegistration {
+        admin_id,
+        account_public_key: env.admin_public_key(),
+        network_address: "validator".to_string(),
+    };
+    let signature = ValidatorSignature::new(&registration, key_pair);
+    let proposed_block = make_first_block(admin_id)
+        .with_operation(SystemOperation::RegisterValidator {
+            public_key: validator,
+            account_public_key: registration.account_public_key,
+            network_address: registration.network_address,
+            signature,
+        })
+        .with_operation(SystemOperation::Stake {
+            owner: AccountOwner::CHAIN,
+            validator,
+            amount: Amount::from_tokens(10),
+        })
+        .with_operation(report.clone())
+        .with_authenticated_signer(Some(admin_owner));
+    let (block, _) = env
+        .worker()
+        .stage_block_execution(proposed_block, None, vec![])
+        .await?;
+    let value = ConfirmedBlock::new(block);
+    env.worker()
+        .fully_handle_certificate_with_notifications(env.make_certificate(value.clone()), &())
+        .await?;
+    {
+        let admin_chain = env.worker().chain_state_view(admin_id).await?;
+        let system = &admin_chain.execution_state.system;
+        assert!(system.excluded_validators.contains(&validator).await?);
+        // The default policy doesn't slash any stake.
+        assert_eq!(
+            system
+                .staking_validators
+                .get(&validator)
+                .await?
+                .unwrap()
+                .stake,
+            Amount::from_tokens(10)
+        );
+    }
+
+    // The same misbehavior cannot be reported twice.
+    let proposed_block = make_child_block(&value).with_operation(report);
+    let result = env
+        .worker()
+        .stage_block_execution(proposed_block, None, vec![])
+        .await;
+    assert_matches!(result, Err(WorkerError::ChainError(error)) if matches!(&*error,
+        ChainError::ExecutionError(error, _) if matches!(&**error,
+        ExecutionError::MisbehaviorAlreadyReported(public_key) if *public_key == validator
+    )));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_scheduled_operation<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let owner = AccountSecretKey::generate().public().into();
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let admin_id = env.admin_id();
+
+    // The owner schedules a transfer from the chain balance.
+    let due = Timestamp::from(1_000);
+    let transfer = Operation::system(SystemOperation::Transfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Account::chain(admin_id),
+        amount: Amount::ONE,
+    });
+    let proposed_block = make_first_block(chain_1)
+        .with_operation(SystemOperation::ScheduleOperation {
+            operation: Box::new(transfer),
+            due,
+            interval: None,
+        })
+        .with_authenticated_signer(Some(owner));
+    let (block, _) = env
+        .worker()
+        .stage_block_execution(proposed_block, None, vec![])
+        .await?;
+    let value = ConfirmedBlock::new(block);
+    env.worker()
+        .fully_handle_certificate_with_notifications(env.make_certificate(value.clone()), &())
+        .await?;
+
+    // The transfer cannot be executed before it is due.
+    let execute = SystemOperation::ExecuteScheduledOperation { id: 0 };
+    let proposed_block = make_child_block(&value)
+        .with_operation(execute.clone())
+        .with_timestamp(due.saturating_sub_micros(1));
+    let result = env
+        .worker()
+        .stage_block_execution(proposed_block, None, vec![])
+        .await;
+    assert_matches!(result, Err(WorkerError::ChainError(error)) if matches!(&*error,
+        ChainError::ExecutionError(error, _) if matches!(&**error,
+        ExecutionError::ScheduledOperationNotDue { id: 0, .. }
+    )));
+
+    // Once it is due, it is executed with the owner's authority, even though the block has
+    // no authenticated signer.
+    let proposed_block = make_child_block(&value)
+        .with_operation(execute)
+        .with_authenticated_signer(None)
+        .with_timestamp(due);
+    let (block, _) = env
+        .worker()
+        .stage_block_execution(proposed_block, None, vec![])
+        .await?;
+    env.worker()
+        .fully_handle_certificate_with_notifications(
+            env.make_certificate(ConfirmedBlock::new(block)),
+            &(),
+        )
+        .await?;
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    let system = &chain.execution_state.system;
+    assert_eq!(*system.balance.get(), Amount::ONE);
+    assert!(system.scheduled_operations.indices().await?.is_empty());
+    Ok(())
+}
+
 #[test(tokio::test)]
 async fn test_cross_chain_helper() -> anyhow::Result<()> {
     let store_config = MemoryDatabase::new_test_config().await?;
@@ -223,6 +223,11 @@ pub enum WorkerError {
         height: BlockHeight,
         chain_id: ChainId,
     },
+    #[error("the certificate at height {height} of chain {chain_id:8} was pruned")]
+    CertificatePruned {
+        height: BlockHeight,
+        chain_id: ChainId,
+    },
     #[error("preprocessed_blocks entry at height {height} for chain {chain_id:8} not found")]
     PreprocessedBlocksEntryNotFound {
         height: BlockHeight,
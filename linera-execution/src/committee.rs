@@ -4,10 +4,13 @@
 
 use std::{borrow::Cow, collections::BTreeMap, str::FromStr};
 
-use linera_base::crypto::{AccountPublicKey, CryptoError, ValidatorPublicKey};
+use linera_base::{
+    crypto::{AccountPublicKey, CryptoError, ValidatorPublicKey},
+    data_types::Amount,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::policy::ResourceControlPolicy;
+use crate::policy::{ResourceControlPolicy, ResourceControlPolicyV0};
 
 /// The identity of a validator.
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
@@ -118,6 +121,21 @@ struct CommitteeMinimal<'a> {
     policy: Cow<'a, ResourceControlPolicy>,
 }
 
+/// The layout of the committees created before the fee sink, the unbonding delay and the
+/// slashing percentage were added to the [`ResourceControlPolicy`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitteeV0 {
+    validators: BTreeMap<ValidatorPublicKey, ValidatorState>,
+    policy: ResourceControlPolicyV0,
+}
+
+impl From<CommitteeV0> for Committee {
+    fn from(committee: CommitteeV0) -> Committee {
+        let CommitteeV0 { validators, policy } = committee;
+        Committee::new(validators, policy.into())
+    }
+}
+
 impl TryFrom<CommitteeFull<'static>> for Committee {
     type Error = String;
 
@@ -235,6 +253,18 @@ impl Committee {
         }
     }
 
+    /// Deserializes the content of a committee blob. Committees created before the last
+    /// change of the [`ResourceControlPolicy`] are read with their former layout.
+    pub fn from_blob_bytes(bytes: &[u8]) -> Result<Self, bcs::Error> {
+        // The layouts only differ by the fields at the end of the policy, so the bytes
+        // are never valid for both.
+        bcs::from_bytes(bytes).or_else(|error| {
+            bcs::from_bytes::<CommitteeV0>(bytes)
+                .map(Committee::from)
+                .map_err(|_| error)
+        })
+    }
+
     #[cfg(with_testing)]
     pub fn make_simple(keys: Vec<(ValidatorPublicKey, AccountPublicKey)>) -> Self {
         let map = keys
@@ -272,6 +302,36 @@ impl Committee {
             .map(|validator| (validator.account_public_key, validator.votes))
     }
 
+    /// Splits an amount among the validators' accounts, in proportion to their votes. The
+    /// remainder of the division is added to the first share.
+    pub fn split_by_votes(&self, amount: Amount) -> Vec<(AccountPublicKey, Amount)> {
+        let total_votes = u128::from(self.total_votes);
+        if total_votes == 0 {
+            return Vec::new();
+        }
+        let attos = u128::from(amount);
+        let mut remainder = attos;
+        let mut shares = self
+            .account_keys_and_weights()
+            .map(|(account_public_key, votes)| {
+                let votes = u128::from(votes);
+                let share = match attos.checked_mul(votes) {
+                    Some(product) => product / total_votes,
+                    None => attos / total_votes * votes,
+                };
+                remainder -= share;
+                (account_public_key, share)
+            })
+            .collect::<Vec<_>>();
+        if let Some((_, share)) = shares.first_mut() {
+            *share += remainder;
+        }
+        shares
+            .into_iter()
+            .map(|(account_public_key, share)| (account_public_key, Amount::from_attos(share)))
+            .collect()
+    }
+
     pub fn network_address(&self, author: &ValidatorPublicKey) -> Option<&str> {
         self.validators
             .get(author)
@@ -0,0 +1,89 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Evidence that a validator signed conflicting votes.
+
+use custom_debug_derive::Debug;
+use linera_base::{
+    crypto::{CryptoHash, ValidatorPublicKey, ValidatorSignature},
+    data_types::{BlockHeight, CertificateKind, Epoch, Round, VoteValue},
+    ensure, hex_debug,
+    identifiers::ChainId,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::ExecutionError;
+
+/// The type name under which blocks are hashed.
+const BLOCK_TYPE_NAME: &str = "Block";
+
+/// The size of the chain ID, epoch and height at the start of a serialized block.
+const BLOCK_POSITION_SIZE: usize = 32 + 4 + 8;
+
+/// A validator's vote for a block, as included in misbehavior evidence.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct BlockVote {
+    /// The BCS serialization of the block.
+    #[serde(with = "serde_bytes")]
+    #[debug(with = "hex_debug")]
+    pub block: Vec<u8>,
+    /// The validator's signature of the vote.
+    pub signature: ValidatorSignature,
+}
+
+impl BlockVote {
+    /// Returns the hash of the block, and the chain and height it was proposed at.
+    fn block_position(&self) -> Result<(CryptoHash, ChainId, BlockHeight), ExecutionError> {
+        let prefix = self.block.get(..BLOCK_POSITION_SIZE).ok_or(
+            ExecutionError::InvalidMisbehaviorEvidence("the block is truncated"),
+        )?;
+        let (chain_id, _, height) = bcs::from_bytes::<(ChainId, Epoch, BlockHeight)>(prefix)?;
+        let hash = CryptoHash::from_bcs_bytes(BLOCK_TYPE_NAME, &self.block);
+        Ok((hash, chain_id, height))
+    }
+}
+
+/// Evidence that a validator equivocated: it signed two votes of the same kind and in the
+/// same round, for different blocks at the same height of a chain.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct MisbehaviorEvidence {
+    /// The validator that signed both votes.
+    pub validator: ValidatorPublicKey,
+    /// The round of both votes.
+    pub round: Round,
+    /// The kind of both votes.
+    pub kind: CertificateKind,
+    /// The first vote.
+    pub first: BlockVote,
+    /// The second vote.
+    pub second: BlockVote,
+}
+
+impl MisbehaviorEvidence {
+    /// Verifies the evidence. Returns the chain and the height at which the validator signed
+    /// the conflicting votes.
+    pub fn verify(&self) -> Result<(ChainId, BlockHeight), ExecutionError> {
+        ensure!(
+            self.kind != CertificateKind::Timeout,
+            ExecutionError::InvalidMisbehaviorEvidence("timeout votes cannot conflict")
+        );
+        let (first_hash, chain_id, height) = self.first.block_position()?;
+        let (second_hash, second_chain_id, second_height) = self.second.block_position()?;
+        ensure!(
+            chain_id == second_chain_id && height == second_height,
+            ExecutionError::InvalidMisbehaviorEvidence(
+                "the blocks are not at the same height of the same chain"
+            )
+        );
+        ensure!(
+            first_hash != second_hash,
+            ExecutionError::InvalidMisbehaviorEvidence("the votes are for the same block")
+        );
+        for (hash, vote) in [(first_hash, &self.first), (second_hash, &self.second)] {
+            vote.signature
+                .check(&VoteValue(hash, self.round, self.kind), self.validator)
+                .map_err(|_| ExecutionError::InvalidMisbehaviorEvidence("invalid signature"))?;
+        }
+        Ok((chain_id, height))
+    }
+}
@@ -26,13 +26,13 @@ use reqwest::{header::HeaderMap, Client, Url};
 use crate::{
     execution::UserAction,
     runtime::ContractSyncRuntime,
-    system::{CreateApplicationResult, OpenChainConfig},
+    system::{CreateApplicationResult, OpenChainConfig, ScheduledOperation},
     util::{OracleResponseExt as _, RespondExt as _},
     ApplicationDescription, ApplicationId, ExecutionError, ExecutionRuntimeConfig,
     ExecutionRuntimeContext, ExecutionStateView, Message, MessageContext, MessageKind, ModuleId,
     Operation, OperationContext, OutgoingMessage, ProcessStreamsContext, QueryContext,
-    QueryOutcome, ResourceController, SystemMessage, TransactionTracker, UserContractCode,
-    UserServiceCode,
+    QueryOutcome, ResourceController, SystemMessage, SystemOperation, TransactionTracker,
+    UserContractCode, UserServiceCode,
 };
 
 /// Actor for handling requests to the execution state.
@@ -341,6 +341,13 @@ where
                 }
             }
 
+            ScheduleOperation {
+                scheduled,
+                callback,
+            } => {
+                callback.respond(self.state.system.schedule_operation(scheduled).await);
+            }
+
             ChangeApplicationPermissions {
                 application_id,
                 application_permissions,
@@ -799,6 +806,23 @@ where
         operation: Operation,
     ) -> Result<(), ExecutionError> {
         assert_eq!(context.chain_id, self.state.context().extra().chain_id());
+        // Scheduled operations are executed with the signer they were scheduled with. They
+        // cannot be `ExecuteScheduledOperation` themselves.
+        let (context, operation) = match operation.as_system_operation() {
+            Some(SystemOperation::ExecuteScheduledOperation { id }) => {
+                let (operation, authenticated_signer) = self
+                    .state
+                    .system
+                    .take_due_operation(*id, context.timestamp)
+                    .await?;
+                let context = OperationContext {
+                    authenticated_signer,
+                    ..context
+                };
+                (context, operation)
+            }
+            _ => (context, operation),
+        };
         match operation {
             Operation::System(op) => {
                 let new_application = self
@@ -1106,6 +1130,12 @@ pub enum ExecutionRequest {
         callback: Sender<Result<(), ExecutionError>>,
     },
 
+    ScheduleOperation {
+        scheduled: ScheduledOperation,
+        #[debug(skip)]
+        callback: Sender<Result<u64, ExecutionError>>,
+    },
+
     ChangeApplicationPermissions {
         application_id: ApplicationId,
         application_permissions: ApplicationPermissions,
@@ -7,7 +7,7 @@ use linera_base::{
     crypto::ValidatorPublicKey,
     data_types::{Amount, ChainDescription, Epoch, Timestamp},
     doc_scalar,
-    identifiers::{AccountOwner, ChainId},
+    identifiers::{Account, AccountOwner, ChainId},
     ownership::ChainOwnership,
 };
 use linera_views::{context::Context, map_view::MapView};
@@ -15,7 +15,7 @@ use linera_views::{context::Context, map_view::MapView};
 use crate::{
     committee::{Committee, ValidatorState},
     policy::ResourceControlPolicy,
-    system::UserData,
+    system::{LockedTransfer, StakingValidator, UserData},
     ExecutionStateView, SystemExecutionStateView,
 };
 
@@ -104,4 +104,66 @@ impl<C: Send + Sync + Context> SystemExecutionStateView<C> {
     async fn _timestamp(&self) -> &Timestamp {
         self.timestamp.get()
     }
+
+    #[graphql(derived(name = "staking_validators"))]
+    async fn _staking_validators(
+        &self,
+    ) -> async_graphql::Result<BTreeMap<ValidatorPublicKey, StakingValidator>> {
+        Ok(self
+            .staking_validators
+            .index_values()
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    #[graphql(derived(name = "stakes"))]
+    async fn _stakes(
+        &self,
+    ) -> async_graphql::Result<BTreeMap<ValidatorPublicKey, BTreeMap<String, Amount>>> {
+        let mut stakes = BTreeMap::new();
+        for validator in self.stakes.indices().await? {
+            let Some(delegators) = self.stakes.try_load_entry(&validator).await? else {
+                continue;
+            };
+            let delegators = delegators
+                .index_values()
+                .await?
+                .into_iter()
+                .map(|(delegator, amount)| (delegator.to_string(), amount))
+                .collect();
+            stakes.insert(validator, delegators);
+        }
+        Ok(stakes)
+    }
+
+    #[graphql(derived(name = "unbonding"))]
+    async fn _unbonding(
+        &self,
+    ) -> async_graphql::Result<BTreeMap<Epoch, Vec<(ValidatorPublicKey, Account, Amount)>>> {
+        let mut unbonding = BTreeMap::<_, Vec<_>>::new();
+        for validator in self.unbonding.indices().await? {
+            let Some(entries) = self.unbonding.try_load_entry(&validator).await? else {
+                continue;
+            };
+            for (epoch, entries) in entries.index_values().await? {
+                unbonding.entry(epoch).or_default().extend(
+                    entries
+                        .into_iter()
+                        .map(|(delegator, amount)| (validator, delegator, amount)),
+                );
+            }
+        }
+        Ok(unbonding)
+    }
+
+    #[graphql(derived(name = "locked_transfers"))]
+    async fn _locked_transfers(&self) -> async_graphql::Result<BTreeMap<u64, LockedTransfer>> {
+        Ok(self
+            .locked_transfers
+            .index_values()
+            .await?
+            .into_iter()
+            .collect())
+    }
 }
@@ -5,6 +5,7 @@
 //! Linera chain.
 
 pub mod committee;
+pub mod evidence;
 pub mod evm;
 mod execution;
 pub mod execution_state_actor;
@@ -30,11 +31,11 @@ use derive_more::Display;
 use js_sys::wasm_bindgen::JsValue;
 use linera_base::{
     abi::Abi,
-    crypto::{BcsHashable, CryptoHash},
+    crypto::{BcsHashable, CryptoHash, ValidatorPublicKey},
     data_types::{
         Amount, ApplicationDescription, ApplicationPermissions, ArithmeticError, Blob, BlockHeight,
         Bytecode, DecompressionError, Epoch, NetworkDescription, SendMessageRequest, StreamUpdate,
-        Timestamp,
+        TimeDelta, Timestamp,
     },
     doc_scalar, hex_debug, http,
     identifiers::{
@@ -65,14 +66,15 @@ pub use crate::{
     committee::Committee,
     execution::{ExecutionStateView, ServiceRuntimeEndpoint},
     execution_state_actor::{ExecutionRequest, ExecutionStateActor},
-    policy::ResourceControlPolicy,
+    policy::{FeeSink, ResourceControlPolicy},
     resources::{BalanceHolder, ResourceController, ResourceTracker},
     runtime::{
         ContractSyncRuntimeHandle, ServiceRuntimeRequest, ServiceSyncRuntime,
         ServiceSyncRuntimeHandle,
     },
     system::{
-        SystemExecutionStateView, SystemMessage, SystemOperation, SystemQuery, SystemResponse,
+        LockedTransfer, ScheduledOperation, StakingValidator, SystemExecutionStateView,
+        SystemMessage, SystemOperation, SystemQuery, SystemResponse, ValidatorRegistration,
     },
     transaction_tracker::{TransactionOutcome, TransactionTracker},
 };
@@ -320,6 +322,48 @@ pub enum ExecutionError {
     InternalError(&'static str),
     #[error("UpdateStreams is outdated")]
     OutdatedUpdateStreams,
+    #[error("Staking is only possible on the admin chain")]
+    StakingOnNonAdminChain,
+    #[error("Stake must have positive amount")]
+    IncorrectStakeAmount,
+    #[error("Staking must be authenticated by the right signer")]
+    UnauthenticatedStakeOwner,
+    #[error("Validator {0} is not registered for staking")]
+    UnknownStakingValidator(ValidatorPublicKey),
+    #[error("Validator {0} is registered for staking with a different account")]
+    StakingValidatorAccountMismatch(ValidatorPublicKey),
+    #[error("Invalid signature of the registration of validator {0}")]
+    InvalidValidatorRegistration(ValidatorPublicKey),
+    #[error("Cannot unstake {amount}: only {stake} is staked")]
+    InsufficientStake { stake: Amount, amount: Amount },
+    #[error("No validator has enough stake to be elected")]
+    NoStakedValidators,
+    #[error("Invalid misbehavior evidence: {0}")]
+    InvalidMisbehaviorEvidence(&'static str),
+    #[error("This misbehavior of validator {0} was already reported")]
+    MisbehaviorAlreadyReported(ValidatorPublicKey),
+    #[error("Validator {0} is neither registered for staking nor in the current committee")]
+    UnknownMisbehavingValidator(ValidatorPublicKey),
+    #[error("Scheduling operations must be authenticated by a chain owner")]
+    UnauthorizedScheduledOperation,
+    #[error("Invalid scheduled operation: {0}")]
+    InvalidScheduledOperation(&'static str),
+    #[error("There is no scheduled operation with ID {0}")]
+    ScheduledOperationNotFound(u64),
+    #[error("A chain cannot have more than {0} scheduled operations")]
+    TooManyScheduledOperations(usize),
+    #[error("Scheduled operation {id} is not due before {due}")]
+    ScheduledOperationNotDue { id: u64, due: Timestamp },
+    #[error("Operations of {0} are not allowed on this chain")]
+    UnauthorizedScheduledApplication(GenericApplicationId),
+    #[error("There is no locked transfer with ID {0}")]
+    LockedTransferNotFound(u64),
+    #[error("The locked transfer expired at {0}")]
+    LockedTransferExpired(Timestamp),
+    #[error("The locked transfer cannot be refunded before {0}")]
+    LockedTransferNotExpired(Timestamp),
+    #[error("The preimage does not match the hash lock of the locked transfer")]
+    InvalidLockedTransferPreimage,
 }
 
 /// The public entry points provided by the contract part of an application.
@@ -782,6 +826,16 @@ pub trait ContractRuntime: BaseRuntime {
     /// Closes the current chain.
     fn close_chain(&mut self) -> Result<(), ExecutionError>;
 
+    /// Schedules an operation of the current application to be executed on the current
+    /// chain once `due` is reached, and then again after every `interval`, if any. Returns
+    /// the ID of the scheduled operation.
+    fn schedule_operation(
+        &mut self,
+        due: Timestamp,
+        interval: Option<TimeDelta>,
+        operation: Vec<u8>,
+    ) -> Result<u64, ExecutionError>;
+
     /// Changes the application permissions on the current chain.
     fn change_application_permissions(
         &mut self,
@@ -1096,7 +1150,7 @@ impl ExecutionRuntimeContext for TestExecutionRuntimeContext {
             .ok_or_else(|| ViewError::NotFound("committee not found".to_owned()))?
             .bytes()
             .to_vec();
-        let committee: Committee = bcs::from_bytes(&committee_blob_bytes)?;
+        let committee = Committee::from_blob_bytes(&committee_blob_bytes)?;
         // TODO(#4146): this currently assigns the first found committee to all epochs,
         // which should be fine for the tests we have at the moment, but might not be in
         // the future.
@@ -1213,6 +1267,8 @@ impl Operation {
             SystemOperation::ProcessNewEpoch(_)
                 | SystemOperation::ProcessRemovedEpoch(_)
                 | SystemOperation::UpdateStreams(_)
+                | SystemOperation::ExecuteScheduledOperation { .. }
+                | SystemOperation::SkipScheduledOperation { .. }
         )
     }
 }
@@ -9,12 +9,12 @@
 //! It also sets overarching limits such as the maximum fuel allowed per block,
 //! the maximum block size, and limits on concurrent operations.
 
-use std::{collections::BTreeSet, fmt};
+use std::{collections::BTreeSet, fmt, str::FromStr};
 
 use linera_base::{
     data_types::{Amount, ArithmeticError, BlobContent, CompressedBytecode, Resources},
     ensure,
-    identifiers::BlobType,
+    identifiers::{AccountOwner, BlobType},
     vm::VmRuntime,
 };
 use serde::{Deserialize, Serialize};
@@ -93,6 +93,170 @@ pub struct ResourceControlPolicy {
     pub http_request_timeout_ms: u64,
     /// The list of hosts that contracts and services can send HTTP requests to.
     pub http_request_allow_list: BTreeSet<String>,
+    /// Where the fees paid for executing blocks go.
+    pub fee_sink: FeeSink,
+    /// The number of epochs that unstaked tokens stay bonded, after the end of the epoch
+    /// in which they are unstaked.
+    pub unbonding_epochs: u32,
+    /// The percentage of the tokens staked with a validator that is burned when the
+    /// validator is proven to have signed conflicting votes.
+    pub slashing_percentage: u8,
+}
+
+/// The layout of [`ResourceControlPolicy`] before the fee sink, the unbonding delay and the
+/// slashing percentage were added. BCS has no default values, so the committees created
+/// before then must be read with this layout.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResourceControlPolicyV0 {
+    wasm_fuel_unit: Amount,
+    evm_fuel_unit: Amount,
+    read_operation: Amount,
+    write_operation: Amount,
+    byte_runtime: Amount,
+    byte_read: Amount,
+    byte_written: Amount,
+    blob_read: Amount,
+    blob_published: Amount,
+    blob_byte_read: Amount,
+    blob_byte_published: Amount,
+    byte_stored: Amount,
+    operation: Amount,
+    operation_byte: Amount,
+    message: Amount,
+    message_byte: Amount,
+    service_as_oracle_query: Amount,
+    http_request: Amount,
+    maximum_wasm_fuel_per_block: u64,
+    maximum_evm_fuel_per_block: u64,
+    maximum_service_oracle_execution_ms: u64,
+    maximum_block_size: u64,
+    maximum_bytecode_size: u64,
+    maximum_blob_size: u64,
+    maximum_published_blobs: u64,
+    maximum_block_proposal_size: u64,
+    maximum_bytes_read_per_block: u64,
+    maximum_bytes_written_per_block: u64,
+    maximum_oracle_response_bytes: u64,
+    maximum_http_response_bytes: u64,
+    http_request_timeout_ms: u64,
+    http_request_allow_list: BTreeSet<String>,
+}
+
+impl From<ResourceControlPolicyV0> for ResourceControlPolicy {
+    fn from(policy: ResourceControlPolicyV0) -> Self {
+        let ResourceControlPolicyV0 {
+            wasm_fuel_unit,
+            evm_fuel_unit,
+            read_operation,
+            write_operation,
+            byte_runtime,
+            byte_read,
+            byte_written,
+            blob_read,
+            blob_published,
+            blob_byte_read,
+            blob_byte_published,
+            byte_stored,
+            operation,
+            operation_byte,
+            message,
+            message_byte,
+            service_as_oracle_query,
+            http_request,
+            maximum_wasm_fuel_per_block,
+            maximum_evm_fuel_per_block,
+            maximum_service_oracle_execution_ms,
+            maximum_block_size,
+            maximum_bytecode_size,
+            maximum_blob_size,
+            maximum_published_blobs,
+            maximum_block_proposal_size,
+            maximum_bytes_read_per_block,
+            maximum_bytes_written_per_block,
+            maximum_oracle_response_bytes,
+            maximum_http_response_bytes,
+            http_request_timeout_ms,
+            http_request_allow_list,
+        } = policy;
+        ResourceControlPolicy {
+            wasm_fuel_unit,
+            evm_fuel_unit,
+            read_operation,
+            write_operation,
+            byte_runtime,
+            byte_read,
+            byte_written,
+            blob_read,
+            blob_published,
+            blob_byte_read,
+            blob_byte_published,
+            byte_stored,
+            operation,
+            operation_byte,
+            message,
+            message_byte,
+            service_as_oracle_query,
+            http_request,
+            maximum_wasm_fuel_per_block,
+            maximum_evm_fuel_per_block,
+            maximum_service_oracle_execution_ms,
+            maximum_block_size,
+            maximum_bytecode_size,
+            maximum_blob_size,
+            maximum_published_blobs,
+            maximum_block_proposal_size,
+            maximum_bytes_read_per_block,
+            maximum_bytes_written_per_block,
+            maximum_oracle_response_bytes,
+            maximum_http_response_bytes,
+            http_request_timeout_ms,
+            http_request_allow_list,
+            fee_sink: FeeSink::Burn,
+            unbonding_epochs: 0,
+            slashing_percentage: 0,
+        }
+    }
+}
+
+/// Where the fees paid for executing blocks go.
+///
+/// Fees that do not burn accrue in the state of the chain paying them, and are credited
+/// to the accounts on the admin chain when the chain changes epoch.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum FeeSink {
+    /// The fees are burnt.
+    #[default]
+    Burn,
+    /// The fees accrue to the given account on the admin chain.
+    AdminAccount(AccountOwner),
+    /// The fees are shared among the validators of the current committee, in proportion
+    /// to their votes, and accrue to their accounts on the admin chain.
+    Validators,
+}
+
+impl fmt::Display for FeeSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeSink::Burn => write!(f, "burnt"),
+            FeeSink::AdminAccount(owner) => {
+                write!(f, "paid to {owner} on the admin chain at epoch changes")
+            }
+            FeeSink::Validators => write!(f, "shared among the validators at epoch changes"),
+        }
+    }
+}
+
+impl FromStr for FeeSink {
+    type Err = anyhow::Error;
+
+    /// Parses `burn`, `validators`, or the owner of an account on the admin chain.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "burn" => Ok(FeeSink::Burn),
+            "validators" => Ok(FeeSink::Validators),
+            owner => Ok(FeeSink::AdminAccount(owner.parse()?)),
+        }
+    }
 }
 
 impl fmt::Display for ResourceControlPolicy {
@@ -130,6 +294,9 @@ impl fmt::Display for ResourceControlPolicy {
             maximum_http_response_bytes,
             http_request_allow_list,
             http_request_timeout_ms,
+            fee_sink,
+            unbonding_epochs,
+            slashing_percentage,
         } = self;
         write!(
             f,
@@ -166,7 +333,10 @@ impl fmt::Display for ResourceControlPolicy {
             {maximum_oracle_response_bytes} maximum number of bytes of an oracle response\n\
             {maximum_http_response_bytes} maximum number of bytes of an HTTP response\n\
             {http_request_timeout_ms} ms timeout for HTTP requests\n\
-            HTTP hosts allowed for contracts and services: {http_request_allow_list:#?}\n",
+            HTTP hosts allowed for contracts and services: {http_request_allow_list:#?}\n\
+            Fees are {fee_sink}\n\
+            {unbonding_epochs} epochs of unbonding delay for unstaked tokens\n\
+            {slashing_percentage}% of the stakes slashed for conflicting votes\n",
         )?;
         Ok(())
     }
@@ -216,6 +386,9 @@ impl ResourceControlPolicy {
             maximum_http_response_bytes: u64::MAX,
             http_request_timeout_ms: u64::MAX,
             http_request_allow_list: BTreeSet::new(),
+            fee_sink: FeeSink::Burn,
+            unbonding_epochs: 0,
+            slashing_percentage: 0,
         }
     }
 
@@ -295,6 +468,9 @@ impl ResourceControlPolicy {
             maximum_http_response_bytes: 10_000,
             http_request_timeout_ms: 20_000,
             http_request_allow_list: BTreeSet::new(),
+            fee_sink: FeeSink::Burn,
+            unbonding_epochs: 1,
+            slashing_percentage: 10,
         }
     }
 
@@ -158,6 +158,8 @@ pub struct ResourceTracker {
     pub service_oracle_execution: Duration,
     /// The amount allocated to message grants.
     pub grants: Amount,
+    /// The fees charged so far.
+    pub fees: Amount,
 }
 
 impl ResourceTracker {
@@ -208,12 +210,19 @@ where
         Ok(())
     }
 
-    /// Subtracts an amount from a balance and reports an error if that is impossible.
+    /// Charges fees to the account and reports an error if that is impossible.
     fn update_balance(&mut self, fees: Amount) -> Result<(), ExecutionError> {
+        self.debit(fees)?;
+        self.tracker.as_mut().fees.try_add_assign(fees)?;
+        Ok(())
+    }
+
+    /// Subtracts an amount from a balance and reports an error if that is impossible.
+    fn debit(&mut self, amount: Amount) -> Result<(), ExecutionError> {
         self.account
-            .try_sub_assign(fees)
+            .try_sub_assign(amount)
             .map_err(|_| ExecutionError::FeesExceedFunding {
-                fees,
+                fees: amount,
                 balance: self.balance().unwrap_or(Amount::MAX),
             })?;
         Ok(())
@@ -232,7 +241,7 @@ where
     /// Tracks the allocation of a grant.
     pub fn track_grant(&mut self, grant: Amount) -> Result<(), ExecutionError> {
         self.tracker.as_mut().grants.try_add_assign(grant)?;
-        self.update_balance(grant)
+        self.debit(grant)
     }
 
     /// Tracks the execution of an operation in block.
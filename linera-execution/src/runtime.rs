@@ -12,7 +12,7 @@ use custom_debug_derive::Debug;
 use linera_base::{
     data_types::{
         Amount, ApplicationPermissions, ArithmeticError, Blob, BlockHeight, Bytecode,
-        SendMessageRequest, Timestamp,
+        SendMessageRequest, TimeDelta, Timestamp,
     },
     ensure, http,
     identifiers::{
@@ -29,7 +29,7 @@ use crate::{
     execution::UserAction,
     execution_state_actor::{ExecutionRequest, ExecutionStateSender},
     resources::ResourceController,
-    system::CreateApplicationResult,
+    system::{CreateApplicationResult, ScheduledOperation},
     util::{ReceiverExt, UnboundedSenderExt},
     ApplicationDescription, ApplicationId, BaseRuntime, ContractRuntime, DataBlobHash,
     ExecutionError, FinalizeContext, Message, MessageContext, MessageKind, ModuleId, Operation,
@@ -1428,6 +1428,34 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
             .recv_response()?
     }
 
+    fn schedule_operation(
+        &mut self,
+        due: Timestamp,
+        interval: Option<TimeDelta>,
+        operation: Vec<u8>,
+    ) -> Result<u64, ExecutionError> {
+        let mut this = self.inner();
+        // The scheduled operation is stored until it is executed or skipped.
+        this.resource_controller.track_write_operations(1)?;
+        this.resource_controller
+            .track_bytes_written(operation.len() as u64)?;
+        let scheduled = ScheduledOperation {
+            operation: Operation::User {
+                application_id: this.current_application().id,
+                bytes: operation,
+            },
+            authenticated_signer: None,
+            due,
+            interval,
+        };
+        this.execution_state_sender
+            .send_request(|callback| ExecutionRequest::ScheduleOperation {
+                scheduled,
+                callback,
+            })?
+            .recv_response()?
+    }
+
     fn change_application_permissions(
         &mut self,
         application_permissions: ApplicationPermissions,
@@ -10,36 +10,45 @@ use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use custom_debug_derive::Debug;
 use linera_base::{
-    crypto::CryptoHash,
+    crypto::{AccountPublicKey, BcsSignable, CryptoHash, ValidatorPublicKey, ValidatorSignature},
     data_types::{
         Amount, ApplicationPermissions, ArithmeticError, Blob, BlobContent, BlockHeight,
-        ChainDescription, ChainOrigin, Epoch, InitialChainConfig, OracleResponse, Timestamp,
+        ChainDescription, ChainOrigin, Epoch, InitialChainConfig, OracleResponse, TimeDelta,
+        Timestamp,
     },
     ensure, hex_debug,
     identifiers::{Account, AccountOwner, BlobId, BlobType, ChainId, EventId, ModuleId, StreamId},
     ownership::{ChainOwnership, TimeoutConfig},
 };
 use linera_views::{
+    collection_view::HashedCollectionView,
     context::Context,
     map_view::HashedMapView,
     register_view::HashedRegisterView,
     set_view::HashedSetView,
     views::{ClonableView, HashableView, ReplaceContext, View},
+    ViewError,
 };
 use serde::{Deserialize, Serialize};
 
 #[cfg(test)]
 use crate::test_utils::SystemExecutionState;
 use crate::{
-    committee::Committee, util::OracleResponseExt as _, ApplicationDescription, ApplicationId,
-    ExecutionError, ExecutionRuntimeContext, MessageContext, MessageKind, OperationContext,
-    OutgoingMessage, QueryContext, QueryOutcome, ResourceController, TransactionTracker,
+    committee::{Committee, CommitteeV0, ValidatorState},
+    evidence::MisbehaviorEvidence,
+    policy::FeeSink,
+    util::OracleResponseExt as _,
+    ApplicationDescription, ApplicationId, ExecutionError, ExecutionRuntimeContext, MessageContext,
+    MessageKind, Operation, OperationContext, OutgoingMessage, QueryContext, QueryOutcome,
+    ResourceController, TransactionTracker,
 };
 
 /// The event stream name for new epochs and committees.
 pub static EPOCH_STREAM_NAME: &[u8] = &[0];
 /// The event stream name for removed epochs.
 pub static REMOVED_EPOCH_STREAM_NAME: &[u8] = &[1];
+/// The maximum number of operations that can be scheduled on a chain at the same time.
+pub const MAX_SCHEDULED_OPERATIONS: usize = 100;
 
 /// The number of times the [`SystemOperation::OpenChain`] was executed.
 #[cfg(with_metrics)]
@@ -60,6 +69,7 @@ mod metrics {
 
 /// A view accessing the execution state of the system of a chain.
 #[derive(Debug, ClonableView, HashableView)]
+#[view(version = 1)]
 pub struct SystemExecutionStateView<C> {
     /// How the chain was created. May be unknown for inactive chains.
     pub description: HashedRegisterView<C, Option<ChainDescription>>,
@@ -71,6 +81,10 @@ pub struct SystemExecutionStateView<C> {
     // Not using a `MapView` because the set active of committees is supposed to be
     // small. Plus, currently, we would create the `BTreeMap` anyway in various places
     // (e.g. the `OpenChain` operation).
+    #[view(
+        migrate_from = HashedRegisterView<C, BTreeMap<Epoch, CommitteeV0>>,
+        migrate_with = migrate_committees
+    )]
     pub committees: HashedRegisterView<C, BTreeMap<Epoch, Committee>>,
     /// Ownership of the chain.
     pub ownership: HashedRegisterView<C, ChainOwnership>,
@@ -88,6 +102,54 @@ pub struct SystemExecutionStateView<C> {
     pub used_blobs: HashedSetView<C, BlobId>,
     /// The event stream subscriptions of applications on this chain.
     pub event_subscriptions: HashedMapView<C, (ChainId, StreamId), EventSubscriptions>,
+    /// The fees collected by this chain and not paid out yet, by the account on the admin
+    /// chain they accrue to.
+    pub fee_rewards: HashedMapView<C, AccountOwner, Amount>,
+    /// The validators registered for staking. Only used on the admin chain.
+    pub staking_validators: HashedMapView<C, ValidatorPublicKey, StakingValidator>,
+    /// The tokens staked with each validator, by delegator account. Only used on the admin
+    /// chain.
+    pub stakes: HashedCollectionView<C, ValidatorPublicKey, HashedMapView<C, Account, Amount>>,
+    /// The unstaked tokens, by the validator they were staked with and the epoch in which
+    /// they are released. Only used on the admin chain.
+    pub unbonding: HashedCollectionView<
+        C,
+        ValidatorPublicKey,
+        HashedMapView<C, Epoch, Vec<(Account, Amount)>>,
+    >,
+    /// The reported misbehaviors, by validator, chain and height. Only used on the admin
+    /// chain.
+    pub reported_misbehaviors: HashedSetView<C, (ValidatorPublicKey, ChainId, BlockHeight)>,
+    /// The validators proven to have misbehaved, which are excluded from the next elected
+    /// committee. This is cleared by each election: validators remain in the current
+    /// committee until then, and can be elected again afterwards if they are still staked
+    /// with. Only used on the admin chain.
+    pub excluded_validators: HashedSetView<C, ValidatorPublicKey>,
+    /// The operations scheduled to be executed on this chain, by ID.
+    pub scheduled_operations: HashedMapView<C, u64, ScheduledOperation>,
+    /// The ID of the next scheduled operation.
+    pub next_scheduled_operation_id: HashedRegisterView<C, u64>,
+    /// The transfers locked on this chain until they are claimed or refunded, by ID.
+    pub locked_transfers: HashedMapView<C, u64, LockedTransfer>,
+    /// The ID of the next locked transfer.
+    pub next_locked_transfer_id: HashedRegisterView<C, u64>,
+}
+
+/// Converts the committees saved before the last change of the
+/// [`ResourceControlPolicy`](crate::ResourceControlPolicy).
+fn migrate_committees<C: Context>(
+    old_committees: HashedRegisterView<C, BTreeMap<Epoch, CommitteeV0>>,
+    _version: u32,
+) -> Result<HashedRegisterView<C, BTreeMap<Epoch, Committee>>, ViewError> {
+    let mut committees = HashedRegisterView::new(old_committees.context().clone())?;
+    committees.set(
+        old_committees
+            .get()
+            .iter()
+            .map(|(epoch, committee)| (*epoch, committee.clone().into()))
+            .collect(),
+    );
+    Ok(committees)
 }
 
 impl<C: Context, C2: Context> ReplaceContext<C2> for SystemExecutionStateView<C> {
@@ -110,6 +172,19 @@ impl<C: Context, C2: Context> ReplaceContext<C2> for SystemExecutionStateView<C>
             application_permissions: self.application_permissions.with_context(ctx.clone()).await,
             used_blobs: self.used_blobs.with_context(ctx.clone()).await,
             event_subscriptions: self.event_subscriptions.with_context(ctx.clone()).await,
+            fee_rewards: self.fee_rewards.with_context(ctx.clone()).await,
+            staking_validators: self.staking_validators.with_context(ctx.clone()).await,
+            stakes: self.stakes.with_context(ctx.clone()).await,
+            unbonding: self.unbonding.with_context(ctx.clone()).await,
+            reported_misbehaviors: self.reported_misbehaviors.with_context(ctx.clone()).await,
+            excluded_validators: self.excluded_validators.with_context(ctx.clone()).await,
+            scheduled_operations: self.scheduled_operations.with_context(ctx.clone()).await,
+            next_scheduled_operation_id: self
+                .next_scheduled_operation_id
+                .with_context(ctx.clone())
+                .await,
+            locked_transfers: self.locked_transfers.with_context(ctx.clone()).await,
+            next_locked_transfer_id: self.next_locked_transfer_id.with_context(ctx.clone()).await,
         }
     }
 }
@@ -124,6 +199,63 @@ pub struct EventSubscriptions {
     pub applications: BTreeSet<ApplicationId>,
 }
 
+/// A validator registered for staking on the admin chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakingValidator {
+    /// The network address (in a string format understood by the networking layer).
+    pub network_address: String,
+    /// The public key of the account associated with the validator.
+    pub account_public_key: AccountPublicKey,
+    /// The total amount staked with the validator.
+    pub stake: Amount,
+}
+
+/// The statement signed by a validator to register for staking on the admin chain. It
+/// proves that the validator controls its key and chose this account and network address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorRegistration {
+    /// The admin chain the validator registers on.
+    pub admin_id: ChainId,
+    /// The public key of the account associated with the validator.
+    pub account_public_key: AccountPublicKey,
+    /// The network address (in a string format understood by the networking layer).
+    pub network_address: String,
+}
+
+impl BcsSignable<'_> for ValidatorRegistration {}
+
+/// An operation scheduled to be executed on a chain once a given time is reached.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledOperation {
+    /// The operation to execute.
+    pub operation: Operation,
+    /// The authenticated signer the operation is executed with, if any.
+    #[debug(skip_if = Option::is_none)]
+    pub authenticated_signer: Option<AccountOwner>,
+    /// The time from which the operation can be executed.
+    pub due: Timestamp,
+    /// The interval after which a recurring operation is due again.
+    #[debug(skip_if = Option::is_none)]
+    pub interval: Option<TimeDelta>,
+}
+
+/// Tokens locked on this chain for an account, possibly on another chain. They can be
+/// claimed before the deadline, or refunded to the sender after it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedTransfer {
+    /// The owner of the account on this chain the tokens are refunded to.
+    pub source: AccountOwner,
+    /// The account that can claim the tokens.
+    pub target: Account,
+    /// The amount of tokens.
+    pub amount: Amount,
+    /// The hash of the preimage needed to claim the tokens, if any.
+    #[debug(skip_if = Option::is_none)]
+    pub hash_lock: Option<CryptoHash>,
+    /// The time from which the tokens can no longer be claimed, but refunded.
+    pub deadline: Timestamp,
+}
+
 /// The initial configuration for a new chain.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct OpenChainConfig {
@@ -224,6 +356,78 @@ pub enum SystemOperation {
     ProcessRemovedEpoch(Epoch),
     /// Updates the event stream trackers.
     UpdateStreams(Vec<(ChainId, StreamId, u32)>),
+    /// Registers a validator for staking on the admin chain, or updates its network
+    /// address. Must be authenticated by the owner of the validator's account, and signed
+    /// by the validator: the signature is of the [`ValidatorRegistration`].
+    RegisterValidator {
+        public_key: ValidatorPublicKey,
+        account_public_key: AccountPublicKey,
+        network_address: String,
+        signature: ValidatorSignature,
+    },
+    /// Stakes `amount` units of value from the given owner's account with a validator
+    /// registered on the admin chain. Validators bond tokens by staking with themselves.
+    Stake {
+        owner: AccountOwner,
+        validator: ValidatorPublicKey,
+        amount: Amount,
+    },
+    /// Unstakes `amount` units of value staked by the given owner with a validator. The
+    /// tokens are credited back to the owner's account after the unbonding delay.
+    Unstake {
+        owner: AccountOwner,
+        validator: ValidatorPublicKey,
+        amount: Amount,
+    },
+    /// Reports to the admin chain that a validator signed conflicting votes. The validator
+    /// is excluded from the next elected committee, but remains in the current one, and
+    /// part of its stake is slashed.
+    ReportMisbehavior(Box<MisbehaviorEvidence>),
+    /// Schedules an operation to be executed on this chain once `due` is reached, and then
+    /// again after every `interval`, if any. The operation is executed with the
+    /// authenticated signer of this one, which must be a chain owner. Occurrences that were
+    /// missed, because no block executed them in time, are skipped.
+    ScheduleOperation {
+        operation: Box<Operation>,
+        due: Timestamp,
+        #[debug(skip_if = Option::is_none)]
+        interval: Option<TimeDelta>,
+    },
+    /// Cancels a scheduled operation. Must be authenticated by a chain owner.
+    CancelScheduledOperation { id: u64 },
+    /// Executes a scheduled operation that is due.
+    ExecuteScheduledOperation { id: u64 },
+    /// Locks `amount` units of value from the given owner's account for the recipient. The
+    /// tokens stay on this chain: the recipient can claim them before the `deadline`, with
+    /// the preimage of the `hash_lock`, if any. After the deadline, they can only be
+    /// refunded, which doesn't require the recipient's cooperation. A claim made before the
+    /// deadline on another chain succeeds even if it is received after the deadline, unless
+    /// the transfer was refunded first.
+    LockTransfer {
+        owner: AccountOwner,
+        recipient: Account,
+        amount: Amount,
+        #[debug(skip_if = Option::is_none)]
+        hash_lock: Option<CryptoHash>,
+        deadline: Timestamp,
+    },
+    /// Claims a transfer locked on chain `target_id` for the given owner's account on this
+    /// chain, before its deadline. The `preimage` is ignored if the transfer has no hash
+    /// lock.
+    ClaimLockedTransfer {
+        owner: AccountOwner,
+        target_id: ChainId,
+        id: u64,
+        #[serde(with = "serde_bytes")]
+        #[debug(with = "hex_debug", skip_if = Vec::is_empty)]
+        preimage: Vec<u8>,
+    },
+    /// Refunds a transfer locked on this chain to its sender, after its deadline.
+    RefundLockedTransfer { id: u64 },
+    /// Skips the current occurrence of a due scheduled operation without executing it, e.g.
+    /// because it fails. A recurring operation remains scheduled; an operation whose signer
+    /// is no longer a chain owner is removed. Must be authenticated by a chain owner.
+    SkipScheduledOperation { id: u64 },
 }
 
 /// Operations that are only allowed on the admin chain.
@@ -239,6 +443,10 @@ pub enum AdminOperation {
     /// so that blocks from the retired epoch will not be accepted until they are followed (hence
     /// re-certified) by a block certified by a recent committee.
     RemoveCommittee { epoch: Epoch },
+    /// Registers a new committee whose voting power is computed from the tokens staked with
+    /// the registered validators. Other chains can then migrate to the new epoch by
+    /// executing [`SystemOperation::ProcessNewEpoch`].
+    ElectCommittee { epoch: Epoch },
 }
 
 /// A system message meant to be executed on a remote chain.
@@ -259,6 +467,42 @@ pub enum SystemMessage {
         amount: Amount,
         recipient: Account,
     },
+    /// Registers a validator for staking on the admin chain, with its signature of the
+    /// [`ValidatorRegistration`].
+    RegisterValidator {
+        public_key: ValidatorPublicKey,
+        account_public_key: AccountPublicKey,
+        network_address: String,
+        signature: ValidatorSignature,
+    },
+    /// Stakes `amount` units of value, carried by the message, for the `delegator` account
+    /// with a validator -- unless the message is bouncing, in which case the amount is
+    /// credited back to the delegator.
+    Stake {
+        delegator: Account,
+        validator: ValidatorPublicKey,
+        amount: Amount,
+    },
+    /// Unstakes `amount` units of value staked by the `delegator` account with a validator.
+    Unstake {
+        delegator: Account,
+        validator: ValidatorPublicKey,
+        amount: Amount,
+    },
+    /// Reports that a validator signed conflicting votes.
+    ReportMisbehavior(Box<MisbehaviorEvidence>),
+    /// Claims a transfer locked on this chain for the account `owner` on the origin chain.
+    /// The deadline is checked against the time at which this chain executes the message,
+    /// since the claimer's chain could backdate its blocks. A claim arriving too late is
+    /// rejected: it carries no tokens, so nothing needs to bounce, and the transfer stays
+    /// locked until it is refunded.
+    ClaimLockedTransfer {
+        id: u64,
+        owner: AccountOwner,
+        #[serde(with = "serde_bytes")]
+        #[debug(with = "hex_debug", skip_if = Vec::is_empty)]
+        preimage: Vec<u8>,
+    },
 }
 
 /// A query to the system state.
@@ -422,9 +666,12 @@ where
                     }
                     AdminOperation::CreateCommittee { epoch, blob_hash } => {
                         self.check_next_epoch(epoch)?;
+                        self.pay_out_fees(txn_tracker).await?;
+                        self.release_unbonded(epoch, txn_tracker).await?;
                         let blob_id = BlobId::new(blob_hash, BlobType::Committee);
-                        let committee =
-                            bcs::from_bytes(self.read_blob_content(blob_id).await?.bytes())?;
+                        let committee = Committee::from_blob_bytes(
+                            self.read_blob_content(blob_id).await?.bytes(),
+                        )?;
                         self.blob_used(txn_tracker, blob_id).await?;
                         self.committees.get_mut().insert(epoch, committee);
                         self.epoch.set(epoch);
@@ -445,6 +692,24 @@ where
                             vec![],
                         );
                     }
+                    AdminOperation::ElectCommittee { epoch } => {
+                        self.check_next_epoch(epoch)?;
+                        let committee = self.elect_committee(context.chain_id).await?;
+                        self.excluded_validators.clear();
+                        let blob = Blob::new_committee(bcs::to_bytes(&committee)?);
+                        let blob_hash = blob.id().hash;
+                        self.used_blobs.insert(&blob.id())?;
+                        txn_tracker.add_created_blob(blob);
+                        self.pay_out_fees(txn_tracker).await?;
+                        self.release_unbonded(epoch, txn_tracker).await?;
+                        self.committees.get_mut().insert(epoch, committee);
+                        self.epoch.set(epoch);
+                        txn_tracker.add_event(
+                            StreamId::system(EPOCH_STREAM_NAME),
+                            epoch.0,
+                            bcs::to_bytes(&blob_hash)?,
+                        );
+                    }
                 }
             }
             PublishModule { module_id } => {
@@ -500,8 +765,10 @@ where
                     .await?
                     .to_event(&event_id)?;
                 let blob_id = BlobId::new(bcs::from_bytes(&bytes)?, BlobType::Committee);
-                let committee = bcs::from_bytes(self.read_blob_content(blob_id).await?.bytes())?;
+                let committee =
+                    Committee::from_blob_bytes(self.read_blob_content(blob_id).await?.bytes())?;
                 self.blob_used(txn_tracker, blob_id).await?;
+                self.pay_out_fees(txn_tracker).await?;
                 self.committees.get_mut().insert(epoch, committee);
                 self.epoch.set(epoch);
             }
@@ -570,6 +837,184 @@ where
                     ExecutionError::EventsNotFound(missing_events)
                 );
             }
+            RegisterValidator {
+                public_key,
+                account_public_key,
+                network_address,
+                signature,
+            } => {
+                ensure!(
+                    context.authenticated_signer == Some(account_public_key.into()),
+                    ExecutionError::UnauthenticatedStakeOwner
+                );
+                let recipient = self.staking_recipient(context.chain_id)?;
+                let registration = ValidatorRegistration {
+                    admin_id: recipient.unwrap_or(context.chain_id),
+                    account_public_key,
+                    network_address,
+                };
+                match recipient {
+                    None => {
+                        self.register_validator(public_key, registration, signature)
+                            .await?
+                    }
+                    Some(admin_id) => {
+                        // Check the signature early, so that invalid registrations are not sent.
+                        Self::check_registration(public_key, &registration, &signature)?;
+                        let message = SystemMessage::RegisterValidator {
+                            public_key,
+                            account_public_key,
+                            network_address: registration.network_address,
+                            signature,
+                        };
+                        txn_tracker.add_outgoing_message(OutgoingMessage::new(admin_id, message));
+                    }
+                }
+            }
+            Stake {
+                owner,
+                validator,
+                amount,
+            } => {
+                self.check_staking_owner(context.authenticated_signer, owner)?;
+                ensure!(amount > Amount::ZERO, ExecutionError::IncorrectStakeAmount);
+                self.debit(&owner, amount).await?;
+                let delegator = Account::new(context.chain_id, owner);
+                match self.staking_recipient(context.chain_id)? {
+                    None => self.stake(delegator, validator, amount).await?,
+                    Some(admin_id) => {
+                        let message = SystemMessage::Stake {
+                            delegator,
+                            validator,
+                            amount,
+                        };
+                        txn_tracker.add_outgoing_message(
+                            OutgoingMessage::new(admin_id, message).with_kind(MessageKind::Tracked),
+                        );
+                    }
+                }
+            }
+            Unstake {
+                owner,
+                validator,
+                amount,
+            } => {
+                self.check_staking_owner(context.authenticated_signer, owner)?;
+                ensure!(amount > Amount::ZERO, ExecutionError::IncorrectStakeAmount);
+                let delegator = Account::new(context.chain_id, owner);
+                match self.staking_recipient(context.chain_id)? {
+                    None => self.unstake(delegator, validator, amount).await?,
+                    Some(admin_id) => {
+                        let message = SystemMessage::Unstake {
+                            delegator,
+                            validator,
+                            amount,
+                        };
+                        txn_tracker.add_outgoing_message(OutgoingMessage::new(admin_id, message));
+                    }
+                }
+            }
+            ReportMisbehavior(evidence) => match self.staking_recipient(context.chain_id)? {
+                None => self.punish_misbehavior(&evidence).await?,
+                Some(admin_id) => {
+                    // Check the evidence early, so that invalid reports are not sent.
+                    evidence.verify()?;
+                    let message = SystemMessage::ReportMisbehavior(evidence);
+                    txn_tracker.add_outgoing_message(OutgoingMessage::new(admin_id, message));
+                }
+            },
+            ScheduleOperation {
+                operation,
+                due,
+                interval,
+            } => {
+                self.check_scheduling_owner(context.authenticated_signer)?;
+                self.schedule_operation(ScheduledOperation {
+                    operation: *operation,
+                    authenticated_signer: context.authenticated_signer,
+                    due,
+                    interval,
+                })
+                .await?;
+            }
+            CancelScheduledOperation { id } => {
+                self.check_scheduling_owner(context.authenticated_signer)?;
+                ensure!(
+                    self.scheduled_operations.contains_key(&id).await?,
+                    ExecutionError::ScheduledOperationNotFound(id)
+                );
+                self.scheduled_operations.remove(&id)?;
+            }
+            ExecuteScheduledOperation { .. } => {
+                return Err(ExecutionError::InternalError(
+                    "scheduled operations must be executed through `take_due_operation`",
+                ));
+            }
+            LockTransfer {
+                owner,
+                recipient,
+                amount,
+                hash_lock,
+                deadline,
+            } => {
+                self.check_transfer_owner(context.authenticated_signer, None, owner)?;
+                ensure!(
+                    amount > Amount::ZERO,
+                    ExecutionError::IncorrectTransferAmount
+                );
+                ensure!(
+                    context.timestamp < deadline,
+                    ExecutionError::LockedTransferExpired(deadline)
+                );
+                self.debit(&owner, amount).await?;
+                self.lock_transfer(LockedTransfer {
+                    source: owner,
+                    target: recipient,
+                    amount,
+                    hash_lock,
+                    deadline,
+                })?;
+            }
+            ClaimLockedTransfer {
+                owner,
+                target_id,
+                id,
+                preimage,
+            } => {
+                self.check_transfer_owner(context.authenticated_signer, None, owner)
+                    .map_err(|_| ExecutionError::UnauthenticatedClaimOwner)?;
+                if target_id == context.chain_id {
+                    let recipient = Account::new(context.chain_id, owner);
+                    let maybe_message = self
+                        .claim_locked_transfer(id, recipient, &preimage, context.timestamp)
+                        .await?;
+                    txn_tracker.add_outgoing_messages(maybe_message);
+                } else {
+                    let message = SystemMessage::ClaimLockedTransfer {
+                        id,
+                        owner,
+                        preimage,
+                    };
+                    txn_tracker.add_outgoing_message(
+                        OutgoingMessage::new(target_id, message)
+                            .with_authenticated_signer(context.authenticated_signer),
+                    );
+                }
+            }
+            RefundLockedTransfer { id } => {
+                let locked = self.locked_transfer(id).await?;
+                ensure!(
+                    context.timestamp >= locked.deadline,
+                    ExecutionError::LockedTransferNotExpired(locked.deadline)
+                );
+                self.locked_transfers.remove(&id)?;
+                self.credit(&locked.source, locked.amount).await?;
+            }
+            SkipScheduledOperation { id } => {
+                self.check_scheduling_owner(context.authenticated_signer)?;
+                let scheduled = self.due_operation(id, context.timestamp).await?;
+                self.consume_due_operation(id, scheduled, context.timestamp)?;
+            }
         }
 
         Ok(new_application)
@@ -586,6 +1031,517 @@ where
         Ok(())
     }
 
+    /// Collects the fees paid in a block, according to the fee sink of the policy.
+    pub async fn collect_fees(
+        &mut self,
+        fees: Amount,
+        fee_sink: FeeSink,
+    ) -> Result<(), ExecutionError> {
+        if fees.is_zero() {
+            return Ok(());
+        }
+        let shares = match fee_sink {
+            FeeSink::Burn => return Ok(()),
+            FeeSink::AdminAccount(owner) => vec![(owner, fees)],
+            FeeSink::Validators => {
+                let (_, committee) = self.current_committee().ok_or_else(|| {
+                    ExecutionError::InactiveChain(self.context().extra().chain_id())
+                })?;
+                committee
+                    .split_by_votes(fees)
+                    .into_iter()
+                    .map(|(account_public_key, share)| (account_public_key.into(), share))
+                    .collect()
+            }
+        };
+        for (owner, share) in shares {
+            if share.is_zero() {
+                continue;
+            }
+            let reward = self.fee_rewards.get_mut_or_default(&owner).await?;
+            reward.try_add_assign(share)?;
+        }
+        Ok(())
+    }
+
+    /// Pays out the collected fees to their accounts on the admin chain.
+    async fn pay_out_fees(
+        &mut self,
+        txn_tracker: &mut TransactionTracker,
+    ) -> Result<(), ExecutionError> {
+        let rewards = self.fee_rewards.index_values().await?;
+        if rewards.is_empty() {
+            return Ok(());
+        }
+        let admin_id = self
+            .admin_id
+            .get()
+            .ok_or_else(|| ExecutionError::InactiveChain(self.context().extra().chain_id()))?;
+        self.fee_rewards.clear();
+        for (owner, amount) in rewards {
+            let recipient = Account::new(admin_id, owner);
+            let maybe_message = self
+                .credit_or_send_message(AccountOwner::CHAIN, recipient, amount)
+                .await?;
+            txn_tracker.add_outgoing_messages(maybe_message);
+        }
+        Ok(())
+    }
+
+    /// Returns `None` if staking operations are executed on this chain, or the admin chain
+    /// they must be sent to.
+    fn staking_recipient(&self, chain_id: ChainId) -> Result<Option<ChainId>, ExecutionError> {
+        let admin_id = self
+            .admin_id
+            .get()
+            .ok_or(ExecutionError::InactiveChain(chain_id))?;
+        Ok((admin_id != chain_id).then_some(admin_id))
+    }
+
+    /// Returns an error if this is not the admin chain.
+    fn check_admin_chain(&self, chain_id: ChainId) -> Result<(), ExecutionError> {
+        ensure!(
+            *self.admin_id.get() == Some(chain_id),
+            ExecutionError::StakingOnNonAdminChain
+        );
+        Ok(())
+    }
+
+    /// Returns an error if the signer is not allowed to stake the owner's tokens.
+    fn check_staking_owner(
+        &self,
+        authenticated_signer: Option<AccountOwner>,
+        owner: AccountOwner,
+    ) -> Result<(), ExecutionError> {
+        let authenticated = if owner == AccountOwner::CHAIN {
+            authenticated_signer.is_some_and(|signer| self.ownership.get().verify_owner(&signer))
+        } else {
+            authenticated_signer == Some(owner)
+        };
+        ensure!(authenticated, ExecutionError::UnauthenticatedStakeOwner);
+        Ok(())
+    }
+
+    /// Returns an error if the signer is not allowed to schedule operations on this chain.
+    fn check_scheduling_owner(
+        &self,
+        authenticated_signer: Option<AccountOwner>,
+    ) -> Result<(), ExecutionError> {
+        ensure!(
+            authenticated_signer.is_some_and(|signer| self.ownership.get().verify_owner(&signer)),
+            ExecutionError::UnauthorizedScheduledOperation
+        );
+        Ok(())
+    }
+
+    /// Schedules an operation and returns its ID.
+    pub async fn schedule_operation(
+        &mut self,
+        scheduled: ScheduledOperation,
+    ) -> Result<u64, ExecutionError> {
+        ensure!(
+            scheduled.interval != Some(TimeDelta::ZERO),
+            ExecutionError::InvalidScheduledOperation("the interval must not be zero")
+        );
+        ensure!(
+            !matches!(
+                scheduled.operation.as_system_operation(),
+                Some(
+                    SystemOperation::ScheduleOperation { .. }
+                        | SystemOperation::ExecuteScheduledOperation { .. }
+                        | SystemOperation::SkipScheduledOperation { .. }
+                )
+            ),
+            ExecutionError::InvalidScheduledOperation(
+                "scheduled operations cannot schedule or execute other scheduled operations"
+            )
+        );
+        ensure!(
+            self.scheduled_operations.count().await? < MAX_SCHEDULED_OPERATIONS,
+            ExecutionError::TooManyScheduledOperations(MAX_SCHEDULED_OPERATIONS)
+        );
+        let id = *self.next_scheduled_operation_id.get();
+        self.next_scheduled_operation_id
+            .set(id.checked_add(1).ok_or(ArithmeticError::Overflow)?);
+        self.scheduled_operations.insert(&id, scheduled)?;
+        Ok(id)
+    }
+
+    /// Returns the scheduled operation with the given ID, if it is due at `timestamp`.
+    async fn due_operation(
+        &self,
+        id: u64,
+        timestamp: Timestamp,
+    ) -> Result<ScheduledOperation, ExecutionError> {
+        let scheduled = self
+            .scheduled_operations
+            .get(&id)
+            .await?
+            .ok_or(ExecutionError::ScheduledOperationNotFound(id))?;
+        ensure!(
+            scheduled.due <= timestamp,
+            ExecutionError::ScheduledOperationNotDue {
+                id,
+                due: scheduled.due
+            }
+        );
+        Ok(scheduled)
+    }
+
+    /// Consumes the occurrence of a due scheduled operation: a recurring operation is
+    /// rescheduled, any other operation is removed. So are operations whose signer is no
+    /// longer a chain owner.
+    ///
+    /// Occurrences missed because the operation was not executed in time are skipped: the
+    /// operation is next due at the first multiple of its interval after `timestamp`, so
+    /// that they are not executed in a burst.
+    fn consume_due_operation(
+        &mut self,
+        id: u64,
+        mut scheduled: ScheduledOperation,
+        timestamp: Timestamp,
+    ) -> Result<(), ExecutionError> {
+        let signer_is_owner = scheduled
+            .authenticated_signer
+            .is_none_or(|signer| self.ownership.get().verify_owner(&signer));
+        match scheduled.interval {
+            Some(interval) if signer_is_owner => {
+                let interval = interval.as_micros();
+                let missed = timestamp.delta_since(scheduled.due).as_micros() / interval;
+                let delay = interval.saturating_mul(missed.saturating_add(1));
+                scheduled.due = scheduled.due.saturating_add(TimeDelta::from_micros(delay));
+                self.scheduled_operations.insert(&id, scheduled)?;
+            }
+            _ => self.scheduled_operations.remove(&id)?,
+        }
+        Ok(())
+    }
+
+    /// Consumes the occurrence of the scheduled operation with the given ID, if it is due at
+    /// `timestamp`. Returns the operation and the signer to execute it with, which must
+    /// still be a chain owner.
+    pub async fn take_due_operation(
+        &mut self,
+        id: u64,
+        timestamp: Timestamp,
+    ) -> Result<(Operation, Option<AccountOwner>), ExecutionError> {
+        let scheduled = self.due_operation(id, timestamp).await?;
+        if let Some(signer) = &scheduled.authenticated_signer {
+            ensure!(
+                self.ownership.get().verify_owner(signer),
+                ExecutionError::UnauthorizedScheduledOperation
+            );
+        }
+        let application_id = scheduled.operation.application_id();
+        ensure!(
+            self.application_permissions
+                .get()
+                .can_execute_operations(&application_id),
+            ExecutionError::UnauthorizedScheduledApplication(application_id)
+        );
+        let operation = scheduled.operation.clone();
+        let authenticated_signer = scheduled.authenticated_signer;
+        self.consume_due_operation(id, scheduled, timestamp)?;
+        Ok((operation, authenticated_signer))
+    }
+
+    /// Stores a locked transfer and returns its ID.
+    fn lock_transfer(&mut self, locked: LockedTransfer) -> Result<u64, ExecutionError> {
+        let id = *self.next_locked_transfer_id.get();
+        self.next_locked_transfer_id
+            .set(id.checked_add(1).ok_or(ArithmeticError::Overflow)?);
+        self.locked_transfers.insert(&id, locked)?;
+        Ok(id)
+    }
+
+    /// Removes the locked transfer with the given ID and credits it to the `recipient`, if
+    /// that is its target, this chain's `timestamp` is before the deadline, and the
+    /// `preimage` matches the hash lock, if any.
+    async fn claim_locked_transfer(
+        &mut self,
+        id: u64,
+        recipient: Account,
+        preimage: &[u8],
+        timestamp: Timestamp,
+    ) -> Result<Option<OutgoingMessage>, ExecutionError> {
+        let locked = self.locked_transfer(id).await?;
+        ensure!(
+            locked.target == recipient,
+            ExecutionError::UnauthenticatedClaimOwner
+        );
+        ensure!(
+            timestamp < locked.deadline,
+            ExecutionError::LockedTransferExpired(locked.deadline)
+        );
+        ensure!(
+            locked
+                .hash_lock
+                .is_none_or(|hash_lock| CryptoHash::keccak256(preimage) == hash_lock),
+            ExecutionError::InvalidLockedTransferPreimage
+        );
+        self.locked_transfers.remove(&id)?;
+        self.credit_or_send_message(locked.source, locked.target, locked.amount)
+            .await
+    }
+
+    /// Returns the locked transfer with the given ID.
+    async fn locked_transfer(&self, id: u64) -> Result<LockedTransfer, ExecutionError> {
+        self.locked_transfers
+            .get(&id)
+            .await?
+            .ok_or(ExecutionError::LockedTransferNotFound(id))
+    }
+
+    /// Returns an error if the signature of the registration is not the validator's.
+    fn check_registration(
+        public_key: ValidatorPublicKey,
+        registration: &ValidatorRegistration,
+        signature: &ValidatorSignature,
+    ) -> Result<(), ExecutionError> {
+        signature
+            .check(registration, public_key)
+            .map_err(|_| ExecutionError::InvalidValidatorRegistration(public_key))
+    }
+
+    /// Registers a validator for staking on this chain, which must be the admin chain, or
+    /// updates its network address.
+    async fn register_validator(
+        &mut self,
+        public_key: ValidatorPublicKey,
+        registration: ValidatorRegistration,
+        signature: ValidatorSignature,
+    ) -> Result<(), ExecutionError> {
+        ensure!(
+            registration.admin_id == self.context().extra().chain_id(),
+            ExecutionError::InvalidValidatorRegistration(public_key)
+        );
+        Self::check_registration(public_key, &registration, &signature)?;
+        let ValidatorRegistration {
+            account_public_key,
+            network_address,
+            ..
+        } = registration;
+        match self.staking_validators.get_mut(&public_key).await? {
+            Some(validator) => {
+                ensure!(
+                    validator.account_public_key == account_public_key,
+                    ExecutionError::StakingValidatorAccountMismatch(public_key)
+                );
+                validator.network_address = network_address;
+            }
+            None => {
+                let validator = StakingValidator {
+                    network_address,
+                    account_public_key,
+                    stake: Amount::ZERO,
+                };
+                self.staking_validators.insert(&public_key, validator)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stakes tokens, already debited from the delegator's account, with a validator.
+    async fn stake(
+        &mut self,
+        delegator: Account,
+        public_key: ValidatorPublicKey,
+        amount: Amount,
+    ) -> Result<(), ExecutionError> {
+        let validator = self
+            .staking_validators
+            .get_mut(&public_key)
+            .await?
+            .ok_or(ExecutionError::UnknownStakingValidator(public_key))?;
+        validator.stake.try_add_assign(amount)?;
+        let stake = self
+            .stakes
+            .load_entry_mut(&public_key)
+            .await?
+            .get_mut_or_default(&delegator)
+            .await?;
+        stake.try_add_assign(amount)?;
+        Ok(())
+    }
+
+    /// Unstakes tokens staked by the delegator with a validator. They are released after the
+    /// unbonding delay of the current committee's policy.
+    async fn unstake(
+        &mut self,
+        delegator: Account,
+        public_key: ValidatorPublicKey,
+        amount: Amount,
+    ) -> Result<(), ExecutionError> {
+        let stakes = self.stakes.load_entry_mut(&public_key).await?;
+        let stake = stakes
+            .get_mut(&delegator)
+            .await?
+            .ok_or(ExecutionError::InsufficientStake {
+                stake: Amount::ZERO,
+                amount,
+            })?;
+        stake
+            .try_sub_assign(amount)
+            .map_err(|_| ExecutionError::InsufficientStake {
+                stake: *stake,
+                amount,
+            })?;
+        if stake.is_zero() {
+            stakes.remove(&delegator)?;
+            if stakes.count().await? == 0 {
+                self.stakes.remove_entry(&public_key)?;
+            }
+        }
+        let validator = self
+            .staking_validators
+            .get_mut(&public_key)
+            .await?
+            .ok_or(ExecutionError::UnknownStakingValidator(public_key))?;
+        validator.stake.try_sub_assign(amount)?;
+        let (epoch, committee) = self
+            .current_committee()
+            .ok_or_else(|| ExecutionError::InactiveChain(self.context().extra().chain_id()))?;
+        let release_epoch = committee
+            .policy()
+            .unbonding_epochs
+            .checked_add(1)
+            .and_then(|delay| epoch.0.checked_add(delay))
+            .ok_or(ArithmeticError::Overflow)?;
+        self.unbonding
+            .load_entry_mut(&public_key)
+            .await?
+            .get_mut_or_default(&Epoch(release_epoch))
+            .await?
+            .push((delegator, amount));
+        Ok(())
+    }
+
+    /// Returns the committee of the registered validators, with one vote per whole token
+    /// staked with them, and the policy of the current committee.
+    async fn elect_committee(&self, chain_id: ChainId) -> Result<Committee, ExecutionError> {
+        let (_, current_committee) = self
+            .current_committee()
+            .ok_or(ExecutionError::InactiveChain(chain_id))?;
+        let mut validators = BTreeMap::new();
+        for (public_key, validator) in self.staking_validators.index_values().await? {
+            if self.excluded_validators.contains(&public_key).await? {
+                continue;
+            }
+            let votes =
+                u64::try_from(validator.stake.saturating_div(Amount::ONE)).unwrap_or(u64::MAX);
+            if votes == 0 {
+                continue;
+            }
+            let state = ValidatorState {
+                network_address: validator.network_address,
+                votes,
+                account_public_key: validator.account_public_key,
+            };
+            validators.insert(public_key, state);
+        }
+        ensure!(!validators.is_empty(), ExecutionError::NoStakedValidators);
+        Ok(Committee::new(
+            validators,
+            current_committee.policy().clone(),
+        ))
+    }
+
+    /// Verifies the evidence of a validator's misbehavior and penalizes the validator: it is
+    /// excluded from the next elected committee, and the slashing percentage of the policy
+    /// is burned from every stake with it, including the tokens that are still unbonding.
+    ///
+    /// The validator must be registered for staking or be in the current committee.
+    async fn punish_misbehavior(
+        &mut self,
+        evidence: &MisbehaviorEvidence,
+    ) -> Result<(), ExecutionError> {
+        let (chain_id, height) = evidence.verify()?;
+        let public_key = evidence.validator;
+        let (_, committee) = self
+            .current_committee()
+            .ok_or_else(|| ExecutionError::InactiveChain(self.context().extra().chain_id()))?;
+        let percentage = u128::from(committee.policy().slashing_percentage.min(100));
+        let in_committee = committee.validators().contains_key(&public_key);
+        let maybe_validator = self.staking_validators.get(&public_key).await?;
+        ensure!(
+            in_committee || maybe_validator.is_some(),
+            ExecutionError::UnknownMisbehavingValidator(public_key)
+        );
+        let misbehavior = (public_key, chain_id, height);
+        ensure!(
+            !self.reported_misbehaviors.contains(&misbehavior).await?,
+            ExecutionError::MisbehaviorAlreadyReported(public_key)
+        );
+        self.reported_misbehaviors.insert(&misbehavior)?;
+        self.excluded_validators.insert(&public_key)?;
+        let Some(mut validator) = maybe_validator else {
+            return Ok(());
+        };
+        let slash = |amount: Amount| {
+            let attos = u128::from(amount);
+            Amount::from_attos(attos / 100 * percentage + attos % 100 * percentage / 100)
+        };
+        if self.unbonding.try_load_entry(&public_key).await?.is_some() {
+            let unbonding = self.unbonding.load_entry_mut(&public_key).await?;
+            for (release_epoch, mut entries) in unbonding.index_values().await? {
+                for (_, amount) in &mut entries {
+                    *amount = amount.saturating_sub(slash(*amount));
+                }
+                unbonding.insert(&release_epoch, entries)?;
+            }
+        }
+        if self.stakes.try_load_entry(&public_key).await?.is_some() {
+            let stakes = self.stakes.load_entry_mut(&public_key).await?;
+            for (delegator, stake) in stakes.index_values().await? {
+                let slashed = slash(stake);
+                let remaining = stake.saturating_sub(slashed);
+                if remaining.is_zero() {
+                    stakes.remove(&delegator)?;
+                } else {
+                    stakes.insert(&delegator, remaining)?;
+                }
+                validator.stake = validator.stake.saturating_sub(slashed);
+            }
+            if stakes.count().await? == 0 {
+                self.stakes.remove_entry(&public_key)?;
+            }
+        }
+        self.staking_validators.insert(&public_key, validator)?;
+        Ok(())
+    }
+
+    /// Credits back the unstaked tokens whose unbonding delay ends at the given epoch or
+    /// before.
+    async fn release_unbonded(
+        &mut self,
+        epoch: Epoch,
+        txn_tracker: &mut TransactionTracker,
+    ) -> Result<(), ExecutionError> {
+        let mut released = Vec::new();
+        for public_key in self.unbonding.indices().await? {
+            let unbonding = self.unbonding.load_entry_mut(&public_key).await?;
+            for (release_epoch, entries) in unbonding.index_values().await? {
+                if release_epoch <= epoch {
+                    unbonding.remove(&release_epoch)?;
+                    released.extend(entries);
+                }
+            }
+            if unbonding.count().await? == 0 {
+                self.unbonding.remove_entry(&public_key)?;
+            }
+        }
+        for (delegator, amount) in released {
+            if amount.is_zero() {
+                continue;
+            }
+            let maybe_message = self
+                .credit_or_send_message(AccountOwner::CHAIN, delegator, amount)
+                .await?;
+            txn_tracker.add_outgoing_messages(maybe_message);
+        }
+        Ok(())
+    }
+
     async fn credit(&mut self, owner: &AccountOwner, amount: Amount) -> Result<(), ExecutionError> {
         if owner == &AccountOwner::CHAIN {
             let new_balance = self.balance.get().saturating_add(amount);
@@ -630,7 +1586,24 @@ where
         recipient: Account,
         amount: Amount,
     ) -> Result<Option<OutgoingMessage>, ExecutionError> {
-        if source == AccountOwner::CHAIN {
+        self.check_transfer_owner(authenticated_signer, authenticated_application_id, source)?;
+        ensure!(
+            amount > Amount::ZERO,
+            ExecutionError::IncorrectTransferAmount
+        );
+        self.debit(&source, amount).await?;
+        self.credit_or_send_message(source, recipient, amount).await
+    }
+
+    /// Returns an error if neither the signer nor the application is allowed to transfer the
+    /// owner's tokens.
+    fn check_transfer_owner(
+        &self,
+        authenticated_signer: Option<AccountOwner>,
+        authenticated_application_id: Option<ApplicationId>,
+        owner: AccountOwner,
+    ) -> Result<(), ExecutionError> {
+        if owner == AccountOwner::CHAIN {
             ensure!(
                 authenticated_signer.is_some()
                     && self
@@ -641,17 +1614,12 @@ where
             );
         } else {
             ensure!(
-                authenticated_signer == Some(source)
-                    || authenticated_application_id.map(AccountOwner::from) == Some(source),
+                authenticated_signer == Some(owner)
+                    || authenticated_application_id.map(AccountOwner::from) == Some(owner),
                 ExecutionError::UnauthenticatedTransferOwner
             );
         }
-        ensure!(
-            amount > Amount::ZERO,
-            ExecutionError::IncorrectTransferAmount
-        );
-        self.debit(&source, amount).await?;
-        self.credit_or_send_message(source, recipient, amount).await
+        Ok(())
     }
 
     pub async fn claim(
@@ -750,6 +1718,66 @@ where
                     outcome.push(message);
                 }
             }
+            RegisterValidator {
+                public_key,
+                account_public_key,
+                network_address,
+                signature,
+            } => {
+                self.check_admin_chain(context.chain_id)?;
+                let registration = ValidatorRegistration {
+                    admin_id: context.chain_id,
+                    account_public_key,
+                    network_address,
+                };
+                self.register_validator(public_key, registration, signature)
+                    .await?;
+            }
+            Stake {
+                delegator,
+                validator,
+                amount,
+            } => {
+                if context.is_bouncing {
+                    self.credit(&delegator.owner, amount).await?;
+                } else {
+                    self.check_admin_chain(context.chain_id)?;
+                    ensure!(
+                        delegator.chain_id == context.origin,
+                        ExecutionError::UnauthenticatedStakeOwner
+                    );
+                    self.stake(delegator, validator, amount).await?;
+                }
+            }
+            Unstake {
+                delegator,
+                validator,
+                amount,
+            } => {
+                self.check_admin_chain(context.chain_id)?;
+                ensure!(
+                    delegator.chain_id == context.origin,
+                    ExecutionError::UnauthenticatedStakeOwner
+                );
+                self.unstake(delegator, validator, amount).await?;
+            }
+            ReportMisbehavior(evidence) => {
+                self.check_admin_chain(context.chain_id)?;
+                self.punish_misbehavior(&evidence).await?;
+            }
+            ClaimLockedTransfer {
+                id,
+                owner,
+                preimage,
+            } => {
+                let recipient = Account::new(context.origin, owner);
+                if let Some(message) = self
+                    .claim_locked_transfer(id, recipient, &preimage, context.timestamp)
+                    .await?
+                {
+                    outcome.push(message);
+                }
+            }
         }
         Ok(outcome)
     }
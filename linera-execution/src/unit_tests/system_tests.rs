@@ -52,6 +52,18 @@ fn expected_application_id(
     From::from(&description)
 }
 
+fn validator_registration(
+    admin_id: ChainId,
+    account_public_key: AccountPublicKey,
+    index: u8,
+) -> ValidatorRegistration {
+    ValidatorRegistration {
+        admin_id,
+        account_public_key,
+        network_address: format!("address{index}"),
+    }
+}
+
 #[tokio::test]
 async fn application_message_index() -> anyhow::Result<()> {
     let (mut view, context) = new_view_and_context().await;
@@ -129,3 +141,812 @@ async fn empty_accounts_are_removed() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Tests that the committees serialized before the last fields of the policy were added
+/// can still be read.
+#[test]
+fn committee_blobs_from_before_the_policy_change_are_read() -> anyhow::Result<()> {
+    use crate::ResourceControlPolicy;
+
+    let mut committee = Committee::make_simple(vec![(
+        ValidatorPublicKey::test_key(0),
+        AccountPublicKey::test_key(0),
+    )]);
+    *committee.policy_mut() = ResourceControlPolicy::all_categories();
+    let bytes = bcs::to_bytes(&committee)?;
+    // The fee sink, the unbonding delay and the slashing percentage were added at the end
+    // of the policy, which is the last field of a committee.
+    let (old_bytes, new_fields) = bytes.split_at(bytes.len() - 6);
+    assert_eq!(new_fields, [0; 6]);
+    let old_committee = bcs::from_bytes::<CommitteeV0>(old_bytes)?;
+    assert_eq!(bcs::to_bytes(&old_committee)?, old_bytes);
+
+    assert!(bcs::from_bytes::<Committee>(old_bytes).is_err());
+    assert_eq!(Committee::from_blob_bytes(old_bytes)?, committee);
+    assert_eq!(Committee::from_blob_bytes(&bytes)?, committee);
+    assert!(Committee::from_blob_bytes(&bytes[..bytes.len() - 1]).is_err());
+    Ok(())
+}
+
+/// Tests that the fees collected by a chain are paid out to the accounts on the admin chain.
+#[tokio::test]
+async fn collected_fees_are_paid_out() -> anyhow::Result<()> {
+    use linera_base::crypto::{AccountPublicKey, ValidatorPublicKey};
+
+    use crate::{committee::ValidatorState, Message, ResourceControlPolicy};
+
+    let (mut view, _context) = new_view_and_context().await;
+    let admin_id = view.system.admin_id.get().unwrap();
+    let validator_owners = [1, 3]
+        .into_iter()
+        .map(|votes| {
+            let account_public_key = AccountPublicKey::test_key(votes as u8);
+            let state = ValidatorState {
+                network_address: String::new(),
+                votes,
+                account_public_key,
+            };
+            (ValidatorPublicKey::test_key(votes as u8), state)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let committee = Committee::new(validator_owners, ResourceControlPolicy::default());
+    view.system
+        .committees
+        .set(BTreeMap::from([(Epoch(1), committee)]));
+    let owner1 = AccountOwner::from(AccountPublicKey::test_key(1));
+    let owner3 = AccountOwner::from(AccountPublicKey::test_key(3));
+
+    view.system
+        .collect_fees(Amount::from_attos(12), FeeSink::Validators)
+        .await?;
+    view.system
+        .collect_fees(Amount::from_attos(5), FeeSink::AdminAccount(owner1))
+        .await?;
+    view.system
+        .collect_fees(Amount::from_attos(7), FeeSink::Burn)
+        .await?;
+    let expected_rewards = BTreeMap::from([
+        (owner1, Amount::from_attos(8)),
+        (owner3, Amount::from_attos(9)),
+    ]);
+    let rewards = view.system.fee_rewards.index_values().await?;
+    assert_eq!(
+        rewards.into_iter().collect::<BTreeMap<_, _>>(),
+        expected_rewards
+    );
+
+    let mut txn_tracker = TransactionTracker::default();
+    view.system.pay_out_fees(&mut txn_tracker).await?;
+    assert!(view.system.fee_rewards.indices().await?.is_empty());
+    let credits = txn_tracker
+        .into_outcome()?
+        .outgoing_messages
+        .into_iter()
+        .map(|message| {
+            assert_eq!(message.destination, admin_id);
+            match message.message {
+                Message::System(SystemMessage::Credit { target, amount, .. }) => (target, amount),
+                message => panic!("unexpected message {message:?}"),
+            }
+        })
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(credits, expected_rewards);
+
+    Ok(())
+}
+
+/// Tests that the committee is elected from the staked tokens, and that unstaked tokens are
+/// released after the unbonding delay.
+#[tokio::test]
+async fn committee_is_elected_from_stakes() -> anyhow::Result<()> {
+    use linera_base::crypto::{AccountPublicKey, ValidatorKeypair, ValidatorSignature};
+
+    use crate::{Message, ResourceControlPolicy};
+
+    let (mut view, _context) = new_view_and_context().await;
+    let chain_id = view.context().extra().chain_id();
+    view.system.admin_id.set(Some(chain_id));
+    let policy = ResourceControlPolicy {
+        unbonding_epochs: 1,
+        ..ResourceControlPolicy::default()
+    };
+    view.system.committees.set(BTreeMap::from([(
+        Epoch(1),
+        Committee::new(BTreeMap::new(), policy.clone()),
+    )]));
+    let key_pair1 = ValidatorKeypair::generate();
+    let key_pair2 = ValidatorKeypair::generate();
+    let validator1 = key_pair1.public_key;
+    let validator2 = key_pair2.public_key;
+    for (key_pair, index) in [(&key_pair1, 1), (&key_pair2, 2)] {
+        let registration =
+            validator_registration(chain_id, AccountPublicKey::test_key(index), index);
+        let signature = ValidatorSignature::new(&registration, &key_pair.secret_key);
+        view.system
+            .register_validator(key_pair.public_key, registration, signature)
+            .await?;
+    }
+    let registration = validator_registration(chain_id, AccountPublicKey::test_key(3), 1);
+    let signature = ValidatorSignature::new(&registration, &key_pair1.secret_key);
+    assert!(matches!(
+        view.system
+            .register_validator(validator1, registration.clone(), signature)
+            .await,
+        Err(ExecutionError::StakingValidatorAccountMismatch(_))
+    ));
+    // Nobody else can register the validator's key, or register it on another chain.
+    let forged_signature = ValidatorSignature::new(&registration, &key_pair2.secret_key);
+    assert!(matches!(
+        view.system
+            .register_validator(validator1, registration, forged_signature)
+            .await,
+        Err(ExecutionError::InvalidValidatorRegistration(_))
+    ));
+    let registration = validator_registration(
+        dummy_chain_description(1).id(),
+        AccountPublicKey::test_key(1),
+        1,
+    );
+    let signature = ValidatorSignature::new(&registration, &key_pair1.secret_key);
+    assert!(matches!(
+        view.system
+            .register_validator(validator1, registration, signature)
+            .await,
+        Err(ExecutionError::InvalidValidatorRegistration(_))
+    ));
+
+    let delegator = Account::new(
+        dummy_chain_description(1).id(),
+        AccountOwner::from(AccountPublicKey::test_key(4)),
+    );
+    view.system
+        .stake(delegator, validator1, Amount::from_tokens(3))
+        .await?;
+    view.system
+        .stake(delegator, validator2, Amount::from_millis(500))
+        .await?;
+    view.system
+        .unstake(delegator, validator1, Amount::from_tokens(1))
+        .await?;
+    assert!(matches!(
+        view.system
+            .unstake(delegator, validator2, Amount::from_tokens(1))
+            .await,
+        Err(ExecutionError::InsufficientStake { .. })
+    ));
+
+    // Only validators with at least one whole token staked get voting power.
+    let committee = view.system.elect_committee(chain_id).await?;
+    assert_eq!(committee.validators().len(), 1);
+    assert_eq!(committee.validators()[&validator1].votes, 2);
+    assert_eq!(
+        committee.validators()[&validator1].network_address,
+        "address1"
+    );
+    assert_eq!(committee.policy(), &policy);
+
+    // The unstaked tokens are released at the second epoch after the current one.
+    let mut txn_tracker = TransactionTracker::default();
+    view.system
+        .release_unbonded(Epoch(2), &mut txn_tracker)
+        .await?;
+    assert!(txn_tracker.into_outcome()?.outgoing_messages.is_empty());
+    let mut txn_tracker = TransactionTracker::default();
+    view.system
+        .release_unbonded(Epoch(3), &mut txn_tracker)
+        .await?;
+    assert!(view.system.unbonding.indices().await?.is_empty());
+    let messages = txn_tracker.into_outcome()?.outgoing_messages;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].destination, delegator.chain_id);
+    assert!(matches!(
+        messages[0].message,
+        Message::System(SystemMessage::Credit { target, amount, .. })
+            if target == delegator.owner && amount == Amount::from_tokens(1)
+    ));
+
+    Ok(())
+}
+
+/// Tests that a validator proven to have signed conflicting votes is slashed and excluded
+/// from the next elected committee.
+#[tokio::test]
+async fn misbehaving_validator_is_slashed_and_excluded() -> anyhow::Result<()> {
+    use linera_base::{
+        crypto::{AccountPublicKey, ValidatorKeypair, ValidatorSignature},
+        data_types::{CertificateKind, Round, VoteValue},
+    };
+
+    use crate::{
+        evidence::{BlockVote, MisbehaviorEvidence},
+        ResourceControlPolicy,
+    };
+
+    let (mut view, _context) = new_view_and_context().await;
+    let chain_id = view.context().extra().chain_id();
+    view.system.admin_id.set(Some(chain_id));
+    let policy = ResourceControlPolicy {
+        slashing_percentage: 10,
+        ..ResourceControlPolicy::default()
+    };
+    view.system.committees.set(BTreeMap::from([(
+        Epoch(1),
+        Committee::new(BTreeMap::new(), policy),
+    )]));
+    let key_pair = ValidatorKeypair::generate();
+    let honest_key_pair = ValidatorKeypair::generate();
+    let honest_validator = honest_key_pair.public_key;
+    for (validator_key_pair, index) in [(&key_pair, 1), (&honest_key_pair, 2)] {
+        let validator = validator_key_pair.public_key;
+        let registration =
+            validator_registration(chain_id, AccountPublicKey::test_key(index), index);
+        let signature = ValidatorSignature::new(&registration, &validator_key_pair.secret_key);
+        view.system
+            .register_validator(validator, registration, signature)
+            .await?;
+        let delegator = Account::chain(dummy_chain_description(index.into()).id());
+        view.system
+            .stake(delegator, validator, Amount::from_tokens(5))
+            .await?;
+    }
+
+    // Votes for two different blocks at height 3 of the same chain.
+    let round = Round::SingleLeader(2);
+    let kind = CertificateKind::Validated;
+    let vote = |key_pair: &ValidatorKeypair, tag: u8| {
+        let block = bcs::to_bytes(&(chain_id, Epoch(1), BlockHeight(3), tag)).unwrap();
+        let hash = CryptoHash::from_bcs_bytes("Block", &block);
+        let signature =
+            ValidatorSignature::new(&VoteValue(hash, round, kind), &key_pair.secret_key);
+        BlockVote { block, signature }
+    };
+    let evidence = MisbehaviorEvidence {
+        validator: key_pair.public_key,
+        round,
+        kind,
+        first: vote(&key_pair, 0),
+        second: vote(&key_pair, 1),
+    };
+    assert_eq!(evidence.verify()?, (chain_id, BlockHeight(3)));
+    let same_block = MisbehaviorEvidence {
+        second: vote(&key_pair, 0),
+        ..evidence.clone()
+    };
+    assert!(matches!(
+        same_block.verify(),
+        Err(ExecutionError::InvalidMisbehaviorEvidence(_))
+    ));
+    let other_round = MisbehaviorEvidence {
+        round: Round::SingleLeader(3),
+        ..evidence.clone()
+    };
+    assert!(matches!(
+        other_round.verify(),
+        Err(ExecutionError::InvalidMisbehaviorEvidence(_))
+    ));
+
+    // Evidence against a validator that is neither registered nor in the committee is
+    // rejected before anything is recorded.
+    let outsider = ValidatorKeypair::generate();
+    let outsider_evidence = MisbehaviorEvidence {
+        validator: outsider.public_key,
+        round,
+        kind,
+        first: vote(&outsider, 0),
+        second: vote(&outsider, 1),
+    };
+    assert!(matches!(
+        view.system.punish_misbehavior(&outsider_evidence).await,
+        Err(ExecutionError::UnknownMisbehavingValidator(_))
+    ));
+    assert!(view
+        .system
+        .reported_misbehaviors
+        .indices()
+        .await?
+        .is_empty());
+    assert!(view.system.excluded_validators.indices().await?.is_empty());
+
+    // Tokens that are still unbonding are slashed, too.
+    let delegator = Account::chain(dummy_chain_description(1).id());
+    view.system
+        .unstake(delegator, key_pair.public_key, Amount::ONE)
+        .await?;
+    view.system.punish_misbehavior(&evidence).await?;
+    assert!(matches!(
+        view.system.punish_misbehavior(&evidence).await,
+        Err(ExecutionError::MisbehaviorAlreadyReported(_))
+    ));
+    let validator = view
+        .system
+        .staking_validators
+        .get(&key_pair.public_key)
+        .await?
+        .unwrap();
+    assert_eq!(validator.stake, Amount::from_millis(3600));
+    let unbonding = view
+        .system
+        .unbonding
+        .try_load_entry(&key_pair.public_key)
+        .await?
+        .unwrap()
+        .index_values()
+        .await?;
+    assert_eq!(
+        unbonding
+            .into_iter()
+            .flat_map(|(_, entries)| entries)
+            .collect::<Vec<_>>(),
+        vec![(delegator, Amount::from_millis(900))]
+    );
+    // The stakes with other validators are unchanged.
+    let honest_stakes = view
+        .system
+        .stakes
+        .try_load_entry(&honest_validator)
+        .await?
+        .unwrap()
+        .index_values()
+        .await?;
+    assert_eq!(
+        honest_stakes,
+        vec![(
+            Account::chain(dummy_chain_description(2).id()),
+            Amount::from_tokens(5)
+        )]
+    );
+
+    let committee = view.system.elect_committee(chain_id).await?;
+    assert_eq!(
+        committee.validators().keys().collect::<Vec<_>>(),
+        vec![&honest_validator]
+    );
+
+    Ok(())
+}
+
+/// Tests that scheduled operations can only be registered by chain owners, and are handed
+/// out for execution once they are due, repeatedly if they are recurring.
+#[tokio::test]
+async fn scheduled_operations_are_taken_when_due() -> anyhow::Result<()> {
+    use linera_base::crypto::AccountPublicKey;
+
+    let (mut view, context) = new_view_and_context().await;
+    let owner = AccountOwner::from(AccountPublicKey::test_key(1));
+    view.system.ownership.set(ChainOwnership::single(owner));
+    let transfer = Operation::system(SystemOperation::Transfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Account::chain(dummy_chain_description(1).id()),
+        amount: Amount::ONE,
+    });
+    let schedule = SystemOperation::ScheduleOperation {
+        operation: Box::new(transfer.clone()),
+        due: Timestamp::from(10),
+        interval: Some(TimeDelta::from_micros(5)),
+    };
+    let mut txn_tracker = TransactionTracker::default();
+    let mut controller = ResourceController::default();
+    assert!(matches!(
+        view.system
+            .execute_operation(context, schedule.clone(), &mut txn_tracker, &mut controller)
+            .await,
+        Err(ExecutionError::UnauthorizedScheduledOperation)
+    ));
+    let context = OperationContext {
+        authenticated_signer: Some(owner),
+        ..context
+    };
+    view.system
+        .execute_operation(context, schedule, &mut txn_tracker, &mut controller)
+        .await?;
+
+    assert!(matches!(
+        view.system.take_due_operation(0, Timestamp::from(9)).await,
+        Err(ExecutionError::ScheduledOperationNotDue { id: 0, .. })
+    ));
+    // The recurring operation is rescheduled after the interval.
+    let (operation, authenticated_signer) = view
+        .system
+        .take_due_operation(0, Timestamp::from(10))
+        .await?;
+    assert_eq!(operation, transfer);
+    assert_eq!(authenticated_signer, Some(owner));
+    assert_eq!(
+        view.system.scheduled_operations.get(&0).await?.unwrap().due,
+        Timestamp::from(15)
+    );
+
+    // Only an owner can cancel it.
+    let cancel = SystemOperation::CancelScheduledOperation { id: 0 };
+    let stranger_context = OperationContext {
+        authenticated_signer: Some(AccountOwner::from(AccountPublicKey::test_key(2))),
+        ..context
+    };
+    assert!(matches!(
+        view.system
+            .execute_operation(
+                stranger_context,
+                cancel.clone(),
+                &mut txn_tracker,
+                &mut controller
+            )
+            .await,
+        Err(ExecutionError::UnauthorizedScheduledOperation)
+    ));
+    view.system
+        .execute_operation(context, cancel, &mut txn_tracker, &mut controller)
+        .await?;
+    assert!(matches!(
+        view.system.take_due_operation(0, Timestamp::from(20)).await,
+        Err(ExecutionError::ScheduledOperationNotFound(0))
+    ));
+
+    Ok(())
+}
+
+/// Tests that missed and skipped occurrences of scheduled operations are not executed, and
+/// that operations are not executed once their signer is no longer a chain owner.
+#[tokio::test]
+async fn scheduled_operations_are_skipped() -> anyhow::Result<()> {
+    use linera_base::crypto::AccountPublicKey;
+
+    let (mut view, context) = new_view_and_context().await;
+    let owner = AccountOwner::from(AccountPublicKey::test_key(1));
+    let new_owner = AccountOwner::from(AccountPublicKey::test_key(2));
+    view.system.ownership.set(ChainOwnership::single(owner));
+    let context = OperationContext {
+        authenticated_signer: Some(owner),
+        ..context
+    };
+    let schedule = SystemOperation::ScheduleOperation {
+        operation: Box::new(Operation::system(SystemOperation::CloseChain)),
+        due: Timestamp::from(10),
+        interval: Some(TimeDelta::from_micros(5)),
+    };
+    let mut txn_tracker = TransactionTracker::default();
+    let mut controller = ResourceController::default();
+    view.system
+        .execute_operation(context, schedule, &mut txn_tracker, &mut controller)
+        .await?;
+
+    // The occurrences at 10, 15, 20 and 25 were missed: only one is executed.
+    view.system
+        .take_due_operation(0, Timestamp::from(27))
+        .await?;
+    assert_eq!(
+        view.system.scheduled_operations.get(&0).await?.unwrap().due,
+        Timestamp::from(30)
+    );
+
+    // A due occurrence can be skipped, but only by an owner.
+    let skip = SystemOperation::SkipScheduledOperation { id: 0 };
+    let skip_context = OperationContext {
+        timestamp: Timestamp::from(30),
+        ..context
+    };
+    let stranger_context = OperationContext {
+        authenticated_signer: Some(new_owner),
+        ..skip_context
+    };
+    assert!(matches!(
+        view.system
+            .execute_operation(
+                stranger_context,
+                skip.clone(),
+                &mut txn_tracker,
+                &mut controller
+            )
+            .await,
+        Err(ExecutionError::UnauthorizedScheduledOperation)
+    ));
+    view.system
+        .execute_operation(
+            skip_context,
+            skip.clone(),
+            &mut txn_tracker,
+            &mut controller,
+        )
+        .await?;
+    assert_eq!(
+        view.system.scheduled_operations.get(&0).await?.unwrap().due,
+        Timestamp::from(35)
+    );
+
+    // Once the signer is no longer an owner, the operation is not executed, and skipping it
+    // removes it.
+    view.system.ownership.set(ChainOwnership::single(new_owner));
+    assert!(matches!(
+        view.system.take_due_operation(0, Timestamp::from(35)).await,
+        Err(ExecutionError::UnauthorizedScheduledOperation)
+    ));
+    let skip_context = OperationContext {
+        timestamp: Timestamp::from(35),
+        ..stranger_context
+    };
+    view.system
+        .execute_operation(skip_context, skip, &mut txn_tracker, &mut controller)
+        .await?;
+    assert!(view.system.scheduled_operations.indices().await?.is_empty());
+
+    Ok(())
+}
+
+/// Tests that scheduled operations cannot schedule other operations, and that the number of
+/// scheduled operations on a chain is limited.
+#[tokio::test]
+async fn scheduled_operations_are_limited() -> anyhow::Result<()> {
+    let (mut view, _context) = new_view_and_context().await;
+    let close_chain = ScheduledOperation {
+        operation: Operation::system(SystemOperation::CloseChain),
+        authenticated_signer: None,
+        due: Timestamp::from(10),
+        interval: None,
+    };
+    let nested = ScheduledOperation {
+        operation: Operation::system(SystemOperation::ScheduleOperation {
+            operation: Box::new(close_chain.operation.clone()),
+            due: Timestamp::from(10),
+            interval: None,
+        }),
+        ..close_chain.clone()
+    };
+    assert!(matches!(
+        view.system.schedule_operation(nested).await,
+        Err(ExecutionError::InvalidScheduledOperation(_))
+    ));
+
+    for _ in 0..MAX_SCHEDULED_OPERATIONS {
+        view.system.schedule_operation(close_chain.clone()).await?;
+    }
+    assert!(matches!(
+        view.system.schedule_operation(close_chain).await,
+        Err(ExecutionError::TooManyScheduledOperations(_))
+    ));
+
+    Ok(())
+}
+
+/// Tests that locked transfers can only be claimed with the preimage before the deadline,
+/// and refunded after it.
+#[tokio::test]
+async fn locked_transfers_are_claimed_or_refunded() -> anyhow::Result<()> {
+    use linera_base::crypto::AccountPublicKey;
+
+    let (mut view, context) = new_view_and_context().await;
+    let owner = AccountOwner::from(AccountPublicKey::test_key(1));
+    let recipient = AccountOwner::from(AccountPublicKey::test_key(2));
+    view.system.ownership.set(ChainOwnership::single(owner));
+    view.system.balance.set(Amount::from_tokens(2));
+    let context = OperationContext {
+        authenticated_signer: Some(owner),
+        ..context
+    };
+    let deadline = Timestamp::from(10);
+    let preimage = b"secret".to_vec();
+    let lock = SystemOperation::LockTransfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Account::new(context.chain_id, recipient),
+        amount: Amount::ONE,
+        hash_lock: Some(CryptoHash::keccak256(&preimage)),
+        deadline,
+    };
+    let mut txn_tracker = TransactionTracker::default();
+    let mut controller = ResourceController::default();
+    view.system
+        .execute_operation(context, lock.clone(), &mut txn_tracker, &mut controller)
+        .await?;
+    assert_eq!(*view.system.balance.get(), Amount::ONE);
+
+    // Only the recipient can claim the tokens, with the right preimage.
+    let claim = |owner: AccountOwner, preimage: &[u8]| SystemOperation::ClaimLockedTransfer {
+        owner,
+        target_id: context.chain_id,
+        id: 0,
+        preimage: preimage.to_vec(),
+    };
+    for claim in [claim(recipient, &preimage), claim(owner, &preimage)] {
+        assert!(matches!(
+            view.system
+                .execute_operation(context, claim, &mut txn_tracker, &mut controller)
+                .await,
+            Err(ExecutionError::UnauthenticatedClaimOwner)
+        ));
+    }
+    let recipient_context = OperationContext {
+        authenticated_signer: Some(recipient),
+        ..context
+    };
+    assert!(matches!(
+        view.system
+            .execute_operation(
+                recipient_context,
+                claim(recipient, b"wrong"),
+                &mut txn_tracker,
+                &mut controller
+            )
+            .await,
+        Err(ExecutionError::InvalidLockedTransferPreimage)
+    ));
+    let mut claim_view = view.clone_unchecked();
+    claim_view
+        .system
+        .execute_operation(
+            recipient_context,
+            claim(recipient, &preimage),
+            &mut txn_tracker,
+            &mut controller,
+        )
+        .await?;
+    assert_eq!(
+        claim_view.system.balances.get(&recipient).await?,
+        Some(Amount::ONE)
+    );
+
+    // After the deadline, the tokens can no longer be claimed, but refunded.
+    let expired_context = OperationContext {
+        timestamp: deadline,
+        ..recipient_context
+    };
+    assert!(matches!(
+        view.system
+            .execute_operation(
+                expired_context,
+                claim(recipient, &preimage),
+                &mut txn_tracker,
+                &mut controller
+            )
+            .await,
+        Err(ExecutionError::LockedTransferExpired(_))
+    ));
+    let refund = SystemOperation::RefundLockedTransfer { id: 0 };
+    assert!(matches!(
+        view.system
+            .execute_operation(context, refund.clone(), &mut txn_tracker, &mut controller)
+            .await,
+        Err(ExecutionError::LockedTransferNotExpired(_))
+    ));
+    view.system
+        .execute_operation(expired_context, refund, &mut txn_tracker, &mut controller)
+        .await?;
+    assert_eq!(*view.system.balance.get(), Amount::from_tokens(2));
+    assert!(view.system.locked_transfers.indices().await?.is_empty());
+
+    Ok(())
+}
+
+/// Tests that locked transfers to other chains stay on the sender's chain, where they are
+/// claimed by messages, or refunded without the recipient's cooperation.
+#[tokio::test]
+async fn cross_chain_locked_transfers_are_claimed_or_refunded() -> anyhow::Result<()> {
+    use linera_base::crypto::AccountPublicKey;
+
+    use crate::Message;
+
+    let (mut view, context) = new_view_and_context().await;
+    let owner = AccountOwner::from(AccountPublicKey::test_key(1));
+    let recipient = AccountOwner::from(AccountPublicKey::test_key(2));
+    let other_chain_id = dummy_chain_description(1).id();
+    view.system.ownership.set(ChainOwnership::single(owner));
+    view.system.balance.set(Amount::from_tokens(2));
+    let context = OperationContext {
+        authenticated_signer: Some(owner),
+        ..context
+    };
+    let deadline = Timestamp::from(10);
+    let lock = SystemOperation::LockTransfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Account::new(other_chain_id, recipient),
+        amount: Amount::ONE,
+        hash_lock: None,
+        deadline,
+    };
+    let mut txn_tracker = TransactionTracker::default();
+    let mut controller = ResourceController::default();
+    for _ in 0..2 {
+        view.system
+            .execute_operation(context, lock.clone(), &mut txn_tracker, &mut controller)
+            .await?;
+    }
+    assert_eq!(*view.system.balance.get(), Amount::ZERO);
+    assert!(txn_tracker.into_outcome()?.outgoing_messages.is_empty());
+
+    // The recipient claims the first transfer from their chain, before the deadline.
+    let recipient_context = OperationContext {
+        chain_id: other_chain_id,
+        authenticated_signer: Some(recipient),
+        ..context
+    };
+    let claim = SystemOperation::ClaimLockedTransfer {
+        owner: recipient,
+        target_id: context.chain_id,
+        id: 0,
+        preimage: Vec::new(),
+    };
+    let mut txn_tracker = TransactionTracker::default();
+    view.system
+        .execute_operation(recipient_context, claim, &mut txn_tracker, &mut controller)
+        .await?;
+    let messages = txn_tracker.into_outcome()?.outgoing_messages;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].destination, context.chain_id);
+    let Message::System(message) = messages[0].message.clone() else {
+        panic!("unexpected message {:?}", messages[0].message);
+    };
+
+    // The claim is only accepted from the recipient's chain, before the deadline on this
+    // chain.
+    let message_context = MessageContext {
+        chain_id: context.chain_id,
+        origin: context.chain_id,
+        is_bouncing: false,
+        authenticated_signer: Some(recipient),
+        refund_grant_to: None,
+        height: context.height,
+        round: context.round,
+        timestamp: context.timestamp,
+    };
+    assert!(matches!(
+        view.system
+            .execute_message(message_context, message.clone())
+            .await,
+        Err(ExecutionError::UnauthenticatedClaimOwner)
+    ));
+    let message_context = MessageContext {
+        origin: other_chain_id,
+        ..message_context
+    };
+    let outcome = view
+        .system
+        .execute_message(message_context, message)
+        .await?;
+    assert_eq!(outcome.len(), 1);
+    assert_eq!(outcome[0].destination, other_chain_id);
+
+    // A claim of the second transfer made before the deadline on the recipient's chain is
+    // rejected if it arrives after the deadline, and the transfer remains locked.
+    let late_claim = SystemOperation::ClaimLockedTransfer {
+        owner: recipient,
+        target_id: context.chain_id,
+        id: 1,
+        preimage: Vec::new(),
+    };
+    let mut txn_tracker = TransactionTracker::default();
+    view.system
+        .execute_operation(
+            recipient_context,
+            late_claim,
+            &mut txn_tracker,
+            &mut controller,
+        )
+        .await?;
+    let messages = txn_tracker.into_outcome()?.outgoing_messages;
+    let Message::System(late_message) = messages[0].message.clone() else {
+        panic!("unexpected message {:?}", messages[0].message);
+    };
+    let late_context = MessageContext {
+        timestamp: deadline,
+        ..message_context
+    };
+    assert!(matches!(
+        view.system
+            .execute_message(late_context, late_message)
+            .await,
+        Err(ExecutionError::LockedTransferExpired(_))
+    ));
+    assert!(view.system.locked_transfers.contains_key(&1).await?);
+
+    // The second transfer is then refunded on this chain alone.
+    let expired_context = OperationContext {
+        timestamp: deadline,
+        ..context
+    };
+    let refund = SystemOperation::RefundLockedTransfer { id: 1 };
+    let mut txn_tracker = TransactionTracker::default();
+    view.system
+        .execute_operation(expired_context, refund, &mut txn_tracker, &mut controller)
+        .await?;
+    assert_eq!(*view.system.balance.get(), Amount::ONE);
+    assert!(view.system.locked_transfers.indices().await?.is_empty());
+
+    Ok(())
+}
@@ -5,7 +5,8 @@ use std::{any::Any, collections::HashMap, marker::PhantomData};
 
 use linera_base::{
     data_types::{
-        Amount, ApplicationPermissions, BlockHeight, Bytecode, SendMessageRequest, Timestamp,
+        Amount, ApplicationPermissions, BlockHeight, Bytecode, SendMessageRequest, TimeDelta,
+        Timestamp,
     },
     http,
     identifiers::{Account, AccountOwner, ApplicationId, ChainId, StreamName},
@@ -19,6 +20,10 @@ use tracing::log;
 use super::WasmExecutionError;
 use crate::{BaseRuntime, ContractRuntime, DataBlobHash, ExecutionError, ModuleId, ServiceRuntime};
 
+/// The Wasm fuel consumed by scheduling an operation. Storing the operation is charged
+/// separately, as a write operation.
+const SCHEDULE_OPERATION_FUEL: u64 = 100_000;
+
 /// Common host data used as the `UserData` of the system API implementations.
 pub struct RuntimeApiData<Runtime> {
     runtime: Runtime,
@@ -499,6 +504,24 @@ where
         }
     }
 
+    /// Schedules an operation of the current application to be executed on the current chain
+    /// once `due` is reached, and then again after every `interval`, if any. Returns the ID of
+    /// the scheduled operation.
+    fn schedule_operation(
+        caller: &mut Caller,
+        due: Timestamp,
+        interval: Option<TimeDelta>,
+        operation: Vec<u8>,
+    ) -> Result<u64, RuntimeError> {
+        let runtime = caller.user_data_mut().runtime_mut();
+        runtime
+            .consume_fuel(SCHEDULE_OPERATION_FUEL, VmRuntime::Wasm)
+            .map_err(|error| RuntimeError::Custom(error.into()))?;
+        runtime
+            .schedule_operation(due, interval, operation)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Changes the application permissions for the current chain. Returns an error if the
     /// application doesn't have permission to do so.
     fn change_application_permissions(
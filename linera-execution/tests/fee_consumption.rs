@@ -17,7 +17,7 @@ use linera_execution::{
         blob_oracle_responses, dummy_chain_description, ExpectedCall, RegisterMockApplication,
         SystemExecutionState,
     },
-    ContractRuntime, ExecutionError, ExecutionStateActor, Message, MessageContext,
+    ContractRuntime, ExecutionError, ExecutionStateActor, FeeSink, Message, MessageContext,
     ResourceControlPolicy, ResourceController, ResourceTracker, TransactionTracker,
 };
 use test_case::test_case;
@@ -239,6 +239,9 @@ async fn test_fee_consumption(
         blob_byte_read: Amount::from_tokens(101),
         blob_byte_published: Amount::from_tokens(103),
         http_request_allow_list: BTreeSet::new(),
+        fee_sink: FeeSink::Burn,
+        unbonding_epochs: 0,
+        slashing_percentage: 0,
     };
 
     let consumed_fees = spends
@@ -303,6 +306,7 @@ async fn test_fee_consumption(
 
     let txn_outcome = txn_tracker.into_outcome()?;
     assert!(txn_outcome.outgoing_messages.is_empty());
+    assert_eq!(controller.tracker.fees, consumed_fees);
 
     match initial_grant {
         None => {
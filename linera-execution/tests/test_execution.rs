@@ -10,7 +10,7 @@ use linera_base::{
     crypto::{AccountPublicKey, ValidatorPublicKey},
     data_types::{
         Amount, ApplicationPermissions, Blob, BlockHeight, ChainDescription, ChainOrigin, Epoch,
-        InitialChainConfig, Resources, SendMessageRequest, Timestamp,
+        InitialChainConfig, Resources, SendMessageRequest, TimeDelta, Timestamp,
     },
     identifiers::{Account, AccountOwner, BlobType},
     ownership::ChainOwnership,
@@ -1330,6 +1330,78 @@ async fn test_close_chain() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Tests the contract API call `schedule_operation`.
+#[tokio::test]
+async fn test_schedule_operation() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+    let due = Timestamp::from(10);
+    let interval = TimeDelta::from_micros(5);
+
+    // The application schedules one of its own operations.
+    let context = create_dummy_operation_context(chain_id);
+    application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            assert_eq!(
+                runtime.schedule_operation(due, Some(interval), b"scheduled".to_vec())?,
+                0
+            );
+            Ok(vec![])
+        },
+    ));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let mut controller = ResourceController::default();
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+    let mut txn_tracker = TransactionTracker::new_replaying_blobs(blobs);
+    ExecutionStateActor::new(&mut view, &mut txn_tracker, &mut controller)
+        .execute_operation(context, operation)
+        .await?;
+    // Storing the scheduled operation is charged like a write.
+    assert_eq!(controller.tracker.write_operations, 1);
+    assert_eq!(controller.tracker.bytes_written, b"scheduled".len() as u64);
+    let scheduled = view.system.scheduled_operations.get(&0).await?.unwrap();
+    assert_eq!(
+        scheduled.operation,
+        Operation::User {
+            application_id,
+            bytes: b"scheduled".to_vec(),
+        }
+    );
+    assert_eq!(scheduled.authenticated_signer, None);
+    assert_eq!(scheduled.due, due);
+
+    // Once it is due, the operation is executed by the application, without a signer.
+    application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, operation| {
+            assert_eq!(operation, b"scheduled");
+            assert_eq!(runtime.authenticated_signer()?, None);
+            Ok(vec![])
+        },
+    ));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let context = OperationContext {
+        timestamp: due,
+        ..context
+    };
+    let operation = SystemOperation::ExecuteScheduledOperation { id: 0 };
+    let mut txn_tracker = TransactionTracker::new_replaying(Vec::new());
+    ExecutionStateActor::new(&mut view, &mut txn_tracker, &mut controller)
+        .execute_operation(context, operation.into())
+        .await?;
+    assert_eq!(
+        view.system.scheduled_operations.get(&0).await?.unwrap().due,
+        due.saturating_add(interval)
+    );
+
+    Ok(())
+}
+
 /// Tests an application attempting to transfer the tokens in the chain's balance while executing
 /// messages.
 #[test_case(
@@ -299,6 +299,19 @@ impl SqliteDatabase {
                     SystemOperation::UpdateStreams(_) => "UpdateStreams",
                     SystemOperation::ChangeOwnership { .. } => "ChangeOwnership",
                     SystemOperation::VerifyBlob { .. } => "VerifyBlob",
+                    SystemOperation::RegisterValidator { .. } => "RegisterValidator",
+                    SystemOperation::Stake { .. } => "Stake",
+                    SystemOperation::Unstake { .. } => "Unstake",
+                    SystemOperation::ReportMisbehavior(_) => "ReportMisbehavior",
+                    SystemOperation::ScheduleOperation { .. } => "ScheduleOperation",
+                    SystemOperation::CancelScheduledOperation { .. } => "CancelScheduledOperation",
+                    SystemOperation::ExecuteScheduledOperation { .. } => {
+                        "ExecuteScheduledOperation"
+                    }
+                    SystemOperation::LockTransfer { .. } => "LockTransfer",
+                    SystemOperation::ClaimLockedTransfer { .. } => "ClaimLockedTransfer",
+                    SystemOperation::RefundLockedTransfer { .. } => "RefundLockedTransfer",
+                    SystemOperation::SkipScheduledOperation { .. } => "SkipScheduledOperation",
                 };
                 ("System", None, Some(sys_op_type))
             }
@@ -1045,6 +1058,40 @@ impl SqliteDatabase {
                         Some(owner.to_string()),
                         Some(recipient.to_string()),
                     ),
+                    SystemMessage::RegisterValidator { .. } => {
+                        ("RegisterValidator", None, None, None, None, None)
+                    }
+                    SystemMessage::Stake {
+                        delegator, amount, ..
+                    } => (
+                        "Stake",
+                        None,
+                        Some(*amount),
+                        None,
+                        Some(delegator.to_string()),
+                        None,
+                    ),
+                    SystemMessage::Unstake {
+                        delegator, amount, ..
+                    } => (
+                        "Unstake",
+                        None,
+                        Some(*amount),
+                        None,
+                        Some(delegator.to_string()),
+                        None,
+                    ),
+                    SystemMessage::ReportMisbehavior(_) => {
+                        ("ReportMisbehavior", None, None, None, None, None)
+                    }
+                    SystemMessage::ClaimLockedTransfer { owner, .. } => (
+                        "ClaimLockedTransfer",
+                        None,
+                        None,
+                        None,
+                        Some(owner.to_string()),
+                        None,
+                    ),
                 };
 
                 MessageClassification {
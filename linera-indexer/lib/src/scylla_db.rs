@@ -56,6 +56,7 @@ impl ScyllaDbRunner {
             max_cache_size: config.client.max_cache_size,
             max_entry_size: config.client.max_entry_size,
             max_cache_entries: config.client.max_cache_entries,
+            shared_database: false,
         };
         let inner_config = ScyllaDbStoreInternalConfig {
             uri: config.client.uri.clone(),
@@ -17,7 +17,7 @@ use linera_chain::{
 use linera_core::{data_types::CrossChainRequest, node::NodeError};
 use linera_execution::{
     system::{AdminOperation, SystemMessage, SystemOperation},
-    Message, MessageKind, Operation,
+    FeeSink, Message, MessageKind, Operation,
 };
 use linera_rpc::RpcMessage;
 use serde_reflection::{Registry, Result, Samples, Tracer, TracerConfig};
@@ -85,6 +85,7 @@ fn get_registry() -> Result<Registry> {
     tracer.trace_type::<BlobType>(&samples)?;
     tracer.trace_type::<BlobContent>(&samples)?;
     tracer.trace_type::<AccountOwner>(&samples)?;
+    tracer.trace_type::<FeeSink>(&samples)?;
     tracer.registry()
 }
 
@@ -7,6 +7,7 @@ use linera_base::{
     crypto::CryptoHash,
     data_types::{
         Amount, ApplicationPermissions, Bytecode, Resources, SendMessageRequest, TimeDelta,
+        Timestamp,
     },
     identifiers::{
         Account, AccountOwner, ApplicationId, ChainId, DataBlobHash, ModuleId, StreamName,
@@ -172,6 +173,14 @@ impl From<TimeDelta> for wit_contract_api::TimeDelta {
     }
 }
 
+impl From<Timestamp> for wit_contract_api::Timestamp {
+    fn from(timestamp: Timestamp) -> Self {
+        Self {
+            inner0: timestamp.micros(),
+        }
+    }
+}
+
 impl From<TimeoutConfig> for wit_contract_api::TimeoutConfig {
     fn from(config: TimeoutConfig) -> Self {
         let TimeoutConfig {
@@ -7,7 +7,7 @@ use linera_base::{
     abi::{ContractAbi, ServiceAbi},
     data_types::{
         Amount, ApplicationPermissions, BlockHeight, Bytecode, Resources, SendMessageRequest,
-        Timestamp,
+        TimeDelta, Timestamp,
     },
     ensure, http,
     identifiers::{
@@ -334,6 +334,20 @@ where
         contract_wit::close_chain().map_err(|error| error.into())
     }
 
+    /// Schedules an operation of this application to be executed on the current chain once
+    /// `due` is reached, and then again after every `interval`, if any. Returns the ID of
+    /// the scheduled operation.
+    pub fn schedule_operation(
+        &mut self,
+        due: Timestamp,
+        interval: Option<TimeDelta>,
+        operation: &Application::Operation,
+    ) -> u64 {
+        let operation_bytes = Application::serialize_operation(operation)
+            .expect("Failed to serialize `Operation` to be scheduled");
+        contract_wit::schedule_operation(due.into(), interval.map(Into::into), &operation_bytes)
+    }
+
     /// Changes the application permissions for the current chain.
     pub fn change_application_permissions(
         &mut self,
@@ -12,7 +12,7 @@ use linera_base::{
     abi::{ContractAbi, ServiceAbi},
     data_types::{
         Amount, ApplicationPermissions, BlockHeight, Bytecode, Resources, SendMessageRequest,
-        Timestamp,
+        TimeDelta, Timestamp,
     },
     ensure, http,
     identifiers::{
@@ -74,6 +74,7 @@ where
     created_events: BTreeMap<StreamName, Vec<Vec<u8>>>,
     events: BTreeMap<(ChainId, StreamName, u32), Vec<u8>>,
     claim_requests: Vec<ClaimRequest>,
+    scheduled_operations: Vec<(Timestamp, Option<TimeDelta>, Vec<u8>)>,
     expected_service_queries: VecDeque<(ApplicationId, String, String)>,
     expected_http_requests: VecDeque<(http::Request, http::Response)>,
     expected_read_data_blob_requests: VecDeque<(DataBlobHash, Vec<u8>)>,
@@ -123,6 +124,7 @@ where
             created_events: BTreeMap::new(),
             events: BTreeMap::new(),
             claim_requests: Vec::new(),
+            scheduled_operations: Vec::new(),
             expected_service_queries: VecDeque::new(),
             expected_http_requests: VecDeque::new(),
             expected_read_data_blob_requests: VecDeque::new(),
@@ -636,6 +638,36 @@ where
         }
     }
 
+    /// Schedules an operation of this application to be executed on the current chain once
+    /// `due` is reached, and then again after every `interval`, if any. Returns the ID of
+    /// the scheduled operation.
+    pub fn schedule_operation(
+        &mut self,
+        due: Timestamp,
+        interval: Option<TimeDelta>,
+        operation: &Application::Operation,
+    ) -> u64 {
+        let operation_bytes = Application::serialize_operation(operation)
+            .expect("Failed to serialize `Operation` to be scheduled");
+        self.scheduled_operations
+            .push((due, interval, operation_bytes));
+        self.scheduled_operations.len() as u64 - 1
+    }
+
+    /// Returns the operations scheduled during the test so far, in the order of their IDs.
+    pub fn scheduled_operations(
+        &self,
+    ) -> Vec<(Timestamp, Option<TimeDelta>, Application::Operation)> {
+        self.scheduled_operations
+            .iter()
+            .map(|(due, interval, operation_bytes)| {
+                let operation = Application::deserialize_operation(operation_bytes.clone())
+                    .expect("Failed to deserialize scheduled `Operation`");
+                (*due, *interval, operation)
+            })
+            .collect()
+    }
+
     /// Changes the application permissions on the current chain. Returns an error if the
     /// application doesn't have permission to do so.
     pub fn change_application_permissions(
@@ -16,8 +16,10 @@ use linera_client::{
     client_options::{
         ApplicationPermissionsConfig, ChainOwnershipConfig, ResourceControlPolicyConfig,
     },
+    remote_signer::SignerAddress,
     util,
 };
+use linera_execution::FeeSink;
 use linera_rpc::config::CrossChainConfig;
 
 const DEFAULT_TOKENS_PER_CHAIN: Amount = Amount::from_millis(100);
@@ -169,8 +171,9 @@ impl BenchmarkCommand {
 
 #[cfg(feature = "kubernetes")]
 use crate::cli_wrappers::local_kubernetes_net::BuildMode;
-use crate::util::{
-    DEFAULT_PAUSE_AFTER_GQL_MUTATIONS_SECS, DEFAULT_PAUSE_AFTER_LINERA_SERVICE_SECS,
+use crate::{
+    storage::StorageConfig,
+    util::{DEFAULT_PAUSE_AFTER_GQL_MUTATIONS_SECS, DEFAULT_PAUSE_AFTER_LINERA_SERVICE_SECS},
 };
 
 #[derive(Clone, clap::Subcommand)]
@@ -395,6 +398,57 @@ pub enum ClientCommand {
     /// Deprecates all committees up to and including the specified one.
     RevokeEpochs { epoch: Epoch },
 
+    /// Register a validator for staking on the admin chain, or update its network address.
+    ///
+    /// The registration is signed with the validator's secret key. The block is proposed on
+    /// the given chain and must be signed by the owner of the validator's account.
+    RegisterValidator {
+        /// Chain ID (must be one of our chains).
+        #[arg(long = "from")]
+        chain_id: Option<ChainId>,
+
+        /// Path to the server configuration of the validator, holding its secret key.
+        #[arg(long = "server")]
+        server_config_path: PathBuf,
+
+        /// Network address. Defaults to the public network of the server configuration.
+        #[arg(long)]
+        address: Option<String>,
+    },
+
+    /// Stake tokens with a validator registered on the admin chain.
+    Stake {
+        /// The account whose tokens are staked (must be on one of our chains).
+        #[arg(long = "from")]
+        owner: Account,
+
+        /// The public key of the validator.
+        #[arg(long)]
+        validator: ValidatorPublicKey,
+
+        /// Amount to stake.
+        amount: Amount,
+    },
+
+    /// Unstake tokens staked with a validator. They are credited back to the account after
+    /// the unbonding delay.
+    Unstake {
+        /// The account whose tokens were staked (must be on one of our chains).
+        #[arg(long = "from")]
+        owner: Account,
+
+        /// The public key of the validator.
+        #[arg(long)]
+        validator: ValidatorPublicKey,
+
+        /// Amount to unstake.
+        amount: Amount,
+    },
+
+    /// Elect a new committee from the tokens staked with the registered validators (admin
+    /// only).
+    ElectCommittee,
+
     /// View or update the resource control policy
     ResourceControlPolicy {
         /// Set the price per unit of Wasm fuel.
@@ -525,6 +579,19 @@ pub enum ClientCommand {
         /// Set the list of hosts that contracts and services can send HTTP requests to.
         #[arg(long)]
         http_request_allow_list: Option<Vec<String>>,
+
+        /// Set where the execution fees go: `burn`, `validators`, or the owner of an
+        /// account on the admin chain.
+        #[arg(long)]
+        fee_sink: Option<FeeSink>,
+
+        /// Set the number of epochs that unstaked tokens stay bonded.
+        #[arg(long)]
+        unbonding_epochs: Option<u32>,
+
+        /// Set the percentage of the stakes slashed when a validator signs conflicting votes.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        slashing_percentage: Option<u8>,
     },
 
     /// Run benchmarks to test network performance.
@@ -714,6 +781,22 @@ pub enum ClientCommand {
         #[arg(long)]
         http_request_allow_list: Option<Vec<String>>,
 
+        /// Set where the execution fees go: `burn`, `validators`, or the owner of an
+        /// account on the admin chain.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        fee_sink: Option<FeeSink>,
+
+        /// Set the number of epochs that unstaked tokens stay bonded.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        unbonding_epochs: Option<u32>,
+
+        /// Set the percentage of the stakes slashed when a validator signs conflicting votes.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        slashing_percentage: Option<u8>,
+
         /// Force this wallet to generate keys using a PRNG and a given seed. USE FOR
         /// TESTING ONLY.
         #[arg(long)]
@@ -946,7 +1029,7 @@ pub enum ClientCommand {
 
     /// Operation on the storage.
     #[command(subcommand)]
-    Storage(DatabaseToolCommand),
+    Storage(StorageCommand),
 
     /// Print CLI help in Markdown format, and exit.
     #[command(hide = true)]
@@ -994,6 +1077,10 @@ impl ClientCommand {
             | ClientCommand::RemoveValidator { .. }
             | ClientCommand::ResourceControlPolicy { .. }
             | ClientCommand::RevokeEpochs { .. }
+            | ClientCommand::RegisterValidator { .. }
+            | ClientCommand::Stake { .. }
+            | ClientCommand::Unstake { .. }
+            | ClientCommand::ElectCommittee
             | ClientCommand::CreateGenesisConfig { .. }
             | ClientCommand::PublishModule { .. }
             | ClientCommand::ListEventsFromIndex { .. }
@@ -1021,6 +1108,31 @@ impl ClientCommand {
     }
 }
 
+/// The operations on the storage.
+#[derive(Clone, clap::Parser)]
+pub enum StorageCommand {
+    /// An operation on a single storage.
+    #[command(flatten)]
+    Database(DatabaseToolCommand),
+
+    /// Copy every key-value pair of a namespace from one storage to another, possibly
+    /// with a different backend
+    Migrate {
+        /// The storage configuration to copy from.
+        #[arg(long)]
+        from: StorageConfig,
+
+        /// The storage configuration to copy to.
+        #[arg(long)]
+        to: StorageConfig,
+
+        /// The file recording the progress of the migration, used to resume it after an
+        /// interruption.
+        #[arg(long, default_value = "linera-migration.json")]
+        checkpoint: PathBuf,
+    },
+}
+
 #[derive(Clone, clap::Parser)]
 pub enum DatabaseToolCommand {
     /// Delete all the namespaces in the database
@@ -1046,6 +1158,69 @@ pub enum DatabaseToolCommand {
 
     /// List the chain IDs in the database
     ListChainIds,
+
+    /// Export the state, confirmed certificates and blobs of a chain into a compressed
+    /// snapshot file
+    ExportChain {
+        /// The chain to export.
+        chain_id: ChainId,
+
+        /// The snapshot file to create.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Verify a chain snapshot file against the committees known to the database and
+    /// restore the chain into the database
+    ImportChain {
+        /// The snapshot file to import.
+        input: PathBuf,
+
+        /// Only verify the snapshot, without writing to the database.
+        #[arg(long)]
+        verify_only: bool,
+    },
+
+    /// Delete the old certificates and archived chain states of every chain, and
+    /// optionally the data blobs that are no longer used
+    Prune {
+        /// The number of most recent blocks of each chain to keep.
+        #[arg(long, default_value = "1000")]
+        keep_heights: u64,
+
+        /// Also keep the blocks created less than this number of seconds ago.
+        #[arg(long = "keep-for-secs", value_parser = util::parse_secs)]
+        keep_for: Option<Duration>,
+
+        /// Also delete the data blobs whose last user was deleted.
+        #[arg(long)]
+        prune_blobs: bool,
+    },
+
+    /// Print a typed dump of the state of a chain, or the differences between two states
+    /// of a chain
+    Inspect {
+        /// The chain to inspect.
+        chain_id: ChainId,
+
+        /// Only dump the key-value pairs of this application.
+        #[arg(long)]
+        application_id: Option<ApplicationId>,
+
+        /// Inspect the state archived after the block at this height instead of the
+        /// current state.
+        #[arg(long)]
+        height: Option<BlockHeight>,
+
+        /// Print the differences with the state archived after the block at this height.
+        #[arg(long, conflicts_with = "diff_namespace")]
+        diff_height: Option<BlockHeight>,
+
+        /// Print the differences with the state of the chain in this other namespace of
+        /// the same storage.
+        #[arg(long)]
+        diff_namespace: Option<String>,
+    },
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -1202,6 +1377,21 @@ pub enum WalletCommand {
         /// TESTING ONLY.
         #[arg(long)]
         testing_prng_seed: Option<u64>,
+
+        /// Encrypt the keystore with the passphrase given by `--keystore-passphrase-file`
+        /// or `LINERA_KEYSTORE_PASSPHRASE`.
+        #[arg(long)]
+        encrypt_keystore: bool,
+
+        /// Do not store any secret key locally and delegate signing to the daemon at the
+        /// given address, e.g. `unix:/run/linera-signer.sock` or `tcp:127.0.0.1:9000`.
+        #[arg(long, requires = "remote_signer_token_file")]
+        remote_signer: Option<SignerAddress>,
+
+        /// The file containing the token shared with the signing daemon, as written by
+        /// `linera-signer --token-file`.
+        #[arg(long, requires = "remote_signer")]
+        remote_signer_token_file: Option<PathBuf>,
     },
 
     /// Request a new chain from a faucet and add it to the wallet.
@@ -1230,6 +1420,29 @@ pub enum WalletCommand {
 
     /// Forgets the specified chain, including the associated key pair.
     ForgetChain { chain_id: ChainId },
+
+    /// Encrypt the secret keys of a plain keystore with the passphrase given by
+    /// `--keystore-passphrase-file` or `LINERA_KEYSTORE_PASSPHRASE`.
+    EncryptKeystore,
+
+    /// Re-encrypt the keys of an encrypted keystore with a new passphrase.
+    ChangePassphrase {
+        /// The file containing the new passphrase.
+        #[arg(long)]
+        new_passphrase_file: PathBuf,
+    },
+
+    /// Replace the key of the chain's preferred owner by a newly generated one, both
+    /// on chain and in the wallet, and remove the old key from the keystore.
+    RotateKey {
+        /// The chain whose key is rotated (if not specified, the default chain from the
+        /// wallet is used).
+        chain_id: Option<ChainId>,
+    },
+
+    /// Remove the secret key of the given owner from the keystore. Keys of chains in the
+    /// wallet are only removed by `forget-keys` or `rotate-key`.
+    RemoveKey { owner: AccountOwner },
 }
 
 #[derive(Clone, clap::Subcommand)]
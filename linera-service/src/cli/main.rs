@@ -38,8 +38,8 @@ use chrono::Utc;
 use colored::Colorize;
 use futures::{lock::Mutex, FutureExt as _, StreamExt};
 use linera_base::{
-    crypto::{InMemorySigner, Signer},
-    data_types::{ApplicationPermissions, Timestamp},
+    crypto::{Signer, ValidatorSignature},
+    data_types::{ApplicationPermissions, TimeDelta, Timestamp},
     identifiers::{AccountOwner, ChainId},
     listen_for_shutdown_signals,
     ownership::ChainOwnership,
@@ -50,7 +50,9 @@ use linera_client::{
     chain_listener::{ChainListener, ChainListenerConfig, ClientContext as _},
     client_context::ClientContext,
     client_options::ClientContextOptions,
-    config::{CommitteeConfig, GenesisConfig},
+    config::{CommitteeConfig, GenesisConfig, ValidatorServerConfig},
+    keystore::Keystore,
+    remote_signer::SignerAddress,
     wallet::{UserChain, Wallet},
 };
 use linera_core::{
@@ -62,7 +64,7 @@ use linera_core::{
 };
 use linera_execution::{
     committee::{Committee, ValidatorState},
-    WasmRuntime, WithWasmDefault as _,
+    ValidatorRegistration, WasmRuntime, WithWasmDefault as _,
 };
 use linera_faucet_server::{FaucetConfig, FaucetService};
 #[cfg(with_metrics)]
@@ -72,17 +74,19 @@ use linera_service::{
     cli::{
         command::{
             BenchmarkCommand, BenchmarkOptions, ChainCommand, ClientCommand, DatabaseToolCommand,
-            NetCommand, ProjectCommand, WalletCommand,
+            NetCommand, ProjectCommand, StorageCommand, WalletCommand,
         },
         net_up_utils,
     },
     cli_wrappers::{self, local_net::PathProvider, ClientWrapper, Network, OnClientDrop},
+    inspect,
+    migration::MigrateJob,
     node_service::NodeService,
     project::{self, Project},
     storage::{CommonStorageOptions, Runnable, RunnableWithStore, StorageConfig},
     util, wallet,
 };
-use linera_storage::{DbStorage, Storage};
+use linera_storage::{DbStorage, PruningPolicy, Storage};
 use linera_views::store::{KeyValueDatabase, KeyValueStore};
 use serde_json::Value;
 use tempfile::NamedTempFile;
@@ -166,7 +170,10 @@ impl Runnable for Job {
                 balance,
                 super_owner,
             } => {
-                let new_owner = owner.unwrap_or_else(|| signer.generate_new().into());
+                let new_owner = match owner {
+                    Some(owner) => owner,
+                    None => signer.generate_new()?.into(),
+                };
                 signer.persist().await?;
                 let mut context = ClientContext::new(
                     storage,
@@ -748,6 +755,9 @@ impl Runnable for Job {
                                     maximum_http_response_bytes,
                                     http_request_timeout_ms,
                                     http_request_allow_list,
+                                    fee_sink,
+                                    unbonding_epochs,
+                                    slashing_percentage,
                                 } => {
                                     let existing_policy = policy.clone();
                                     policy = linera_execution::ResourceControlPolicy {
@@ -820,6 +830,11 @@ impl Runnable for Job {
                                         http_request_allow_list: http_request_allow_list
                                             .map(BTreeSet::from_iter)
                                             .unwrap_or(existing_policy.http_request_allow_list),
+                                        fee_sink: fee_sink.unwrap_or(existing_policy.fee_sink),
+                                        unbonding_epochs: unbonding_epochs
+                                            .unwrap_or(existing_policy.unbonding_epochs),
+                                        slashing_percentage: slashing_percentage
+                                            .unwrap_or(existing_policy.slashing_percentage),
                                     };
                                     info!("{policy}");
                                     if committee.policy() == &policy {
@@ -876,6 +891,132 @@ impl Runnable for Job {
                 );
             }
 
+            RegisterValidator {
+                chain_id,
+                server_config_path,
+                address,
+            } => {
+                let server_config: ValidatorServerConfig = util::read_json(&server_config_path)
+                    .with_context(|| {
+                        format!(
+                            "Failed to read server config {}",
+                            server_config_path.display()
+                        )
+                    })?;
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                let public_key = server_config.validator.public_key;
+                let registration = ValidatorRegistration {
+                    admin_id: context.wallet.genesis_admin_chain(),
+                    account_public_key: server_config.validator.account_key,
+                    network_address: address
+                        .unwrap_or_else(|| server_config.validator.network.to_string()),
+                };
+                let signature =
+                    ValidatorSignature::new(&registration, &server_config.validator_secret);
+                info!("Registering validator {public_key} for staking");
+                let time_start = Instant::now();
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        let registration = registration.clone();
+                        async move {
+                            chain_client
+                                .register_validator(public_key, registration, signature)
+                                .await
+                        }
+                    })
+                    .await
+                    .context("Failed to register validator")?;
+                let time_total = time_start.elapsed();
+                info!("Registration confirmed after {} ms", time_total.as_millis());
+                debug!("{:?}", certificate);
+            }
+
+            Stake {
+                owner,
+                validator,
+                amount,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_client = context.make_chain_client(owner.chain_id);
+                info!("Staking {amount} native tokens of {owner} with validator {validator}");
+                let time_start = Instant::now();
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        async move { chain_client.stake(owner.owner, validator, amount).await }
+                    })
+                    .await
+                    .context("Failed to stake")?;
+                let time_total = time_start.elapsed();
+                info!("Staking confirmed after {} ms", time_total.as_millis());
+                debug!("{:?}", certificate);
+            }
+
+            Unstake {
+                owner,
+                validator,
+                amount,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_client = context.make_chain_client(owner.chain_id);
+                info!("Unstaking {amount} native tokens of {owner} from validator {validator}");
+                let time_start = Instant::now();
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        async move { chain_client.unstake(owner.owner, validator, amount).await }
+                    })
+                    .await
+                    .context("Failed to unstake")?;
+                let time_total = time_start.elapsed();
+                info!("Unstaking confirmed after {} ms", time_total.as_millis());
+                debug!("{:?}", certificate);
+            }
+
+            ElectCommittee => {
+                info!("Starting operations to elect a new committee");
+                let time_start = Instant::now();
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_client = context.make_chain_client(context.wallet.genesis_admin_chain());
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        async move { chain_client.elect_committee().await }
+                    })
+                    .await
+                    .context("Failed to elect committee")?;
+                context.save_wallet().await?;
+                let time_total = time_start.elapsed();
+                info!(
+                    "Committee election confirmed after {} ms",
+                    time_total.as_millis()
+                );
+                debug!("{:?}", certificate);
+            }
+
             Benchmark(benchmark_command) => match benchmark_command {
                 BenchmarkCommand::Single {
                     options: benchmark_options,
@@ -913,9 +1054,9 @@ impl Runnable for Job {
                         ..Default::default()
                     };
 
-                    let pub_keys: Vec<_> = std::iter::repeat_with(|| signer.generate_new())
+                    let pub_keys = std::iter::repeat_with(|| signer.generate_new())
                         .take(num_chains)
-                        .collect();
+                        .collect::<Result<Vec<_>, _>>()?;
                     signer.persist().await?;
 
                     let mut context = ClientContext::new(
@@ -1691,7 +1832,7 @@ impl Runnable for Job {
                 set_default,
             }) => {
                 let start_time = Instant::now();
-                let public_key = signer.mutate(|s| s.generate_new()).await?;
+                let public_key = signer.mutate(|s| s.generate_new()).await??;
                 let mut context = ClientContext::new(
                     storage,
                     options.context_options.clone(),
@@ -1765,6 +1906,26 @@ impl Runnable for Job {
                 );
             }
 
+            Wallet(WalletCommand::RotateKey { chain_id }) => {
+                let start_time = Instant::now();
+                let new_owner = signer.mutate(|s| s.generate_new()).await??.into();
+                // The keystore shares its keys with its clones, so the new key is visible to
+                // the context and the old one can be removed below.
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    (*signer).clone(),
+                );
+                let old_owner = context.rotate_key(chain_id, new_owner).await?;
+                signer.mutate(|s| s.remove_key(&old_owner)).await??;
+                println!("{new_owner}");
+                info!(
+                    "Key of {old_owner} rotated to {new_owner} in {} ms",
+                    start_time.elapsed().as_millis()
+                );
+            }
+
             Chain(ChainCommand::ShowBlock { chain_id, height }) => {
                 let context = ClientContext::new(
                     storage,
@@ -1870,9 +2031,12 @@ impl ClientOptions {
         debug!("Running command using storage configuration: {storage_config}");
         let store_config =
             storage_config.add_common_storage_options(&self.common_storage_options)?;
-        let output =
-            Box::pin(store_config.run_with_storage(self.wasm_runtime.with_wasm_default(), job))
-                .await?;
+        let output = Box::pin(store_config.run_with_storage(
+            self.wasm_runtime.with_wasm_default(),
+            self.common_storage_options.storage_archive_chain_states,
+            job,
+        ))
+        .await?;
         Ok(output)
     }
 
@@ -1899,8 +2063,26 @@ impl ClientOptions {
         Ok(persistent::File::read(&self.wallet_path()?)?)
     }
 
-    fn signer(&self) -> Result<persistent::File<InMemorySigner>, Error> {
-        Ok(persistent::File::read(&self.keystore_path()?)?)
+    fn signer(&self) -> Result<persistent::File<Keystore>, Error> {
+        let signer = persistent::File::<Keystore>::read(&self.keystore_path()?)?;
+        if signer.is_encrypted() {
+            if let Some(passphrase) = self.keystore_passphrase()? {
+                signer.set_passphrase(passphrase);
+            }
+        }
+        Ok(signer)
+    }
+
+    /// Returns the passphrase of an encrypted keystore, if one was provided.
+    fn keystore_passphrase(&self) -> Result<Option<String>, Error> {
+        if let Some(path) = &self.context_options.keystore_passphrase_file {
+            let mut passphrase = fs_err::read_to_string(path)?;
+            // Trim in place rather than copying, so that no copy of the passphrase is left.
+            passphrase.truncate(passphrase.trim_end_matches(['\n', '\r']).len());
+            return Ok(Some(passphrase));
+        }
+        let suffix = self.suffix();
+        Ok(env::var(format!("LINERA_KEYSTORE_PASSPHRASE{suffix}")).ok())
     }
 
     fn suffix(&self) -> String {
@@ -1994,15 +2176,42 @@ impl ClientOptions {
     pub fn create_keystore(
         &self,
         testing_prng_seed: Option<u64>,
-    ) -> Result<persistent::File<InMemorySigner>, Error> {
+    ) -> Result<persistent::File<Keystore>, Error> {
         let keystore_path = self.keystore_path()?;
         if keystore_path.exists() {
             bail!("Keystore already exists: {}", keystore_path.display());
         }
         Ok(persistent::File::read_or_create(&keystore_path, || {
-            Ok(InMemorySigner::new(testing_prng_seed))
+            Ok(Keystore::new(testing_prng_seed))
         })?)
     }
+
+    pub fn create_encrypted_keystore(&self) -> Result<persistent::File<Keystore>, Error> {
+        let passphrase = self.keystore_passphrase()?.context(
+            "Creating an encrypted keystore requires a passphrase: \
+             use --keystore-passphrase-file or LINERA_KEYSTORE_PASSPHRASE",
+        )?;
+        self.create_keystore_with(Keystore::new_encrypted(passphrase)?)
+    }
+
+    pub fn create_remote_keystore(
+        &self,
+        address: SignerAddress,
+        token_file: PathBuf,
+    ) -> Result<persistent::File<Keystore>, Error> {
+        self.create_keystore_with(Keystore::new_remote(address, token_file))
+    }
+
+    fn create_keystore_with(
+        &self,
+        keystore: Keystore,
+    ) -> Result<persistent::File<Keystore>, Error> {
+        let keystore_path = self.keystore_path()?;
+        if keystore_path.exists() {
+            bail!("Keystore already exists: {}", keystore_path.display());
+        }
+        Ok(persistent::File::new(&keystore_path, keystore)?)
+    }
 }
 
 struct DatabaseToolJob<'a>(&'a DatabaseToolCommand);
@@ -2093,6 +2302,105 @@ impl RunnableWithStore for DatabaseToolJob<'_> {
                     println!("{}", id);
                 }
             }
+            DatabaseToolCommand::ExportChain { chain_id, output } => {
+                let storage = DbStorage::<D, _>::connect(&config, &namespace, None).await?;
+                let mut encoder = zstd::stream::Encoder::new(fs_err::File::create(output)?, 0)?;
+                let header = storage.export_chain(*chain_id, &mut encoder).await?;
+                encoder.finish()?;
+                info!(
+                    "Chain {chain_id} exported with blocks {} to {} in {} ms",
+                    header.first_height,
+                    header.next_block_height,
+                    start_time.elapsed().as_millis()
+                );
+            }
+            DatabaseToolCommand::ImportChain { input, verify_only } => {
+                let decoder = zstd::stream::Decoder::new(fs_err::File::open(input)?)?;
+                if *verify_only {
+                    let storage = DbStorage::<D, _>::connect(&config, &namespace, None).await?;
+                    let header = storage.verify_chain_snapshot(decoder).await?;
+                    info!(
+                        "Snapshot of chain {} verified in {} ms",
+                        header.chain_id,
+                        start_time.elapsed().as_millis()
+                    );
+                } else {
+                    let storage =
+                        DbStorage::<D, _>::maybe_create_and_connect(&config, &namespace, None)
+                            .await?;
+                    let header = storage.import_chain(decoder).await?;
+                    info!(
+                        "Chain {} imported into namespace {namespace} in {} ms",
+                        header.chain_id,
+                        start_time.elapsed().as_millis()
+                    );
+                }
+            }
+            DatabaseToolCommand::Prune {
+                keep_heights,
+                keep_for,
+                prune_blobs,
+            } => {
+                let chain_ids = DbStorage::<D, _>::list_chain_ids(&config, &namespace).await?;
+                let storage = DbStorage::<D, _>::connect(&config, &namespace, None).await?;
+                let policy = PruningPolicy {
+                    keep_heights: *keep_heights,
+                    keep_for: keep_for.map(TimeDelta::from_duration),
+                    prune_blobs: *prune_blobs,
+                };
+                let summary = storage.prune(&chain_ids, &policy).await?;
+                info!(
+                    "Pruned {} certificates, {} archived chain states and {} blobs of {} chains \
+                     in {} ms",
+                    summary.certificates,
+                    summary.archived_chain_states,
+                    summary.blobs,
+                    chain_ids.len(),
+                    start_time.elapsed().as_millis()
+                );
+            }
+            DatabaseToolCommand::Inspect {
+                chain_id,
+                application_id,
+                height,
+                diff_height,
+                diff_namespace,
+            } => {
+                let storage = DbStorage::<D, _>::connect(&config, &namespace, None).await?;
+                let dump = inspect::dump_chain_state(&storage, *chain_id, *height, *application_id)
+                    .await?;
+                let other_dump = if let Some(diff_height) = diff_height {
+                    Some(
+                        inspect::dump_chain_state(
+                            &storage,
+                            *chain_id,
+                            Some(*diff_height),
+                            *application_id,
+                        )
+                        .await?,
+                    )
+                } else if let Some(diff_namespace) = diff_namespace {
+                    let storage = DbStorage::<D, _>::connect(&config, diff_namespace, None).await?;
+                    Some(
+                        inspect::dump_chain_state(&storage, *chain_id, *height, *application_id)
+                            .await?,
+                    )
+                } else {
+                    None
+                };
+                match other_dump {
+                    None => println!("{}", serde_json::to_string_pretty(&dump)?),
+                    Some(other_dump) => {
+                        for difference in inspect::diff_dumps(&dump, &other_dump)? {
+                            println!("{difference}");
+                        }
+                    }
+                }
+                info!(
+                    "Chain {chain_id} inspected in {} ms",
+                    start_time.elapsed().as_millis()
+                );
+            }
         }
         Ok(0)
     }
@@ -2215,6 +2523,9 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             maximum_http_response_bytes,
             http_request_timeout_ms,
             http_request_allow_list,
+            fee_sink,
+            unbonding_epochs,
+            slashing_percentage,
             testing_prng_seed,
             network_name,
         } => {
@@ -2272,6 +2583,10 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                     .as_ref()
                     .map(|list| list.iter().cloned().collect())
                     .unwrap_or(existing_policy.http_request_allow_list),
+                fee_sink: fee_sink.unwrap_or(existing_policy.fee_sink),
+                unbonding_epochs: unbonding_epochs.unwrap_or(existing_policy.unbonding_epochs),
+                slashing_percentage: slashing_percentage
+                    .unwrap_or(existing_policy.slashing_percentage),
             };
             let timestamp = start_timestamp.map_or_else(Timestamp::now, |st| {
                 let micros =
@@ -2280,7 +2595,7 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             });
 
             let mut signer = options.create_keystore(*testing_prng_seed)?;
-            let admin_public_key = signer.mutate(|s| s.generate_new()).await?;
+            let admin_public_key = signer.mutate(|s| s.generate_new()).await??;
 
             let network_name = network_name.clone().unwrap_or_else(|| {
                 // Default: e.g. "linera-2023-11-14T23:13:20"
@@ -2305,7 +2620,7 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             )];
             for _ in 0..*num_other_initial_chains {
                 // Create keys.
-                let public_key = signer.mutate(|s| s.generate_new()).await?;
+                let public_key = signer.mutate(|s| s.generate_new()).await??;
                 let description = genesis_config.add_root_chain(public_key, *initial_funding);
                 let chain = UserChain::make_initial(public_key.into(), description, timestamp);
                 chains.push(chain);
@@ -2358,7 +2673,7 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
         ClientCommand::Keygen => {
             let start_time = Instant::now();
             let mut signer = options.signer()?;
-            let public_key = signer.mutate(|s| s.generate_new()).await?;
+            let public_key = signer.mutate(|s| s.generate_new()).await??;
             let owner = AccountOwner::from(public_key);
             println!("{}", owner);
             info!("Key generated in {} ms", start_time.elapsed().as_millis());
@@ -2465,7 +2780,28 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             }
         },
 
-        ClientCommand::Storage(command) => {
+        ClientCommand::Storage(StorageCommand::Migrate {
+            from,
+            to,
+            checkpoint,
+        }) => {
+            let start_time = Instant::now();
+            let source = from.add_common_storage_options(&options.common_storage_options)?;
+            let job = MigrateJob {
+                source: from.to_string(),
+                target: to.add_common_storage_options(&options.common_storage_options)?,
+                target_description: to.to_string(),
+                checkpoint_path: checkpoint.clone(),
+            };
+            Box::pin(source.run_with_store(job)).await?;
+            info!(
+                "Storage {from} migrated to {to} in {} ms",
+                start_time.elapsed().as_millis()
+            );
+            Ok(0)
+        }
+
+        ClientCommand::Storage(StorageCommand::Database(command)) => {
             Ok(options.run_with_store(DatabaseToolJob(command)).await?)
         }
 
@@ -2544,6 +2880,9 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                 genesis_config_path,
                 faucet,
                 testing_prng_seed,
+                encrypt_keystore,
+                remote_signer,
+                remote_signer_token_file,
             } => {
                 let start_time = Instant::now();
                 let genesis_config: GenesisConfig = match (genesis_config_path, faucet) {
@@ -2575,7 +2914,25 @@ Make sure to use a Linera client compatible with this network.
                     }
                     (_, _) => bail!("Either --faucet or --genesis must be specified, but not both"),
                 };
-                let mut keystore = options.create_keystore(*testing_prng_seed)?;
+                let mut keystore = match (remote_signer, encrypt_keystore) {
+                    (Some(_), true) => {
+                        bail!("Cannot use both --remote-signer and --encrypt-keystore")
+                    }
+                    (Some(address), false) => {
+                        let token_file = remote_signer_token_file
+                            .clone()
+                            .context("--remote-signer requires --remote-signer-token-file")?;
+                        options.create_remote_keystore(address.clone(), token_file)?
+                    }
+                    (None, true) => {
+                        ensure!(
+                            testing_prng_seed.is_none(),
+                            "Cannot use --testing-prng-seed with an encrypted keystore"
+                        );
+                        options.create_encrypted_keystore()?
+                    }
+                    (None, false) => options.create_keystore(*testing_prng_seed)?,
+                };
                 keystore.persist().await?;
                 options.create_wallet(genesis_config)?.persist().await?;
                 options.initialize_storage().boxed().await?;
@@ -2587,7 +2944,65 @@ Make sure to use a Linera client compatible with this network.
                 Ok(0)
             }
 
-            WalletCommand::FollowChain { .. } | WalletCommand::RequestChain { .. } => {
+            WalletCommand::EncryptKeystore => {
+                let start_time = Instant::now();
+                let passphrase = options.keystore_passphrase()?.context(
+                    "Encrypting the keystore requires a passphrase: \
+                     use --keystore-passphrase-file or LINERA_KEYSTORE_PASSPHRASE",
+                )?;
+                options
+                    .signer()?
+                    .mutate(|keystore| keystore.encrypt(passphrase))
+                    .await??;
+                info!(
+                    "Keystore encrypted in {} ms",
+                    start_time.elapsed().as_millis()
+                );
+                Ok(0)
+            }
+
+            WalletCommand::ChangePassphrase {
+                new_passphrase_file,
+            } => {
+                let start_time = Instant::now();
+                let mut new_passphrase = fs_err::read_to_string(new_passphrase_file)?;
+                new_passphrase.truncate(new_passphrase.trim_end_matches(['\n', '\r']).len());
+                options
+                    .signer()?
+                    .mutate(|keystore| keystore.change_passphrase(new_passphrase))
+                    .await??;
+                info!(
+                    "Keystore passphrase changed in {} ms",
+                    start_time.elapsed().as_millis()
+                );
+                Ok(0)
+            }
+
+            WalletCommand::RemoveKey { owner } => {
+                let start_time = Instant::now();
+                let wallet = options.wallet()?;
+                if let Some(chain_id) = wallet.chain_ids().into_iter().find(|chain_id| {
+                    wallet.get(*chain_id).and_then(|chain| chain.owner) == Some(*owner)
+                }) {
+                    bail!(
+                        "The key of {owner} is used by chain {chain_id}: \
+                         use `linera wallet forget-keys` or `linera wallet rotate-key` instead"
+                    );
+                }
+                if !options
+                    .signer()?
+                    .mutate(|keystore| keystore.remove_key(owner))
+                    .await??
+                {
+                    warn!("no key found in keystore for owner {owner}");
+                }
+                info!("Key removed in {} ms", start_time.elapsed().as_millis());
+                Ok(0)
+            }
+
+            WalletCommand::FollowChain { .. }
+            | WalletCommand::RequestChain { .. }
+            | WalletCommand::RotateKey { .. } => {
                 options.run_with_storage(Job(options.clone())).await??;
                 Ok(0)
             }
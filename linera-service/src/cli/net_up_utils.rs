@@ -95,12 +95,20 @@ impl StorageConfigProvider {
             InnerStorageConfig::RocksDb { .. } => {
                 anyhow::bail!("Not possible to work with RocksDB")
             }
+            #[cfg(feature = "redb")]
+            InnerStorageConfig::Redb { .. } => {
+                anyhow::bail!("Not possible to work with redb")
+            }
             #[cfg(feature = "storage-service")]
             InnerStorageConfig::Service { .. } => Ok(Database::Service),
             #[cfg(feature = "dynamodb")]
             InnerStorageConfig::DynamoDb { .. } => Ok(Database::DynamoDb),
             #[cfg(feature = "scylladb")]
             InnerStorageConfig::ScyllaDb { .. } => Ok(Database::ScyllaDb),
+            #[cfg(feature = "postgres")]
+            InnerStorageConfig::Postgres { .. } => {
+                anyhow::bail!("Not possible to work with PostgreSQL")
+            }
             #[cfg(all(feature = "rocksdb", feature = "scylladb"))]
             InnerStorageConfig::DualRocksDbScyllaDb { .. } => Ok(Database::DualRocksDbScyllaDb),
         }
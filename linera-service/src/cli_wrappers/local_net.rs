@@ -713,7 +713,7 @@ impl LocalNet {
             .command_for_binary("linera-proxy")
             .await?
             .arg(format!("server_{}.json", validator))
-            .args(["--storage", &storage.to_string()])
+            .args(["--storage", &storage.to_argument()])
             .args(["--id", &proxy_id.to_string()])
             .spawn_into()?;
 
@@ -752,7 +752,7 @@ impl LocalNet {
             .command_for_binary("linera-exporter")
             .await?
             .args(["--config-path", &config_path])
-            .args(["--storage", &storage.to_string()])
+            .args(["--storage", &storage.to_argument()])
             .spawn_into()?;
 
         match self.network.internal {
@@ -855,7 +855,7 @@ impl LocalNet {
         }
         command.args(["storage", "initialize"]);
         command
-            .args(["--storage", &storage.to_string()])
+            .args(["--storage", &storage.to_argument()])
             .args(["--genesis", "genesis.json"])
             .spawn_and_wait_for_stdout()
             .await?;
@@ -882,7 +882,7 @@ impl LocalNet {
         }
         command
             .arg("run")
-            .args(["--storage", &storage.to_string()])
+            .args(["--storage", &storage.to_argument()])
             .args(["--server", &format!("server_{}.json", validator)])
             .args(["--shard", &shard.to_string()])
             .args(self.cross_chain_config.to_args());
@@ -176,7 +176,14 @@ impl ExporterOptions {
                 .storage_config
                 .add_common_storage_options(&self.common_storage_options)
                 .unwrap();
-            store_config.run_with_storage(None, context).boxed().await
+            store_config
+                .run_with_storage(
+                    None,
+                    self.common_storage_options.storage_archive_chain_states,
+                    context,
+                )
+                .boxed()
+                .await
         };
 
         runtime.block_on(future)?.map_err(|e| e.into())
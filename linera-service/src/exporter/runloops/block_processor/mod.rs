@@ -92,7 +92,7 @@ where
                                     },
                                 };
 
-                                let committee: Committee = match bcs::from_bytes(blob.bytes()) {
+                                let committee = match Committee::from_blob_bytes(blob.bytes()) {
                                     Ok(committee) => committee,
                                     Err(e) => {
                                         tracing::error!("unable to serialize the committee blob: {:?}, received error: {:?}", blob_id, e);
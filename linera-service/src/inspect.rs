@@ -0,0 +1,404 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Typed dumps of the state of a chain in storage, and the differences between two of
+//! them.
+
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{bail, Context as _};
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::{Amount, ApplicationPermissions, BlockHeight, ChainDescription, Epoch, Timestamp},
+    identifiers::{ApplicationId, BlobId, ChainId},
+    ownership::ChainOwnership,
+};
+use linera_chain::{data_types::MessageBundle, ChainStateView};
+use linera_execution::{
+    ExecutionRuntimeContext, LockedTransfer, ScheduledOperation, StakingValidator,
+};
+use linera_storage::Storage;
+use linera_views::context::Context;
+use serde::Serialize;
+use serde_json::Value;
+
+/// The state of a chain, as stored at some height.
+#[derive(Debug, Serialize)]
+pub struct ChainStateDump {
+    /// The chain.
+    pub chain_id: ChainId,
+    /// The state of the latest block of the chain.
+    pub tip: Value,
+    /// The hash of the execution state.
+    pub execution_state_hash: Option<CryptoHash>,
+    /// The state of the system application. Omitted when inspecting a single application.
+    pub system: Option<SystemStateDump>,
+    /// The inboxes of the chain, by origin chain.
+    pub inboxes: BTreeMap<String, InboxDump>,
+    /// The outboxes of the chain, by target chain.
+    pub outboxes: BTreeMap<String, OutboxDump>,
+    /// The hex-encoded key-value pairs of the user applications, by application ID.
+    pub applications: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// The state of the system application of a chain.
+#[derive(Debug, Serialize)]
+pub struct SystemStateDump {
+    pub description: Option<ChainDescription>,
+    pub epoch: Epoch,
+    pub admin_id: Option<ChainId>,
+    /// The epochs of the committees known to the chain.
+    pub committee_epochs: Vec<Epoch>,
+    pub ownership: ChainOwnership,
+    pub balance: Amount,
+    /// The balances of the accounts of the chain, by owner.
+    pub balances: BTreeMap<String, Amount>,
+    pub timestamp: Timestamp,
+    pub closed: bool,
+    pub application_permissions: ApplicationPermissions,
+    pub used_blobs: Vec<BlobId>,
+    /// The fees not paid out yet, by owner of the account on the admin chain.
+    pub fee_rewards: BTreeMap<String, Amount>,
+    /// The validators registered for staking, by public key.
+    pub staking_validators: BTreeMap<String, StakingValidator>,
+    /// The staked tokens, by validator and by delegator account.
+    pub stakes: BTreeMap<String, BTreeMap<String, Amount>>,
+    /// The unstaked tokens, with the validator they were staked with, by the epoch in which
+    /// they are released.
+    pub unbonding: BTreeMap<Epoch, Vec<(String, String, Amount)>>,
+    /// The scheduled operations, by ID.
+    pub scheduled_operations: BTreeMap<u64, ScheduledOperation>,
+    /// The transfers locked on this chain, by ID.
+    pub locked_transfers: BTreeMap<u64, LockedTransfer>,
+}
+
+/// The state of an inbox.
+#[derive(Debug, Serialize)]
+pub struct InboxDump {
+    pub next_cursor_to_add: Value,
+    pub next_cursor_to_remove: Value,
+    pub added_bundles: Vec<MessageBundle>,
+    pub removed_bundles: Vec<MessageBundle>,
+}
+
+/// The state of an outbox.
+#[derive(Debug, Serialize)]
+pub struct OutboxDump {
+    pub next_height_to_schedule: BlockHeight,
+    /// The heights of the blocks whose messages are not acknowledged yet.
+    pub queue: Vec<BlockHeight>,
+}
+
+/// Loads the state of a chain, or its state archived after the block at `height`, and
+/// dumps it. If `application_id` is given, only the state of that application is
+/// dumped, without the system state and the mailboxes.
+pub async fn dump_chain_state<S>(
+    storage: &S,
+    chain_id: ChainId,
+    height: Option<BlockHeight>,
+    application_id: Option<ApplicationId>,
+) -> anyhow::Result<ChainStateDump>
+where
+    S: Storage + Send + Sync,
+{
+    let chain = match height {
+        None => storage.load_chain(chain_id).await?,
+        Some(height) => storage
+            .load_chain_at_height(chain_id, height)
+            .await?
+            .with_context(|| format!("no state of chain {chain_id} is archived at {height}"))?,
+    };
+    if chain.execution_state.system.description.get().is_none() {
+        bail!("chain {chain_id} was not found in storage");
+    }
+    let mut dump = ChainStateDump {
+        chain_id,
+        tip: serde_json::to_value(chain.tip_state.get())?,
+        execution_state_hash: *chain.execution_state_hash.get(),
+        system: None,
+        inboxes: BTreeMap::new(),
+        outboxes: BTreeMap::new(),
+        applications: BTreeMap::new(),
+    };
+    let application_ids = match application_id {
+        Some(application_id) => vec![application_id],
+        None => {
+            dump.system = Some(dump_system_state(&chain).await?);
+            dump_mailboxes(&chain, &mut dump).await?;
+            chain.execution_state.users.indices().await?
+        }
+    };
+    let users = &chain.execution_state.users;
+    let entries = users.try_load_entries(&application_ids).await?;
+    for (application_id, entry) in application_ids.iter().zip(entries) {
+        let Some(view) = entry else {
+            if dump.system.is_none() {
+                bail!("application {application_id} has no state on chain {chain_id}");
+            }
+            continue;
+        };
+        let key_values = view
+            .index_values()
+            .await?
+            .into_iter()
+            .map(|(key, value)| (hex::encode(key), hex::encode(value)))
+            .collect();
+        dump.applications
+            .insert(application_id.to_string(), key_values);
+    }
+    Ok(dump)
+}
+
+async fn dump_system_state<C>(chain: &ChainStateView<C>) -> anyhow::Result<SystemStateDump>
+where
+    C: Context + Clone + Send + Sync + 'static,
+    C::Extra: ExecutionRuntimeContext,
+{
+    let system = &chain.execution_state.system;
+    let balances = system
+        .balances
+        .index_values()
+        .await?
+        .into_iter()
+        .map(|(owner, amount)| (owner.to_string(), amount))
+        .collect();
+    let fee_rewards = system
+        .fee_rewards
+        .index_values()
+        .await?
+        .into_iter()
+        .map(|(owner, amount)| (owner.to_string(), amount))
+        .collect();
+    let staking_validators = system
+        .staking_validators
+        .index_values()
+        .await?
+        .into_iter()
+        .map(|(public_key, validator)| (public_key.to_string(), validator))
+        .collect();
+    let mut stakes = BTreeMap::new();
+    for validator in system.stakes.indices().await? {
+        let Some(delegators) = system.stakes.try_load_entry(&validator).await? else {
+            continue;
+        };
+        let delegators = delegators
+            .index_values()
+            .await?
+            .into_iter()
+            .map(|(delegator, amount)| (delegator.to_string(), amount))
+            .collect();
+        stakes.insert(validator.to_string(), delegators);
+    }
+    let mut unbonding = BTreeMap::<_, Vec<_>>::new();
+    for validator in system.unbonding.indices().await? {
+        let Some(entries) = system.unbonding.try_load_entry(&validator).await? else {
+            continue;
+        };
+        for (epoch, entries) in entries.index_values().await? {
+            unbonding.entry(epoch).or_default().extend(
+                entries.into_iter().map(|(delegator, amount)| {
+                    (validator.to_string(), delegator.to_string(), amount)
+                }),
+            );
+        }
+    }
+    Ok(SystemStateDump {
+        description: system.description.get().clone(),
+        epoch: *system.epoch.get(),
+        admin_id: *system.admin_id.get(),
+        committee_epochs: system.committees.get().keys().copied().collect(),
+        ownership: system.ownership.get().clone(),
+        balance: *system.balance.get(),
+        balances,
+        timestamp: *system.timestamp.get(),
+        closed: *system.closed.get(),
+        application_permissions: system.application_permissions.get().clone(),
+        used_blobs: system.used_blobs.indices().await?,
+        fee_rewards,
+        staking_validators,
+        stakes,
+        unbonding,
+        scheduled_operations: system
+            .scheduled_operations
+            .index_values()
+            .await?
+            .into_iter()
+            .collect(),
+        locked_transfers: system
+            .locked_transfers
+            .index_values()
+            .await?
+            .into_iter()
+            .collect(),
+    })
+}
+
+async fn dump_mailboxes<C>(
+    chain: &ChainStateView<C>,
+    dump: &mut ChainStateDump,
+) -> anyhow::Result<()>
+where
+    C: Context + Clone + Send + Sync + 'static,
+    C::Extra: ExecutionRuntimeContext,
+{
+    let origins = chain.inboxes.indices().await?;
+    for (origin, inbox) in origins
+        .iter()
+        .zip(chain.inboxes.try_load_entries(&origins).await?)
+    {
+        let Some(inbox) = inbox else {
+            continue;
+        };
+        let inbox = InboxDump {
+            next_cursor_to_add: serde_json::to_value(inbox.next_cursor_to_add.get())?,
+            next_cursor_to_remove: serde_json::to_value(inbox.next_cursor_to_remove.get())?,
+            added_bundles: inbox.added_bundles.elements().await?,
+            removed_bundles: inbox.removed_bundles.elements().await?,
+        };
+        dump.inboxes.insert(origin.to_string(), inbox);
+    }
+    let targets = chain.outboxes.indices().await?;
+    for (target, outbox) in targets
+        .iter()
+        .zip(chain.outboxes.try_load_entries(&targets).await?)
+    {
+        let Some(outbox) = outbox else {
+            continue;
+        };
+        let outbox = OutboxDump {
+            next_height_to_schedule: *outbox.next_height_to_schedule.get(),
+            queue: outbox.queue.elements().await?,
+        };
+        dump.outboxes.insert(target.to_string(), outbox);
+    }
+    Ok(())
+}
+
+/// A value that differs between two dumps.
+#[derive(Debug, PartialEq)]
+pub struct Difference {
+    /// The path of the value in the dumps, e.g. `system.balances.<owner>`.
+    pub path: String,
+    /// The value in the first dump, if any.
+    pub old: Option<Value>,
+    /// The value in the second dump, if any.
+    pub new: Option<Value>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| value.as_ref().map_or("-".to_string(), Value::to_string);
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.path,
+            show(&self.old),
+            show(&self.new)
+        )
+    }
+}
+
+/// Returns the differences between two dumps.
+pub fn diff_dumps(
+    old: &ChainStateDump,
+    new: &ChainStateDump,
+) -> Result<Vec<Difference>, serde_json::Error> {
+    Ok(diff_values(
+        &serde_json::to_value(old)?,
+        &serde_json::to_value(new)?,
+    ))
+}
+
+/// Returns the differences between two JSON values, sorted by path.
+pub fn diff_values(old: &Value, new: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    collect_differences(String::new(), old, new, &mut differences);
+    differences.sort_by(|difference1, difference2| difference1.path.cmp(&difference2.path));
+    differences
+}
+
+/// Compares two JSON values, descending into the objects present in both of them.
+fn collect_differences(path: String, old: &Value, new: &Value, differences: &mut Vec<Difference>) {
+    if old == new {
+        return;
+    }
+    let (Value::Object(old_fields), Value::Object(new_fields)) = (old, new) else {
+        differences.push(Difference {
+            path,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        });
+        return;
+    };
+    let field_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    for (key, old_value) in old_fields {
+        match new_fields.get(key) {
+            Some(new_value) => {
+                collect_differences(field_path(key), old_value, new_value, differences)
+            }
+            None => differences.push(Difference {
+                path: field_path(key),
+                old: Some(old_value.clone()),
+                new: None,
+            }),
+        }
+    }
+    for (key, new_value) in new_fields {
+        if !old_fields.contains_key(key) {
+            differences.push(Difference {
+                path: field_path(key),
+                old: None,
+                new: Some(new_value.clone()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{diff_values, Difference};
+
+    #[test]
+    fn test_diff_values() {
+        let old = json!({
+            "tip": { "next_block_height": 1, "block_hash": "aa" },
+            "applications": { "app1": { "00": "01" } },
+            "closed": false,
+        });
+        let new = json!({
+            "tip": { "next_block_height": 2, "block_hash": "aa" },
+            "applications": { "app2": { "00": "02" } },
+            "closed": false,
+        });
+        let differences = diff_values(&old, &new);
+        assert_eq!(
+            differences,
+            vec![
+                Difference {
+                    path: "applications.app1".to_string(),
+                    old: Some(json!({ "00": "01" })),
+                    new: None,
+                },
+                Difference {
+                    path: "applications.app2".to_string(),
+                    old: None,
+                    new: Some(json!({ "00": "02" })),
+                },
+                Difference {
+                    path: "tip.next_block_height".to_string(),
+                    old: Some(json!(1)),
+                    new: Some(json!(2)),
+                },
+            ]
+        );
+        assert_eq!(differences[2].to_string(), "tip.next_block_height: 1 -> 2");
+    }
+}
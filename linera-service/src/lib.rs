@@ -7,6 +7,8 @@
 pub mod cli;
 pub mod cli_wrappers;
 pub mod config;
+pub mod inspect;
+pub mod migration;
 pub mod node_service;
 pub mod project;
 pub mod storage;
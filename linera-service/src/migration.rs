@@ -0,0 +1,419 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Copying a whole namespace from one storage backend to another.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use anyhow::{bail, ensure, Context as _};
+use async_trait::async_trait;
+use linera_base::crypto::{BcsHashable, CryptoHash, CryptoHashVec};
+use linera_persistent::{self as persistent, PersistExt as _};
+use linera_views::{
+    batch::Batch,
+    store::{
+        KeyValueDatabase, KeyValueStore, ReadableKeyValueStore as _, WritableKeyValueStore as _,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::storage::{RunnableWithStore, StoreConfig};
+
+/// The maximal number of key-value pairs written to the target in a single batch.
+const MIGRATION_BATCH_SIZE: usize = 1000;
+
+/// The progress of a migration, saved after each root key so that an interrupted
+/// migration can be resumed.
+///
+/// The storage descriptions are the redacted ones, so that the checkpoint file never
+/// contains credentials.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MigrationCheckpoint {
+    /// The source storage configuration.
+    source: String,
+    /// The target storage configuration.
+    target: String,
+    /// The hashes of the root keys copied so far, indexed by hex-encoded root key.
+    root_keys: BTreeMap<String, CryptoHash>,
+}
+
+/// A batch of at most [`MIGRATION_BATCH_SIZE`] key-value pairs of a root key.
+#[derive(Serialize, Deserialize)]
+struct KeyValueBatch {
+    key_values: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl BcsHashable<'_> for KeyValueBatch {}
+
+/// The key prefixes, relative to some root key, of the other root keys nested in it.
+///
+/// Some backends store partitions as plain key prefixes, so reading a root key also
+/// returns the keys of the root keys that extend it. These are copied with their own
+/// root key instead.
+struct NestedRootKeys {
+    suffixes: BTreeSet<Vec<u8>>,
+    lengths: BTreeSet<usize>,
+}
+
+impl NestedRootKeys {
+    fn new(root_key: &[u8], root_keys: &[Vec<u8>]) -> Self {
+        let suffixes = root_keys
+            .iter()
+            .filter(|other| other.len() > root_key.len() && other.starts_with(root_key))
+            .map(|other| other[root_key.len()..].to_vec())
+            .collect::<BTreeSet<_>>();
+        let lengths = suffixes.iter().map(Vec::len).collect();
+        NestedRootKeys { suffixes, lengths }
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.lengths
+            .iter()
+            .take_while(|length| **length <= key.len())
+            .any(|length| self.suffixes.contains(&key[..*length]))
+    }
+}
+
+/// Reads the content of `root_key` one batch at a time, calling `f` on each non-empty
+/// batch, and returns the hash of the whole content.
+///
+/// Only the keys starting with a given byte and the values of a single batch are held in
+/// memory at any time.
+async fn for_each_batch<D, F>(
+    database: &D,
+    root_key: &[u8],
+    nested: &NestedRootKeys,
+    mut f: impl FnMut(Vec<(Vec<u8>, Vec<u8>)>) -> F,
+) -> anyhow::Result<CryptoHash>
+where
+    D: KeyValueDatabase,
+    D::Store: KeyValueStore,
+    D::Error: Send + Sync,
+    F: std::future::Future<Output = anyhow::Result<()>>,
+{
+    let store = database.open_exclusive(root_key)?;
+    let mut hashes = Vec::new();
+    // The empty key comes first, then the keys grouped by their first byte.
+    for first_byte in std::iter::once(None).chain((0..=u8::MAX).map(Some)) {
+        let keys = match first_byte {
+            None if store.contains_key(&[]).await? => vec![Vec::new()],
+            None => continue,
+            Some(first_byte) => store
+                .find_keys_by_prefix(&[first_byte])
+                .await?
+                .into_iter()
+                .map(|mut key| {
+                    key.insert(0, first_byte);
+                    key
+                })
+                .filter(|key| !nested.contains(key))
+                .collect::<Vec<_>>(),
+        };
+        for keys in keys.chunks(MIGRATION_BATCH_SIZE) {
+            let values = store.read_multi_values_bytes(keys.to_vec()).await?;
+            let key_values = keys
+                .iter()
+                .cloned()
+                .zip(values)
+                .filter_map(|(key, value)| Some((key, value?)))
+                .collect::<Vec<_>>();
+            if key_values.is_empty() {
+                continue;
+            }
+            let batch = KeyValueBatch { key_values };
+            hashes.push(CryptoHash::new(&batch));
+            f(batch.key_values).await?;
+        }
+    }
+    Ok(CryptoHash::new(&CryptoHashVec(hashes)))
+}
+
+/// Copies the namespace of the source database into the target database, recording
+/// progress in `checkpoint`, then verifies the copy.
+///
+/// The verification reads the whole target namespace again, so it also fails if the
+/// target contains keys that are not in the source.
+async fn migrate<S, T>(
+    source_config: &S::Config,
+    source_namespace: &str,
+    target_config: &T::Config,
+    target_namespace: &str,
+    checkpoint: &mut persistent::File<MigrationCheckpoint>,
+) -> anyhow::Result<()>
+where
+    S: KeyValueDatabase,
+    S::Store: KeyValueStore,
+    S::Error: Send + Sync,
+    T: KeyValueDatabase,
+    T::Store: KeyValueStore,
+    T::Error: Send + Sync,
+{
+    let source = S::connect(source_config, source_namespace).await?;
+    let target = T::maybe_create_and_connect(target_config, target_namespace).await?;
+    let mut root_keys = S::list_root_keys(source_config, source_namespace).await?;
+    // The shared partition is not necessarily listed.
+    root_keys.push(Vec::new());
+    root_keys.sort();
+    root_keys.dedup();
+
+    let total = root_keys.len();
+    for (index, root_key) in root_keys.iter().enumerate() {
+        let hex_root_key = hex::encode(root_key);
+        if checkpoint.root_keys.contains_key(&hex_root_key) {
+            continue;
+        }
+        let target_store = &target.open_exclusive(root_key)?;
+        let nested = NestedRootKeys::new(root_key, &root_keys);
+        let hash = for_each_batch(&source, root_key, &nested, move |key_values| async move {
+            let mut batch = Batch::new();
+            for (key, value) in key_values {
+                batch.put_key_value_bytes(key, value);
+            }
+            target_store.write_batch(batch).await?;
+            Ok(())
+        })
+        .await
+        .with_context(|| format!("copying root key {hex_root_key}"))?;
+        checkpoint
+            .mutate(|checkpoint| checkpoint.root_keys.insert(hex_root_key, hash))
+            .await?;
+        info!("Copied root key {} of {total}", index + 1);
+    }
+
+    info!("Verifying the migrated namespace");
+    let mut target_root_keys = T::list_root_keys(target_config, target_namespace).await?;
+    target_root_keys.extend(root_keys);
+    target_root_keys.sort();
+    target_root_keys.dedup();
+    let empty_hash = CryptoHash::new(&CryptoHashVec(Vec::new()));
+    for root_key in &target_root_keys {
+        let hex_root_key = hex::encode(root_key);
+        // Root keys that are not in the source may be listed, but must be empty.
+        let expected_hash = checkpoint
+            .root_keys
+            .get(&hex_root_key)
+            .unwrap_or(&empty_hash);
+        let nested = NestedRootKeys::new(root_key, &target_root_keys);
+        let hash = for_each_batch(&target, root_key, &nested, |_| async { Ok(()) }).await?;
+        ensure!(
+            hash == *expected_hash,
+            "the content of root key {hex_root_key} differs after the migration"
+        );
+    }
+    Ok(())
+}
+
+/// Migrates the namespace of the store it is run with into `target`.
+pub struct MigrateJob {
+    /// The description of the source storage, without credentials.
+    pub source: String,
+    pub target: StoreConfig,
+    /// The description of the target storage, without credentials.
+    pub target_description: String,
+    pub checkpoint_path: PathBuf,
+}
+
+#[async_trait]
+impl RunnableWithStore for MigrateJob {
+    type Output = ();
+
+    async fn run<D>(self, config: D::Config, namespace: String) -> anyhow::Result<()>
+    where
+        D: KeyValueDatabase + Clone + Send + Sync + 'static,
+        D::Store: KeyValueStore + Clone + Send + Sync + 'static,
+        D::Error: Send + Sync,
+    {
+        let MigrateJob {
+            source,
+            target,
+            target_description,
+            checkpoint_path,
+        } = self;
+        let checkpoint = persistent::File::read_or_create(&checkpoint_path, || {
+            Ok(MigrationCheckpoint {
+                source: source.clone(),
+                target: target_description.clone(),
+                root_keys: BTreeMap::new(),
+            })
+        })?;
+        if checkpoint.source != source || checkpoint.target != target_description {
+            bail!(
+                "the checkpoint {} belongs to a migration from {} to {}",
+                checkpoint_path.display(),
+                checkpoint.source,
+                checkpoint.target
+            );
+        }
+        let job = MigrateToJob::<D> {
+            source_config: config,
+            source_namespace: namespace,
+            checkpoint,
+        };
+        Box::pin(target.run_with_store(job)).await
+    }
+}
+
+/// The second half of a [`MigrateJob`], once the type of the source database is known.
+struct MigrateToJob<S: KeyValueDatabase> {
+    source_config: S::Config,
+    source_namespace: String,
+    checkpoint: persistent::File<MigrationCheckpoint>,
+}
+
+#[async_trait]
+impl<S> RunnableWithStore for MigrateToJob<S>
+where
+    S: KeyValueDatabase + Clone + Send + Sync + 'static,
+    S::Store: KeyValueStore + Clone + Send + Sync + 'static,
+    S::Error: Send + Sync,
+{
+    type Output = ();
+
+    async fn run<T>(mut self, config: T::Config, namespace: String) -> anyhow::Result<()>
+    where
+        T: KeyValueDatabase + Clone + Send + Sync + 'static,
+        T::Store: KeyValueStore + Clone + Send + Sync + 'static,
+        T::Error: Send + Sync,
+    {
+        migrate::<S, T>(
+            &self.source_config,
+            &self.source_namespace,
+            &config,
+            &namespace,
+            &mut self.checkpoint,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_persistent::{self as persistent, PersistExt as _};
+    use linera_views::{
+        batch::Batch,
+        memory::MemoryDatabase,
+        random::generate_test_namespace,
+        store::{
+            KeyValueDatabase as _, ReadableKeyValueStore as _, TestKeyValueDatabase as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    use super::{migrate, MigrationCheckpoint, NestedRootKeys};
+
+    #[test]
+    fn test_nested_root_keys() {
+        let root_keys = vec![vec![], vec![0, 1], vec![0, 1, 2], vec![3]];
+        let nested = NestedRootKeys::new(&[], &root_keys);
+        assert!(nested.contains(&[0, 1]));
+        assert!(nested.contains(&[0, 1, 7]));
+        assert!(nested.contains(&[3, 4]));
+        assert!(!nested.contains(&[0]));
+        assert!(!nested.contains(&[0, 2, 1]));
+
+        let nested = NestedRootKeys::new(&[0, 1], &root_keys);
+        assert!(nested.contains(&[2, 5]));
+        assert!(!nested.contains(&[3]));
+        assert!(!nested.contains(&[]));
+    }
+
+    async fn put(database: &MemoryDatabase, root_key: &[u8], key: &[u8], value: &[u8]) {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(key.to_vec(), value.to_vec());
+        let store = database.open_exclusive(root_key).unwrap();
+        store.write_batch(batch).await.unwrap();
+    }
+
+    async fn get(database: &MemoryDatabase, root_key: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+        let store = database.open_exclusive(root_key).unwrap();
+        store.read_value_bytes(key).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migrate_resume_and_verify() {
+        let config = MemoryDatabase::new_test_config().await.unwrap();
+        let source_namespace = generate_test_namespace();
+        let target_namespace = generate_test_namespace();
+        let source = MemoryDatabase::maybe_create_and_connect(&config, &source_namespace)
+            .await
+            .unwrap();
+        put(&source, &[], &[], b"empty key").await;
+        put(&source, &[], &[0, 1], b"shared").await;
+        for index in 0..2500u16 {
+            put(&source, &[1], &index.to_be_bytes(), b"value").await;
+        }
+        put(&source, &[2], &[5], b"two").await;
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("checkpoint.json");
+        let mut checkpoint =
+            persistent::File::read_or_create(&path, || Ok(MigrationCheckpoint::default())).unwrap();
+        migrate::<MemoryDatabase, MemoryDatabase>(
+            &config,
+            &source_namespace,
+            &config,
+            &target_namespace,
+            &mut checkpoint,
+        )
+        .await
+        .unwrap();
+        let target = MemoryDatabase::connect(&config, &target_namespace)
+            .await
+            .unwrap();
+        assert_eq!(get(&target, &[], &[]).await, Some(b"empty key".to_vec()));
+        assert_eq!(get(&target, &[], &[0, 1]).await, Some(b"shared".to_vec()));
+        assert_eq!(
+            get(&target, &[1], &2499u16.to_be_bytes()).await,
+            Some(b"value".to_vec())
+        );
+        assert_eq!(get(&target, &[2], &[5]).await, Some(b"two".to_vec()));
+
+        // Resuming only copies the root keys missing from the checkpoint.
+        let hex_root_key = hex::encode([2]);
+        checkpoint
+            .mutate(|checkpoint| checkpoint.root_keys.remove(&hex_root_key))
+            .await
+            .unwrap();
+        put(&source, &[1], &[9, 9], b"too late").await;
+        put(&source, &[2], &[6], b"added").await;
+        let root_keys = checkpoint.root_keys.clone();
+        drop(checkpoint);
+        let mut checkpoint = persistent::File::read(&path).unwrap();
+        assert_eq!(checkpoint.root_keys, root_keys);
+        migrate::<MemoryDatabase, MemoryDatabase>(
+            &config,
+            &source_namespace,
+            &config,
+            &target_namespace,
+            &mut checkpoint,
+        )
+        .await
+        .unwrap();
+        assert_eq!(get(&target, &[1], &[9, 9]).await, None);
+        assert_eq!(get(&target, &[2], &[6]).await, Some(b"added".to_vec()));
+
+        // Keys of the target that are not in the source fail the verification, whether
+        // under a copied root key or another one.
+        for root_key in [&[2][..], &[7]] {
+            put(&target, root_key, &[8], b"extra").await;
+            let result = migrate::<MemoryDatabase, MemoryDatabase>(
+                &config,
+                &source_namespace,
+                &config,
+                &target_namespace,
+                &mut checkpoint,
+            )
+            .await;
+            assert!(result.is_err());
+            let mut batch = Batch::new();
+            batch.delete_key(vec![8]);
+            let store = target.open_exclusive(root_key).unwrap();
+            store.write_batch(batch).await.unwrap();
+        }
+        assert_eq!(checkpoint.root_keys.len(), 3);
+    }
+}
@@ -1,7 +1,9 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{borrow::Cow, future::IntoFuture, iter, net::SocketAddr, num::NonZeroU16, sync::Arc};
+use std::{
+    borrow::Cow, future::IntoFuture, iter, net::SocketAddr, num::NonZeroU16, ops::Deref, sync::Arc,
+};
 
 use async_graphql::{
     futures_util::Stream, resolver_utils::ContainerType, Error, MergedObject, OutputType,
@@ -13,7 +15,8 @@ use futures::{lock::Mutex, Future, FutureExt as _};
 use linera_base::{
     crypto::{CryptoError, CryptoHash},
     data_types::{
-        Amount, ApplicationDescription, ApplicationPermissions, Bytecode, Epoch, TimeDelta,
+        Amount, ApplicationDescription, ApplicationPermissions, BlockHeight, Bytecode, Epoch,
+        TimeDelta,
     },
     identifiers::{
         Account, AccountOwner, ApplicationId, ChainId, IndexAndEvent, ModuleId, StreamId,
@@ -82,6 +85,8 @@ enum NodeServiceError {
     JsonError(#[from] serde_json::Error),
     #[error("malformed chain ID: {0}")]
     InvalidChainId(CryptoError),
+    #[error("cannot execute operations at a past block height")]
+    HistoricalOperations,
 }
 
 impl IntoResponse for NodeServiceError {
@@ -98,6 +103,9 @@ impl IntoResponse for NodeServiceError {
                 StatusCode::BAD_REQUEST,
                 vec!["invalid chain ID".to_string()],
             ),
+            NodeServiceError::HistoricalOperations => {
+                (StatusCode::BAD_REQUEST, vec![self.to_string()])
+            }
         };
         let tuple = (tuple.0, json!({"error": tuple.1}).to_string());
         tuple.into_response()
@@ -604,26 +612,52 @@ impl<C> QueryRoot<C>
 where
     C: ClientContext + 'static,
 {
+    /// Returns the state of a chain, or its state right after the block at `height` if
+    /// the node archives chain states.
     async fn chain(
         &self,
         chain_id: ChainId,
+        height: Option<BlockHeight>,
     ) -> Result<
         ChainStateExtendedView<<C::Environment as linera_core::Environment>::StorageContext>,
         Error,
     > {
         let client = self.context.lock().await.make_chain_client(chain_id);
-        let view = client.chain_state_view().await?;
+        let view = match height {
+            None => ChainStateViewGuard::Current(client.chain_state_view().await?),
+            Some(height) => {
+                ChainStateViewGuard::Archived(Box::new(client.chain_state_view_at(height).await?))
+            }
+        };
         Ok(ChainStateExtendedView::new(view))
     }
 
-    async fn applications(&self, chain_id: ChainId) -> Result<Vec<ApplicationOverview>, Error> {
+    /// Returns the applications of a chain, or those that existed right after the block
+    /// at `height` if the node archives chain states.
+    async fn applications(
+        &self,
+        chain_id: ChainId,
+        height: Option<BlockHeight>,
+    ) -> Result<Vec<ApplicationOverview>, Error> {
         let client = self.context.lock().await.make_chain_client(chain_id);
-        let applications = client
-            .chain_state_view()
-            .await?
-            .execution_state
-            .list_applications()
-            .await?;
+        let applications = match height {
+            None => {
+                client
+                    .chain_state_view()
+                    .await?
+                    .execution_state
+                    .list_applications()
+                    .await?
+            }
+            Some(height) => {
+                client
+                    .chain_state_view_at(height)
+                    .await?
+                    .execution_state
+                    .list_applications()
+                    .await?
+            }
+        };
 
         let overviews = applications
             .into_iter()
@@ -732,11 +766,34 @@ where
     C::Extra: linera_execution::ExecutionRuntimeContext;
 
 /// A wrapper type that allows proxying GraphQL queries to a [`ChainStateView`] that's behind an
-/// [`OwnedRwLockReadGuard`].
-pub struct ReadOnlyChainStateView<C>(OwnedRwLockReadGuard<ChainStateView<C>>)
+/// [`OwnedRwLockReadGuard`], or to an archived one.
+pub struct ReadOnlyChainStateView<C>(ChainStateViewGuard<C>)
 where
     C: linera_views::context::Context + Clone + Send + Sync + 'static;
 
+/// Either the current [`ChainStateView`] of a chain or one of its archived states.
+enum ChainStateViewGuard<C>
+where
+    C: linera_views::context::Context + Clone + Send + Sync + 'static,
+{
+    Current(OwnedRwLockReadGuard<ChainStateView<C>>),
+    Archived(Box<ChainStateView<C>>),
+}
+
+impl<C> Deref for ChainStateViewGuard<C>
+where
+    C: linera_views::context::Context + Clone + Send + Sync + 'static,
+{
+    type Target = ChainStateView<C>;
+
+    fn deref(&self) -> &ChainStateView<C> {
+        match self {
+            ChainStateViewGuard::Current(guard) => guard,
+            ChainStateViewGuard::Archived(view) => view,
+        }
+    }
+}
+
 impl<C> ContainerType for ReadOnlyChainStateView<C>
 where
     C: linera_views::context::Context + Clone + Send + Sync + 'static,
@@ -775,7 +832,7 @@ where
     C: linera_views::context::Context + Clone + Send + Sync + 'static,
     C::Extra: linera_execution::ExecutionRuntimeContext,
 {
-    fn new(view: OwnedRwLockReadGuard<ChainStateView<C>>) -> Self {
+    fn new(view: ChainStateViewGuard<C>) -> Self {
         Self(
             ChainStateViewExtension(view.chain_id()),
             ReadOnlyChainStateView(view),
@@ -783,6 +840,13 @@ where
     }
 }
 
+/// The URL parameters of an application query.
+#[derive(Deserialize)]
+struct HistoricalQuery {
+    /// The block height after which to query the application state.
+    height: Option<u64>,
+}
+
 #[derive(SimpleObject)]
 pub struct ApplicationOverview {
     id: ApplicationId,
@@ -939,16 +1003,20 @@ where
         application_id: ApplicationId,
         request: Vec<u8>,
         chain_id: ChainId,
+        height: Option<BlockHeight>,
     ) -> Result<Vec<u8>, NodeServiceError> {
         let QueryOutcome {
             response,
             operations,
         } = self
-            .query_user_application(application_id, request, chain_id)
+            .query_user_application(application_id, request, chain_id, height)
             .await?;
         if operations.is_empty() {
             return Ok(response);
         }
+        if height.is_some() {
+            return Err(NodeServiceError::HistoricalOperations);
+        }
 
         trace!("Query requested a new block with operations: {operations:?}");
         let client = self.context.lock().await.make_chain_client(chain_id);
@@ -969,12 +1037,14 @@ where
         Ok(serde_json::to_vec(&response)?)
     }
 
-    /// Queries a user application, returning the raw [`QueryOutcome`].
+    /// Queries a user application, returning the raw [`QueryOutcome`]. If `height` is
+    /// given, the query runs against the archived state right after that block.
     async fn query_user_application(
         &self,
         application_id: ApplicationId,
         bytes: Vec<u8>,
         chain_id: ChainId,
+        height: Option<BlockHeight>,
     ) -> Result<QueryOutcome<Vec<u8>>, NodeServiceError> {
         let query = Query::User {
             application_id,
@@ -984,7 +1054,10 @@ where
         let QueryOutcome {
             response,
             operations,
-        } = client.query_application(query).await?;
+        } = match height {
+            None => client.query_application(query).await?,
+            Some(height) => client.query_application_at(query, height).await?,
+        };
         match response {
             QueryResponse::System(_) => {
                 unreachable!("cannot get a system response for a user query")
@@ -1008,9 +1081,11 @@ where
 
     /// Executes a GraphQL query against an application.
     /// Pattern matches on the `OperationType` of the query and routes the query
-    /// accordingly.
+    /// accordingly. The optional `height` URL parameter runs the query against the
+    /// archived state right after that block.
     async fn application_handler(
         Path((chain_id, application_id)): Path<(String, String)>,
+        axum::extract::Query(HistoricalQuery { height }): axum::extract::Query<HistoricalQuery>,
         service: Extension<Self>,
         request: String,
     ) -> Result<Vec<u8>, NodeServiceError> {
@@ -1023,7 +1098,12 @@ where
         );
         let response = service
             .0
-            .handle_service_request(application_id, request.into_bytes(), chain_id)
+            .handle_service_request(
+                application_id,
+                request.into_bytes(),
+                chain_id,
+                height.map(BlockHeight),
+            )
             .await?;
 
         Ok(response)
@@ -490,7 +490,11 @@ impl ProxyOptions {
             .storage_config
             .add_common_storage_options(&self.common_storage_options)?;
         store_config
-            .run_with_storage(None, ProxyContext::from_options(self)?)
+            .run_with_storage(
+                None,
+                self.common_storage_options.storage_archive_chain_states,
+                ProxyContext::from_options(self)?,
+            )
             .boxed()
             .await?
     }
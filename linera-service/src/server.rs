@@ -511,7 +511,11 @@ async fn run(options: ServerOptions) {
                 .add_common_storage_options(&common_storage_options)
                 .unwrap();
             store_config
-                .run_with_storage(wasm_runtime, job)
+                .run_with_storage(
+                    wasm_runtime,
+                    common_storage_options.storage_archive_chain_states,
+                    job,
+                )
                 .boxed()
                 .await
                 .unwrap()
@@ -0,0 +1,90 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A reference signing daemon for the `RemoteSigner` of `linera-client`, holding the
+//! secret keys of a plain or encrypted keystore in memory.
+
+use std::{env, path::PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use clap::Parser as _;
+use linera_base::listen_for_shutdown_signals;
+use linera_client::{
+    keystore::Keystore,
+    remote_signer::{self, SignerAddress, SignerToken},
+};
+use linera_service::util;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+#[derive(clap::Parser)]
+#[command(
+    name = "linera-signer",
+    version = linera_version::VersionInfo::default_clap_str(),
+    about = "Sign block proposals on behalf of Linera clients using a local keystore",
+)]
+struct SignerOptions {
+    /// The keystore file holding the secret keys, possibly encrypted.
+    #[arg(long = "keystore")]
+    keystore_path: PathBuf,
+
+    /// The file containing the passphrase of an encrypted keystore. If missing, the
+    /// passphrase is read from the environment variable `LINERA_KEYSTORE_PASSPHRASE`.
+    #[arg(long = "keystore-passphrase-file")]
+    keystore_passphrase_file: Option<PathBuf>,
+
+    /// The address to listen on, e.g. `unix:/run/linera-signer.sock` or
+    /// `tcp:127.0.0.1:9000`. Requests are not encrypted, so TCP addresses should only be
+    /// reachable through trusted networks.
+    #[arg(long)]
+    listen: SignerAddress,
+
+    /// The file containing the token that clients must know. A new token is written to
+    /// it if it doesn't exist.
+    #[arg(long)]
+    token_file: PathBuf,
+}
+
+impl SignerOptions {
+    /// Returns the passphrase of an encrypted keystore, if one was provided.
+    fn keystore_passphrase(&self) -> Result<Option<String>> {
+        if let Some(path) = &self.keystore_passphrase_file {
+            let mut passphrase = fs_err::read_to_string(path)?;
+            // Trim in place rather than copying, so that no copy of the passphrase is left.
+            passphrase.truncate(passphrase.trim_end_matches(['\n', '\r']).len());
+            return Ok(Some(passphrase));
+        }
+        Ok(env::var("LINERA_KEYSTORE_PASSPHRASE").ok())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    linera_base::tracing::init("signer");
+
+    let options = SignerOptions::parse();
+    let keystore: Keystore = util::read_json(&options.keystore_path)?;
+    match &keystore {
+        Keystore::Remote(_) => bail!("The keystore of a signing daemon must contain its keys"),
+        Keystore::Encrypted(_) => {
+            let passphrase = options.keystore_passphrase()?.context(
+                "An encrypted keystore requires a passphrase: \
+                 use --keystore-passphrase-file or LINERA_KEYSTORE_PASSPHRASE",
+            )?;
+            keystore.set_passphrase(passphrase);
+        }
+        Keystore::Plain(signer) => info!("Loaded {} keys", signer.keys().len()),
+    }
+    info!("Loaded the keystore {}", options.keystore_path.display());
+
+    if !options.token_file.exists() {
+        SignerToken::generate().write(&options.token_file)?;
+        info!("Wrote a new token to {}", options.token_file.display());
+    }
+    let token = SignerToken::read(&options.token_file).await?;
+
+    let shutdown_signal = CancellationToken::new();
+    tokio::spawn(listen_for_shutdown_signals(shutdown_signal.clone()));
+    remote_signer::serve(keystore, &options.listen, token, shutdown_signal).await?;
+    Ok(())
+}
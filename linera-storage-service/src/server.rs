@@ -5,19 +5,22 @@ use std::{collections::BTreeMap, sync::Arc};
 
 use async_lock::RwLock;
 use linera_storage_service::common::{KeyPrefix, MAX_PAYLOAD_SIZE};
+#[cfg(any(with_rocksdb, with_postgres))]
+use linera_views::lru_caching::StorageCacheConfig;
+#[cfg(with_postgres)]
+use linera_views::postgres_db::{
+    PostgresDatabase, PostgresStoreConfig, PostgresStoreInternalConfig,
+};
+#[cfg(with_rocksdb)]
+use linera_views::rocks_db::{
+    PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
+    RocksDbStoreInternalConfig,
+};
 use linera_views::{
     batch::Batch,
     memory::{MemoryDatabase, MemoryStoreConfig},
     store::{KeyValueDatabase, ReadableKeyValueStore, WritableKeyValueStore},
 };
-#[cfg(with_rocksdb)]
-use linera_views::{
-    lru_caching::StorageCacheConfig,
-    rocks_db::{
-        PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
-        RocksDbStoreInternalConfig,
-    },
-};
 use serde::Serialize;
 use tonic::{transport::Server, Request, Response, Status};
 use tracing::{info, instrument};
@@ -43,6 +46,9 @@ enum LocalStore {
     /// The RocksDB key value store
     #[cfg(with_rocksdb)]
     RocksDb(<RocksDbDatabase as KeyValueDatabase>::Store),
+    /// The PostgreSQL key value store
+    #[cfg(with_postgres)]
+    Postgres(<PostgresDatabase as KeyValueDatabase>::Store),
 }
 
 #[derive(Default)]
@@ -75,6 +81,10 @@ impl StorageServer {
                 .read_value_bytes(key)
                 .await
                 .map_err(|e| Status::unknown(format!("RocksDB error {:?} at read_value_bytes", e))),
+            #[cfg(with_postgres)]
+            LocalStore::Postgres(store) => store.read_value_bytes(key).await.map_err(|e| {
+                Status::unknown(format!("PostgreSQL error {:?} at read_value_bytes", e))
+            }),
         }
     }
 
@@ -89,6 +99,11 @@ impl StorageServer {
                 .contains_key(key)
                 .await
                 .map_err(|e| Status::unknown(format!("RocksDB error {:?} at contains_key", e))),
+            #[cfg(with_postgres)]
+            LocalStore::Postgres(store) => store
+                .contains_key(key)
+                .await
+                .map_err(|e| Status::unknown(format!("PostgreSQL error {:?} at contains_key", e))),
         }
     }
 
@@ -103,6 +118,11 @@ impl StorageServer {
                 .contains_keys(keys)
                 .await
                 .map_err(|e| Status::unknown(format!("RocksDB error {:?} at contains_keys", e))),
+            #[cfg(with_postgres)]
+            LocalStore::Postgres(store) => store
+                .contains_keys(keys)
+                .await
+                .map_err(|e| Status::unknown(format!("PostgreSQL error {:?} at contains_keys", e))),
         }
     }
 
@@ -118,6 +138,13 @@ impl StorageServer {
             LocalStore::RocksDb(store) => store.read_multi_values_bytes(keys).await.map_err(|e| {
                 Status::unknown(format!("RocksDB error {:?} at read_multi_values_bytes", e))
             }),
+            #[cfg(with_postgres)]
+            LocalStore::Postgres(store) => store.read_multi_values_bytes(keys).await.map_err(|e| {
+                Status::unknown(format!(
+                    "PostgreSQL error {:?} at read_multi_values_bytes",
+                    e
+                ))
+            }),
         }
     }
 
@@ -132,6 +159,12 @@ impl StorageServer {
                     Status::unknown(format!("RocksDB error {:?} at find_keys_by_prefix", e))
                 })
             }
+            #[cfg(with_postgres)]
+            LocalStore::Postgres(store) => {
+                store.find_keys_by_prefix(key_prefix).await.map_err(|e| {
+                    Status::unknown(format!("PostgreSQL error {:?} at find_keys_by_prefix", e))
+                })
+            }
         }
     }
 
@@ -161,6 +194,16 @@ impl StorageServer {
                         e
                     ))
                 }),
+            #[cfg(with_postgres)]
+            LocalStore::Postgres(store) => store
+                .find_key_values_by_prefix(key_prefix)
+                .await
+                .map_err(|e| {
+                    Status::unknown(format!(
+                        "PostgreSQL error {:?} at find_key_values_by_prefix",
+                        e
+                    ))
+                }),
         }
     }
 
@@ -175,6 +218,11 @@ impl StorageServer {
                 .write_batch(batch)
                 .await
                 .map_err(|e| Status::unknown(format!("RocksDB error {:?} at write_batch", e))),
+            #[cfg(with_postgres)]
+            LocalStore::Postgres(store) => store
+                .write_batch(batch)
+                .await
+                .map_err(|e| Status::unknown(format!("PostgreSQL error {:?} at write_batch", e))),
         }
     }
 
@@ -289,6 +337,35 @@ enum StorageServerOptions {
         #[arg(long, default_value = "1000")]
         max_cache_entries: usize,
     },
+
+    #[cfg(with_postgres)]
+    #[command(name = "postgres")]
+    Postgres {
+        /// The storage namespace.
+        #[arg(long, default_value = "linera_storage_service")]
+        namespace: String,
+        /// The storage service address.
+        #[arg(long)]
+        endpoint: String,
+        /// The connection URI of the PostgreSQL database.
+        #[arg(long)]
+        uri: String,
+        /// The maximal number of simultaneous connections to the database.
+        #[arg(long)]
+        max_concurrent_queries: Option<usize>,
+        /// Preferred buffer size for async streams.
+        #[arg(long, default_value = "10")]
+        max_stream_queries: usize,
+        /// The maximum size of the cache, in bytes (keys size + value sizes)
+        #[arg(long, default_value = "10000000")]
+        max_cache_size: usize,
+        /// The maximum size of an entry size, in bytes
+        #[arg(long, default_value = "1000000")]
+        max_entry_size: usize,
+        /// The maximum number of entries in the cache.
+        #[arg(long, default_value = "1000")]
+        max_cache_entries: usize,
+    },
 }
 
 #[tonic::async_trait]
@@ -662,6 +739,7 @@ async fn main() {
                 max_cache_size,
                 max_entry_size,
                 max_cache_entries,
+                shared_database: false,
             };
             let config = RocksDbStoreConfig {
                 inner_config,
@@ -674,6 +752,40 @@ async fn main() {
             let store = LocalStore::RocksDb(store);
             (store, endpoint)
         }
+
+        #[cfg(with_postgres)]
+        StorageServerOptions::Postgres {
+            namespace,
+            endpoint,
+            uri,
+            max_concurrent_queries,
+            max_stream_queries,
+            max_cache_size,
+            max_entry_size,
+            max_cache_entries,
+        } => {
+            let inner_config = PostgresStoreInternalConfig {
+                uri,
+                max_concurrent_queries,
+                max_stream_queries,
+            };
+            let storage_cache_config = StorageCacheConfig {
+                max_cache_size,
+                max_entry_size,
+                max_cache_entries,
+                shared_database: false,
+            };
+            let config = PostgresStoreConfig {
+                inner_config,
+                storage_cache_config,
+            };
+            let database = PostgresDatabase::maybe_create_and_connect(&config, &namespace)
+                .await
+                .expect("store");
+            let store = database.open_shared(&[]).expect("Failed to open store");
+            let store = LocalStore::Postgres(store);
+            (store, endpoint)
+        }
     };
     let pending_big_puts = Arc::new(RwLock::new(BTreeMap::default()));
     let pending_big_reads = Arc::new(RwLock::new(PendingBigReads::default()));
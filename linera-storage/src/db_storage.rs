@@ -8,7 +8,7 @@ use async_trait::async_trait;
 use linera_base::prometheus_util::MeasureLatency as _;
 use linera_base::{
     crypto::CryptoHash,
-    data_types::{Blob, NetworkDescription, TimeDelta, Timestamp},
+    data_types::{Blob, BlockHeight, NetworkDescription, TimeDelta, Timestamp},
     identifiers::{ApplicationId, BlobId, ChainId, EventId, IndexAndEvent, StreamId},
 };
 use linera_chain::{
@@ -20,7 +20,7 @@ use linera_execution::{
 };
 use linera_views::{
     backends::dual::{DualStoreRootKeyAssignment, StoreInUse},
-    context::ViewContext,
+    context::{Context as _, ViewContext},
     store::{
         KeyValueDatabase, KeyValueStore, ReadableKeyValueStore as _, WritableKeyValueStore as _,
     },
@@ -37,6 +37,13 @@ use {
 
 use crate::{ChainRuntimeContext, Clock, Storage};
 
+mod archive;
+mod prune;
+mod snapshot;
+
+pub use prune::{PruningPolicy, PruningSummary};
+pub use snapshot::{ChainSnapshotHeader, SnapshotError, CHAIN_SNAPSHOT_VERSION};
+
 #[cfg(with_metrics)]
 pub mod metrics {
     use std::sync::LazyLock;
@@ -92,6 +99,15 @@ pub mod metrics {
         )
     });
 
+    /// The metric counting how often the state of a chain is archived.
+    pub(super) static ARCHIVE_CHAIN_STATE_COUNTER: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "archive_chain_state",
+            "The metric counting how often the state of a chain is archived",
+            &[],
+        )
+    });
+
     /// The metric counting how often a blob is read from storage.
     #[doc(hidden)]
     pub(super) static READ_BLOB_COUNTER: LazyLock<IntCounterVec> = LazyLock::new(|| {
@@ -304,6 +320,8 @@ pub struct DbStorage<Database, Clock = WallClock> {
     user_contracts: Arc<papaya::HashMap<ApplicationId, UserContractCode>>,
     user_services: Arc<papaya::HashMap<ApplicationId, UserServiceCode>>,
     execution_runtime_config: ExecutionRuntimeConfig,
+    /// Whether to keep the history of the state of each chain.
+    archive_chain_states: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -316,6 +334,8 @@ enum BaseKey {
     Event(EventId),
     BlockExporterState(u32),
     NetworkDescription,
+    ArchivedChainState(ChainId, BlockHeight),
+    PrunedHeight(ChainId),
 }
 
 const INDEX_CHAIN_ID: u8 = 0;
@@ -408,7 +428,7 @@ impl DualStoreRootKeyAssignment for ChainStatesFirstAssignment {
             return Ok(StoreInUse::Second);
         }
         let store = match bcs::from_bytes(root_key)? {
-            BaseKey::ChainState(_) => StoreInUse::First,
+            BaseKey::ChainState(_) | BaseKey::ArchivedChainState(..) => StoreInUse::First,
             _ => StoreInUse::Second,
         };
         Ok(store)
@@ -563,6 +583,54 @@ where
         ChainStateView::load(context).await
     }
 
+    async fn load_chain_at_height(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Option<ChainStateView<Self::Context>>, ViewError> {
+        let Some(store) = self.archived_chain_state_store(chain_id, height).await? else {
+            return Ok(None);
+        };
+        let runtime_context = ChainRuntimeContext {
+            storage: self.clone(),
+            chain_id,
+            execution_runtime_config: self.execution_runtime_config,
+            user_contracts: self.user_contracts.clone(),
+            user_services: self.user_services.clone(),
+        };
+        let context = ViewContext::create_root_context(store, runtime_context).await?;
+        let chain = ChainStateView::load(context).await?;
+        // An archived state always contains the block at `height`.
+        if chain.tip_state.get().next_block_height <= height {
+            return Ok(None);
+        }
+        Ok(Some(chain))
+    }
+
+    async fn save_chain(
+        &self,
+        chain: &mut ChainStateView<Self::Context>,
+        height: Option<BlockHeight>,
+    ) -> Result<(), ViewError> {
+        let mut batch = linera_views::batch::Batch::new();
+        chain.flush(&mut batch)?;
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let store = chain.context().store();
+        if self.archive_chain_states {
+            Self::add_undo_entry(store, &mut batch, height).await?;
+        }
+        store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn pruned_height(&self, chain_id: ChainId) -> Result<BlockHeight, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let key = bcs::to_bytes(&BaseKey::PrunedHeight(chain_id))?;
+        Ok(store.read_value(&key).await?.unwrap_or(BlockHeight::ZERO))
+    }
+
     async fn contains_blob(&self, blob_id: BlobId) -> Result<bool, ViewError> {
         let store = self.database.open_shared(&[])?;
         let blob_key = bcs::to_bytes(&BaseKey::Blob(blob_id))?;
@@ -1019,8 +1087,21 @@ impl<Database, C> DbStorage<Database, C> {
             user_contracts: Arc::new(papaya::HashMap::new()),
             user_services: Arc::new(papaya::HashMap::new()),
             execution_runtime_config: ExecutionRuntimeConfig::default(),
+            archive_chain_states: false,
         }
     }
+
+    /// Sets whether the storage keeps the history of the state of each chain, so that
+    /// its state after every executed block can be queried later with
+    /// [`Storage::load_chain_at_height`].
+    ///
+    /// Each write to a chain state then also stores the previous values of the keys it
+    /// changes. Archiving should stay enabled once the storage is in use: the states
+    /// loaded from before a write made without it would be wrong.
+    pub fn with_archived_chain_states(mut self, archive_chain_states: bool) -> Self {
+        self.archive_chain_states = archive_chain_states;
+        self
+    }
 }
 
 impl<Database> DbStorage<Database, WallClock>
@@ -0,0 +1,237 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The history of chain states, used to load the state of a chain after a past block.
+//!
+//! Every write to the state of a chain is accompanied, in the same batch, by an undo
+//! entry holding the previous values of the keys it changes. Each executed block records
+//! the index of the next undo entry, so the state after that block is the current one
+//! with the later undo entries applied in reverse order. This state is only built, and
+//! kept under its own root key, when it is first loaded.
+//!
+//! The history is only complete if archiving stays enabled: the writes made while it is
+//! disabled cannot be undone.
+
+use std::collections::BTreeMap;
+
+use linera_base::{data_types::BlockHeight, identifiers::ChainId};
+use linera_views::{
+    batch::{Batch, WriteOperation},
+    store::{
+        KeyValueDatabase, KeyValueStore, ReadableKeyValueStore as _, WritableKeyValueStore as _,
+    },
+    ViewError,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{BaseKey, DbStorage};
+use crate::Clock;
+
+/// The first byte of the keys recording the history of a chain state, among the keys of
+/// that state. Views only use keys starting with smaller tags.
+pub(super) const ARCHIVE_TAG: u8 = u8::MAX;
+
+/// The previous values of the keys changed by a write, `None` for absent keys.
+type UndoEntry = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) enum ArchiveKey {
+    /// The index of the next undo entry.
+    NextUndoIndex,
+    /// The index of the first undo entry that was not pruned.
+    FirstUndoIndex,
+    /// The undo entry with the given index.
+    Undo(u64),
+    /// The index of the first undo entry written after the block at this height.
+    Height(BlockHeight),
+}
+
+impl ArchiveKey {
+    pub(super) fn to_bytes(&self) -> Result<Vec<u8>, bcs::Error> {
+        let mut key = vec![ARCHIVE_TAG];
+        bcs::serialize_into(&mut key, self)?;
+        Ok(key)
+    }
+}
+
+/// Returns whether `key`, among the keys of a chain state, records its history.
+pub(super) fn is_archive_key(key: &[u8]) -> bool {
+    key.first() == Some(&ARCHIVE_TAG)
+}
+
+impl<Database, C> DbStorage<Database, C>
+where
+    Database: KeyValueDatabase + Clone + Send + Sync + 'static,
+    Database::Store: KeyValueStore + Clone + Send + Sync + 'static,
+    C: Clock + Clone + Send + Sync + 'static,
+    Database::Error: Send + Sync,
+{
+    /// Adds to `batch`, about to be written to the state of a chain, the undo entry of
+    /// that write and, if `height` is given, records the result as the state after the
+    /// block at `height`.
+    pub(super) async fn add_undo_entry(
+        store: &Database::Store,
+        batch: &mut Batch,
+        height: Option<BlockHeight>,
+    ) -> Result<(), ViewError> {
+        let next_index_key = ArchiveKey::NextUndoIndex.to_bytes()?;
+        let mut keys = vec![next_index_key.clone()];
+        let mut prefixes = Vec::new();
+        for operation in &batch.operations {
+            match operation {
+                WriteOperation::Put { key, .. } | WriteOperation::Delete { key } => {
+                    keys.push(key.clone())
+                }
+                WriteOperation::DeletePrefix { key_prefix } => prefixes.push(key_prefix.clone()),
+            }
+        }
+        let mut values = store
+            .read_multi_values_bytes(keys.clone())
+            .await?
+            .into_iter();
+        let index = match values.next().flatten() {
+            Some(bytes) => bcs::from_bytes::<u64>(&bytes)?,
+            None => 0,
+        };
+        let mut undo = BTreeMap::new();
+        for (key, value) in keys.into_iter().skip(1).zip(values) {
+            undo.entry(key).or_insert(value);
+        }
+        for prefix in prefixes {
+            for (suffix, value) in store.find_key_values_by_prefix(&prefix).await? {
+                let key = [prefix.as_slice(), suffix.as_slice()].concat();
+                if !is_archive_key(&key) {
+                    undo.entry(key).or_insert(Some(value));
+                }
+            }
+        }
+        let undo = undo.into_iter().collect::<UndoEntry>();
+        batch.put_key_value(ArchiveKey::Undo(index).to_bytes()?, &undo)?;
+        batch.put_key_value(next_index_key, &(index + 1))?;
+        if let Some(height) = height {
+            batch.put_key_value(ArchiveKey::Height(height).to_bytes()?, &(index + 1))?;
+            #[cfg(with_metrics)]
+            super::metrics::ARCHIVE_CHAIN_STATE_COUNTER
+                .with_label_values(&[])
+                .inc();
+        }
+        Ok(())
+    }
+
+    /// Opens the partition holding the state of `chain_id` after the block at `height`,
+    /// building that state first if needed. Returns `None` if it is not archived.
+    pub(super) async fn archived_chain_state_store(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Option<Database::Store>, ViewError> {
+        let root_key = bcs::to_bytes(&BaseKey::ArchivedChainState(chain_id, height))?;
+        let archived_store = self.database.open_exclusive(&root_key)?;
+        // The built state is written in a single batch, together with this marker.
+        if archived_store.contains_key(&[ARCHIVE_TAG]).await? {
+            return Ok(Some(archived_store));
+        }
+        let root_key = bcs::to_bytes(&BaseKey::ChainState(chain_id))?;
+        let store = self.database.open_exclusive(&root_key)?;
+        let Some(start) = store
+            .read_value::<u64>(&ArchiveKey::Height(height).to_bytes()?)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let mut state = store
+            .find_key_values_by_prefix(&[])
+            .await?
+            .into_iter()
+            .filter(|(key, _)| !is_archive_key(key))
+            .collect::<BTreeMap<_, _>>();
+        // Undoing the writes made after the state was read as well gives the same result,
+        // so the end is read last.
+        let end = store
+            .read_value::<u64>(&ArchiveKey::NextUndoIndex.to_bytes()?)
+            .await?
+            .unwrap_or(start);
+        let keys = (start..end)
+            .rev()
+            .map(|index| ArchiveKey::Undo(index).to_bytes())
+            .collect::<Result<Vec<_>, _>>()?;
+        for undo in store.read_multi_values::<UndoEntry>(keys).await? {
+            for (key, value) in undo.ok_or(ViewError::MissingEntries)? {
+                match value {
+                    Some(value) => state.insert(key, value),
+                    None => state.remove(&key),
+                };
+            }
+        }
+        let mut batch = Batch::new();
+        for (key, value) in state {
+            batch.put_key_value_bytes(key, value);
+        }
+        batch.put_key_value_bytes(vec![ARCHIVE_TAG], Vec::new());
+        archived_store.write_batch(batch).await?;
+        Ok(Some(archived_store))
+    }
+
+    /// Deletes the history of `chain_id` needed to load its states after the blocks from
+    /// `start` to `end`, and returns the number of these states that were archived.
+    pub(super) async fn prune_archived_chain_states(
+        &self,
+        chain_id: ChainId,
+        start: BlockHeight,
+        end: BlockHeight,
+        batch_size: usize,
+    ) -> Result<usize, ViewError> {
+        let root_key = bcs::to_bytes(&BaseKey::ChainState(chain_id))?;
+        let store = self.database.open_exclusive(&root_key)?;
+        let first_index_key = ArchiveKey::FirstUndoIndex.to_bytes()?;
+        let keys = vec![first_index_key.clone(), ArchiveKey::Height(end).to_bytes()?];
+        let indices = store.read_multi_values::<u64>(keys).await?;
+        // The undo entries are still needed if the state after `end` is not archived, as
+        // the states after later blocks may be.
+        if let Some(end_index) = indices[1] {
+            let start_index = indices[0].unwrap_or(0);
+            let indices = (start_index..end_index).collect::<Vec<_>>();
+            for indices in indices.chunks(batch_size) {
+                let mut batch = Batch::new();
+                for index in indices {
+                    batch.delete_key(ArchiveKey::Undo(*index).to_bytes()?);
+                }
+                store.write_batch(batch).await?;
+            }
+            let mut batch = Batch::new();
+            batch.put_key_value(first_index_key, &end_index)?;
+            store.write_batch(batch).await?;
+        }
+
+        let mut count = 0;
+        let heights = (start.0..end.0).map(BlockHeight).collect::<Vec<_>>();
+        for heights in heights.chunks(batch_size) {
+            let futures = heights.iter().map(|height| async move {
+                let root_key = bcs::to_bytes(&BaseKey::ArchivedChainState(chain_id, *height))?;
+                let store = self.database.open_exclusive(&root_key)?;
+                if store.contains_key(&[ARCHIVE_TAG]).await? {
+                    let mut batch = Batch::new();
+                    batch.delete_key_prefix(Vec::new());
+                    store.write_batch(batch).await?;
+                }
+                Ok::<_, ViewError>(())
+            });
+            futures::future::try_join_all(futures).await?;
+            let keys = heights
+                .iter()
+                .map(|height| ArchiveKey::Height(*height).to_bytes())
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut batch = Batch::new();
+            for (key, exists) in keys.iter().zip(store.contains_keys(keys.clone()).await?) {
+                if exists {
+                    batch.delete_key(key.clone());
+                    count += 1;
+                }
+            }
+            if !batch.is_empty() {
+                store.write_batch(batch).await?;
+            }
+        }
+        Ok(count)
+    }
+}
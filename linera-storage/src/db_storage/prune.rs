@@ -0,0 +1,231 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deletion of the certificates, archived chain states and blobs that are no longer
+//! needed, so that the storage of a long-running node does not grow without bound.
+
+use std::collections::BTreeSet;
+
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::{BlockHeight, TimeDelta, Timestamp},
+    identifiers::{BlobId, BlobType, ChainId},
+};
+use linera_chain::types::ConfirmedBlock;
+use linera_views::{
+    batch::Batch,
+    store::{
+        KeyValueDatabase, KeyValueStore, ReadableKeyValueStore as _, WritableKeyValueStore as _,
+    },
+    ViewError,
+};
+
+use super::{BaseKey, DbStorage, BLOB_ID_LENGTH, INDEX_BLOB_ID};
+use crate::{Clock, Storage};
+
+/// The maximal number of entries deleted or read at once.
+const PRUNING_BATCH_SIZE: usize = 500;
+
+/// What to keep when pruning the storage.
+///
+/// The latest block of each chain is always kept, and so are the blocks whose outgoing
+/// messages have not been acknowledged by all their recipients yet.
+#[derive(Clone, Debug)]
+pub struct PruningPolicy {
+    /// The number of most recent blocks of each chain whose certificates are kept.
+    pub keep_heights: u64,
+    /// If set, the blocks more recent than this are kept as well.
+    pub keep_for: Option<TimeDelta>,
+    /// Whether to delete the data blobs that are no longer used by any kept certificate.
+    pub prune_blobs: bool,
+}
+
+/// The number of entries deleted by [`DbStorage::prune`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PruningSummary {
+    /// The number of deleted certificates, together with their confirmed blocks.
+    pub certificates: usize,
+    /// The number of deleted archived chain states.
+    pub archived_chain_states: usize,
+    /// The number of deleted blobs.
+    pub blobs: usize,
+}
+
+impl<Database, C> DbStorage<Database, C>
+where
+    Database: KeyValueDatabase + Clone + Send + Sync + 'static,
+    Database::Store: KeyValueStore + Clone + Send + Sync + 'static,
+    C: Clock + Clone + Send + Sync + 'static,
+    Database::Error: Send + Sync,
+{
+    /// Deletes the certificates and archived states of `chain_ids` that `policy` does not
+    /// keep, then, if requested, the data blobs that are no longer needed.
+    ///
+    /// A data blob is only deleted if the certificate recorded as its last user in its
+    /// [`BlobState`](linera_execution::BlobState) is gone and no kept block of `chain_ids`
+    /// requires it, so all the chains of the storage should be given when pruning blobs.
+    /// Blobs describing chains, applications and committees are never deleted.
+    ///
+    /// Pruning is incremental: the blocks of each chain that were already pruned are not
+    /// visited again.
+    pub async fn prune(
+        &self,
+        chain_ids: &[ChainId],
+        policy: &PruningPolicy,
+    ) -> Result<PruningSummary, ViewError> {
+        let mut summary = PruningSummary::default();
+        let mut kept_blob_ids = BTreeSet::new();
+        for chain_id in chain_ids {
+            self.prune_chain(*chain_id, policy, &mut summary, &mut kept_blob_ids)
+                .await?;
+        }
+        if policy.prune_blobs {
+            summary.blobs = self.prune_blobs(&kept_blob_ids).await?;
+        }
+        Ok(summary)
+    }
+
+    async fn prune_chain(
+        &self,
+        chain_id: ChainId,
+        policy: &PruningPolicy,
+        summary: &mut PruningSummary,
+        kept_blob_ids: &mut BTreeSet<BlobId>,
+    ) -> Result<(), ViewError> {
+        let start = self.pruned_height(chain_id).await?;
+        let chain = self.load_chain(chain_id).await?;
+        let next_height = chain.tip_state.get().next_block_height;
+        let mut end = BlockHeight(next_height.0.saturating_sub(policy.keep_heights.max(1)));
+        // The certificates of blocks with unacknowledged messages are still needed to
+        // send these messages.
+        if let Some(height) = chain.outbox_counters.get().keys().next() {
+            end = end.min(*height);
+        }
+        end = end.max(start);
+        let mut hashes = chain
+            .confirmed_log
+            .read(usize::try_from(start)?..usize::try_from(next_height)?)
+            .await?;
+        let mut num_pruned = usize::try_from(end.0 - start.0)?;
+        if let Some(keep_for) = policy.keep_for {
+            let cutoff = self.clock.current_time().saturating_sub(keep_for);
+            num_pruned = self
+                .count_blocks_before(&hashes[..num_pruned], cutoff)
+                .await?;
+            end = BlockHeight(start.0 + num_pruned as u64);
+        }
+        let kept_hashes = hashes.split_off(num_pruned);
+        if policy.prune_blobs {
+            self.collect_required_blob_ids(&kept_hashes, kept_blob_ids)
+                .await?;
+        }
+        if hashes.is_empty() {
+            return Ok(());
+        }
+
+        let store = self.database.open_shared(&[])?;
+        for hashes in hashes.chunks(PRUNING_BATCH_SIZE) {
+            let mut batch = Batch::new();
+            for key in Self::get_keys_for_certificates(hashes)? {
+                batch.delete_key(key);
+            }
+            store.write_batch(batch).await?;
+        }
+        summary.certificates += hashes.len();
+        summary.archived_chain_states += self
+            .prune_archived_chain_states(chain_id, start, end, PRUNING_BATCH_SIZE)
+            .await?;
+
+        // The pruned height is only recorded once everything below it is deleted, so that
+        // an interrupted pruning is resumed from the same point.
+        let mut batch = Batch::new();
+        batch.put_key_value(bcs::to_bytes(&BaseKey::PrunedHeight(chain_id))?, &end)?;
+        store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    /// Returns the number of leading blocks in `hashes` created before `cutoff`.
+    ///
+    /// The timestamps of the blocks of a chain are increasing, so this is a binary search.
+    async fn count_blocks_before(
+        &self,
+        hashes: &[CryptoHash],
+        cutoff: Timestamp,
+    ) -> Result<usize, ViewError> {
+        let (mut low, mut high) = (0, hashes.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let block = self
+                .read_confirmed_block(hashes[middle])
+                .await?
+                .ok_or(ViewError::MissingEntries)?;
+            if block.block().header.timestamp < cutoff {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        Ok(low)
+    }
+
+    /// Adds the blobs required by the blocks with the given hashes to `blob_ids`.
+    async fn collect_required_blob_ids(
+        &self,
+        hashes: &[CryptoHash],
+        blob_ids: &mut BTreeSet<BlobId>,
+    ) -> Result<(), ViewError> {
+        let store = self.database.open_shared(&[])?;
+        for hashes in hashes.chunks(PRUNING_BATCH_SIZE) {
+            let keys = hashes
+                .iter()
+                .map(|hash| bcs::to_bytes(&BaseKey::ConfirmedBlock(*hash)))
+                .collect::<Result<Vec<_>, _>>()?;
+            for block in store.read_multi_values::<ConfirmedBlock>(keys).await? {
+                let block = block.ok_or(ViewError::MissingEntries)?;
+                blob_ids.extend(block.block().required_blob_ids());
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the data blobs whose last user was deleted and that are not in
+    /// `kept_blob_ids`, and returns how many there were.
+    async fn prune_blobs(&self, kept_blob_ids: &BTreeSet<BlobId>) -> Result<usize, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let mut blob_ids = Vec::new();
+        for key in store.find_keys_by_prefix(&[INDEX_BLOB_ID]).await? {
+            let blob_id = bcs::from_bytes::<BlobId>(&key[..BLOB_ID_LENGTH])?;
+            if blob_id.blob_type == BlobType::Data && !kept_blob_ids.contains(&blob_id) {
+                blob_ids.push(blob_id);
+            }
+        }
+        let mut count = 0;
+        for blob_ids in blob_ids.chunks(PRUNING_BATCH_SIZE) {
+            let mut candidates = Vec::new();
+            let mut certificate_keys = Vec::new();
+            for (blob_id, blob_state) in blob_ids.iter().zip(self.read_blob_states(blob_ids).await?)
+            {
+                // Blobs that are not yet used by a processed certificate are kept.
+                let Some(hash) = blob_state.and_then(|state| state.last_used_by) else {
+                    continue;
+                };
+                candidates.push(*blob_id);
+                certificate_keys.push(bcs::to_bytes(&BaseKey::Certificate(hash))?);
+            }
+            let mut batch = Batch::new();
+            for (blob_id, in_use) in candidates
+                .into_iter()
+                .zip(store.contains_keys(certificate_keys).await?)
+            {
+                if !in_use {
+                    batch.delete_key(bcs::to_bytes(&BaseKey::Blob(blob_id))?);
+                    count += 1;
+                }
+            }
+            if !batch.is_empty() {
+                store.write_batch(batch).await?;
+            }
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,487 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Snapshots of a single chain, to move it between namespaces or storage backends
+//! without replaying it from genesis.
+//!
+//! A snapshot is a stream of records, so that neither exporting nor importing a chain
+//! needs to hold it in memory: a [`ChainSnapshotHeader`], the blobs and confirmed
+//! certificates of the chain by increasing height, each blob right before the first
+//! certificate requiring it, the key-value pairs of the chain state, and finally the hash
+//! of all the previous records. Each record is prefixed with its length.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Read, Write},
+};
+
+use linera_base::{
+    crypto::{BcsHashable, CryptoHash},
+    data_types::{Blob, BlockHeight, Epoch},
+    identifiers::{BlobId, BlobType, ChainId},
+};
+use linera_chain::types::ConfirmedBlockCertificate;
+use linera_views::{
+    batch::Batch,
+    store::{
+        KeyValueDatabase, KeyValueStore, ReadableKeyValueStore as _, WritableKeyValueStore as _,
+    },
+    ViewError,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{archive, BaseKey, DbStorage};
+use crate::{Clock, Storage};
+
+/// The current version of the snapshot format.
+pub const CHAIN_SNAPSHOT_VERSION: u32 = 2;
+
+/// The maximal number of certificates or state entries read or written at once.
+const SNAPSHOT_BATCH_SIZE: usize = 500;
+
+/// The key marking, among the keys of a chain state, that the state is being imported.
+/// Views only use keys starting with smaller tags.
+const IMPORT_MARKER: [u8; 1] = [archive::ARCHIVE_TAG];
+
+/// An error while exporting, verifying or importing a chain snapshot.
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error(transparent)]
+    View(#[from] ViewError),
+    #[error("failed to read or write the snapshot: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Bcs(#[from] bcs::Error),
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedVersion(u32),
+    #[error("chain {0} was not found in storage")]
+    ChainNotFound(ChainId),
+    #[error("chain {0} already exists in the target storage")]
+    ChainAlreadyExists(ChainId),
+    #[error("the snapshot content does not match its hash")]
+    HashMismatch,
+    #[error("the snapshot should end at height {expected} but ends at {found}")]
+    BlockCountMismatch {
+        expected: BlockHeight,
+        found: BlockHeight,
+    },
+    #[error("the snapshot records are not in the expected order")]
+    UnexpectedRecord,
+    #[error("the snapshot does not contain the state of the chain")]
+    MissingState,
+    #[error("missing certificate {0} of the chain")]
+    MissingCertificate(CryptoHash),
+    #[error("missing blob {0}")]
+    MissingBlob(BlobId),
+    #[error("certificate for block {0} does not extend the previous one")]
+    InvalidCertificate(BlockHeight),
+    #[error("no committee is known for epoch {0}")]
+    UnknownEpoch(Epoch),
+    #[error("certificate for block {0} is not signed by the committee of its epoch")]
+    InvalidSignatures(BlockHeight),
+}
+
+/// The first record of a chain snapshot, describing its content.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChainSnapshotHeader {
+    /// The version of the snapshot format.
+    pub version: u32,
+    /// The chain contained in the snapshot.
+    pub chain_id: ChainId,
+    /// The height of the first certificate in the snapshot. The earlier ones were pruned.
+    pub first_height: BlockHeight,
+    /// The height of the next block of the chain, i.e. the end of the certificates.
+    pub next_block_height: BlockHeight,
+}
+
+impl BcsHashable<'_> for ChainSnapshotHeader {}
+
+/// A record of a chain snapshot, after the header.
+#[derive(Debug, Serialize, Deserialize)]
+enum ChainSnapshotRecord {
+    /// A blob used or created by the chain, including its description.
+    Blob(Blob),
+    /// The confirmed certificate of the next block.
+    Certificate(ConfirmedBlockCertificate),
+    /// Raw key-value pairs of the chain state.
+    State(Vec<(Vec<u8>, Vec<u8>)>),
+    /// The hash of all the previous records, ending the snapshot.
+    End(CryptoHash),
+}
+
+impl BcsHashable<'_> for ChainSnapshotRecord {}
+
+/// The hash of the records of a snapshot up to, and including, `record`.
+#[derive(Serialize, Deserialize)]
+struct ChainSnapshotDigest {
+    previous: CryptoHash,
+    record: CryptoHash,
+}
+
+impl BcsHashable<'_> for ChainSnapshotDigest {}
+
+/// Writes the records of a snapshot, keeping track of their hash.
+struct RecordWriter<W> {
+    writer: W,
+    hash: CryptoHash,
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn new(mut writer: W, header: &ChainSnapshotHeader) -> Result<Self, SnapshotError> {
+        write_bytes(&mut writer, &bcs::to_bytes(header)?)?;
+        let hash = CryptoHash::new(header);
+        Ok(Self { writer, hash })
+    }
+
+    fn write(&mut self, record: &ChainSnapshotRecord) -> Result<(), SnapshotError> {
+        write_bytes(&mut self.writer, &bcs::to_bytes(record)?)?;
+        self.hash = CryptoHash::new(&ChainSnapshotDigest {
+            previous: self.hash,
+            record: CryptoHash::new(record),
+        });
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), SnapshotError> {
+        let end = ChainSnapshotRecord::End(self.hash);
+        write_bytes(&mut self.writer, &bcs::to_bytes(&end)?)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the records of a snapshot, checking their hash.
+struct RecordReader<R> {
+    reader: R,
+    hash: CryptoHash,
+}
+
+impl<R: Read> RecordReader<R> {
+    fn new(mut reader: R) -> Result<(Self, ChainSnapshotHeader), SnapshotError> {
+        let header = read_value::<ChainSnapshotHeader>(&mut reader)?;
+        if header.version != CHAIN_SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(header.version));
+        }
+        let hash = CryptoHash::new(&header);
+        Ok((Self { reader, hash }, header))
+    }
+
+    /// Returns the next record, or `None` once the end of the snapshot is reached.
+    fn read(&mut self) -> Result<Option<ChainSnapshotRecord>, SnapshotError> {
+        let record = read_value::<ChainSnapshotRecord>(&mut self.reader)?;
+        if let ChainSnapshotRecord::End(hash) = record {
+            if hash != self.hash {
+                return Err(SnapshotError::HashMismatch);
+            }
+            return Ok(None);
+        }
+        self.hash = CryptoHash::new(&ChainSnapshotDigest {
+            previous: self.hash,
+            record: CryptoHash::new(&record),
+        });
+        Ok(Some(record))
+    }
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> Result<(), SnapshotError> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_value<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T, SnapshotError> {
+    let mut length = [0; 8];
+    reader.read_exact(&mut length)?;
+    let length = u64::from_le_bytes(length);
+    // The buffer grows with the data actually read, whatever the length claims.
+    let mut bytes = Vec::new();
+    reader.take(length).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    bcs::from_bytes(&bytes).map_err(SnapshotError::from)
+}
+
+fn description_blob_id(chain_id: ChainId) -> BlobId {
+    BlobId::new(chain_id.0, BlobType::ChainDescription)
+}
+
+impl<Database, C> DbStorage<Database, C>
+where
+    Database: KeyValueDatabase + Clone + Send + Sync + 'static,
+    Database::Store: KeyValueStore + Clone + Send + Sync + 'static,
+    C: Clock + Clone + Send + Sync + 'static,
+    Database::Error: Send + Sync,
+{
+    /// Writes a snapshot of the chain `chain_id` to `writer`. The certificates of a
+    /// pruned chain start at its pruned height.
+    pub async fn export_chain(
+        &self,
+        chain_id: ChainId,
+        writer: impl Write + Send,
+    ) -> Result<ChainSnapshotHeader, SnapshotError> {
+        let store = self.chain_state_store(chain_id)?;
+        let mut keys = store
+            .find_keys_by_prefix(&[])
+            .await
+            .map_err(ViewError::from)?;
+        // The history of the chain state is not part of a snapshot.
+        keys.retain(|key| !archive::is_archive_key(key));
+        if keys.is_empty() {
+            return Err(SnapshotError::ChainNotFound(chain_id));
+        }
+
+        let chain = self.load_chain(chain_id).await?;
+        let header = ChainSnapshotHeader {
+            version: CHAIN_SNAPSHOT_VERSION,
+            chain_id,
+            first_height: self.pruned_height(chain_id).await?,
+            next_block_height: chain.tip_state.get().next_block_height,
+        };
+        let mut writer = RecordWriter::new(writer, &header)?;
+        let mut blob_ids = BTreeSet::new();
+        self.export_blobs(&mut writer, &mut blob_ids, [description_blob_id(chain_id)])
+            .await?;
+        let start = usize::try_from(header.first_height).map_err(ViewError::from)?;
+        let end = usize::try_from(header.next_block_height).map_err(ViewError::from)?;
+        for batch_start in (start..end).step_by(SNAPSHOT_BATCH_SIZE) {
+            let batch_end = end.min(batch_start + SNAPSHOT_BATCH_SIZE);
+            let hashes = chain.confirmed_log.read(batch_start..batch_end).await?;
+            let certificates = self.read_certificates(hashes.clone()).await?;
+            for (certificate, hash) in certificates.into_iter().zip(hashes) {
+                let certificate = certificate.ok_or(SnapshotError::MissingCertificate(hash))?;
+                self.export_blobs(
+                    &mut writer,
+                    &mut blob_ids,
+                    certificate.block().required_blob_ids(),
+                )
+                .await?;
+                writer.write(&ChainSnapshotRecord::Certificate(certificate))?;
+            }
+        }
+
+        for keys in keys.chunks(SNAPSHOT_BATCH_SIZE) {
+            let values = store
+                .read_multi_values_bytes(keys.to_vec())
+                .await
+                .map_err(ViewError::from)?;
+            let state = keys
+                .iter()
+                .cloned()
+                .zip(values)
+                .filter_map(|(key, value)| Some((key, value?)))
+                .collect();
+            writer.write(&ChainSnapshotRecord::State(state))?;
+        }
+        writer.finish()?;
+        Ok(header)
+    }
+
+    /// Writes the blobs with the given IDs that are not in `exported_blob_ids` yet.
+    async fn export_blobs<W: Write>(
+        &self,
+        writer: &mut RecordWriter<W>,
+        exported_blob_ids: &mut BTreeSet<BlobId>,
+        blob_ids: impl IntoIterator<Item = BlobId>,
+    ) -> Result<(), SnapshotError> {
+        let blob_ids = blob_ids
+            .into_iter()
+            .filter(|blob_id| exported_blob_ids.insert(*blob_id))
+            .collect::<Vec<_>>();
+        for (blob, blob_id) in self.read_blobs(&blob_ids).await?.into_iter().zip(&blob_ids) {
+            let blob = blob.ok_or(SnapshotError::MissingBlob(*blob_id))?;
+            writer.write(&ChainSnapshotRecord::Blob(blob))?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the snapshot read from `reader` is consistent: its records match
+    /// their hash, the certificates are signed by the committees known to this storage
+    /// and extend each other, and all the required blobs are present.
+    ///
+    /// The first certificate of a pruned chain is not checked against the previous one.
+    pub async fn verify_chain_snapshot(
+        &self,
+        reader: impl Read + Send,
+    ) -> Result<ChainSnapshotHeader, SnapshotError> {
+        self.read_chain_snapshot(reader, false).await
+    }
+
+    /// Verifies the snapshot read from `reader`, like
+    /// [`verify_chain_snapshot`](Self::verify_chain_snapshot), and restores the chain it
+    /// contains. The chain must not exist in this storage yet.
+    ///
+    /// The chain state is written last, so that an interrupted import leaves no usable
+    /// chain behind and can be retried.
+    pub async fn import_chain(
+        &self,
+        reader: impl Read + Send,
+    ) -> Result<ChainSnapshotHeader, SnapshotError> {
+        self.read_chain_snapshot(reader, true).await
+    }
+
+    async fn read_chain_snapshot(
+        &self,
+        reader: impl Read + Send,
+        import: bool,
+    ) -> Result<ChainSnapshotHeader, SnapshotError> {
+        let (mut reader, header) = RecordReader::new(reader)?;
+        let chain_id = header.chain_id;
+        let store = self.chain_state_store(chain_id)?;
+        if import {
+            self.start_import(&store, &header).await?;
+        }
+
+        let mut blob_ids = BTreeSet::new();
+        let mut committees = BTreeMap::new();
+        let mut height = header.first_height;
+        let mut previous_hash = None;
+        let mut has_state = false;
+        while let Some(record) = reader.read()? {
+            match record {
+                ChainSnapshotRecord::Blob(blob) => {
+                    if has_state {
+                        return Err(SnapshotError::UnexpectedRecord);
+                    }
+                    if import {
+                        self.write_blob(&blob).await?;
+                    }
+                    blob_ids.insert(blob.id());
+                }
+                ChainSnapshotRecord::Certificate(certificate) => {
+                    if has_state || height >= header.next_block_height {
+                        return Err(SnapshotError::UnexpectedRecord);
+                    }
+                    let block = certificate.block();
+                    // The block before the first one of a pruned chain is not in the snapshot.
+                    let follows_pruned_block =
+                        previous_hash.is_none() && height > BlockHeight::ZERO;
+                    if block.header.chain_id != chain_id
+                        || block.header.height != height
+                        || (!follows_pruned_block
+                            && block.header.previous_block_hash != previous_hash)
+                    {
+                        return Err(SnapshotError::InvalidCertificate(height));
+                    }
+                    let required_blob_ids = block.required_blob_ids();
+                    if let Some(blob_id) = required_blob_ids.difference(&blob_ids).next() {
+                        return Err(SnapshotError::MissingBlob(*blob_id));
+                    }
+                    let epoch = block.header.epoch;
+                    if !committees.contains_key(&epoch) {
+                        let committee = self
+                            .committees_for(epoch..=epoch)
+                            .await?
+                            .remove(&epoch)
+                            .ok_or(SnapshotError::UnknownEpoch(epoch))?;
+                        committees.insert(epoch, committee);
+                    }
+                    certificate
+                        .check(&committees[&epoch])
+                        .map_err(|_| SnapshotError::InvalidSignatures(height))?;
+                    if import {
+                        self.import_certificate(&certificate, required_blob_ids)
+                            .await?;
+                    }
+                    previous_hash = Some(certificate.hash());
+                    height = height.try_add_one().map_err(ViewError::from)?;
+                }
+                ChainSnapshotRecord::State(state) => {
+                    if height != header.next_block_height {
+                        return Err(SnapshotError::BlockCountMismatch {
+                            expected: header.next_block_height,
+                            found: height,
+                        });
+                    }
+                    has_state = true;
+                    if import {
+                        let mut batch = Batch::new();
+                        for (key, value) in state {
+                            batch.put_key_value_bytes(key, value);
+                        }
+                        store.write_batch(batch).await.map_err(ViewError::from)?;
+                    }
+                }
+                ChainSnapshotRecord::End(_) => unreachable!("the end is not returned"),
+            }
+        }
+        if !blob_ids.contains(&description_blob_id(chain_id)) {
+            return Err(SnapshotError::MissingBlob(description_blob_id(chain_id)));
+        }
+        if !has_state {
+            return Err(SnapshotError::MissingState);
+        }
+
+        if import {
+            let mut batch = Batch::new();
+            batch.delete_key(IMPORT_MARKER.to_vec());
+            store.write_batch(batch).await.map_err(ViewError::from)?;
+        }
+        Ok(header)
+    }
+
+    /// Checks that the chain of `header` can be imported into `store`, and marks it as
+    /// being imported. The remains of an interrupted import are deleted first.
+    async fn start_import(
+        &self,
+        store: &Database::Store,
+        header: &ChainSnapshotHeader,
+    ) -> Result<(), SnapshotError> {
+        let keys = store
+            .find_keys_by_prefix(&[])
+            .await
+            .map_err(ViewError::from)?;
+        let mut batch = Batch::new();
+        if !keys.is_empty() {
+            if !store
+                .contains_key(&IMPORT_MARKER)
+                .await
+                .map_err(ViewError::from)?
+            {
+                return Err(SnapshotError::ChainAlreadyExists(header.chain_id));
+            }
+            batch.delete_key_prefix(Vec::new());
+        }
+        batch.put_key_value_bytes(IMPORT_MARKER.to_vec(), Vec::new());
+        store.write_batch(batch).await.map_err(ViewError::from)?;
+
+        if header.first_height > BlockHeight::ZERO {
+            let mut batch = Batch::new();
+            let key = bcs::to_bytes(&BaseKey::PrunedHeight(header.chain_id))?;
+            batch.put_key_value(key, &header.first_height)?;
+            let root_store = self.database.open_shared(&[])?;
+            root_store
+                .write_batch(batch)
+                .await
+                .map_err(ViewError::from)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a verified certificate, whose blobs are already written, and its events.
+    async fn import_certificate(
+        &self,
+        certificate: &ConfirmedBlockCertificate,
+        required_blob_ids: BTreeSet<BlobId>,
+    ) -> Result<(), SnapshotError> {
+        let block = certificate.block();
+        self.write_blobs_and_certificate(&[], certificate).await?;
+        let events = block
+            .body
+            .events
+            .iter()
+            .flatten()
+            .map(|event| (event.id(block.header.chain_id), event.value.clone()));
+        self.write_events(events).await?;
+        let blob_ids = required_blob_ids.into_iter().collect::<Vec<_>>();
+        self.maybe_write_blob_states(&blob_ids, certificate.value().to_blob_state(true))
+            .await?;
+        Ok(())
+    }
+
+    /// Opens the partition of the storage holding the state of `chain_id`.
+    fn chain_state_store(&self, chain_id: ChainId) -> Result<Database::Store, ViewError> {
+        let root_key = bcs::to_bytes(&BaseKey::ChainState(chain_id))?;
+        Ok(self.database.open_exclusive(&root_key)?)
+    }
+}
@@ -12,7 +12,7 @@ use itertools::Itertools;
 use linera_base::{
     crypto::CryptoHash,
     data_types::{
-        ApplicationDescription, Blob, ChainDescription, CompressedBytecode, Epoch,
+        ApplicationDescription, Blob, BlockHeight, ChainDescription, CompressedBytecode, Epoch,
         NetworkDescription, TimeDelta, Timestamp,
     },
     identifiers::{ApplicationId, BlobId, BlobType, ChainId, EventId, IndexAndEvent, StreamId},
@@ -40,7 +40,10 @@ use linera_views::{context::Context, views::RootView, ViewError};
 pub use crate::db_storage::metrics;
 #[cfg(with_testing)]
 pub use crate::db_storage::TestClock;
-pub use crate::db_storage::{ChainStatesFirstAssignment, DbStorage, WallClock};
+pub use crate::db_storage::{
+    ChainSnapshotHeader, ChainStatesFirstAssignment, DbStorage, PruningPolicy, PruningSummary,
+    SnapshotError, WallClock, CHAIN_SNAPSHOT_VERSION,
+};
 
 /// The default namespace to be used when none is specified
 pub const DEFAULT_NAMESPACE: &str = "table_linera";
@@ -70,6 +73,30 @@ pub trait Storage: Sized {
     /// storage. This can lead to invalid states and data corruption.
     async fn load_chain(&self, id: ChainId) -> Result<ChainStateView<Self::Context>, ViewError>;
 
+    /// Loads the view of a chain state as it was right after executing the block at
+    /// `height`, or returns `None` if that state was not archived.
+    ///
+    /// The returned view must not be saved: changes to it are discarded.
+    async fn load_chain_at_height(
+        &self,
+        id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Option<ChainStateView<Self::Context>>, ViewError>;
+
+    /// Saves the view of a chain state. If `height` is given, the saved state is the one
+    /// right after executing the block at `height`, which
+    /// [`load_chain_at_height`](Self::load_chain_at_height) loads if historical states
+    /// are retained.
+    async fn save_chain(
+        &self,
+        chain: &mut ChainStateView<Self::Context>,
+        height: Option<BlockHeight>,
+    ) -> Result<(), ViewError>;
+
+    /// Returns the height below which the certificates of a chain were pruned. Their
+    /// hashes remain in the chain's `confirmed_log`, but they can no longer be read.
+    async fn pruned_height(&self, id: ChainId) -> Result<BlockHeight, ViewError>;
+
     /// Tests the existence of a blob with the given blob ID.
     async fn contains_blob(&self, blob_id: BlobId) -> Result<bool, ViewError>;
 
@@ -196,7 +223,7 @@ pub trait Storage: Sized {
                 .read_blob(blob_id)
                 .await?
                 .ok_or_else(|| ViewError::NotFound(format!("blob {}", blob_id)))?;
-            Ok(bcs::from_bytes(committee_blob.bytes())?)
+            Ok(Committee::from_blob_bytes(committee_blob.bytes())?)
         };
 
         let network_description = self
@@ -258,7 +285,7 @@ pub trait Storage: Sized {
         assert!(!chain.is_active(), "Attempting to create a chain twice");
         let current_time = self.clock().current_time();
         chain.ensure_is_active(current_time).await?;
-        chain.save().await?;
+        self.save_chain(&mut chain, None).await?;
         Ok(())
     }
 
@@ -12,6 +12,16 @@ use syn::{parse_macro_input, parse_quote, Error, ItemStruct, Type};
 #[deluxe(attributes(view))]
 struct StructAttrs {
     context: Option<syn::Type>,
+    version: Option<u32>,
+}
+
+#[derive(Debug, deluxe::ParseAttributes)]
+#[deluxe(attributes(view))]
+struct FieldAttrs {
+    migrate_from: Option<syn::Type>,
+    migrate_with: Option<syn::Path>,
+    migrate_async: Option<syn::Path>,
+    changed_in: Option<u32>,
 }
 
 struct Constraints<'a> {
@@ -89,6 +99,10 @@ fn generate_view_code(input: ItemStruct, root: bool) -> Result<TokenStream2, Err
     let mut num_init_keys_quotes = Vec::new();
     let mut pre_load_keys_quotes = Vec::new();
     let mut post_load_keys_quotes = Vec::new();
+    let mut load_keys_quotes = Vec::new();
+    let mut has_async_migrations = false;
+    let mut new_quotes = Vec::new();
+    let mut old_types = Vec::new();
     let num_fields = input.fields.len();
     for (idx, e) in input.fields.iter().enumerate() {
         let name = e.ident.clone().unwrap();
@@ -120,17 +134,100 @@ fn generate_view_code(input: ItemStruct, root: bool) -> Result<TokenStream2, Err
                 let __linera_reserved_base_key = context.base_key().derive_tag_key(linera_views::views::MIN_VIEW_TAG, &__linera_reserved_index)?;
             }
         };
-
-        pre_load_keys_quotes.push(quote! {
+        new_quotes.push(quote! {
             #derive_key_logic
-            keys.extend(#g :: pre_load(&context.clone_with_base_key(__linera_reserved_base_key))?);
+            let #name = #g :: new(context.clone_with_base_key(__linera_reserved_base_key))?;
         });
-        post_load_keys_quotes.push(quote! {
+
+        let FieldAttrs {
+            migrate_from,
+            migrate_with,
+            migrate_async,
+            changed_in,
+        } = deluxe::parse_attributes(e)?;
+        let (old_type, migrate_with, is_async) = match (migrate_from, migrate_with, migrate_async) {
+            (Some(old_type), Some(migrate_with), None) => (old_type, migrate_with, false),
+            (Some(old_type), None, Some(migrate_async)) => (old_type, migrate_async, true),
+            (None, None, None) if changed_in.is_none() => {
+                pre_load_keys_quotes.push(quote! {
+                    #derive_key_logic
+                    keys.extend(#g :: pre_load(&context.clone_with_base_key(__linera_reserved_base_key))?);
+                });
+                let post_load_field = quote! {
+                    #derive_key_logic
+                    let __linera_reserved_pos_next = __linera_reserved_pos + #g :: NUM_INIT_KEYS;
+                    let #name = #g :: post_load(context.clone_with_base_key(__linera_reserved_base_key), &values[__linera_reserved_pos..__linera_reserved_pos_next])?;
+                    __linera_reserved_pos = __linera_reserved_pos_next;
+                };
+                load_keys_quotes.push(post_load_field.clone());
+                post_load_keys_quotes.push(post_load_field);
+                continue;
+            }
+            _ => {
+                return Err(Error::new_spanned(
+                    e,
+                    "Migrations need #[view(migrate_from = OldType, migrate_with = function)] \
+                     or #[view(migrate_from = OldType, migrate_async = function)]",
+                ))
+            }
+        };
+        let Some(version) = attrs.version else {
+            return Err(Error::new_spanned(
+                &input,
+                "Migrations require a version: add #[view(version = N)] to the struct",
+            ));
+        };
+        let changed_in = changed_in.unwrap_or(version);
+        if changed_in == 0 || changed_in > version {
+            return Err(Error::new_spanned(
+                e,
+                format!("The layout of a field must change in a version between 1 and {version}"),
+            ));
+        }
+        let old_g = get_extended_entry(old_type.clone())?;
+        // The keys of both layouts are read, since the layout is only known once the
+        // version is. This lets the fields be migrated in `post_load`, so that the views
+        // nested in collections are migrated when they are loaded.
+        pre_load_keys_quotes.push(quote! {
             #derive_key_logic
-            let __linera_reserved_pos_next = __linera_reserved_pos + #g :: NUM_INIT_KEYS;
-            let #name = #g :: post_load(context.clone_with_base_key(__linera_reserved_base_key), &values[__linera_reserved_pos..__linera_reserved_pos_next])?;
-            __linera_reserved_pos = __linera_reserved_pos_next;
+            let __linera_reserved_context = context.clone_with_base_key(__linera_reserved_base_key);
+            keys.extend(#old_g :: pre_load(&__linera_reserved_context)?);
+            keys.extend(#g :: pre_load(&__linera_reserved_context)?);
         });
+        let migrate_field = |migrate: TokenStream2| {
+            quote! {
+                #derive_key_logic
+                let __linera_reserved_context = context.clone_with_base_key(__linera_reserved_base_key);
+                let __linera_reserved_pos_old = __linera_reserved_pos + #old_g :: NUM_INIT_KEYS;
+                let __linera_reserved_pos_next = __linera_reserved_pos_old + #g :: NUM_INIT_KEYS;
+                let #name = if __linera_reserved_version < #changed_in {
+                    #migrate
+                } else {
+                    #g :: post_load(__linera_reserved_context, &values[__linera_reserved_pos_old..__linera_reserved_pos_next])?
+                };
+                __linera_reserved_pos = __linera_reserved_pos_next;
+            }
+        };
+        if is_async {
+            // Asynchronous migrations can iterate over the entries of the old view, so they
+            // only run in `load`.
+            post_load_keys_quotes.push(migrate_field(quote! {
+                return Err(linera_views::ViewError::MigrationRequiresLoad(__linera_reserved_version));
+            }));
+            load_keys_quotes.push(migrate_field(quote! {
+                let __linera_reserved_old = #old_g :: post_load(__linera_reserved_context, &values[__linera_reserved_pos..__linera_reserved_pos_old])?;
+                #migrate_with(__linera_reserved_old, __linera_reserved_version).await?
+            }));
+            has_async_migrations = true;
+        } else {
+            let migrate_field = migrate_field(quote! {
+                let __linera_reserved_old = #old_g :: post_load(__linera_reserved_context, &values[__linera_reserved_pos..__linera_reserved_pos_old])?;
+                #migrate_with(__linera_reserved_old, __linera_reserved_version)?
+            });
+            load_keys_quotes.push(migrate_field.clone());
+            post_load_keys_quotes.push(migrate_field);
+        }
+        old_types.push(old_type);
     }
 
     let first_name_quote = name_quotes.first().ok_or(Error::new_spanned(
@@ -154,14 +251,105 @@ fn generate_view_code(input: ItemStruct, root: bool) -> Result<TokenStream2, Err
         quote! {}
     };
 
+    let flush_fields = quote! {
+        #(#flush_quotes)*
+        Ok( #(#test_flush_quotes)&&* )
+    };
+
+    let (num_version_keys, pre_load_version, post_load_version, new_fn, flush_body, versioned_view) =
+        match attrs.version {
+            None => (
+                quote! {},
+                quote! {},
+                quote! { let mut __linera_reserved_pos = 0; },
+                quote! {},
+                flush_fields,
+                quote! {},
+            ),
+            Some(version) => (
+                quote! { 1 + },
+                quote! {
+                    keys.push(context.base_key().base_tag(linera_views::views::VERSION_TAG));
+                },
+                quote! {
+                    let __linera_reserved_version = linera_views::views::parse_version(
+                        values.first().ok_or(linera_views::ViewError::PostLoadValuesError)?,
+                    )?;
+                    if __linera_reserved_version > #version {
+                        return Err(linera_views::ViewError::IncompatibleVersion {
+                            stored: __linera_reserved_version,
+                            current: #version,
+                        });
+                    }
+                    let mut __linera_reserved_pos = 1;
+                },
+                // Creating the fields directly does not run the migrations on empty values.
+                quote! {
+                    fn new(context: #context) -> Result<Self, linera_views::ViewError> {
+                        use linera_views::context::Context as _;
+                        #(#new_quotes)*
+                        Ok(Self {#(#name_quotes),*})
+                    }
+                },
+                // The version is written even if the fields are unchanged, e.g. when a
+                // migration kept the stored values.
+                quote! {
+                    use linera_views::context::Context as _;
+                    #(#flush_quotes)*
+                    let __linera_reserved_deleted = #(#test_flush_quotes)&&*;
+                    let __linera_reserved_version_key = linera_views::views::View::context(self).base_key().base_tag(linera_views::views::VERSION_TAG);
+                    if __linera_reserved_deleted {
+                        batch.delete_key(__linera_reserved_version_key);
+                    } else {
+                        batch.put_key_value(__linera_reserved_version_key, &#version)?;
+                    }
+                    Ok(__linera_reserved_deleted)
+                },
+                quote! {
+                    impl #impl_generics linera_views::views::VersionedView for #struct_name #type_generics
+                    where
+                        #context: linera_views::context::Context,
+                        #(#input_constraints,)*
+                        #(#field_types: linera_views::views::View<Context = #context>,)*
+                        #(#old_types: linera_views::views::View<Context = #context>,)*
+                    {
+                        const VERSION: u32 = #version;
+                    }
+                },
+            ),
+        };
+
+    // With asynchronous migrations, `load` cannot use `post_load`, so the fields are loaded
+    // again, awaiting the migrations.
+    let load_body = if !has_async_migrations {
+        quote! {
+            if Self::NUM_INIT_KEYS == 0 {
+                Self::post_load(context, &[])
+            } else {
+                let keys = Self::pre_load(&context)?;
+                let values = context.store().read_multi_values_bytes(keys).await?;
+                Self::post_load(context, &values)
+            }
+        }
+    } else {
+        quote! {
+            let keys = Self::pre_load(&context)?;
+            let values = context.store().read_multi_values_bytes(keys).await?;
+            #post_load_version
+            #(#load_keys_quotes)*
+            Ok(Self {#(#name_quotes),*})
+        }
+    };
+
     Ok(quote! {
         impl #impl_generics linera_views::views::View for #struct_name #type_generics
         where
             #context: linera_views::context::Context,
             #(#input_constraints,)*
             #(#field_types: linera_views::views::View<Context = #context>,)*
+            #(#old_types: linera_views::views::View<Context = #context>,)*
         {
-            const NUM_INIT_KEYS: usize = #(<#field_types as linera_views::views::View>::NUM_INIT_KEYS)+*;
+            const NUM_INIT_KEYS: usize = #num_version_keys #(<#field_types as linera_views::views::View>::NUM_INIT_KEYS)+* #(+ <#old_types as linera_views::views::View>::NUM_INIT_KEYS)*;
 
             type Context = #context;
 
@@ -172,13 +360,14 @@ fn generate_view_code(input: ItemStruct, root: bool) -> Result<TokenStream2, Err
             fn pre_load(context: &#context) -> Result<Vec<Vec<u8>>, linera_views::ViewError> {
                 use linera_views::context::Context as _;
                 let mut keys = Vec::new();
+                #pre_load_version
                 #(#pre_load_keys_quotes)*
                 Ok(keys)
             }
 
             fn post_load(context: #context, values: &[Option<Vec<u8>>]) -> Result<Self, linera_views::ViewError> {
                 use linera_views::context::Context as _;
-                let mut __linera_reserved_pos = 0;
+                #post_load_version
                 #(#post_load_keys_quotes)*
                 Ok(Self {#(#name_quotes),*})
             }
@@ -186,15 +375,10 @@ fn generate_view_code(input: ItemStruct, root: bool) -> Result<TokenStream2, Err
             async fn load(context: #context) -> Result<Self, linera_views::ViewError> {
                 use linera_views::{context::Context as _, store::ReadableKeyValueStore as _};
                 #load_metrics
-                if Self::NUM_INIT_KEYS == 0 {
-                    Self::post_load(context, &[])
-                } else {
-                    let keys = Self::pre_load(&context)?;
-                    let values = context.store().read_multi_values_bytes(keys).await?;
-                    Self::post_load(context, &values)
-                }
+                #load_body
             }
 
+            #new_fn
 
             fn rollback(&mut self) {
                 #(#rollback_quotes)*
@@ -206,14 +390,15 @@ fn generate_view_code(input: ItemStruct, root: bool) -> Result<TokenStream2, Err
             }
 
             fn flush(&mut self, batch: &mut linera_views::batch::Batch) -> Result<bool, linera_views::ViewError> {
-                #(#flush_quotes)*
-                Ok( #(#test_flush_quotes)&&* )
+                #flush_body
             }
 
             fn clear(&mut self) {
                 #(#clear_quotes)*
             }
         }
+
+        #versioned_view
     })
 }
 
@@ -783,6 +968,71 @@ pub mod tests {
         assert!(error_msg.contains("Expected a path type"));
     }
 
+    #[test]
+    fn test_migration_without_version_failure() {
+        let input: ItemStruct = parse_quote! {
+            struct TestView<C> {
+                #[view(migrate_from = RegisterView<C, u32>, migrate_with = migrate_register)]
+                register: RegisterView<C, u64>,
+            }
+        };
+        let result = generate_view_code(input, true);
+        assert!(result.is_err());
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("Migrations require a version"));
+    }
+
+    #[test]
+    fn test_migration_without_function_failure() {
+        let input: ItemStruct = parse_quote! {
+            #[view(version = 1)]
+            struct TestView<C> {
+                #[view(migrate_from = RegisterView<C, u32>)]
+                register: RegisterView<C, u64>,
+            }
+        };
+        let result = generate_view_code(input, true);
+        assert!(result.is_err());
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("Migrations need both"));
+    }
+
+    #[test]
+    fn test_migration_from_future_version_failure() {
+        let input: ItemStruct = parse_quote! {
+            #[view(version = 1)]
+            struct TestView<C> {
+                #[view(migrate_from = RegisterView<C, u32>, migrate_with = migrate_register, changed_in = 2)]
+                register: RegisterView<C, u64>,
+            }
+        };
+        let result = generate_view_code(input, true);
+        assert!(result.is_err());
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("between 1 and 1"));
+    }
+
+    #[test]
+    fn test_generate_versioned_view_code() {
+        let input: ItemStruct = parse_quote! {
+            #[view(version = 2)]
+            struct TestView<C> {
+                #[view(migrate_from = RegisterView<C, u32>, migrate_with = migrate_register, changed_in = 1)]
+                register: RegisterView<C, u64>,
+                collection: CollectionView<C, usize, RegisterView<C, usize>>,
+            }
+        };
+        let output = pretty(generate_view_code(input, true).unwrap());
+        assert!(output.contains("const VERSION: u32 = 2u32;"));
+        assert!(output.contains("linera_views::views::VERSION_TAG"));
+        assert!(output.contains("__linera_reserved_version < 1u32"));
+        assert!(
+            output.contains("migrate_register(__linera_reserved_old, __linera_reserved_version)")
+        );
+        assert!(output.contains("RegisterView<C, u32>: linera_views::views::View<Context = C>"));
+        assert!(output.contains("fn new(context: C)"));
+    }
+
     #[test]
     fn test_generate_root_view_code_with_empty_struct() {
         let input: ItemStruct = parse_quote! {
@@ -6,10 +6,13 @@ fn main() {
         web: { all(target_arch = "wasm32", feature = "web") },
         with_testing: { any(test, feature = "test") },
         with_metrics: { all(not(target_arch = "wasm32"), feature = "metrics") },
+        with_encryption: { all(not(target_arch = "wasm32"), feature = "encryption") },
         with_dynamodb: { all(not(target_arch = "wasm32"), feature = "dynamodb") },
         with_indexeddb: { all(web, feature = "indexeddb") },
         with_rocksdb: { all(not(target_arch = "wasm32"), feature = "rocksdb") },
+        with_redb: { all(not(target_arch = "wasm32"), feature = "redb") },
         with_scylladb: { all(not(target_arch = "wasm32"), feature = "scylladb") },
+        with_postgres: { all(not(target_arch = "wasm32"), feature = "postgres") },
         with_graphql: { not(web) },
     };
 }
@@ -0,0 +1,539 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Adds a change feed to a key-value database.
+//!
+//! Every batch successfully written to a store of the database is appended to a feed
+//! with a monotonic sequence number. Subscribers can follow the feed from any cursor
+//! that is still retained, e.g. to maintain a hot-standby replica or to export the
+//! changes to another system.
+//!
+//! The feed is kept in memory and holds the last `capacity` batches. A subscriber that
+//! falls further behind gets a [`ChangeFeedError::Truncated`] error and has to resume
+//! from a full copy of the database instead. Since the sequence numbers start again
+//! from 0 each time the database is connected, cursors carry a random epoch identifying
+//! the feed, and the cursors of other feeds are rejected with
+//! [`ChangeFeedError::StaleCursor`].
+//!
+//! Writes through the database are serialized, so that the batches are recorded in the
+//! order in which they are written.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use async_lock::Mutex as AsyncMutex;
+use futures::Stream;
+use rand::Rng as _;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::watch;
+
+#[cfg(with_testing)]
+use crate::store::TestKeyValueDatabase;
+use crate::{
+    batch::Batch,
+    random::make_nondeterministic_rng,
+    store::{KeyValueDatabase, ReadableKeyValueStore, WithError, WritableKeyValueStore},
+};
+
+/// The default number of batches retained by a change feed.
+pub const DEFAULT_CHANGE_FEED_CAPACITY: usize = 10_000;
+
+/// A position in a [`ChangeFeed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChangeFeedCursor {
+    /// The epoch of the feed.
+    pub epoch: u64,
+    /// The sequence number of a record of the feed.
+    pub sequence: u64,
+}
+
+/// A batch written to a store of a [`ChangeFeedDatabase`].
+#[derive(Clone, Debug)]
+pub struct ChangeRecord {
+    /// The epoch of the feed.
+    pub epoch: u64,
+    /// The position of the batch in the feed.
+    pub sequence: u64,
+    /// The root key of the store that the batch was written to.
+    pub root_key: Vec<u8>,
+    /// The batch that was written.
+    pub batch: Batch,
+}
+
+impl ChangeRecord {
+    /// Returns the cursor resuming the feed after this record.
+    pub fn next_cursor(&self) -> ChangeFeedCursor {
+        ChangeFeedCursor {
+            epoch: self.epoch,
+            sequence: self.sequence + 1,
+        }
+    }
+}
+
+/// An error when reading from a [`ChangeFeed`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeFeedError {
+    /// The requested record is no longer retained by the feed.
+    #[error("record {requested} is no longer in the change feed, the oldest one is {first}")]
+    Truncated {
+        /// The sequence number of the requested record.
+        requested: u64,
+        /// The sequence number of the oldest record still retained.
+        first: u64,
+    },
+    /// The cursor was obtained from another feed, e.g. before the database was connected
+    /// again.
+    #[error("the cursor is from the change feed of epoch {requested}, not {current}")]
+    StaleCursor {
+        /// The epoch of the cursor.
+        requested: u64,
+        /// The epoch of the feed.
+        current: u64,
+    },
+}
+
+/// The records retained by a [`ChangeFeed`].
+struct ChangeLog {
+    records: VecDeque<Arc<ChangeRecord>>,
+    next_sequence: u64,
+    capacity: usize,
+}
+
+impl ChangeLog {
+    fn first_sequence(&self) -> u64 {
+        self.next_sequence - self.records.len() as u64
+    }
+
+    fn get(&self, sequence: u64) -> Result<Option<Arc<ChangeRecord>>, ChangeFeedError> {
+        let first = self.first_sequence();
+        if sequence < first {
+            return Err(ChangeFeedError::Truncated {
+                requested: sequence,
+                first,
+            });
+        }
+        let index = usize::try_from(sequence - first).unwrap_or(usize::MAX);
+        Ok(self.records.get(index).cloned())
+    }
+}
+
+struct ChangeFeedInner {
+    /// A random number identifying this feed.
+    epoch: u64,
+    log: Mutex<ChangeLog>,
+    /// Held while writing a batch and appending it to the log.
+    write_lock: AsyncMutex<()>,
+    /// Holds the sequence number of the next record, updated after each append.
+    sender: watch::Sender<u64>,
+}
+
+/// The feed of the batches written to the stores of a [`ChangeFeedDatabase`].
+#[derive(Clone)]
+pub struct ChangeFeed {
+    inner: Arc<ChangeFeedInner>,
+}
+
+impl ChangeFeed {
+    /// Creates an empty feed retaining the last `capacity` batches.
+    pub fn new(capacity: usize) -> Self {
+        let log = ChangeLog {
+            records: VecDeque::new(),
+            next_sequence: 0,
+            capacity,
+        };
+        let (sender, _) = watch::channel(0);
+        let inner = ChangeFeedInner {
+            epoch: make_nondeterministic_rng().gen(),
+            log: Mutex::new(log),
+            write_lock: AsyncMutex::new(()),
+            sender,
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Returns the random number identifying this feed.
+    pub fn epoch(&self) -> u64 {
+        self.inner.epoch
+    }
+
+    /// Returns the cursor of the next batch written.
+    pub fn next_cursor(&self) -> ChangeFeedCursor {
+        self.cursor(self.inner.log.lock().unwrap().next_sequence)
+    }
+
+    /// Returns the cursor of the oldest batch still retained.
+    pub fn first_cursor(&self) -> ChangeFeedCursor {
+        self.cursor(self.inner.log.lock().unwrap().first_sequence())
+    }
+
+    fn cursor(&self, sequence: u64) -> ChangeFeedCursor {
+        ChangeFeedCursor {
+            epoch: self.inner.epoch,
+            sequence,
+        }
+    }
+
+    /// Subscribes to the feed, starting with the batch at `cursor`.
+    pub fn subscribe_from(
+        &self,
+        cursor: ChangeFeedCursor,
+    ) -> Result<ChangeFeedSubscription, ChangeFeedError> {
+        if cursor.epoch != self.inner.epoch {
+            return Err(ChangeFeedError::StaleCursor {
+                requested: cursor.epoch,
+                current: self.inner.epoch,
+            });
+        }
+        self.inner.log.lock().unwrap().get(cursor.sequence)?;
+        Ok(ChangeFeedSubscription {
+            feed: self.clone(),
+            receiver: self.inner.sender.subscribe(),
+            next_sequence: cursor.sequence,
+        })
+    }
+
+    fn append(&self, root_key: Vec<u8>, batch: Batch) {
+        let next_sequence = {
+            let mut log = self.inner.log.lock().unwrap();
+            let record = ChangeRecord {
+                epoch: self.inner.epoch,
+                sequence: log.next_sequence,
+                root_key,
+                batch,
+            };
+            log.records.push_back(Arc::new(record));
+            if log.records.len() > log.capacity {
+                log.records.pop_front();
+            }
+            log.next_sequence += 1;
+            log.next_sequence
+        };
+        self.inner.sender.send_replace(next_sequence);
+    }
+}
+
+/// A subscription to a [`ChangeFeed`], returning its records in order.
+pub struct ChangeFeedSubscription {
+    feed: ChangeFeed,
+    receiver: watch::Receiver<u64>,
+    next_sequence: u64,
+}
+
+impl ChangeFeedSubscription {
+    /// Returns the cursor of the next record returned by the subscription.
+    pub fn next_cursor(&self) -> ChangeFeedCursor {
+        self.feed.cursor(self.next_sequence)
+    }
+
+    /// Waits for the next record of the feed.
+    pub async fn next(&mut self) -> Result<Arc<ChangeRecord>, ChangeFeedError> {
+        loop {
+            // Marking the current value as seen before looking at the log ensures that
+            // no append is missed.
+            self.receiver.borrow_and_update();
+            let record = self
+                .feed
+                .inner
+                .log
+                .lock()
+                .unwrap()
+                .get(self.next_sequence)?;
+            if let Some(record) = record {
+                self.next_sequence += 1;
+                return Ok(record);
+            }
+            self.receiver
+                .changed()
+                .await
+                .expect("the sender is owned by the feed");
+        }
+    }
+
+    /// Turns the subscription into a stream of records.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Arc<ChangeRecord>, ChangeFeedError>> {
+        futures::stream::unfold(self, |mut subscription| async move {
+            let record = subscription.next().await;
+            Some((record, subscription))
+        })
+    }
+}
+
+/// A key-value database recording the batches written to its stores in a [`ChangeFeed`].
+#[derive(Clone)]
+pub struct ChangeFeedDatabase<D> {
+    /// The underlying database.
+    database: D,
+    /// The feed shared by all the stores of the database.
+    feed: ChangeFeed,
+}
+
+/// A key-value store recording the batches written to it in a [`ChangeFeed`].
+#[derive(Clone)]
+pub struct ChangeFeedStore<S> {
+    /// The underlying store.
+    store: S,
+    /// The root key of the store.
+    root_key: Vec<u8>,
+    /// The feed of the database.
+    feed: ChangeFeed,
+}
+
+/// The configuration type for the `ChangeFeedDatabase`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeFeedConfig<C> {
+    /// The inner configuration of the `ChangeFeedDatabase`.
+    pub inner_config: C,
+    /// The number of batches retained by the feed.
+    pub capacity: usize,
+}
+
+impl<D> ChangeFeedDatabase<D> {
+    /// Returns the feed of the batches written to the stores of this database.
+    pub fn change_feed(&self) -> &ChangeFeed {
+        &self.feed
+    }
+}
+
+impl<D> WithError for ChangeFeedDatabase<D>
+where
+    D: WithError,
+{
+    type Error = D::Error;
+}
+
+impl<S> WithError for ChangeFeedStore<S>
+where
+    S: WithError,
+{
+    type Error = S::Error;
+}
+
+impl<S> ReadableKeyValueStore for ChangeFeedStore<S>
+where
+    S: ReadableKeyValueStore,
+{
+    const MAX_KEY_SIZE: usize = S::MAX_KEY_SIZE;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.store.read_value_bytes(key).await
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        self.store.contains_key(key).await
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        self.store.contains_keys(keys).await
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        self.store.read_multi_values_bytes(keys).await
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.store.find_keys_by_prefix(key_prefix).await
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        self.store.find_key_values_by_prefix(key_prefix).await
+    }
+}
+
+impl<S> WritableKeyValueStore for ChangeFeedStore<S>
+where
+    S: WritableKeyValueStore,
+{
+    const MAX_VALUE_SIZE: usize = S::MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        if batch.is_empty() {
+            return self.store.write_batch(batch).await;
+        }
+        // Otherwise, a batch written after another one could be recorded before it.
+        let _guard = self.feed.inner.write_lock.lock().await;
+        self.store.write_batch(batch.clone()).await?;
+        self.feed.append(self.root_key.clone(), batch);
+        Ok(())
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        self.store.clear_journal().await
+    }
+}
+
+impl<D> KeyValueDatabase for ChangeFeedDatabase<D>
+where
+    D: KeyValueDatabase,
+{
+    type Config = ChangeFeedConfig<D::Config>;
+
+    type Store = ChangeFeedStore<D::Store>;
+
+    fn get_name() -> String {
+        format!("change feed {}", D::get_name())
+    }
+
+    async fn connect(config: &Self::Config, namespace: &str) -> Result<Self, Self::Error> {
+        let database = D::connect(&config.inner_config, namespace).await?;
+        let feed = ChangeFeed::new(config.capacity);
+        Ok(ChangeFeedDatabase { database, feed })
+    }
+
+    fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_shared(root_key)?;
+        Ok(ChangeFeedStore {
+            store,
+            root_key: root_key.to_vec(),
+            feed: self.feed.clone(),
+        })
+    }
+
+    fn open_exclusive(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_exclusive(root_key)?;
+        Ok(ChangeFeedStore {
+            store,
+            root_key: root_key.to_vec(),
+            feed: self.feed.clone(),
+        })
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        D::list_all(&config.inner_config).await
+    }
+
+    async fn list_root_keys(
+        config: &Self::Config,
+        namespace: &str,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        D::list_root_keys(&config.inner_config, namespace).await
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), Self::Error> {
+        D::delete_all(&config.inner_config).await
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        D::exists(&config.inner_config, namespace).await
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        D::create(&config.inner_config, namespace).await
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        D::delete(&config.inner_config, namespace).await
+    }
+}
+
+#[cfg(with_testing)]
+impl<D> TestKeyValueDatabase for ChangeFeedDatabase<D>
+where
+    D: TestKeyValueDatabase,
+{
+    async fn new_test_config() -> Result<ChangeFeedConfig<D::Config>, D::Error> {
+        let inner_config = D::new_test_config().await?;
+        Ok(ChangeFeedConfig {
+            inner_config,
+            capacity: DEFAULT_CHANGE_FEED_CAPACITY,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_views::{
+        batch::Batch,
+        change_feed::{ChangeFeedConfig, ChangeFeedCursor, ChangeFeedDatabase, ChangeFeedError},
+        memory::MemoryDatabase,
+        random::generate_test_namespace,
+        store::{KeyValueDatabase as _, TestKeyValueDatabase as _, WritableKeyValueStore as _},
+    };
+
+    #[tokio::test]
+    async fn test_change_feed_subscription() {
+        let inner_config = MemoryDatabase::new_test_config().await.unwrap();
+        let config = ChangeFeedConfig {
+            inner_config,
+            capacity: 2,
+        };
+        let namespace = generate_test_namespace();
+        let database =
+            ChangeFeedDatabase::<MemoryDatabase>::recreate_and_connect(&config, &namespace)
+                .await
+                .unwrap();
+        let feed = database.change_feed().clone();
+        let mut subscription = feed.subscribe_from(feed.first_cursor()).unwrap();
+
+        let store = database.open_exclusive(&[1]).unwrap();
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![0], vec![42]);
+        store.write_batch(batch.clone()).await.unwrap();
+        store.write_batch(Batch::new()).await.unwrap();
+        let record = subscription.next().await.unwrap();
+        assert_eq!(record.sequence, 0);
+        assert_eq!(record.epoch, feed.epoch());
+        assert_eq!(record.root_key, vec![1]);
+        assert_eq!(record.batch, batch);
+
+        // A subscriber waiting for the next record is woken up by the next write.
+        let handle = tokio::spawn(async move { subscription.next().await.map(|r| r.sequence) });
+        let mut other_batch = Batch::new();
+        other_batch.delete_key(vec![0]);
+        store.write_batch(other_batch.clone()).await.unwrap();
+        assert_eq!(handle.await.unwrap(), Ok(1));
+
+        // Only the last two batches are retained.
+        store.write_batch(batch).await.unwrap();
+        assert_eq!(
+            feed.next_cursor(),
+            ChangeFeedCursor {
+                epoch: feed.epoch(),
+                sequence: 3,
+            }
+        );
+        assert_eq!(
+            feed.subscribe_from(ChangeFeedCursor {
+                epoch: feed.epoch(),
+                sequence: 0,
+            })
+            .err(),
+            Some(ChangeFeedError::Truncated {
+                requested: 0,
+                first: 1
+            })
+        );
+        let mut subscription = feed.subscribe_from(record.next_cursor()).unwrap();
+        let record = subscription.next().await.unwrap();
+        assert_eq!(record.batch, other_batch);
+        assert_eq!(subscription.next_cursor(), record.next_cursor());
+
+        // The cursors of a feed are rejected after connecting to the database again.
+        let database = ChangeFeedDatabase::<MemoryDatabase>::connect(&config, &namespace)
+            .await
+            .unwrap();
+        let new_feed = database.change_feed();
+        assert_eq!(new_feed.next_cursor().sequence, 0);
+        assert_eq!(
+            new_feed.subscribe_from(record.next_cursor()).err(),
+            Some(ChangeFeedError::StaleCursor {
+                requested: feed.epoch(),
+                current: new_feed.epoch(),
+            })
+        );
+    }
+}
@@ -0,0 +1,597 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Encrypts the data of a key-value database at rest.
+//!
+//! Values are sealed with XChaCha20-Poly1305 under a random nonce. The root key and the
+//! key of a value are used as associated data, so that a value moved to another key is
+//! detected when it is read.
+//!
+//! Keys can optionally be encrypted as well. Every byte of a key is then masked with a
+//! byte derived from the encryption key and the bytes preceding it, so that the encryption
+//! of a prefix is a prefix of the encryption of the key and prefix scans keep working.
+//! This encryption is deterministic: it reveals the length of the keys and which keys
+//! share a prefix. Root keys are never encrypted.
+//!
+//! The encryption key consists of 32 hex-encoded bytes, read from a file or from an
+//! environment variable when connecting to the database, so that it is never part of the
+//! configuration.
+
+use std::{fmt, sync::Arc};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::RngCore as _;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest as _, Sha3_256};
+use thiserror::Error;
+
+#[cfg(with_testing)]
+use crate::store::TestKeyValueDatabase;
+use crate::{
+    batch::{Batch, WriteOperation},
+    common::PathWithGuard,
+    store::{
+        KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore, WithError,
+        WritableKeyValueStore,
+    },
+    views::MIN_VIEW_TAG,
+};
+
+/// The length of an encryption key, in bytes.
+pub const ENCRYPTION_KEY_LENGTH: usize = 32;
+/// The length of the XChaCha20 nonces, in bytes.
+const NONCE_LENGTH: usize = 24;
+/// The length of the Poly1305 authentication tags, in bytes.
+const TAG_LENGTH: usize = 16;
+/// The first byte of the encrypted keys. The keys starting with 0 are used by journals.
+const ENCRYPTED_KEY_TAG: u8 = MIN_VIEW_TAG;
+
+/// Where the encryption key of an [`EncryptionDatabase`] is read from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EncryptionKeySource {
+    /// A file containing the hex-encoded key.
+    File(PathWithGuard),
+    /// An environment variable containing the hex-encoded key.
+    Env(String),
+}
+
+impl fmt::Display for EncryptionKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionKeySource::File(path) => write!(f, "file {}", path.path_buf.display()),
+            EncryptionKeySource::Env(name) => write!(f, "environment variable {name}"),
+        }
+    }
+}
+
+/// The configuration type for the `EncryptionDatabase`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig<C> {
+    /// The inner configuration of the `EncryptionDatabase`.
+    pub inner_config: C,
+    /// Where the encryption key is read from.
+    pub key_source: EncryptionKeySource,
+    /// Whether keys are encrypted as well as values.
+    pub encrypt_keys: bool,
+}
+
+/// The composed error type built from the inner error type.
+#[derive(Error, Debug)]
+pub enum EncryptionError<E> {
+    /// inner store error
+    #[error(transparent)]
+    InnerStoreError(#[from] E),
+
+    /// The encryption key could not be read
+    #[error("the encryption key could not be read from the {location}: {error}")]
+    KeyUnavailable {
+        /// Where the key was read from.
+        location: String,
+        /// The reason why it could not be read.
+        error: String,
+    },
+
+    /// The encryption key is malformed
+    #[error("the encryption key must consist of {ENCRYPTION_KEY_LENGTH} hex-encoded bytes")]
+    InvalidKey,
+
+    /// A value could not be decrypted
+    #[error("a value could not be decrypted: wrong encryption key or corrupted data")]
+    DecryptionFailed,
+}
+
+impl<E: KeyValueStoreError> From<bcs::Error> for EncryptionError<E> {
+    fn from(error: bcs::Error) -> Self {
+        let error = E::from(error);
+        EncryptionError::InnerStoreError(error)
+    }
+}
+
+impl<E: KeyValueStoreError + 'static> KeyValueStoreError for EncryptionError<E> {
+    const BACKEND: &'static str = "encryption";
+}
+
+impl EncryptionKeySource {
+    /// Reads the encryption key.
+    fn read<E>(&self) -> Result<[u8; ENCRYPTION_KEY_LENGTH], EncryptionError<E>> {
+        let unavailable = |error: String| EncryptionError::KeyUnavailable {
+            location: self.to_string(),
+            error,
+        };
+        let encoded = match self {
+            EncryptionKeySource::File(path) => std::fs::read_to_string(&path.path_buf)
+                .map_err(|error| unavailable(error.to_string()))?,
+            EncryptionKeySource::Env(name) => {
+                std::env::var(name).map_err(|error| unavailable(error.to_string()))?
+            }
+        };
+        let bytes = hex::decode(encoded.trim()).map_err(|_| EncryptionError::InvalidKey)?;
+        bytes.try_into().map_err(|_| EncryptionError::InvalidKey)
+    }
+}
+
+/// The ciphers derived from an encryption key.
+struct Ciphers {
+    /// The cipher of the values.
+    values: XChaCha20Poly1305,
+    /// The hasher of the masks of the keys, or `None` if keys are not encrypted.
+    keys: Option<Sha3_256>,
+}
+
+impl Ciphers {
+    fn new(key: &[u8; ENCRYPTION_KEY_LENGTH], encrypt_keys: bool) -> Self {
+        let derive = |label: &[u8]| Sha3_256::new().chain_update(label).chain_update(key);
+        let value_key = derive(b"linera-views value encryption").finalize();
+        let keys = encrypt_keys.then(|| {
+            let mask_key = derive(b"linera-views key encryption").finalize();
+            Sha3_256::new().chain_update(mask_key)
+        });
+        Ciphers {
+            values: XChaCha20Poly1305::new(Key::from_slice(&value_key)),
+            keys,
+        }
+    }
+
+    /// Masks the bytes of `input` following `prefix`, where `decrypt` tells whether
+    /// `input` is the plaintext or the encrypted bytes.
+    fn mask(hasher: &Sha3_256, prefix: &[u8], input: &[u8], decrypt: bool) -> Vec<u8> {
+        let mut hasher = hasher.clone();
+        hasher.update(prefix);
+        let mut output = Vec::with_capacity(input.len());
+        for byte in input {
+            let masked = byte ^ hasher.clone().finalize()[0];
+            let plain = if decrypt { masked } else { *byte };
+            hasher.update([plain]);
+            output.push(masked);
+        }
+        output
+    }
+
+    /// Encrypts a key, or a prefix of keys.
+    fn encrypt_key(&self, key: &[u8]) -> Vec<u8> {
+        let Some(hasher) = &self.keys else {
+            return key.to_vec();
+        };
+        let mut encrypted = vec![ENCRYPTED_KEY_TAG];
+        encrypted.extend(Self::mask(hasher, &[], key, false));
+        encrypted
+    }
+
+    /// Decrypts the suffix of a key returned by a scan of the given plaintext prefix.
+    fn decrypt_suffix(&self, prefix: &[u8], suffix: Vec<u8>) -> Vec<u8> {
+        match &self.keys {
+            None => suffix,
+            Some(hasher) => Self::mask(hasher, prefix, &suffix, true),
+        }
+    }
+
+    /// Returns whether the results of prefix scans must be sorted again after decryption.
+    fn reorders_keys(&self) -> bool {
+        self.keys.is_some()
+    }
+
+    fn associated_data(root_key: &[u8], key: &[u8]) -> Vec<u8> {
+        let length = u32::try_from(root_key.len()).expect("root keys are small");
+        [length.to_le_bytes().as_slice(), root_key, key].concat()
+    }
+
+    fn seal(&self, root_key: &[u8], key: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let aad = Self::associated_data(root_key, key);
+        let payload = Payload {
+            msg: value,
+            aad: &aad,
+        };
+        let ciphertext = self
+            .values
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .expect("encryption into a vector should not fail");
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+
+    fn open<E>(
+        &self,
+        root_key: &[u8],
+        key: &[u8],
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError<E>> {
+        if sealed.len() < NONCE_LENGTH + TAG_LENGTH {
+            return Err(EncryptionError::DecryptionFailed);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        let aad = Self::associated_data(root_key, key);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &aad,
+        };
+        self.values
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| EncryptionError::DecryptionFailed)
+    }
+}
+
+/// A key-value database encrypting the data of its stores.
+#[derive(Clone)]
+pub struct EncryptionDatabase<D> {
+    /// The underlying database.
+    database: D,
+    /// The ciphers shared by all the stores of the database.
+    ciphers: Arc<Ciphers>,
+}
+
+/// A key-value store encrypting its data.
+#[derive(Clone)]
+pub struct EncryptionStore<S> {
+    /// The underlying store.
+    store: S,
+    /// The root key of the store, authenticated with every value.
+    root_key: Vec<u8>,
+    /// The ciphers of the database.
+    ciphers: Arc<Ciphers>,
+}
+
+impl<D> WithError for EncryptionDatabase<D>
+where
+    D: WithError,
+    D::Error: 'static,
+{
+    type Error = EncryptionError<D::Error>;
+}
+
+impl<S> WithError for EncryptionStore<S>
+where
+    S: WithError,
+    S::Error: 'static,
+{
+    type Error = EncryptionError<S::Error>;
+}
+
+impl<S> EncryptionStore<S> {
+    fn open_value(
+        &self,
+        key: &[u8],
+        sealed: Option<Vec<u8>>,
+    ) -> Result<Option<Vec<u8>>, EncryptionError<S::Error>>
+    where
+        S: WithError,
+    {
+        sealed
+            .map(|sealed| self.ciphers.open(&self.root_key, key, &sealed))
+            .transpose()
+    }
+}
+
+impl<S> ReadableKeyValueStore for EncryptionStore<S>
+where
+    S: ReadableKeyValueStore,
+    S::Error: 'static,
+{
+    // Encrypted keys start with a tag.
+    const MAX_KEY_SIZE: usize = S::MAX_KEY_SIZE - 1;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        let encrypted_key = self.ciphers.encrypt_key(key);
+        let sealed = self.store.read_value_bytes(&encrypted_key).await?;
+        self.open_value(key, sealed)
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        let encrypted_key = self.ciphers.encrypt_key(key);
+        Ok(self.store.contains_key(&encrypted_key).await?)
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        let encrypted_keys = keys
+            .iter()
+            .map(|key| self.ciphers.encrypt_key(key))
+            .collect();
+        Ok(self.store.contains_keys(encrypted_keys).await?)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let encrypted_keys = keys
+            .iter()
+            .map(|key| self.ciphers.encrypt_key(key))
+            .collect();
+        let values = self.store.read_multi_values_bytes(encrypted_keys).await?;
+        keys.iter()
+            .zip(values)
+            .map(|(key, sealed)| self.open_value(key, sealed))
+            .collect()
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let encrypted_prefix = self.ciphers.encrypt_key(key_prefix);
+        let mut keys = self
+            .store
+            .find_keys_by_prefix(&encrypted_prefix)
+            .await?
+            .into_iter()
+            .map(|suffix| self.ciphers.decrypt_suffix(key_prefix, suffix))
+            .collect::<Vec<_>>();
+        if self.ciphers.reorders_keys() {
+            keys.sort_unstable();
+        }
+        Ok(keys)
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        let encrypted_prefix = self.ciphers.encrypt_key(key_prefix);
+        let mut key_values = Vec::new();
+        for (suffix, sealed) in self
+            .store
+            .find_key_values_by_prefix(&encrypted_prefix)
+            .await?
+        {
+            let suffix = self.ciphers.decrypt_suffix(key_prefix, suffix);
+            let key = [key_prefix, &suffix].concat();
+            let value = self.ciphers.open(&self.root_key, &key, &sealed)?;
+            key_values.push((suffix, value));
+        }
+        if self.ciphers.reorders_keys() {
+            key_values.sort_unstable_by(|(key1, _), (key2, _)| key1.cmp(key2));
+        }
+        Ok(key_values)
+    }
+}
+
+impl<S> WritableKeyValueStore for EncryptionStore<S>
+where
+    S: WritableKeyValueStore,
+    S::Error: 'static,
+{
+    // Encrypted values start with a nonce and end with an authentication tag.
+    const MAX_VALUE_SIZE: usize = S::MAX_VALUE_SIZE - NONCE_LENGTH - TAG_LENGTH;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        let operations = batch
+            .operations
+            .into_iter()
+            .map(|operation| match operation {
+                WriteOperation::Delete { key } => WriteOperation::Delete {
+                    key: self.ciphers.encrypt_key(&key),
+                },
+                WriteOperation::DeletePrefix { key_prefix } => WriteOperation::DeletePrefix {
+                    key_prefix: self.ciphers.encrypt_key(&key_prefix),
+                },
+                WriteOperation::Put { key, value } => WriteOperation::Put {
+                    value: self.ciphers.seal(&self.root_key, &key, &value),
+                    key: self.ciphers.encrypt_key(&key),
+                },
+            })
+            .collect();
+        Ok(self.store.write_batch(Batch { operations }).await?)
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        Ok(self.store.clear_journal().await?)
+    }
+}
+
+impl<D> KeyValueDatabase for EncryptionDatabase<D>
+where
+    D: KeyValueDatabase,
+    D::Error: 'static,
+{
+    type Config = EncryptionConfig<D::Config>;
+
+    type Store = EncryptionStore<D::Store>;
+
+    fn get_name() -> String {
+        format!("encryption {}", D::get_name())
+    }
+
+    async fn connect(config: &Self::Config, namespace: &str) -> Result<Self, Self::Error> {
+        let key = config.key_source.read()?;
+        let ciphers = Arc::new(Ciphers::new(&key, config.encrypt_keys));
+        let database = D::connect(&config.inner_config, namespace).await?;
+        Ok(EncryptionDatabase { database, ciphers })
+    }
+
+    fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_shared(root_key)?;
+        Ok(EncryptionStore {
+            store,
+            root_key: root_key.to_vec(),
+            ciphers: self.ciphers.clone(),
+        })
+    }
+
+    fn open_exclusive(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_exclusive(root_key)?;
+        Ok(EncryptionStore {
+            store,
+            root_key: root_key.to_vec(),
+            ciphers: self.ciphers.clone(),
+        })
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        Ok(D::list_all(&config.inner_config).await?)
+    }
+
+    async fn list_root_keys(
+        config: &Self::Config,
+        namespace: &str,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(D::list_root_keys(&config.inner_config, namespace).await?)
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), Self::Error> {
+        Ok(D::delete_all(&config.inner_config).await?)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        Ok(D::exists(&config.inner_config, namespace).await?)
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(D::create(&config.inner_config, namespace).await?)
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(D::delete(&config.inner_config, namespace).await?)
+    }
+}
+
+/// Writes a new random encryption key to a file.
+pub fn generate_key_file(path: PathWithGuard) -> std::io::Result<EncryptionKeySource> {
+    let mut key = [0u8; ENCRYPTION_KEY_LENGTH];
+    rand::rngs::OsRng.fill_bytes(&mut key);
+    std::fs::write(&path.path_buf, hex::encode(key))?;
+    Ok(EncryptionKeySource::File(path))
+}
+
+#[cfg(with_testing)]
+impl<D> TestKeyValueDatabase for EncryptionDatabase<D>
+where
+    D: TestKeyValueDatabase,
+    D::Error: 'static,
+{
+    async fn new_test_config() -> Result<EncryptionConfig<D::Config>, Self::Error> {
+        let inner_config = D::new_test_config().await?;
+        // The key file is deleted with its directory, when the last clone of the
+        // configuration is dropped.
+        let path = PathWithGuard::new_testing().join("encryption_key");
+        let location = format!("file {}", path.path_buf.display());
+        let key_source =
+            generate_key_file(path).map_err(|error| EncryptionError::KeyUnavailable {
+                location,
+                error: error.to_string(),
+            })?;
+        Ok(EncryptionConfig {
+            inner_config,
+            key_source,
+            encrypt_keys: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_views::{
+        batch::Batch,
+        encryption::{EncryptionConfig, EncryptionDatabase, EncryptionError, EncryptionKeySource},
+        memory::MemoryDatabase,
+        random::generate_test_namespace,
+        store::{
+            KeyValueDatabase as _, ReadableKeyValueStore as _, TestKeyValueDatabase as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_encryption_at_rest() {
+        let config = EncryptionDatabase::<MemoryDatabase>::new_test_config()
+            .await
+            .unwrap();
+        let namespace = generate_test_namespace();
+        let database =
+            EncryptionDatabase::<MemoryDatabase>::recreate_and_connect(&config, &namespace)
+                .await
+                .unwrap();
+        let store = database.open_exclusive(&[1]).unwrap();
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 2, 3], b"secret".to_vec());
+        batch.put_key_value_bytes(vec![1, 2, 4], b"other secret".to_vec());
+        batch.put_key_value_bytes(vec![2], b"unrelated".to_vec());
+        store.write_batch(batch).await.unwrap();
+
+        assert_eq!(
+            store.read_value_bytes(&[1, 2, 3]).await.unwrap(),
+            Some(b"secret".to_vec())
+        );
+        assert_eq!(
+            store.find_keys_by_prefix(&[1, 2]).await.unwrap(),
+            vec![vec![3], vec![4]]
+        );
+        assert_eq!(
+            store.find_key_values_by_prefix(&[1]).await.unwrap(),
+            vec![
+                (vec![2, 3], b"secret".to_vec()),
+                (vec![2, 4], b"other secret".to_vec())
+            ]
+        );
+
+        // Neither the keys nor the values are stored in the clear.
+        let inner_database = MemoryDatabase::connect(&config.inner_config, &namespace)
+            .await
+            .unwrap();
+        let inner_store = inner_database.open_exclusive(&[1]).unwrap();
+        let key_values = inner_store.find_key_values_by_prefix(&[]).await.unwrap();
+        assert_eq!(key_values.len(), 3);
+        for (_, value) in &key_values {
+            assert!(!value.windows(6).any(|window| window == b"secret"));
+        }
+
+        // A value moved to another key is rejected.
+        let mut encrypted_keys = key_values.iter().map(|(key, _)| key.clone());
+        let first_key = encrypted_keys.next().unwrap();
+        let second_key = encrypted_keys.next().unwrap();
+        let first_value = inner_store.read_value_bytes(&first_key).await.unwrap();
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(second_key, first_value.unwrap());
+        inner_store.write_batch(batch).await.unwrap();
+        assert!(matches!(
+            store.find_key_values_by_prefix(&[]).await,
+            Err(EncryptionError::DecryptionFailed)
+        ));
+
+        // The data cannot be read with another key.
+        let other_config = EncryptionDatabase::<MemoryDatabase>::new_test_config()
+            .await
+            .unwrap();
+        let config = EncryptionConfig {
+            inner_config: config.inner_config,
+            ..other_config
+        };
+        let database = EncryptionDatabase::<MemoryDatabase>::connect(&config, &namespace)
+            .await
+            .unwrap();
+        let store = database.open_exclusive(&[1]).unwrap();
+        assert_eq!(store.read_value_bytes(&[1, 2, 3]).await.unwrap(), None);
+
+        // The key files of the test configurations are deleted with them.
+        let EncryptionKeySource::File(path) = &config.key_source else {
+            panic!("test configurations read their key from a file");
+        };
+        let path = path.path_buf.clone();
+        assert!(path.exists());
+        drop(config);
+        assert!(!path.exists());
+    }
+}
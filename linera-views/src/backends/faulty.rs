@@ -0,0 +1,570 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Injects faults into a key-value database, to test how its users recover from storage
+//! failures.
+//!
+//! A [`FaultyDatabase`] turns a database into a direct store with small batches, meant to
+//! be wrapped by the journaling database: large batches are then written in several
+//! steps, and a fault injected between two of them leaves a journal to be replayed when
+//! the store is opened again. [`FaultyMemoryDatabase`] is such a stack over the memory
+//! database.
+//!
+//! The journaling database relies on the batches of the underlying store being written
+//! atomically. A torn write of one of the batches writing the journal leaves it
+//! incomplete, so that the journaled batch has no effect, but a torn write while the
+//! journal is replayed cannot be recovered from.
+//!
+//! Faults are described by [`FaultRule`]s, selecting the operations and the keys they
+//! apply to. They are drawn from a seeded RNG, so that failing runs can be reproduced.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use linera_base::time::timer::sleep;
+use rand::{rngs::SmallRng, Rng as _, SeedableRng as _};
+use thiserror::Error;
+
+use crate::{
+    batch::{Batch, SimpleUnorderedBatch},
+    journaling::{JournalConsistencyError, JournalingKeyValueDatabase},
+    memory::MemoryDatabase,
+    store::{
+        DirectWritableKeyValueStore, KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore,
+        TestKeyValueDatabase, WithError, WritableKeyValueStore,
+    },
+};
+
+/// The maximal number of operations in a batch of a [`FaultyStore`]. It is small so that
+/// most of the batches written to the journaling database are journaled.
+pub const FAULTY_MAX_BATCH_SIZE: usize = 8;
+
+/// The maximal number of bytes of a batch of a [`FaultyStore`]. Only the number of
+/// operations is limited.
+const FAULTY_MAX_BATCH_TOTAL_SIZE: usize = usize::MAX / 4;
+
+/// The kinds of operations that faults can be injected into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultyOperation {
+    /// Reading values, or checking that keys exist.
+    Read,
+    /// Finding the keys with a given prefix.
+    Find,
+    /// Writing a batch.
+    Write,
+}
+
+/// A fault injected into an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The operation fails without any effect.
+    Error,
+    /// The operation succeeds after the given delay.
+    Latency(Duration),
+    /// Only the first operations of the batch are written, and the write fails as if
+    /// the process had stopped in the middle of it. The number of operations written is
+    /// drawn at random, and smaller than the size of the batch.
+    TornWrite,
+}
+
+/// A rule injecting a fault into the matching operations.
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    fault: Fault,
+    operations: Vec<FaultyOperation>,
+    key_prefix: Vec<u8>,
+    probability: f64,
+    remaining: Option<usize>,
+}
+
+impl FaultRule {
+    /// Creates a rule injecting the given fault into every operation.
+    pub fn new(fault: Fault) -> Self {
+        FaultRule {
+            fault,
+            operations: Vec::new(),
+            key_prefix: Vec::new(),
+            probability: 1.0,
+            remaining: None,
+        }
+    }
+
+    /// Only injects the fault into the given kinds of operations.
+    pub fn with_operations(
+        mut self,
+        operations: impl IntoIterator<Item = FaultyOperation>,
+    ) -> Self {
+        self.operations = operations.into_iter().collect();
+        self
+    }
+
+    /// Only injects the fault into the operations involving keys with the given prefix.
+    pub fn with_key_prefix(mut self, key_prefix: Vec<u8>) -> Self {
+        self.key_prefix = key_prefix;
+        self
+    }
+
+    /// Injects the fault into the matching operations with the given probability.
+    pub fn with_probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    /// Injects the fault at most `count` times.
+    pub fn with_max_count(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    fn matches(&self, operation: FaultyOperation, keys: &[&[u8]]) -> bool {
+        if !self.operations.is_empty() && !self.operations.contains(&operation) {
+            return false;
+        }
+        if self.remaining == Some(0) {
+            return false;
+        }
+        keys.iter().any(|key| match operation {
+            FaultyOperation::Find => {
+                key.starts_with(&self.key_prefix) || self.key_prefix.starts_with(key)
+            }
+            FaultyOperation::Read | FaultyOperation::Write => key.starts_with(&self.key_prefix),
+        })
+    }
+}
+
+struct FaultInjectorState {
+    rng: SmallRng,
+    rules: Vec<FaultRule>,
+    injected_count: usize,
+}
+
+/// Decides which operations of the stores of a [`FaultyDatabase`] fail. It is shared by
+/// all the stores, and by the clones of the configuration it is part of.
+#[derive(Clone)]
+pub struct FaultInjector {
+    state: Arc<Mutex<FaultInjectorState>>,
+}
+
+impl std::fmt::Debug for FaultInjector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("FaultInjector")
+            .field("rules", &state.rules)
+            .field("injected_count", &state.injected_count)
+            .finish()
+    }
+}
+
+impl FaultInjector {
+    /// Creates an injector without rules, drawing faults with an RNG seeded with `seed`.
+    pub fn new(seed: u64) -> Self {
+        let state = FaultInjectorState {
+            rng: SmallRng::seed_from_u64(seed),
+            rules: Vec::new(),
+            injected_count: 0,
+        };
+        FaultInjector {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Adds a rule. The first matching rule that draws a fault decides it.
+    pub fn add_rule(&self, rule: FaultRule) {
+        self.state.lock().unwrap().rules.push(rule);
+    }
+
+    /// Removes all the rules.
+    pub fn clear_rules(&self) {
+        self.state.lock().unwrap().rules.clear();
+    }
+
+    /// Returns the number of faults injected so far.
+    pub fn injected_count(&self) -> usize {
+        self.state.lock().unwrap().injected_count
+    }
+
+    /// Draws the number of operations written by a torn write of a batch of `len`
+    /// operations.
+    fn draw_torn_len(&self, len: usize) -> usize {
+        if len == 0 {
+            return 0;
+        }
+        self.state.lock().unwrap().rng.gen_range(0..len)
+    }
+
+    fn draw(&self, operation: FaultyOperation, keys: &[&[u8]]) -> Option<Fault> {
+        let mut state = self.state.lock().unwrap();
+        let FaultInjectorState {
+            rng,
+            rules,
+            injected_count,
+        } = &mut *state;
+        for rule in rules {
+            if rule.matches(operation, keys) && rng.gen_bool(rule.probability) {
+                if let Some(remaining) = &mut rule.remaining {
+                    *remaining -= 1;
+                }
+                *injected_count += 1;
+                return Some(rule.fault);
+            }
+        }
+        None
+    }
+}
+
+/// The configuration type for the `FaultyDatabase`.
+#[derive(Debug, Clone)]
+pub struct FaultyConfig<C> {
+    /// The inner configuration of the `FaultyDatabase`.
+    pub inner_config: C,
+    /// The injector of the faults.
+    pub injector: FaultInjector,
+}
+
+/// The composed error type built from the inner error type.
+#[derive(Error, Debug)]
+pub enum FaultyStoreError<E> {
+    /// inner store error
+    #[error(transparent)]
+    InnerStoreError(#[from] E),
+
+    /// A fault was injected into the operation
+    #[error("fault injected into a {0:?} operation")]
+    InjectedFault(FaultyOperation),
+
+    /// The journal is not coherent
+    #[error(transparent)]
+    JournalConsistencyError(JournalConsistencyError),
+}
+
+impl<E: KeyValueStoreError> From<bcs::Error> for FaultyStoreError<E> {
+    fn from(error: bcs::Error) -> Self {
+        let error = E::from(error);
+        FaultyStoreError::InnerStoreError(error)
+    }
+}
+
+impl<E: KeyValueStoreError> From<JournalConsistencyError> for FaultyStoreError<E> {
+    fn from(error: JournalConsistencyError) -> Self {
+        FaultyStoreError::JournalConsistencyError(error)
+    }
+}
+
+impl<E: KeyValueStoreError + 'static> KeyValueStoreError for FaultyStoreError<E> {
+    const BACKEND: &'static str = "faulty";
+}
+
+/// A key-value database injecting faults into the operations of its stores.
+#[derive(Clone)]
+pub struct FaultyDatabase<D> {
+    /// The underlying database.
+    database: D,
+    /// The injector of the faults.
+    injector: FaultInjector,
+}
+
+/// A key-value store injecting faults into its operations.
+#[derive(Clone)]
+pub struct FaultyStore<S> {
+    /// The underlying store.
+    store: S,
+    /// The injector of the faults.
+    injector: FaultInjector,
+}
+
+/// The memory database with injected faults, writing large batches through a journal.
+pub type FaultyMemoryDatabase = JournalingKeyValueDatabase<FaultyDatabase<MemoryDatabase>>;
+
+impl<D> WithError for FaultyDatabase<D>
+where
+    D: WithError,
+{
+    type Error = FaultyStoreError<D::Error>;
+}
+
+impl<S> WithError for FaultyStore<S>
+where
+    S: WithError,
+{
+    type Error = FaultyStoreError<S::Error>;
+}
+
+impl<S> FaultyStore<S>
+where
+    S: WithError,
+{
+    /// Draws a fault for an operation. Returns whether the operation must be torn.
+    async fn inject(
+        &self,
+        operation: FaultyOperation,
+        keys: &[&[u8]],
+    ) -> Result<bool, FaultyStoreError<S::Error>> {
+        match self.injector.draw(operation, keys) {
+            None => Ok(false),
+            Some(Fault::Error) => Err(FaultyStoreError::InjectedFault(operation)),
+            Some(Fault::Latency(delay)) => {
+                sleep(delay).await;
+                Ok(false)
+            }
+            Some(Fault::TornWrite) => Ok(operation == FaultyOperation::Write),
+        }
+    }
+}
+
+impl<S> ReadableKeyValueStore for FaultyStore<S>
+where
+    S: ReadableKeyValueStore,
+{
+    const MAX_KEY_SIZE: usize = S::MAX_KEY_SIZE;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.inject(FaultyOperation::Read, &[key]).await?;
+        Ok(self.store.read_value_bytes(key).await?)
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        self.inject(FaultyOperation::Read, &[key]).await?;
+        Ok(self.store.contains_key(key).await?)
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        let key_slices = keys.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        self.inject(FaultyOperation::Read, &key_slices).await?;
+        Ok(self.store.contains_keys(keys).await?)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let key_slices = keys.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        self.inject(FaultyOperation::Read, &key_slices).await?;
+        Ok(self.store.read_multi_values_bytes(keys).await?)
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.inject(FaultyOperation::Find, &[key_prefix]).await?;
+        Ok(self.store.find_keys_by_prefix(key_prefix).await?)
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        self.inject(FaultyOperation::Find, &[key_prefix]).await?;
+        Ok(self.store.find_key_values_by_prefix(key_prefix).await?)
+    }
+}
+
+impl<S> DirectWritableKeyValueStore for FaultyStore<S>
+where
+    S: WritableKeyValueStore,
+{
+    const MAX_BATCH_SIZE: usize = FAULTY_MAX_BATCH_SIZE;
+    const MAX_BATCH_TOTAL_SIZE: usize = FAULTY_MAX_BATCH_TOTAL_SIZE;
+    const MAX_VALUE_SIZE: usize = S::MAX_VALUE_SIZE;
+
+    type Batch = SimpleUnorderedBatch;
+
+    async fn write_batch(&self, batch: SimpleUnorderedBatch) -> Result<(), Self::Error> {
+        let torn = {
+            let keys = batch
+                .deletions
+                .iter()
+                .chain(batch.insertions.iter().map(|(key, _)| key))
+                .map(Vec::as_slice)
+                .collect::<Vec<_>>();
+            self.inject(FaultyOperation::Write, &keys).await?
+        };
+        let mut inner_batch = Batch::new();
+        for key in batch.deletions {
+            inner_batch.delete_key(key);
+        }
+        for (key, value) in batch.insertions {
+            inner_batch.put_key_value_bytes(key, value);
+        }
+        if torn {
+            let len = self.injector.draw_torn_len(inner_batch.operations.len());
+            inner_batch.operations.truncate(len);
+        }
+        self.store.write_batch(inner_batch).await?;
+        if torn {
+            return Err(FaultyStoreError::InjectedFault(FaultyOperation::Write));
+        }
+        Ok(())
+    }
+}
+
+impl<D> KeyValueDatabase for FaultyDatabase<D>
+where
+    D: KeyValueDatabase,
+{
+    type Config = FaultyConfig<D::Config>;
+
+    type Store = FaultyStore<D::Store>;
+
+    fn get_name() -> String {
+        format!("faulty {}", D::get_name())
+    }
+
+    async fn connect(config: &Self::Config, namespace: &str) -> Result<Self, Self::Error> {
+        let database = D::connect(&config.inner_config, namespace).await?;
+        Ok(FaultyDatabase {
+            database,
+            injector: config.injector.clone(),
+        })
+    }
+
+    fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_shared(root_key)?;
+        Ok(FaultyStore {
+            store,
+            injector: self.injector.clone(),
+        })
+    }
+
+    fn open_exclusive(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_exclusive(root_key)?;
+        Ok(FaultyStore {
+            store,
+            injector: self.injector.clone(),
+        })
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        Ok(D::list_all(&config.inner_config).await?)
+    }
+
+    async fn list_root_keys(
+        config: &Self::Config,
+        namespace: &str,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(D::list_root_keys(&config.inner_config, namespace).await?)
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), Self::Error> {
+        Ok(D::delete_all(&config.inner_config).await?)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        Ok(D::exists(&config.inner_config, namespace).await?)
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(D::create(&config.inner_config, namespace).await?)
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(D::delete(&config.inner_config, namespace).await?)
+    }
+}
+
+impl<D> TestKeyValueDatabase for JournalingKeyValueDatabase<FaultyDatabase<D>>
+where
+    D: TestKeyValueDatabase,
+{
+    async fn new_test_config() -> Result<FaultyConfig<D::Config>, Self::Error> {
+        let inner_config = D::new_test_config().await?;
+        Ok(FaultyConfig {
+            inner_config,
+            injector: FaultInjector::new(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_views::{
+        batch::Batch,
+        faulty::{Fault, FaultRule, FaultyMemoryDatabase, FaultyOperation, FaultyStoreError},
+        store::{
+            KeyValueDatabase as _, ReadableKeyValueStore as _, TestKeyValueDatabase as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_faulty_store_replays_journal() {
+        let config = FaultyMemoryDatabase::new_test_config().await.unwrap();
+        let injector = config.injector.clone();
+        let namespace = linera_views::random::generate_test_namespace();
+        let database = FaultyMemoryDatabase::recreate_and_connect(&config, &namespace)
+            .await
+            .unwrap();
+        let store = database.open_exclusive(&[1]).unwrap();
+
+        // Only the reads and writes of the keys starting with 2 fail.
+        injector.add_rule(
+            FaultRule::new(Fault::Error)
+                .with_operations([FaultyOperation::Read])
+                .with_key_prefix(vec![2]),
+        );
+        assert!(store.read_value_bytes(&[1]).await.is_ok());
+        assert!(matches!(
+            store.read_value_bytes(&[2, 0]).await,
+            Err(FaultyStoreError::InjectedFault(FaultyOperation::Read))
+        ));
+        injector.clear_rules();
+
+        // A torn write only writes some of the first operations of a small batch.
+        let mut batch = Batch::new();
+        for i in 0..5u8 {
+            batch.put_key_value_bytes(vec![2, i], vec![i]);
+        }
+        injector.add_rule(
+            FaultRule::new(Fault::TornWrite)
+                .with_operations([FaultyOperation::Write])
+                .with_max_count(1),
+        );
+        assert!(store.write_batch(batch).await.is_err());
+        assert_eq!(injector.injected_count(), 2);
+        let keys = store.find_keys_by_prefix(&[2]).await.unwrap();
+        assert!(keys.len() < 5);
+        assert_eq!(
+            keys,
+            (0..keys.len() as u8).map(|i| vec![i]).collect::<Vec<_>>()
+        );
+        injector.clear_rules();
+
+        // A large batch is journaled. A torn write of the entries of the journal, whose
+        // keys start with `[0, 2]`, leaves it without a header, so the batch has no effect.
+        let mut batch = Batch::new();
+        for i in 0..20u8 {
+            batch.put_key_value_bytes(vec![3, i], vec![i]);
+        }
+        injector.add_rule(
+            FaultRule::new(Fault::TornWrite)
+                .with_operations([FaultyOperation::Write])
+                .with_key_prefix(vec![0, 2])
+                .with_max_count(1),
+        );
+        assert!(store.write_batch(batch.clone()).await.is_err());
+        assert_eq!(injector.injected_count(), 3);
+        store.clear_journal().await.unwrap();
+        assert!(store.find_keys_by_prefix(&[3]).await.unwrap().is_empty());
+        injector.clear_rules();
+
+        // The write fails once the journal is complete, when the first of its entries is
+        // executed.
+        injector.add_rule(
+            FaultRule::new(Fault::Error)
+                .with_operations([FaultyOperation::Write])
+                .with_key_prefix(vec![3])
+                .with_max_count(1),
+        );
+        assert!(store.write_batch(batch).await.is_err());
+        assert_eq!(injector.injected_count(), 4);
+        assert!(store.find_keys_by_prefix(&[3]).await.unwrap().is_empty());
+
+        // Opening the store again replays the journal.
+        store.clear_journal().await.unwrap();
+        let keys = store.find_keys_by_prefix(&[3]).await.unwrap();
+        assert_eq!(keys, (0..20u8).map(|i| vec![i]).collect::<Vec<_>>());
+    }
+}
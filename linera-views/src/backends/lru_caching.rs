@@ -2,10 +2,22 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Add LRU (least recently used) caching to a given store.
+//!
+//! When several processes write to the same database, the cache of each process is
+//! validated with a generation stored under every root key. Once a batch is written, the
+//! generation of the root key is replaced with a new one, or deleted along with the root
+//! key. Readers read the generation together with the keys missing from the cache and
+//! discard the cached entries if it changed. In that mode, the cache saves load on the
+//! database rather than latency: every read still costs a round trip.
 
 use std::{
     collections::{btree_map, hash_map::RandomState, BTreeMap},
-    sync::{Arc, Mutex},
+    future::Future,
+    hash::BuildHasher as _,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use linked_hash_map::LinkedHashMap;
@@ -63,6 +75,24 @@ mod metrics {
             &[],
         )
     });
+
+    /// The total number of reads of the generation of a root key
+    pub static GENERATION_CHECK_COUNT: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "num_cache_generation_checks",
+            "Number of reads of the generation of a root key",
+            &[],
+        )
+    });
+
+    /// The total number of caches discarded because another process wrote to the root key
+    pub static CACHE_INVALIDATION_COUNT: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "num_cache_invalidations",
+            "Number of caches discarded because another process wrote to the root key",
+            &[],
+        )
+    });
 }
 
 /// The parametrization of the cache.
@@ -74,6 +104,11 @@ pub struct StorageCacheConfig {
     pub max_entry_size: usize,
     /// The maximum number of entries in the cache.
     pub max_cache_entries: usize,
+    /// Whether other processes write to the database. If so, the cache is validated
+    /// with the generation of the root key on each read. All the processes writing
+    /// to the database must then set this option.
+    #[serde(default)]
+    pub shared_database: bool,
 }
 
 /// The maximum number of entries in the cache.
@@ -83,8 +118,20 @@ pub const DEFAULT_STORAGE_CACHE_CONFIG: StorageCacheConfig = StorageCacheConfig
     max_cache_size: 10000000,
     max_entry_size: 1000000,
     max_cache_entries: 1000,
+    shared_database: false,
 };
 
+/// The key storing the generation of a root key in shared databases. Views do not use
+/// keys starting with 0, and journals only use the keys starting with `[0, 1]` and `[0, 2]`.
+const GENERATION_KEY: &[u8] = b"\0cache-generation";
+
+/// Returns a new generation for a root key. Generations are random rather than
+/// incremented, so that concurrent writers do not need to agree on the next one.
+fn new_generation() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    RandomState::new().hash_one(COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
 enum CacheEntry {
     DoesNotExist,
     Exists,
@@ -111,6 +158,8 @@ struct LruPrefixCache {
     total_size: usize,
     /// Whether we have exclusive R/W access to the keys under the root key of the store.
     has_exclusive_access: bool,
+    /// In a shared database, the generation of the root key when the entries were read.
+    generation: Option<u64>,
 }
 
 impl LruPrefixCache {
@@ -122,7 +171,38 @@ impl LruPrefixCache {
             config,
             total_size: 0,
             has_exclusive_access,
+            generation: None,
+        }
+    }
+
+    /// Removes all the entries.
+    fn clear(&mut self) {
+        self.map.clear();
+        self.queue.clear();
+        self.total_size = 0;
+        self.generation = None;
+    }
+
+    /// Records the current generation of the root key, removing all the entries if
+    /// it was written since they were read.
+    fn validate(&mut self, generation: Option<u64>) {
+        if self.generation == generation {
+            return;
         }
+        if !self.map.is_empty() {
+            #[cfg(with_metrics)]
+            metrics::CACHE_INVALIDATION_COUNT
+                .with_label_values(&[])
+                .inc();
+        }
+        self.clear();
+        self.generation = generation;
+    }
+
+    /// Returns whether values read after the given generation of the root key can be
+    /// inserted. Nothing is cached for root keys without a generation.
+    fn accepts(&self, generation: Option<u64>) -> bool {
+        generation.is_some() && self.generation == generation
     }
 
     /// Trim the cache so that it fits within the constraints.
@@ -141,7 +221,8 @@ impl LruPrefixCache {
     /// Inserts an entry into the cache.
     fn insert(&mut self, key: Vec<u8>, cache_entry: CacheEntry) {
         let key_value_size = key.len() + cache_entry.size();
-        if (matches!(cache_entry, CacheEntry::DoesNotExist) && !self.has_exclusive_access)
+        let may_be_created = !self.has_exclusive_access && !self.config.shared_database;
+        if (matches!(cache_entry, CacheEntry::DoesNotExist) && may_be_created)
             || key_value_size > self.config.max_entry_size
         {
             // Just forget about the entry.
@@ -264,6 +345,8 @@ pub struct LruCachingStore<S> {
     store: S,
     /// The LRU cache of values.
     cache: Option<Arc<Mutex<LruPrefixCache>>>,
+    /// Whether other processes write to the database.
+    shared_database: bool,
 }
 
 impl<D> WithError for LruCachingDatabase<D>
@@ -295,6 +378,20 @@ where
         let Some(cache) = &self.cache else {
             return self.store.read_value_bytes(key).await;
         };
+        if self.shared_database {
+            let (mut values, _hit_count) = self
+                .read_shared(
+                    cache,
+                    vec![key.to_vec()],
+                    LruPrefixCache::query_read_value,
+                    LruPrefixCache::insert_read_value,
+                    |keys| self.store.read_multi_values_bytes(keys),
+                )
+                .await?;
+            #[cfg(with_metrics)]
+            record_read_value_metrics(_hit_count, 1);
+            return Ok(values.pop().expect("one value per key"));
+        }
         // First inquiring in the read_value_bytes LRU
         {
             let mut cache = cache.lock().unwrap();
@@ -320,6 +417,20 @@ where
         let Some(cache) = &self.cache else {
             return self.store.contains_key(key).await;
         };
+        if self.shared_database {
+            let (mut results, _hit_count) = self
+                .read_shared(
+                    cache,
+                    vec![key.to_vec()],
+                    LruPrefixCache::query_contains_key,
+                    |cache, key, result| cache.insert_contains_key(key, *result),
+                    |keys| self.store.contains_keys(keys),
+                )
+                .await?;
+            #[cfg(with_metrics)]
+            record_contains_key_metrics(_hit_count, 1);
+            return Ok(results.pop().expect("one result per key"));
+        }
         {
             let mut cache = cache.lock().unwrap();
             if let Some(value) = cache.query_contains_key(key) {
@@ -344,6 +455,21 @@ where
         let Some(cache) = &self.cache else {
             return self.store.contains_keys(keys).await;
         };
+        if self.shared_database {
+            let _key_count = keys.len();
+            let (results, _hit_count) = self
+                .read_shared(
+                    cache,
+                    keys,
+                    LruPrefixCache::query_contains_key,
+                    |cache, key, result| cache.insert_contains_key(key, *result),
+                    |keys| self.store.contains_keys(keys),
+                )
+                .await?;
+            #[cfg(with_metrics)]
+            record_contains_key_metrics(_hit_count, _key_count);
+            return Ok(results);
+        }
         let size = keys.len();
         let mut results = vec![false; size];
         let mut indices = Vec::new();
@@ -385,6 +511,21 @@ where
         let Some(cache) = &self.cache else {
             return self.store.read_multi_values_bytes(keys).await;
         };
+        if self.shared_database {
+            let _key_count = keys.len();
+            let (values, _hit_count) = self
+                .read_shared(
+                    cache,
+                    keys,
+                    LruPrefixCache::query_read_value,
+                    LruPrefixCache::insert_read_value,
+                    |keys| self.store.read_multi_values_bytes(keys),
+                )
+                .await?;
+            #[cfg(with_metrics)]
+            record_read_value_metrics(_hit_count, _key_count);
+            return Ok(values);
+        }
 
         let mut result = Vec::with_capacity(keys.len());
         let mut cache_miss_indices = Vec::new();
@@ -427,14 +568,22 @@ where
     }
 
     async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
-        self.store.find_keys_by_prefix(key_prefix).await
+        let mut keys = self.store.find_keys_by_prefix(key_prefix).await?;
+        if self.shared_database {
+            keys.retain(|key| !is_generation_key(key_prefix, key));
+        }
+        Ok(keys)
     }
 
     async fn find_key_values_by_prefix(
         &self,
         key_prefix: &[u8],
     ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
-        self.store.find_key_values_by_prefix(key_prefix).await
+        let mut key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
+        if self.shared_database {
+            key_values.retain(|(key, _)| !is_generation_key(key_prefix, key));
+        }
+        Ok(key_values)
     }
 }
 
@@ -446,6 +595,35 @@ where
     const MAX_VALUE_SIZE: usize = K::MAX_VALUE_SIZE;
 
     async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        if self.shared_database {
+            // The generation is only replaced once the batch is written, since batches are
+            // not atomic in every backend: readers could otherwise cache a partial write
+            // under the new generation. If we stop in between, the caches of other
+            // processes are only refreshed by the next write to the root key.
+            let deletes_root_key = deletes_root_key(&batch);
+            self.store.write_batch(batch).await?;
+            let mut generation_batch = Batch::new();
+            let generation = if deletes_root_key {
+                generation_batch.delete_key(GENERATION_KEY.to_vec());
+                None
+            } else {
+                let generation = new_generation();
+                generation_batch.put_key_value_bytes(
+                    GENERATION_KEY.to_vec(),
+                    generation.to_le_bytes().to_vec(),
+                );
+                Some(generation)
+            };
+            self.store.write_batch(generation_batch).await?;
+            // Other processes may have written since the cache was last validated, so the
+            // entries cannot be updated with the batch.
+            if let Some(cache) = &self.cache {
+                let mut cache = cache.lock().unwrap();
+                cache.clear();
+                cache.generation = generation;
+            }
+            return Ok(());
+        }
         let Some(cache) = &self.cache else {
             return self.store.write_batch(batch).await;
         };
@@ -566,10 +744,159 @@ impl<S> LruCachingStore<S> {
                 ))))
             }
         };
-        Self { store, cache }
+        let shared_database = config.shared_database;
+        Self {
+            store,
+            cache,
+            shared_database,
+        }
     }
 }
 
+impl<S> LruCachingStore<S>
+where
+    S: ReadableKeyValueStore,
+{
+    /// Reads the generation of the root key, or `None` if it has none.
+    async fn read_generation(&self) -> Result<Option<u64>, S::Error> {
+        #[cfg(with_metrics)]
+        metrics::GENERATION_CHECK_COUNT.with_label_values(&[]).inc();
+        Ok(self
+            .store
+            .read_value_bytes(GENERATION_KEY)
+            .await?
+            .and_then(|bytes| <[u8; 8]>::try_from(bytes.as_slice()).ok())
+            .map(u64::from_le_bytes))
+    }
+
+    /// Reads `keys` in a shared database, using `query` and `insert` to access the cache
+    /// and `read` to access the store. Returns the results and the number of cache hits.
+    ///
+    /// The generation of the root key is read concurrently with the keys missing from the
+    /// cache. If it is still the generation of the cached entries, the entries found are
+    /// valid and the missing ones are cached: a write that they miss can only replace the
+    /// generation afterwards, so the next read discards them. Otherwise, the cache is
+    /// cleared and the keys that were found are read again.
+    async fn read_shared<T, F>(
+        &self,
+        cache: &Mutex<LruPrefixCache>,
+        keys: Vec<Vec<u8>>,
+        query: impl Fn(&mut LruPrefixCache, &[u8]) -> Option<T>,
+        insert: impl Fn(&mut LruPrefixCache, Vec<u8>, &T),
+        read: impl Fn(Vec<Vec<u8>>) -> F,
+    ) -> Result<(Vec<T>, usize), S::Error>
+    where
+        F: Future<Output = Result<Vec<T>, S::Error>>,
+    {
+        let mut results = Vec::with_capacity(keys.len());
+        let mut hit_indices = Vec::new();
+        let mut miss_indices = Vec::new();
+        let cached_generation = {
+            let mut cache = cache.lock().unwrap();
+            for (i, key) in keys.iter().enumerate() {
+                let result = query(&mut cache, key);
+                if result.is_some() {
+                    hit_indices.push(i);
+                } else {
+                    miss_indices.push(i);
+                }
+                results.push(result);
+            }
+            cache.generation
+        };
+        let miss_keys = miss_indices
+            .iter()
+            .map(|i| keys[*i].clone())
+            .collect::<Vec<_>>();
+        let read_misses = async {
+            if miss_keys.is_empty() {
+                Ok(Vec::new())
+            } else {
+                read(miss_keys.clone()).await
+            }
+        };
+        let (generation, values) = futures::try_join!(self.read_generation(), read_misses)?;
+        let is_valid = generation.is_some() && generation == cached_generation;
+        {
+            let mut cache = cache.lock().unwrap();
+            if !is_valid {
+                cache.validate(generation);
+            }
+            let accepts = is_valid && cache.accepts(generation);
+            for ((i, key), value) in miss_indices.into_iter().zip(miss_keys).zip(values) {
+                if accepts {
+                    insert(&mut cache, key, &value);
+                }
+                results[i] = Some(value);
+            }
+        }
+        let mut hit_count = hit_indices.len();
+        if !is_valid && !hit_indices.is_empty() {
+            // These keys are read after the generation, so they can be cached with it.
+            let hit_keys = hit_indices
+                .iter()
+                .map(|i| keys[*i].clone())
+                .collect::<Vec<_>>();
+            let values = read(hit_keys.clone()).await?;
+            let mut cache = cache.lock().unwrap();
+            let accepts = cache.accepts(generation);
+            for ((i, key), value) in hit_indices.into_iter().zip(hit_keys).zip(values) {
+                if accepts {
+                    insert(&mut cache, key, &value);
+                }
+                results[i] = Some(value);
+            }
+            hit_count = 0;
+        }
+        let results = results
+            .into_iter()
+            .map(|result| result.expect("every key was read"))
+            .collect();
+        Ok((results, hit_count))
+    }
+}
+
+/// Returns whether a batch leaves the root key empty, in which case its generation is
+/// deleted too.
+fn deletes_root_key(batch: &Batch) -> bool {
+    let mut deletes_root_key = false;
+    for operation in &batch.operations {
+        match operation {
+            WriteOperation::DeletePrefix { key_prefix } if key_prefix.is_empty() => {
+                deletes_root_key = true;
+            }
+            WriteOperation::Put { .. } => deletes_root_key = false,
+            _ => {}
+        }
+    }
+    deletes_root_key
+}
+
+#[cfg(with_metrics)]
+fn record_read_value_metrics(hit_count: usize, key_count: usize) {
+    metrics::READ_VALUE_CACHE_HIT_COUNT
+        .with_label_values(&[])
+        .inc_by(hit_count as u64);
+    metrics::READ_VALUE_CACHE_MISS_COUNT
+        .with_label_values(&[])
+        .inc_by((key_count - hit_count) as u64);
+}
+
+#[cfg(with_metrics)]
+fn record_contains_key_metrics(hit_count: usize, key_count: usize) {
+    metrics::CONTAINS_KEY_CACHE_HIT_COUNT
+        .with_label_values(&[])
+        .inc_by(hit_count as u64);
+    metrics::CONTAINS_KEY_CACHE_MISS_COUNT
+        .with_label_values(&[])
+        .inc_by((key_count - hit_count) as u64);
+}
+
+/// Returns whether a key found under `key_prefix` is the generation of the root key.
+fn is_generation_key(key_prefix: &[u8], key: &[u8]) -> bool {
+    GENERATION_KEY.strip_prefix(key_prefix) == Some(key)
+}
+
 /// A memory darabase with caching.
 #[cfg(with_testing)]
 pub type LruCachingMemoryDatabase = LruCachingDatabase<MemoryDatabase>;
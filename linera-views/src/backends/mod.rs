@@ -3,6 +3,8 @@
 
 pub mod journaling;
 
+pub mod change_feed;
+
 #[cfg(with_metrics)]
 pub mod metering;
 
@@ -14,14 +16,26 @@ pub mod lru_caching;
 
 pub mod dual;
 
+#[cfg(with_testing)]
+pub mod faulty;
+
+#[cfg(with_encryption)]
+pub mod encryption;
+
 #[cfg(with_scylladb)]
 pub mod scylla_db;
 
 #[cfg(with_rocksdb)]
 pub mod rocks_db;
 
+#[cfg(with_redb)]
+pub mod redb_db;
+
 #[cfg(with_dynamodb)]
 pub mod dynamo_db;
 
+#[cfg(with_postgres)]
+pub mod postgres_db;
+
 #[cfg(with_indexeddb)]
 pub mod indexed_db;
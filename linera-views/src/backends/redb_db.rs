@@ -0,0 +1,572 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements [`crate::store::KeyValueStore`] for the redb embedded database.
+//!
+//! Every namespace is a single redb file in the configured directory and every root key
+//! is a table of that file.
+
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, Weak},
+};
+
+use linera_base::ensure;
+use redb::{ReadableTable as _, TableDefinition, TableHandle as _};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use crate::common::PathWithGuard;
+#[cfg(with_metrics)]
+use crate::metering::MeteredDatabase;
+#[cfg(with_testing)]
+use crate::store::TestKeyValueDatabase;
+use crate::{
+    batch::{Batch, WriteOperation},
+    lru_caching::{LruCachingConfig, LruCachingDatabase},
+    store::{
+        KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore, WithError,
+        WritableKeyValueStore,
+    },
+    value_splitting::{ValueSplittingDatabase, ValueSplittingError},
+};
+
+/// The number of streams for the test
+#[cfg(with_testing)]
+const TEST_REDB_MAX_STREAM_QUERIES: usize = 10;
+
+// redb stores the lengths of values as `u32`.
+// For offset reasons we decrease by 400
+const MAX_VALUE_SIZE: usize = 3 * 1024 * 1024 * 1024 - 400;
+
+// Keys are stored in the B-tree pages, so we keep them reasonably small.
+const MAX_KEY_SIZE: usize = 1024 * 1024;
+
+/// The extension of the files holding the namespaces.
+const NAMESPACE_EXTENSION: &str = "redb";
+
+/// The prefix of the names of the tables holding the root keys.
+const TABLE_NAME_PREFIX: &str = "root_key_";
+
+/// The type of the tables holding the root keys.
+type Table<'a> = TableDefinition<'a, &'static [u8], &'static [u8]>;
+
+/// The redb databases currently open in this process. A redb file cannot be opened twice,
+/// so all the connections to a namespace share the same [`redb::Database`].
+static OPEN_DATABASES: LazyLock<Mutex<HashMap<PathBuf, Weak<redb::Database>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn check_key_size(key: &[u8]) -> Result<(), RedbStoreInternalError> {
+    ensure!(
+        key.len() <= MAX_KEY_SIZE,
+        RedbStoreInternalError::KeyTooLong
+    );
+    Ok(())
+}
+
+fn table_name(root_key: &[u8]) -> String {
+    format!("{TABLE_NAME_PREFIX}{}", hex::encode(root_key))
+}
+
+/// Runs a blocking redb operation outside of the asynchronous runtime.
+async fn spawn<F, O>(f: F) -> Result<O, RedbStoreInternalError>
+where
+    F: FnOnce() -> Result<O, RedbStoreInternalError> + Send + 'static,
+    O: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await?
+}
+
+/// The redb database and the table of a root key.
+#[derive(Clone)]
+struct RedbStoreExecutor {
+    db: Arc<redb::Database>,
+    table_name: String,
+}
+
+impl RedbStoreExecutor {
+    /// Calls `f` with the table of the root key, or returns `default` if nothing was
+    /// ever written to it.
+    fn read<F, O>(&self, default: O, f: F) -> Result<O, RedbStoreInternalError>
+    where
+        F: FnOnce(
+            redb::ReadOnlyTable<&'static [u8], &'static [u8]>,
+        ) -> Result<O, RedbStoreInternalError>,
+    {
+        let transaction = self.db.begin_read()?;
+        match transaction.open_table(Table::new(&self.table_name)) {
+            Ok(table) => f(table),
+            Err(redb::TableError::TableDoesNotExist(_)) => Ok(default),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn read_multi_values_bytes_internal(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, RedbStoreInternalError> {
+        for key in &keys {
+            check_key_size(key)?;
+        }
+        let default = vec![None; keys.len()];
+        self.read(default, |table| {
+            keys.iter()
+                .map(|key| {
+                    Ok(table
+                        .get(key.as_slice())?
+                        .map(|value| value.value().to_vec()))
+                })
+                .collect()
+        })
+    }
+
+    fn contains_keys_internal(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<bool>, RedbStoreInternalError> {
+        for key in &keys {
+            check_key_size(key)?;
+        }
+        let default = vec![false; keys.len()];
+        self.read(default, |table| {
+            keys.iter()
+                .map(|key| Ok(table.get(key.as_slice())?.is_some()))
+                .collect()
+        })
+    }
+
+    #[expect(clippy::type_complexity)]
+    fn find_key_values_by_prefix_internal(
+        &self,
+        key_prefix: Vec<u8>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, RedbStoreInternalError> {
+        check_key_size(&key_prefix)?;
+        let len = key_prefix.len();
+        self.read(Vec::new(), |table| {
+            let mut key_values = Vec::new();
+            for entry in table.range(key_prefix.as_slice()..)? {
+                let (key, value) = entry?;
+                let key = key.value();
+                if !key.starts_with(&key_prefix) {
+                    break;
+                }
+                key_values.push((key[len..].to_vec(), value.value().to_vec()));
+            }
+            Ok(key_values)
+        })
+    }
+
+    fn find_keys_by_prefix_internal(
+        &self,
+        key_prefix: Vec<u8>,
+    ) -> Result<Vec<Vec<u8>>, RedbStoreInternalError> {
+        check_key_size(&key_prefix)?;
+        let len = key_prefix.len();
+        self.read(Vec::new(), |table| {
+            let mut keys = Vec::new();
+            for entry in table.range(key_prefix.as_slice()..)? {
+                let (key, _) = entry?;
+                let key = key.value();
+                if !key.starts_with(&key_prefix) {
+                    break;
+                }
+                keys.push(key[len..].to_vec());
+            }
+            Ok(keys)
+        })
+    }
+
+    fn write_batch_internal(&self, batch: Batch) -> Result<(), RedbStoreInternalError> {
+        let transaction = self.db.begin_write()?;
+        {
+            let mut table = transaction.open_table(Table::new(&self.table_name))?;
+            for operation in batch.operations {
+                match operation {
+                    WriteOperation::Delete { key } => {
+                        check_key_size(&key)?;
+                        table.remove(key.as_slice())?;
+                    }
+                    WriteOperation::Put { key, value } => {
+                        check_key_size(&key)?;
+                        table.insert(key.as_slice(), value.as_slice())?;
+                    }
+                    WriteOperation::DeletePrefix { key_prefix } => {
+                        check_key_size(&key_prefix)?;
+                        let mut keys = Vec::new();
+                        for entry in table.range(key_prefix.as_slice()..)? {
+                            let (key, _) = entry?;
+                            let key = key.value();
+                            if !key.starts_with(&key_prefix) {
+                                break;
+                            }
+                            keys.push(key.to_vec());
+                        }
+                        for key in keys {
+                            table.remove(key.as_slice())?;
+                        }
+                    }
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+/// The inner client
+#[derive(Clone)]
+pub struct RedbStoreInternal {
+    executor: RedbStoreExecutor,
+    _path_with_guard: PathWithGuard,
+    max_stream_queries: usize,
+}
+
+/// Database-level connection to redb for managing namespaces and partitions.
+#[derive(Clone)]
+pub struct RedbDatabaseInternal {
+    db: Arc<redb::Database>,
+    _path_with_guard: PathWithGuard,
+    max_stream_queries: usize,
+}
+
+impl WithError for RedbDatabaseInternal {
+    type Error = RedbStoreInternalError;
+}
+
+/// The initial configuration of the system
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RedbStoreInternalConfig {
+    /// The path to the directory containing the namespaces
+    pub path_with_guard: PathWithGuard,
+    /// Preferred buffer size for async streams.
+    pub max_stream_queries: usize,
+}
+
+impl RedbDatabaseInternal {
+    fn check_namespace(namespace: &str) -> Result<(), RedbStoreInternalError> {
+        if !namespace
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '_')
+        {
+            return Err(RedbStoreInternalError::InvalidNamespace);
+        }
+        Ok(())
+    }
+
+    fn namespace_path(
+        config: &RedbStoreInternalConfig,
+        namespace: &str,
+    ) -> Result<PathBuf, RedbStoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let mut path_buf = config.path_with_guard.path_buf.clone();
+        path_buf.push(namespace);
+        path_buf.set_extension(NAMESPACE_EXTENSION);
+        Ok(path_buf)
+    }
+
+    /// Opens the file of a namespace, creating it if needed, or returns the database
+    /// already open in this process.
+    fn open_database(path: &Path) -> Result<Arc<redb::Database>, RedbStoreInternalError> {
+        let mut databases = OPEN_DATABASES
+            .lock()
+            .expect("Lock to open redb databases should not be poisoned");
+        if let Some(db) = databases.get(path).and_then(Weak::upgrade) {
+            return Ok(db);
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let db = Arc::new(redb::Database::create(path)?);
+        databases.retain(|_, db| db.strong_count() > 0);
+        databases.insert(path.to_path_buf(), Arc::downgrade(&db));
+        Ok(db)
+    }
+
+    fn build(
+        config: &RedbStoreInternalConfig,
+        namespace: &str,
+    ) -> Result<RedbDatabaseInternal, RedbStoreInternalError> {
+        let path_buf = Self::namespace_path(config, namespace)?;
+        let db = Self::open_database(&path_buf)?;
+        let mut path_with_guard = config.path_with_guard.clone();
+        path_with_guard.path_buf = path_buf;
+        Ok(RedbDatabaseInternal {
+            db,
+            _path_with_guard: path_with_guard,
+            max_stream_queries: config.max_stream_queries,
+        })
+    }
+}
+
+impl WithError for RedbStoreInternal {
+    type Error = RedbStoreInternalError;
+}
+
+impl ReadableKeyValueStore for RedbStoreInternal {
+    const MAX_KEY_SIZE: usize = MAX_KEY_SIZE;
+
+    fn max_stream_queries(&self) -> usize {
+        self.max_stream_queries
+    }
+
+    async fn read_value_bytes(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, RedbStoreInternalError> {
+        let executor = self.executor.clone();
+        let keys = vec![key.to_vec()];
+        let mut values = spawn(move || executor.read_multi_values_bytes_internal(keys)).await?;
+        Ok(values.pop().flatten())
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, RedbStoreInternalError> {
+        let executor = self.executor.clone();
+        let keys = vec![key.to_vec()];
+        let results = spawn(move || executor.contains_keys_internal(keys)).await?;
+        Ok(results[0])
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, RedbStoreInternalError> {
+        let executor = self.executor.clone();
+        spawn(move || executor.contains_keys_internal(keys)).await
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, RedbStoreInternalError> {
+        let executor = self.executor.clone();
+        spawn(move || executor.read_multi_values_bytes_internal(keys)).await
+    }
+
+    async fn find_keys_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<Vec<u8>>, RedbStoreInternalError> {
+        let executor = self.executor.clone();
+        let key_prefix = key_prefix.to_vec();
+        spawn(move || executor.find_keys_by_prefix_internal(key_prefix)).await
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, RedbStoreInternalError> {
+        let executor = self.executor.clone();
+        let key_prefix = key_prefix.to_vec();
+        spawn(move || executor.find_key_values_by_prefix_internal(key_prefix)).await
+    }
+}
+
+impl WritableKeyValueStore for RedbStoreInternal {
+    const MAX_VALUE_SIZE: usize = MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), RedbStoreInternalError> {
+        let executor = self.executor.clone();
+        spawn(move || executor.write_batch_internal(batch)).await
+    }
+
+    async fn clear_journal(&self) -> Result<(), RedbStoreInternalError> {
+        Ok(())
+    }
+}
+
+impl KeyValueDatabase for RedbDatabaseInternal {
+    type Config = RedbStoreInternalConfig;
+    type Store = RedbStoreInternal;
+
+    fn get_name() -> String {
+        "redb internal".to_string()
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+    ) -> Result<Self, RedbStoreInternalError> {
+        let config = config.clone();
+        let namespace = namespace.to_string();
+        spawn(move || Self::build(&config, &namespace)).await
+    }
+
+    fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, RedbStoreInternalError> {
+        let executor = RedbStoreExecutor {
+            db: self.db.clone(),
+            table_name: table_name(root_key),
+        };
+        Ok(RedbStoreInternal {
+            executor,
+            _path_with_guard: self._path_with_guard.clone(),
+            max_stream_queries: self.max_stream_queries,
+        })
+    }
+
+    fn open_exclusive(&self, root_key: &[u8]) -> Result<Self::Store, RedbStoreInternalError> {
+        self.open_shared(root_key)
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, RedbStoreInternalError> {
+        let entries = std::fs::read_dir(config.path_with_guard.path_buf.clone())?;
+        let mut namespaces = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new(NAMESPACE_EXTENSION)) {
+                continue;
+            }
+            let Some(namespace) = path.file_stem() else {
+                continue;
+            };
+            let namespace = namespace
+                .to_os_string()
+                .into_string()
+                .map_err(RedbStoreInternalError::IntoStringError)?;
+            namespaces.push(namespace);
+        }
+        Ok(namespaces)
+    }
+
+    async fn list_root_keys(
+        config: &Self::Config,
+        namespace: &str,
+    ) -> Result<Vec<Vec<u8>>, RedbStoreInternalError> {
+        let database = Self::connect(config, namespace).await?;
+        spawn(move || {
+            let transaction = database.db.begin_read()?;
+            let mut root_keys = Vec::new();
+            for table in transaction.list_tables()? {
+                let Some(root_key) = table.name().strip_prefix(TABLE_NAME_PREFIX) else {
+                    continue;
+                };
+                let root_key = hex::decode(root_key)
+                    .map_err(|_| RedbStoreInternalError::InvalidTableName(table.name().into()))?;
+                root_keys.push(root_key);
+            }
+            Ok(root_keys)
+        })
+        .await
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), RedbStoreInternalError> {
+        let namespaces = Self::list_all(config).await?;
+        for namespace in namespaces {
+            Self::delete(config, &namespace).await?;
+        }
+        Ok(())
+    }
+
+    async fn exists(
+        config: &Self::Config,
+        namespace: &str,
+    ) -> Result<bool, RedbStoreInternalError> {
+        let path_buf = Self::namespace_path(config, namespace)?;
+        Ok(path_buf.exists())
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), RedbStoreInternalError> {
+        let path_buf = Self::namespace_path(config, namespace)?;
+        if path_buf.exists() {
+            return Err(RedbStoreInternalError::StoreAlreadyExists);
+        }
+        spawn(move || Self::open_database(&path_buf).map(|_| ())).await
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), RedbStoreInternalError> {
+        let path_buf = Self::namespace_path(config, namespace)?;
+        OPEN_DATABASES
+            .lock()
+            .expect("Lock to open redb databases should not be poisoned")
+            .remove(&path_buf);
+        std::fs::remove_file(path_buf)?;
+        Ok(())
+    }
+}
+
+#[cfg(with_testing)]
+impl TestKeyValueDatabase for RedbDatabaseInternal {
+    async fn new_test_config() -> Result<RedbStoreInternalConfig, RedbStoreInternalError> {
+        let path_with_guard = PathWithGuard::new_testing();
+        let max_stream_queries = TEST_REDB_MAX_STREAM_QUERIES;
+        Ok(RedbStoreInternalConfig {
+            path_with_guard,
+            max_stream_queries,
+        })
+    }
+}
+
+/// The error type for [`RedbStoreInternal`]
+#[derive(Error, Debug)]
+pub enum RedbStoreInternalError {
+    /// Store already exists
+    #[error("Store already exists")]
+    StoreAlreadyExists,
+
+    /// Tokio join error in redb.
+    #[error("tokio join error: {0}")]
+    TokioJoinError(#[from] tokio::task::JoinError),
+
+    /// Error while opening a redb database.
+    #[error("redb database error: {0}")]
+    Database(#[from] redb::DatabaseError),
+
+    /// Error while starting a redb transaction.
+    #[error("redb transaction error: {0}")]
+    Transaction(#[from] redb::TransactionError),
+
+    /// Error while opening a redb table.
+    #[error("redb table error: {0}")]
+    Table(#[from] redb::TableError),
+
+    /// Error while reading or writing a redb table.
+    #[error("redb storage error: {0}")]
+    Storage(#[from] redb::StorageError),
+
+    /// Error while committing a redb transaction.
+    #[error("redb commit error: {0}")]
+    Commit(#[from] redb::CommitError),
+
+    /// The database contains a table that does not belong to a root key.
+    #[error("Invalid table name {0:?}")]
+    InvalidTableName(String),
+
+    /// Error converting `OsString` to `String`
+    #[error("error in the conversion from OsString: {0:?}")]
+    IntoStringError(OsString),
+
+    /// The key must have at most 1 MiB
+    #[error("The key must have at most 1 MiB")]
+    KeyTooLong,
+
+    /// Namespace contains forbidden characters
+    #[error("Namespace contains forbidden characters")]
+    InvalidNamespace,
+
+    /// Filesystem error
+    #[error("Filesystem error: {0}")]
+    FsError(#[from] std::io::Error),
+
+    /// BCS serialization error.
+    #[error(transparent)]
+    BcsError(#[from] bcs::Error),
+}
+
+impl KeyValueStoreError for RedbStoreInternalError {
+    const BACKEND: &'static str = "redb";
+}
+
+/// The composed error type for the `RedbStore`
+pub type RedbStoreError = ValueSplittingError<RedbStoreInternalError>;
+
+/// The composed config type for the `RedbStore`
+pub type RedbStoreConfig = LruCachingConfig<RedbStoreInternalConfig>;
+
+/// The `RedbDatabase` composed type with metrics
+#[cfg(with_metrics)]
+pub type RedbDatabase = MeteredDatabase<
+    LruCachingDatabase<
+        MeteredDatabase<ValueSplittingDatabase<MeteredDatabase<RedbDatabaseInternal>>>,
+    >,
+>;
+/// The `RedbDatabase` composed type
+#[cfg(not(with_metrics))]
+pub type RedbDatabase = LruCachingDatabase<ValueSplittingDatabase<RedbDatabaseInternal>>;
@@ -6,7 +6,6 @@
 use std::{
     ffi::OsString,
     fmt::Display,
-    path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -17,9 +16,9 @@ use linera_base::ensure;
 use rocksdb::{BlockBasedOptions, Cache, DBCompactionStyle, SliceTransform};
 use serde::{Deserialize, Serialize};
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
-use tempfile::TempDir;
 use thiserror::Error;
 
+pub use crate::common::PathWithGuard;
 #[cfg(with_metrics)]
 use crate::metering::MeteredDatabase;
 #[cfg(with_testing)]
@@ -693,42 +692,6 @@ pub enum RocksDbStoreInternalError {
     BcsError(#[from] bcs::Error),
 }
 
-/// A path and the guard for the temporary directory if needed
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct PathWithGuard {
-    /// The path to the data
-    pub path_buf: PathBuf,
-    /// The guard for the directory if one is needed
-    #[serde(skip)]
-    _dir: Option<Arc<TempDir>>,
-}
-
-impl PathWithGuard {
-    /// Creates a `PathWithGuard` from an existing path.
-    pub fn new(path_buf: PathBuf) -> Self {
-        Self {
-            path_buf,
-            _dir: None,
-        }
-    }
-
-    /// Returns the test path for RocksDB without common config.
-    #[cfg(with_testing)]
-    fn new_testing() -> PathWithGuard {
-        let dir = TempDir::new().unwrap();
-        let path_buf = dir.path().to_path_buf();
-        let _dir = Some(Arc::new(dir));
-        PathWithGuard { path_buf, _dir }
-    }
-}
-
-impl PartialEq for PathWithGuard {
-    fn eq(&self, other: &Self) -> bool {
-        self.path_buf == other.path_buf
-    }
-}
-impl Eq for PathWithGuard {}
-
 impl KeyValueStoreError for RocksDbStoreInternalError {
     const BACKEND: &'static str = "rocks_db";
 }
@@ -4,14 +4,18 @@
 //! This provides some common code for the linera-views.
 
 use std::{
+    cmp::Reverse,
     collections::BTreeSet,
     ops::{
         Bound,
         Bound::{Excluded, Included, Unbounded},
     },
+    path::PathBuf,
+    sync::Arc,
 };
 
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tempfile::TempDir;
 
 use crate::ViewError;
 
@@ -296,6 +300,233 @@ impl CustomSerialize for u128 {
     }
 }
 
+/// A serialization whose lexicographic order is the order of the values, as needed by
+/// sorted views. It is prefix-free, so the serialization of a tuple is simply the
+/// concatenation of the serializations of its components.
+pub trait OrderedSerialize: Ord + Sized {
+    /// Appends the serialization of the value to `bytes`.
+    fn write_ordered_bytes(&self, bytes: &mut Vec<u8>);
+
+    /// Reads a value at the start of `bytes` and advances `bytes` past it.
+    fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError>;
+
+    /// Serializes the value.
+    fn to_ordered_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_ordered_bytes(&mut bytes);
+        bytes
+    }
+
+    /// Deserializes a value, which has to use all the bytes.
+    fn from_ordered_bytes(mut bytes: &[u8]) -> Result<Self, ViewError> {
+        let value = Self::read_ordered_bytes(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(ViewError::InvalidOrderedKey);
+        }
+        Ok(value)
+    }
+}
+
+macro_rules! impl_ordered_serialize_for_unsigned {
+    ($($int:ty),*) => {
+        $(
+            impl OrderedSerialize for $int {
+                fn write_ordered_bytes(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError> {
+                    let (value, rest) = bytes
+                        .split_first_chunk()
+                        .ok_or(ViewError::InvalidOrderedKey)?;
+                    *bytes = rest;
+                    Ok(<$int>::from_be_bytes(*value))
+                }
+            }
+        )*
+    };
+}
+
+impl_ordered_serialize_for_unsigned!(u8, u16, u32, u64, u128);
+
+// Flipping the sign bit maps the signed integers to the unsigned ones, in order.
+macro_rules! impl_ordered_serialize_for_signed {
+    ($($int:ty => $unsigned:ty),*) => {
+        $(
+            impl OrderedSerialize for $int {
+                fn write_ordered_bytes(&self, bytes: &mut Vec<u8>) {
+                    let value = (*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1));
+                    value.write_ordered_bytes(bytes);
+                }
+
+                fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError> {
+                    let value = <$unsigned>::read_ordered_bytes(bytes)?;
+                    Ok((value ^ (1 << (<$unsigned>::BITS - 1))) as $int)
+                }
+            }
+        )*
+    };
+}
+
+impl_ordered_serialize_for_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl OrderedSerialize for bool {
+    fn write_ordered_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.push(u8::from(*self));
+    }
+
+    fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError> {
+        match u8::read_ordered_bytes(bytes)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ViewError::InvalidOrderedKey),
+        }
+    }
+}
+
+impl OrderedSerialize for () {
+    fn write_ordered_bytes(&self, _bytes: &mut Vec<u8>) {}
+
+    fn read_ordered_bytes(_bytes: &mut &[u8]) -> Result<Self, ViewError> {
+        Ok(())
+    }
+}
+
+// The bytes of a string are followed by `[0, 0]`, with the zero bytes escaped as
+// `[0, 255]`, so that a string is ordered before its extensions.
+impl OrderedSerialize for String {
+    fn write_ordered_bytes(&self, bytes: &mut Vec<u8>) {
+        for byte in self.as_bytes() {
+            bytes.push(*byte);
+            if *byte == 0 {
+                bytes.push(255);
+            }
+        }
+        bytes.extend_from_slice(&[0, 0]);
+    }
+
+    fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError> {
+        let mut string = Vec::new();
+        loop {
+            let (byte, rest) = bytes.split_first().ok_or(ViewError::InvalidOrderedKey)?;
+            *bytes = rest;
+            if *byte != 0 {
+                string.push(*byte);
+                continue;
+            }
+            let (escape, rest) = bytes.split_first().ok_or(ViewError::InvalidOrderedKey)?;
+            *bytes = rest;
+            match escape {
+                0 => break,
+                255 => string.push(0),
+                _ => return Err(ViewError::InvalidOrderedKey),
+            }
+        }
+        String::from_utf8(string).map_err(|_| ViewError::InvalidOrderedKey)
+    }
+}
+
+// Each element is preceded by `1` and the sequence is terminated by `0`.
+impl<T: OrderedSerialize> OrderedSerialize for Vec<T> {
+    fn write_ordered_bytes(&self, bytes: &mut Vec<u8>) {
+        for value in self {
+            bytes.push(1);
+            value.write_ordered_bytes(bytes);
+        }
+        bytes.push(0);
+    }
+
+    fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError> {
+        let mut values = Vec::new();
+        while bool::read_ordered_bytes(bytes)? {
+            values.push(T::read_ordered_bytes(bytes)?);
+        }
+        Ok(values)
+    }
+}
+
+// Complementing the bytes reverses the order since the serialization is prefix-free.
+impl<T: OrderedSerialize> OrderedSerialize for Reverse<T> {
+    fn write_ordered_bytes(&self, bytes: &mut Vec<u8>) {
+        let start = bytes.len();
+        self.0.write_ordered_bytes(bytes);
+        for byte in &mut bytes[start..] {
+            *byte = !*byte;
+        }
+    }
+
+    fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError> {
+        let complement = bytes.iter().map(|byte| !byte).collect::<Vec<_>>();
+        let mut rest = complement.as_slice();
+        let value = T::read_ordered_bytes(&mut rest)?;
+        *bytes = &bytes[complement.len() - rest.len()..];
+        Ok(Reverse(value))
+    }
+}
+
+macro_rules! impl_ordered_serialize_for_tuple {
+    ($($name:ident: $index:tt),*) => {
+        impl<$($name: OrderedSerialize),*> OrderedSerialize for ($($name,)*) {
+            fn write_ordered_bytes(&self, bytes: &mut Vec<u8>) {
+                $(self.$index.write_ordered_bytes(bytes);)*
+            }
+
+            fn read_ordered_bytes(bytes: &mut &[u8]) -> Result<Self, ViewError> {
+                Ok(($($name::read_ordered_bytes(bytes)?,)*))
+            }
+        }
+    };
+}
+
+impl_ordered_serialize_for_tuple!(A: 0, B: 1);
+impl_ordered_serialize_for_tuple!(A: 0, B: 1, C: 2);
+impl_ordered_serialize_for_tuple!(A: 0, B: 1, C: 2, D: 3);
+
+/// A path and the guard for the temporary directory if needed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PathWithGuard {
+    /// The path to the data
+    pub path_buf: PathBuf,
+    /// The guard for the directory if one is needed
+    #[serde(skip)]
+    _dir: Option<Arc<TempDir>>,
+}
+
+impl PathWithGuard {
+    /// Creates a `PathWithGuard` from an existing path.
+    pub fn new(path_buf: PathBuf) -> Self {
+        Self {
+            path_buf,
+            _dir: None,
+        }
+    }
+
+    /// Returns a path to a new temporary directory, deleted when the last clone is dropped.
+    #[cfg(with_testing)]
+    pub(crate) fn new_testing() -> PathWithGuard {
+        let dir = TempDir::new().unwrap();
+        let path_buf = dir.path().to_path_buf();
+        let _dir = Some(Arc::new(dir));
+        PathWithGuard { path_buf, _dir }
+    }
+
+    /// Returns the path of the entry `name` of this directory, with the same guard.
+    #[cfg(with_testing)]
+    pub(crate) fn join(&self, name: &str) -> PathWithGuard {
+        PathWithGuard {
+            path_buf: self.path_buf.join(name),
+            _dir: self._dir.clone(),
+        }
+    }
+}
+
+impl PartialEq for PathWithGuard {
+    fn eq(&self, other: &Self) -> bool {
+        self.path_buf == other.path_buf
+    }
+}
+impl Eq for PathWithGuard {}
+
 /// This computes the offset of the BCS serialization of a vector.
 /// The formula that should be satisfied is
 /// `serialized_size(vec![v_1, ...., v_n]) = get_uleb128_size(n)`
@@ -312,9 +543,9 @@ pub(crate) const fn get_uleb128_size(len: usize) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeSet;
+    use std::{cmp::Reverse, collections::BTreeSet};
 
-    use linera_views::common::CustomSerialize;
+    use linera_views::common::{CustomSerialize, OrderedSerialize};
     use rand::Rng;
 
     #[test]
@@ -343,6 +574,37 @@ mod tests {
             assert_eq!(val2, val_ret2);
         }
     }
+
+    #[test]
+    fn test_ordered_serialization() {
+        let mut rng = crate::random::make_deterministic_rng();
+        let mut set = BTreeSet::new();
+        for _ in 0..1000 {
+            let string = (0..rng.gen_range(0..4))
+                .map(|_| ['\0', 'a', 'b'][rng.gen_range(0..3)])
+                .collect::<String>();
+            let bytes = (0..rng.gen_range(0..3))
+                .map(|_| rng.gen_range(0..3))
+                .collect::<Vec<u8>>();
+            let value = (rng.gen_range(-3i64..3), string, Reverse(bytes));
+            set.insert(value);
+        }
+        let values = set.into_iter().collect::<Vec<_>>();
+        let serialized = values
+            .iter()
+            .map(OrderedSerialize::to_ordered_bytes)
+            .collect::<Vec<_>>();
+        for i in 1..values.len() {
+            assert!(serialized[i - 1] < serialized[i]);
+        }
+        for (value, bytes) in values.iter().zip(&serialized) {
+            let decoded: (i64, String, Reverse<Vec<u8>>) =
+                OrderedSerialize::from_ordered_bytes(bytes).unwrap();
+            assert_eq!(&decoded, value);
+        }
+        assert!(u32::from_ordered_bytes(&[0, 1, 2]).is_err());
+        assert!(u8::from_ordered_bytes(&[0, 1]).is_err());
+    }
 }
 
 #[test]
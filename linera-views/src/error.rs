@@ -44,6 +44,10 @@ pub enum ViewError {
     #[error("the key must not be too long")]
     KeyTooLong,
 
+    /// The bytes are not the order-preserving serialization of a key.
+    #[error("invalid order-preserving serialization of a key")]
+    InvalidOrderedKey,
+
     /// The entry does not exist in memory
     // FIXME(#148): This belongs to a future `linera_storage::StoreError`.
     #[error("entry does not exist in storage: {0}")]
@@ -57,6 +61,22 @@ pub enum ViewError {
     #[error("missing database entries")]
     MissingEntries,
 
+    /// The stored layout of a versioned view cannot be loaded without migration.
+    #[error("cannot load version {stored} of the view layout as version {current}")]
+    IncompatibleVersion {
+        /// The version of the stored layout.
+        stored: u32,
+        /// The current version of the layout.
+        current: u32,
+    },
+
+    /// A view with an asynchronous migration from the given version was not loaded with
+    /// `View::load`.
+    #[error(
+        "the layout version {0} of the view can only be migrated by loading it with `View::load`"
+    )]
+    MigrationRequiresLoad(u32),
+
     /// The values are incoherent.
     #[error("post load values error")]
     PostLoadValuesError,
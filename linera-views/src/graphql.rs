@@ -3,6 +3,10 @@
 
 use std::borrow::Cow;
 
+use async_graphql::connection::{Connection, Edge};
+
+use crate::pagination::{Order, Page, MAX_PAGE_SIZE};
+
 // TODO(#1858): come up with a better name-mangling scheme
 /// Mangle a GraphQL type into something that can be interpolated into a GraphQL type name
 pub(crate) fn mangle(type_name: impl AsRef<str>) -> String {
@@ -226,3 +230,68 @@ pub(crate) fn missing_key_error(key: &impl std::fmt::Debug) -> async_graphql::Er
         extensions: None,
     }
 }
+
+/// The arguments of a paginated GraphQL query, following the cursor connection
+/// specification: `first` entries `after` a cursor, or `last` entries `before` it. At
+/// most [`MAX_PAGE_SIZE`] entries are returned, which is also the default.
+pub(crate) struct PageQuery {
+    pub cursor: Option<Vec<u8>>,
+    /// The number of entries to read, at least one to know if there are any.
+    pub limit: usize,
+    pub order: Order,
+    /// The number of entries to return.
+    count: usize,
+}
+
+impl PageQuery {
+    pub(crate) fn new(
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<usize>,
+        last: Option<usize>,
+    ) -> Result<Self, async_graphql::Error> {
+        let backward = before.is_some() || last.is_some();
+        if backward && (after.is_some() || first.is_some()) {
+            return Err(async_graphql::Error::new(
+                "cannot paginate forward and backward in the same query",
+            ));
+        }
+        let (cursor, count, order) = if backward {
+            (before, last, Order::Descending)
+        } else {
+            (after, first, Order::Ascending)
+        };
+        let count = count.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        Ok(PageQuery {
+            cursor: cursor.map(hex::decode).transpose()?,
+            limit: count.max(1),
+            order,
+            count,
+        })
+    }
+
+    /// Builds the connection for a page obtained with this query. The cursor of each
+    /// node is its serialized key, in hexadecimal.
+    pub(crate) fn connection<N: async_graphql::OutputType>(
+        &self,
+        page: Page<(Vec<u8>, N)>,
+    ) -> Connection<String, N> {
+        let mut items = page.items;
+        let has_more = page.next_cursor.is_some() || items.len() > self.count;
+        items.truncate(self.count);
+        let has_cursor = self.cursor.is_some();
+        let mut connection = match self.order {
+            Order::Ascending => Connection::new(has_cursor, has_more),
+            Order::Descending => {
+                items.reverse();
+                Connection::new(has_more, has_cursor)
+            }
+        };
+        connection.edges.extend(
+            items
+                .into_iter()
+                .map(|(key, node)| Edge::new(hex::encode(key), node)),
+        );
+        connection
+    }
+}
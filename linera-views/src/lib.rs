@@ -50,7 +50,11 @@ The following views implement the `View` trait:
 * `LogView` implements a log, which is a list of entries that can be expanded.
 * `QueueView` implements a queue, which is a list of entries that can be expanded and reduced.
 * `MapView` implements a map with keys and values.
+* `AggregateMapView` implements a map maintaining the count and sum of its values.
+* `IndexedMapView` implements a map whose values can be looked up by secondary indexes.
 * `SetView` implements a set with keys.
+* `SortedSetView` implements a set whose keys are sorted by their natural order.
+* `PriorityQueueView` implements a queue whose entries are sorted by priority.
 * `CollectionView` implements a map whose values are views themselves.
 * `ReentrantCollectionView` implements a map for which different keys can be accessed independently.
 * `ViewContainer<C>` implements a `KeyValueStore` and is used internally.
@@ -105,18 +109,27 @@ pub mod test_utils;
 
 #[cfg(with_dynamodb)]
 pub use backends::dynamo_db;
+#[cfg(with_encryption)]
+pub use backends::encryption;
+#[cfg(with_testing)]
+pub use backends::faulty;
 #[cfg(with_indexeddb)]
 pub use backends::indexed_db;
 #[cfg(with_metrics)]
 pub use backends::metering;
+#[cfg(with_postgres)]
+pub use backends::postgres_db;
+#[cfg(with_redb)]
+pub use backends::redb_db;
 #[cfg(with_rocksdb)]
 pub use backends::rocks_db;
 #[cfg(with_scylladb)]
 pub use backends::scylla_db;
-pub use backends::{journaling, lru_caching, memory, value_splitting};
+pub use backends::{change_feed, journaling, lru_caching, memory, value_splitting};
 pub use views::{
-    bucket_queue_view, collection_view, hashable_wrapper, key_value_store_view, log_view, map_view,
-    queue_view, reentrant_collection_view, register_view, set_view,
+    aggregate_map_view, bucket_queue_view, collection_view, hashable_wrapper, indexed_map_view,
+    key_value_store_view, log_view, map_view, pagination, priority_queue_view, proof, queue_view,
+    reentrant_collection_view, register_view, set_view, sorted_set_view,
 };
 /// Re-exports used by the derive macros of this library.
 #[doc(hidden)]
@@ -0,0 +1,92 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fixtures of the storage of views, to check that the layouts saved by older versions
+//! of a view can be loaded by the current one.
+//!
+//! A fixture is a text file with one key-value pair per line, as the hexadecimal
+//! encodings of the key and the value separated by a space.
+
+use std::{fmt::Write as _, io, path::Path};
+
+use crate::{
+    batch::Batch,
+    context::{Context as _, MemoryContext},
+    store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    views::{stored_version, RootView, VersionedView, View as _},
+    ViewError,
+};
+
+/// Saves the view and returns the key-value pairs of its storage.
+pub async fn view_fixture<V>(view: &mut V) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ViewError>
+where
+    V: RootView<Context = MemoryContext<()>>,
+{
+    view.save().await?;
+    let context = view.context();
+    let prefix = &context.base_key().bytes;
+    let key_values = context.store().find_key_values_by_prefix(prefix).await?;
+    Ok(key_values
+        .into_iter()
+        .map(|(key, value)| ([prefix.as_slice(), &key].concat(), value))
+        .collect())
+}
+
+/// Writes the key-value pairs of a fixture to a file.
+pub fn write_fixture(path: impl AsRef<Path>, key_values: &[(Vec<u8>, Vec<u8>)]) -> io::Result<()> {
+    let mut contents = String::new();
+    for (key, value) in key_values {
+        writeln!(contents, "{} {}", hex::encode(key), hex::encode(value))
+            .expect("writing to a string cannot fail");
+    }
+    std::fs::write(path, contents)
+}
+
+/// Reads the key-value pairs of a fixture from a file.
+pub fn read_fixture(path: impl AsRef<Path>) -> io::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+    let mut key_values = Vec::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(' ')
+            .ok_or_else(|| invalid(format!("invalid fixture line: {line}")))?;
+        let key = hex::decode(key).map_err(|error| invalid(error.to_string()))?;
+        let value = hex::decode(value).map_err(|error| invalid(error.to_string()))?;
+        key_values.push((key, value));
+    }
+    Ok(key_values)
+}
+
+/// Loads a view from the storage given by a fixture, migrating it to the current layout,
+/// and checks that the migrated view can be saved and loaded again. Returns the view
+/// loaded after the migration.
+pub async fn check_fixture<V>(key_values: Vec<(Vec<u8>, Vec<u8>)>) -> Result<V, ViewError>
+where
+    V: RootView<Context = MemoryContext<()>> + VersionedView,
+{
+    let context = MemoryContext::new_for_testing(());
+    let mut batch = Batch::new();
+    for (key, value) in key_values {
+        batch.put_key_value_bytes(key, value);
+    }
+    context.store().write_batch(batch).await?;
+
+    let mut view = V::load(context.clone()).await?;
+    view.save().await?;
+    assert_eq!(
+        stored_version(&context).await?,
+        V::VERSION,
+        "the version of the migrated view was not saved"
+    );
+
+    let view = V::load(context).await?;
+    assert!(
+        !view.has_pending_changes().await,
+        "the view has pending changes after being migrated and saved"
+    );
+    Ok(view)
+}
@@ -3,6 +3,8 @@
 
 pub mod test_views;
 
+pub mod fixtures;
+
 /// Functions for computing the performance of stores.
 #[cfg(not(target_arch = "wasm32"))]
 pub mod performance;
@@ -0,0 +1,539 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{borrow::Cow, marker::PhantomData};
+
+use linera_base::data_types::{Amount, ArithmeticError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    batch::Batch,
+    common::{from_bytes_option_or_default, HasherOutput, OrderedSerialize},
+    context::Context,
+    hashable_wrapper::WrappedHashableContainerView,
+    map_view::ByteMapView,
+    store::ReadableKeyValueStore as _,
+    views::{ClonableView, HashableView, Hasher, ReplaceContext, View, ViewError, MIN_VIEW_TAG},
+};
+
+/// Key tags to create the sub-keys of an `AggregateMapView` on top of the base key.
+#[repr(u8)]
+enum KeyTag {
+    /// Prefix for the storing of the variable `stored_aggregates`.
+    Aggregates = MIN_VIEW_TAG,
+    /// Prefix for the entries of the map.
+    Map,
+}
+
+/// Values whose sum is maintained by an [`AggregateMapView`].
+pub trait Summable {
+    /// The quantity added to the sum for this value.
+    fn summand(&self) -> u128;
+}
+
+macro_rules! impl_summable {
+    ($($int:ty),*) => {
+        $(
+            impl Summable for $int {
+                fn summand(&self) -> u128 {
+                    u128::from(*self)
+                }
+            }
+        )*
+    };
+}
+
+impl_summable!(u8, u16, u32, u64, u128, Amount);
+
+/// The aggregates of the entries of an [`AggregateMapView`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Aggregates {
+    /// The number of entries.
+    pub count: u64,
+    /// The sum of the values.
+    pub sum: u128,
+    /// The smallest key, serialized with [`OrderedSerialize`].
+    pub min_key: Option<Vec<u8>>,
+    /// The largest key, serialized with [`OrderedSerialize`].
+    pub max_key: Option<Vec<u8>>,
+}
+
+/// A map view keeping track of the [`Aggregates`] of its entries, which are stored next
+/// to the entries so that reading them does not iterate over the map. The keys are
+/// serialized with [`OrderedSerialize`], so they are ordered as in a
+/// [`crate::sorted_set_view::SortedSetView`].
+///
+/// Since the aggregates depend on the previous value of an entry, inserting and
+/// removing entries reads it. Removing the smallest or largest key also lists the keys
+/// sharing the longest prefix with it, which are the closest ones, to find the new one.
+#[derive(Debug)]
+pub struct AggregateMapView<C, I, V> {
+    context: C,
+    delete_storage_first: bool,
+    stored_aggregates: Aggregates,
+    aggregates: Aggregates,
+    map: ByteMapView<C, V>,
+    _phantom: PhantomData<I>,
+}
+
+impl<C, I, V, C2> ReplaceContext<C2> for AggregateMapView<C, I, V>
+where
+    C: Context,
+    C2: Context,
+    I: Send + Sync,
+    V: Send + Sync + Serialize + Clone,
+{
+    type Target = AggregateMapView<C2, I, V>;
+
+    async fn with_context(
+        &mut self,
+        ctx: impl FnOnce(&Self::Context) -> C2 + Clone,
+    ) -> Self::Target {
+        AggregateMapView {
+            context: ctx.clone()(&self.context),
+            delete_storage_first: self.delete_storage_first,
+            stored_aggregates: self.stored_aggregates.clone(),
+            aggregates: self.aggregates.clone(),
+            map: self.map.with_context(ctx).await,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I, V> View for AggregateMapView<C, I, V>
+where
+    C: Context,
+    I: Send + Sync,
+    V: Send + Sync + Serialize,
+{
+    const NUM_INIT_KEYS: usize = 1 + ByteMapView::<C, V>::NUM_INIT_KEYS;
+
+    type Context = C;
+
+    fn context(&self) -> &C {
+        &self.context
+    }
+
+    fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = vec![context.base_key().base_tag(KeyTag::Aggregates as u8)];
+        let base_key = context.base_key().base_tag(KeyTag::Map as u8);
+        keys.extend(ByteMapView::<C, V>::pre_load(
+            &context.clone_with_base_key(base_key),
+        )?);
+        Ok(keys)
+    }
+
+    fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let stored_aggregates: Aggregates =
+            from_bytes_option_or_default(values.first().ok_or(ViewError::PostLoadValuesError)?)?;
+        let base_key = context.base_key().base_tag(KeyTag::Map as u8);
+        let map = ByteMapView::post_load(
+            context.clone_with_base_key(base_key),
+            values.get(1..).ok_or(ViewError::PostLoadValuesError)?,
+        )?;
+        Ok(Self {
+            context,
+            delete_storage_first: false,
+            aggregates: stored_aggregates.clone(),
+            stored_aggregates,
+            map,
+            _phantom: PhantomData,
+        })
+    }
+
+    async fn load(context: C) -> Result<Self, ViewError> {
+        let keys = Self::pre_load(&context)?;
+        let values = context.store().read_multi_values_bytes(keys).await?;
+        Self::post_load(context, &values)
+    }
+
+    fn rollback(&mut self) {
+        self.delete_storage_first = false;
+        self.aggregates = self.stored_aggregates.clone();
+        self.map.rollback();
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        if self.delete_storage_first || self.aggregates != self.stored_aggregates {
+            return true;
+        }
+        self.map.has_pending_changes().await
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        let mut delete_view = false;
+        if self.delete_storage_first {
+            batch.delete_key_prefix(self.context.base_key().bytes.clone());
+            self.stored_aggregates = Aggregates::default();
+            delete_view = true;
+        }
+        delete_view &= self.map.flush(batch)?;
+        if self.aggregates != self.stored_aggregates {
+            let key = self.context.base_key().base_tag(KeyTag::Aggregates as u8);
+            if self.aggregates == Aggregates::default() {
+                batch.delete_key(key);
+            } else {
+                batch.put_key_value(key, &self.aggregates)?;
+                delete_view = false;
+            }
+            self.stored_aggregates = self.aggregates.clone();
+        }
+        self.delete_storage_first = false;
+        Ok(delete_view)
+    }
+
+    fn clear(&mut self) {
+        self.delete_storage_first = true;
+        self.aggregates = Aggregates::default();
+        self.map.clear();
+    }
+}
+
+impl<C, I, V> ClonableView for AggregateMapView<C, I, V>
+where
+    C: Context,
+    I: Send + Sync,
+    V: Clone + Send + Sync + Serialize,
+{
+    fn clone_unchecked(&mut self) -> Self {
+        AggregateMapView {
+            context: self.context.clone(),
+            delete_storage_first: self.delete_storage_first,
+            stored_aggregates: self.stored_aggregates.clone(),
+            aggregates: self.aggregates.clone(),
+            map: self.map.clone_unchecked(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I, V> AggregateMapView<C, I, V>
+where
+    C: Context,
+    I: OrderedSerialize,
+{
+    /// Returns the aggregates of the entries.
+    pub fn aggregates(&self) -> &Aggregates {
+        &self.aggregates
+    }
+
+    /// Returns the number of entries.
+    pub fn count(&self) -> u64 {
+        self.aggregates.count
+    }
+
+    /// Returns the sum of the values.
+    pub fn sum(&self) -> u128 {
+        self.aggregates.sum
+    }
+
+    /// Returns the smallest index, if any.
+    pub fn min_index(&self) -> Result<Option<I>, ViewError> {
+        Self::deserialize_index(&self.aggregates.min_key)
+    }
+
+    /// Returns the largest index, if any.
+    pub fn max_index(&self) -> Result<Option<I>, ViewError> {
+        Self::deserialize_index(&self.aggregates.max_key)
+    }
+
+    fn deserialize_index(key: &Option<Vec<u8>>) -> Result<Option<I>, ViewError> {
+        key.as_deref().map(I::from_ordered_bytes).transpose()
+    }
+
+    /// Obtains the extra data.
+    pub fn extra(&self) -> &C::Extra {
+        self.context.extra()
+    }
+}
+
+impl<C, I, V> AggregateMapView<C, I, V>
+where
+    C: Context,
+    I: OrderedSerialize,
+    V: Summable + Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Inserts or resets a value at an index, updating the aggregates.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::aggregate_map_view::AggregateMapView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map: AggregateMapView<_, u32, u64> = AggregateMapView::load(context).await.unwrap();
+    /// map.insert(&7, 10).await.unwrap();
+    /// map.insert(&300, 5).await.unwrap();
+    /// map.insert(&7, 1).await.unwrap();
+    /// assert_eq!(map.count(), 2);
+    /// assert_eq!(map.sum(), 6);
+    /// assert_eq!(map.max_index().unwrap(), Some(300));
+    /// # })
+    /// ```
+    pub async fn insert(&mut self, index: &I, value: V) -> Result<(), ViewError> {
+        let key = index.to_ordered_bytes();
+        let mut aggregates = self.aggregates.clone();
+        match self.map.get(&key).await? {
+            Some(old_value) => {
+                aggregates.sum = aggregates
+                    .sum
+                    .checked_sub(old_value.summand())
+                    .ok_or(ArithmeticError::Underflow)?;
+            }
+            None => {
+                aggregates.count += 1;
+                if aggregates
+                    .min_key
+                    .as_ref()
+                    .is_none_or(|min_key| key < *min_key)
+                {
+                    aggregates.min_key = Some(key.clone());
+                }
+                if aggregates
+                    .max_key
+                    .as_ref()
+                    .is_none_or(|max_key| key > *max_key)
+                {
+                    aggregates.max_key = Some(key.clone());
+                }
+            }
+        }
+        aggregates.sum = aggregates
+            .sum
+            .checked_add(value.summand())
+            .ok_or(ArithmeticError::Overflow)?;
+        self.map.insert(key, value);
+        self.aggregates = aggregates;
+        Ok(())
+    }
+
+    /// Removes the value at an index, if any, updating the aggregates.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::aggregate_map_view::AggregateMapView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map: AggregateMapView<_, u32, u64> = AggregateMapView::load(context).await.unwrap();
+    /// map.insert(&7, 10).await.unwrap();
+    /// map.insert(&3, 5).await.unwrap();
+    /// map.remove(&3).await.unwrap();
+    /// assert_eq!(map.count(), 1);
+    /// assert_eq!(map.sum(), 10);
+    /// assert_eq!(map.min_index().unwrap(), Some(7));
+    /// # })
+    /// ```
+    pub async fn remove(&mut self, index: &I) -> Result<(), ViewError> {
+        let key = index.to_ordered_bytes();
+        let Some(old_value) = self.map.get(&key).await? else {
+            return Ok(());
+        };
+        let mut aggregates = self.aggregates.clone();
+        aggregates.count -= 1;
+        aggregates.sum = aggregates
+            .sum
+            .checked_sub(old_value.summand())
+            .ok_or(ArithmeticError::Underflow)?;
+        if aggregates.min_key.as_ref() == Some(&key) {
+            aggregates.min_key = self.closest_key(&key, /* largest */ false).await?;
+        }
+        if aggregates.max_key.as_ref() == Some(&key) {
+            aggregates.max_key = self.closest_key(&key, /* largest */ true).await?;
+        }
+        self.map.remove(key);
+        self.aggregates = aggregates;
+        Ok(())
+    }
+
+    /// Returns the largest key other than `key`, which is the largest one, or the
+    /// smallest key other than `key` if it is the smallest one. Since the serialization
+    /// is prefix-free, the keys closest to `key` are the ones sharing the longest prefix
+    /// with it, so the keys are listed under shorter and shorter prefixes of `key` until
+    /// one is found.
+    async fn closest_key(&self, key: &[u8], largest: bool) -> Result<Option<Vec<u8>>, ViewError> {
+        for length in (0..key.len()).rev() {
+            let prefix = key[..length].to_vec();
+            let mut closest = None;
+            self.map
+                .for_each_key_while(
+                    |suffix| {
+                        if suffix == &key[length..] {
+                            return Ok(true);
+                        }
+                        closest = Some(suffix.to_vec());
+                        Ok(largest)
+                    },
+                    prefix.clone(),
+                )
+                .await?;
+            if let Some(suffix) = closest {
+                return Ok(Some([prefix, suffix].concat()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the value at the given index, if any.
+    pub async fn get(&self, index: &I) -> Result<Option<V>, ViewError> {
+        self.map.get(&index.to_ordered_bytes()).await
+    }
+
+    /// Returns whether the map has a value at the given index.
+    pub async fn contains_key(&self, index: &I) -> Result<bool, ViewError> {
+        self.map.contains_key(&index.to_ordered_bytes()).await
+    }
+}
+
+impl<C, I, V> AggregateMapView<C, I, V>
+where
+    C: Context,
+    I: OrderedSerialize + Send,
+    V: Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Applies a function f on each index and value, by increasing index. If the
+    /// function returns false, then the loop ends prematurely.
+    pub async fn for_each_index_value_while<'a, F>(&'a self, mut f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(I, Cow<'a, V>) -> Result<bool, ViewError> + Send,
+    {
+        self.map
+            .for_each_key_value_while(
+                |key, value| {
+                    let index = I::from_ordered_bytes(key)?;
+                    f(index, value)
+                },
+                Vec::new(),
+            )
+            .await
+    }
+
+    /// Returns the list of indices, in increasing order.
+    pub async fn indices(&self) -> Result<Vec<I>, ViewError> {
+        let mut indices = Vec::new();
+        self.map
+            .for_each_key(
+                |key| {
+                    indices.push(I::from_ordered_bytes(key)?);
+                    Ok(())
+                },
+                Vec::new(),
+            )
+            .await?;
+        Ok(indices)
+    }
+
+    /// Returns the list of indices and values, by increasing index.
+    pub async fn index_values(&self) -> Result<Vec<(I, V)>, ViewError> {
+        let mut index_values = Vec::new();
+        self.for_each_index_value_while(|index, value| {
+            index_values.push((index, value.into_owned()));
+            Ok(true)
+        })
+        .await?;
+        Ok(index_values)
+    }
+}
+
+impl<C, I, V> HashableView for AggregateMapView<C, I, V>
+where
+    C: Context,
+    I: Send + Sync,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    type Hasher = <ByteMapView<C, V> as HashableView>::Hasher;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.map.hash_mut().await
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.map.hash().await
+    }
+}
+
+/// Type wrapping `AggregateMapView` while memoizing the hash.
+pub type HashedAggregateMapView<C, I, V> =
+    WrappedHashableContainerView<C, AggregateMapView<C, I, V>, HasherOutput>;
+
+#[cfg(with_graphql)]
+mod graphql {
+    use std::borrow::Cow;
+
+    use super::{AggregateMapView, Summable};
+    use crate::{
+        common::OrderedSerialize,
+        context::Context,
+        graphql::{hash_name, mangle, Entry},
+    };
+
+    impl<C: Send + Sync, I: async_graphql::OutputType, V: async_graphql::OutputType>
+        async_graphql::TypeName for AggregateMapView<C, I, V>
+    {
+        fn type_name() -> Cow<'static, str> {
+            format!(
+                "AggregateMapView_{}_{}_{:08x}",
+                mangle(I::type_name()),
+                mangle(V::type_name()),
+                hash_name::<(I, V)>(),
+            )
+            .into()
+        }
+    }
+
+    #[async_graphql::Object(cache_control(no_cache), name_type)]
+    impl<C, I, V> AggregateMapView<C, I, V>
+    where
+        C: Context,
+        I: async_graphql::OutputType
+            + async_graphql::InputType
+            + OrderedSerialize
+            + std::fmt::Debug
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        V: async_graphql::OutputType
+            + Summable
+            + serde::ser::Serialize
+            + serde::de::DeserializeOwned
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+    {
+        #[graphql(name = "count")]
+        async fn count_(&self) -> u64 {
+            self.count()
+        }
+
+        /// The sum of the values, in decimal since it may not fit in a GraphQL integer.
+        #[graphql(name = "sum")]
+        async fn sum_(&self) -> String {
+            self.sum().to_string()
+        }
+
+        async fn min_key(&self) -> Result<Option<I>, async_graphql::Error> {
+            Ok(self.min_index()?)
+        }
+
+        async fn max_key(&self) -> Result<Option<I>, async_graphql::Error> {
+            Ok(self.max_index()?)
+        }
+
+        async fn keys(&self, count: Option<usize>) -> Result<Vec<I>, async_graphql::Error> {
+            let indices = self.indices().await?;
+            let it = indices.iter().cloned();
+            Ok(if let Some(count) = count {
+                it.take(count).collect()
+            } else {
+                it.collect()
+            })
+        }
+
+        async fn entry(&self, key: I) -> Result<Entry<I, Option<V>>, async_graphql::Error> {
+            Ok(Entry {
+                value: self.get(&key).await?,
+                key,
+            })
+        }
+    }
+}
@@ -7,6 +7,7 @@ use std::{
     io::Write,
     marker::PhantomData,
     mem,
+    ops::{Deref, DerefMut, RangeBounds},
 };
 
 use async_lock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
@@ -16,9 +17,11 @@ use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
     batch::Batch,
-    common::{CustomSerialize, HasherOutput, Update},
+    common::{get_interval, CustomSerialize, HasherOutput, Update},
     context::{BaseKey, Context},
     hashable_wrapper::WrappedHashableContainerView,
+    pagination::{self, Order, Page, RangeScan},
+    proof::{EntryLocator, EntryProof, MerkleTreeBuilder},
     store::ReadableKeyValueStore as _,
     views::{ClonableView, HashableView, Hasher, View, ViewError, MIN_VIEW_TAG},
 };
@@ -479,23 +482,76 @@ impl<W: View> ByteCollectionView<W::Context, W> {
     /// assert_eq!(count, 1);
     /// # })
     /// ```
-    pub async fn for_each_key_while<F>(&self, mut f: F) -> Result<(), ViewError>
+    pub async fn for_each_key_while<F>(&self, f: F) -> Result<(), ViewError>
     where
         F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
     {
+        self.for_each_key_in_range_while(f, ..).await
+    }
+
+    /// Applies a function f on each index (aka key) in the range. Keys are visited in the
+    /// lexicographic order. If the function returns false, then the loop ends
+    /// prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// view.load_entry_mut(&[0, 2]).await.unwrap();
+    /// view.load_entry_mut(&[1, 0]).await.unwrap();
+    /// let mut keys = Vec::new();
+    /// view.for_each_key_in_range_while(
+    ///     |key| {
+    ///         keys.push(key.to_vec());
+    ///         Ok(true)
+    ///     },
+    ///     vec![0, 2]..,
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(keys, vec![vec![0, 2], vec![1, 0]]);
+    /// # })
+    /// ```
+    pub async fn for_each_key_in_range_while<F>(
+        &self,
+        mut f: F,
+        range: impl RangeBounds<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
+    {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        let mut scan = RangeScan::new(range, Order::Ascending);
+        while let Some(prefix) = scan.next_prefix() {
+            for key in scan.select(self.keys_by_prefix(prefix).await?) {
+                if !f(&key)? {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the keys having the given prefix, in lexicographic order.
+    async fn keys_by_prefix(&self, prefix: Vec<u8>) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = Vec::new();
         let updates = self.updates.write().await;
-        let mut updates = updates.iter();
+        let mut updates = updates.range(get_interval(prefix.clone()));
         let mut update = updates.next();
         if !self.delete_storage_first {
-            let base = self.get_index_key(&[]);
-            for index in self.context.store().find_keys_by_prefix(&base).await? {
+            let base = self.get_index_key(&prefix);
+            for suffix in self.context.store().find_keys_by_prefix(&base).await? {
+                let index = [prefix.as_slice(), suffix.as_slice()].concat();
                 loop {
                     match update {
                         Some((key, value)) if key <= &index => {
                             if let Update::Set(_) = value {
-                                if !f(key)? {
-                                    return Ok(());
-                                }
+                                keys.push(key.clone());
                             }
                             update = updates.next();
                             if key == &index {
@@ -503,9 +559,7 @@ impl<W: View> ByteCollectionView<W::Context, W> {
                             }
                         }
                         _ => {
-                            if !f(&index)? {
-                                return Ok(());
-                            }
+                            keys.push(index);
                             break;
                         }
                     }
@@ -514,13 +568,11 @@ impl<W: View> ByteCollectionView<W::Context, W> {
         }
         while let Some((key, value)) = update {
             if let Update::Set(_) = value {
-                if !f(key)? {
-                    return Ok(());
-                }
+                keys.push(key.clone());
             }
             update = updates.next();
         }
-        Ok(())
+        Ok(keys)
     }
 
     /// Applies a function f on each index (aka key). Keys are visited in a
@@ -607,6 +659,82 @@ impl<W: View> ByteCollectionView<W::Context, W> {
         .await?;
         Ok(count)
     }
+
+    /// Returns the list of keys in the range. The order is lexicographic.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// view.load_entry_mut(&[0, 2]).await.unwrap();
+    /// assert_eq!(
+    ///     view.keys_in_range(..vec![0, 2]).await.unwrap(),
+    ///     vec![vec![0, 1]]
+    /// );
+    /// # })
+    /// ```
+    pub async fn keys_in_range(
+        &self,
+        range: impl RangeBounds<Vec<u8>>,
+    ) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = Vec::new();
+        self.for_each_key_in_range_while(
+            |key| {
+                keys.push(key.to_vec());
+                Ok(true)
+            },
+            range,
+        )
+        .await?;
+        Ok(keys)
+    }
+
+    /// Returns up to `limit` keys following the cursor, or preceding it in descending
+    /// order, starting from the first or last key if there is no cursor. The cursor of
+    /// the returned page resumes the iteration.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::pagination::Order;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// view.load_entry_mut(&[0, 2]).await.unwrap();
+    /// let page = view.page(None, 1, Order::Ascending).await.unwrap();
+    /// assert_eq!(page.items, vec![vec![0, 1]]);
+    /// let cursor = page.next_cursor.unwrap();
+    /// let page = view.page(Some(&cursor), 1, Order::Ascending).await.unwrap();
+    /// assert_eq!(page.items, vec![vec![0, 2]]);
+    /// assert_eq!(page.next_cursor, None);
+    /// # })
+    /// ```
+    pub async fn page(
+        &self,
+        cursor: Option<&[u8]>,
+        limit: usize,
+        order: Order,
+    ) -> Result<Page<Vec<u8>>, ViewError> {
+        let mut scan = RangeScan::new(pagination::cursor_range(cursor, order), order);
+        let mut entries = Vec::new();
+        while entries.len() <= limit {
+            let Some(prefix) = scan.next_prefix() else {
+                break;
+            };
+            for key in scan.select(self.keys_by_prefix(prefix).await?) {
+                entries.push((key.clone(), key));
+            }
+        }
+        Ok(pagination::make_page(entries, limit))
+    }
 }
 
 impl<W: HashableView> HashableView for ByteCollectionView<W::Context, W> {
@@ -677,6 +805,36 @@ impl<W: HashableView> HashableView for ByteCollectionView<W::Context, W> {
     }
 }
 
+impl<W: HashableView> ByteCollectionView<W::Context, W> {
+    /// Adds the subviews of the collection to the Merkle tree, in key order, with their
+    /// hashes as values.
+    async fn push_subview_hashes(&self, builder: &mut MerkleTreeBuilder) -> Result<(), ViewError> {
+        let keys = self.keys().await?;
+        let updates = self.updates.read().await;
+        for key in keys {
+            let hash = match updates.get(&key) {
+                Some(entry) => {
+                    let Update::Set(view) = entry else {
+                        unreachable!();
+                    };
+                    view.hash().await?
+                }
+                None => {
+                    let key = self
+                        .context
+                        .base_key()
+                        .base_tag_index(KeyTag::Subview as u8, &key);
+                    let context = self.context.clone_with_base_key(key);
+                    let view = W::load(context).await?;
+                    view.hash().await?
+                }
+            };
+            builder.push(&key, hash.as_ref())?;
+        }
+        Ok(())
+    }
+}
+
 /// A view that supports accessing a collection of views of the same kind, indexed by a
 /// key, one subview at a time.
 #[derive(Debug)]
@@ -942,6 +1100,56 @@ where
     pub async fn count(&self) -> Result<usize, ViewError> {
         self.collection.count().await
     }
+
+    /// Returns the list of indices in the range, in the order determined by the
+    /// serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::collection_view::CollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut view: CollectionView<_, u64, RegisterView<_, String>> =
+    ///     CollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// let indices = view.indices_in_range(24..).await.unwrap();
+    /// assert_eq!(indices, vec![25]);
+    /// # })
+    /// ```
+    pub async fn indices_in_range<Q>(&self, range: impl RangeBounds<Q>) -> Result<Vec<I>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let range = pagination::serialize_range(range, BaseKey::derive_short_key)?;
+        let mut indices = Vec::new();
+        self.collection
+            .for_each_key_in_range_while(
+                |key| {
+                    indices.push(BaseKey::deserialize_value(key)?);
+                    Ok(true)
+                },
+                range,
+            )
+            .await?;
+        Ok(indices)
+    }
+
+    /// Returns up to `limit` indices following the cursor, or preceding it in descending
+    /// order, in the order determined by the serialization.
+    pub async fn page(
+        &self,
+        cursor: Option<&[u8]>,
+        limit: usize,
+        order: Order,
+    ) -> Result<Page<I>, ViewError> {
+        self.collection
+            .page(cursor, limit, order)
+            .await?
+            .try_map(|key| Ok(BaseKey::deserialize_value(&key)?))
+    }
 }
 
 impl<I: DeserializeOwned, W: View> CollectionView<W::Context, I, W> {
@@ -1034,6 +1242,166 @@ where
     }
 }
 
+/// A `CollectionView` whose hash is the root of a Merkle tree over its subviews, so that
+/// the presence or absence of a subview can be proven with an [`EntryProof`]. The hash
+/// is different from the one of the inner `CollectionView`.
+#[derive(Debug)]
+pub struct MerkleCollectionView<C, I, W> {
+    collection: CollectionView<C, I, W>,
+}
+
+impl<W: View, I> View for MerkleCollectionView<W::Context, I, W>
+where
+    I: Send + Sync + Serialize + DeserializeOwned,
+{
+    const NUM_INIT_KEYS: usize = CollectionView::<W::Context, I, W>::NUM_INIT_KEYS;
+
+    type Context = W::Context;
+
+    fn context(&self) -> &Self::Context {
+        self.collection.context()
+    }
+
+    fn pre_load(context: &Self::Context) -> Result<Vec<Vec<u8>>, ViewError> {
+        CollectionView::<W::Context, I, W>::pre_load(context)
+    }
+
+    fn post_load(context: Self::Context, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let collection = CollectionView::post_load(context, values)?;
+        Ok(MerkleCollectionView { collection })
+    }
+
+    async fn load(context: Self::Context) -> Result<Self, ViewError> {
+        Self::post_load(context, &[])
+    }
+
+    fn rollback(&mut self) {
+        self.collection.rollback()
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        self.collection.has_pending_changes().await
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        self.collection.flush(batch)
+    }
+
+    fn clear(&mut self) {
+        self.collection.clear()
+    }
+}
+
+impl<I, W: ClonableView> ClonableView for MerkleCollectionView<W::Context, I, W>
+where
+    I: Send + Sync + Serialize + DeserializeOwned,
+{
+    fn clone_unchecked(&mut self) -> Self {
+        MerkleCollectionView {
+            collection: self.collection.clone_unchecked(),
+        }
+    }
+}
+
+impl<C, I, W> Deref for MerkleCollectionView<C, I, W> {
+    type Target = CollectionView<C, I, W>;
+
+    fn deref(&self) -> &CollectionView<C, I, W> {
+        &self.collection
+    }
+}
+
+impl<C, I, W> DerefMut for MerkleCollectionView<C, I, W> {
+    fn deref_mut(&mut self) -> &mut CollectionView<C, I, W> {
+        &mut self.collection
+    }
+}
+
+/// The subviews are stored in the same way, so an existing `CollectionView` can be
+/// converted without rewriting them. Only the hash changes.
+impl<C, I, W> From<CollectionView<C, I, W>> for MerkleCollectionView<C, I, W> {
+    fn from(collection: CollectionView<C, I, W>) -> Self {
+        MerkleCollectionView { collection }
+    }
+}
+
+impl<C, I, W> MerkleCollectionView<C, I, W> {
+    /// Returns the inner `CollectionView`, with its flat hash.
+    pub fn into_inner(self) -> CollectionView<C, I, W> {
+        self.collection
+    }
+}
+
+impl<I: Serialize, W: HashableView> MerkleCollectionView<W::Context, I, W> {
+    /// Produces a proof that the subview at `index` is present, or that it is absent.
+    /// The proof is checked against the hash of the collection with
+    /// [`EntryProof::collection_root_hash`], given the hash of the subview.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::collection_view::MerkleCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::{HashableView, View};
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut view: MerkleCollectionView<_, u64, RegisterView<_, String>> =
+    ///     MerkleCollectionView::load(context).await.unwrap();
+    /// let subview = view.load_entry_mut(&23).await.unwrap();
+    /// subview.set(String::from("Hello"));
+    /// let subview_hash = subview.hash().await.unwrap();
+    /// let hash = view.hash().await.unwrap();
+    /// let proof = view.prove(&23).await.unwrap();
+    /// assert_eq!(
+    ///     proof
+    ///         .collection_root_hash(&23u64, Some(&subview_hash))
+    ///         .unwrap(),
+    ///     hash
+    /// );
+    /// let proof = view.prove(&24).await.unwrap();
+    /// assert_eq!(proof.collection_root_hash(&24u64, None).unwrap(), hash);
+    /// # })
+    /// ```
+    pub async fn prove<Q>(&self, index: &Q) -> Result<EntryProof, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let short_key = BaseKey::derive_short_key(index)?;
+        let collection = &self.collection.collection;
+        let mut locator = EntryLocator::new(&short_key);
+        collection
+            .for_each_key(|key| {
+                locator.visit(key);
+                Ok(())
+            })
+            .await?;
+        let mut builder = MerkleTreeBuilder::new(locator.tracked_positions());
+        collection.push_subview_hashes(&mut builder).await?;
+        builder.into_proof(&locator)
+    }
+}
+
+impl<I, W: HashableView> HashableView for MerkleCollectionView<W::Context, I, W>
+where
+    I: Clone + Send + Sync + Serialize + DeserializeOwned,
+{
+    type Hasher = sha3::Sha3_256;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.hash().await
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        #[cfg(with_metrics)]
+        let _hash_latency = metrics::COLLECTION_VIEW_HASH_RUNTIME.measure_latency();
+        let mut builder = MerkleTreeBuilder::new([]);
+        self.collection
+            .collection
+            .push_subview_hashes(&mut builder)
+            .await?;
+        Ok(builder.finish()?)
+    }
+}
+
 /// A map view that serializes the indices.
 #[derive(Debug)]
 pub struct CustomCollectionView<C, I, W> {
@@ -1288,6 +1656,57 @@ impl<I: CustomSerialize + Send, W: View> CustomCollectionView<W::Context, I, W>
     pub async fn count(&self) -> Result<usize, ViewError> {
         self.collection.count().await
     }
+
+    /// Returns the list of indices in the range, in the order of their custom
+    /// serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::collection_view::CustomCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut view: CustomCollectionView<_, u128, RegisterView<_, String>> =
+    ///     CustomCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// view.load_entry_mut(&300).await.unwrap();
+    /// let indices = view.indices_in_range(24..=300).await.unwrap();
+    /// assert_eq!(indices, vec![25, 300]);
+    /// # })
+    /// ```
+    pub async fn indices_in_range<Q>(&self, range: impl RangeBounds<Q>) -> Result<Vec<I>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+    {
+        let range = pagination::serialize_range(range, Q::to_custom_bytes)?;
+        let mut indices = Vec::new();
+        self.collection
+            .for_each_key_in_range_while(
+                |key| {
+                    indices.push(I::from_custom_bytes(key)?);
+                    Ok(true)
+                },
+                range,
+            )
+            .await?;
+        Ok(indices)
+    }
+
+    /// Returns up to `limit` indices following the cursor, or preceding it in descending
+    /// order, in the order of their custom serialization.
+    pub async fn page(
+        &self,
+        cursor: Option<&[u8]>,
+        limit: usize,
+        order: Order,
+    ) -> Result<Page<I>, ViewError> {
+        self.collection
+            .page(cursor, limit, order)
+            .await?
+            .try_map(|key| I::from_custom_bytes(&key))
+    }
 }
 
 impl<I: CustomSerialize, W: View> CustomCollectionView<W::Context, I, W> {
@@ -1394,13 +1813,22 @@ pub type HashedCollectionView<C, I, W> =
 pub type HashedCustomCollectionView<C, I, W> =
     WrappedHashableContainerView<C, CustomCollectionView<C, I, W>, HasherOutput>;
 
+/// Type wrapping `MerkleCollectionView` while memoizing the hash.
+pub type HashedMerkleCollectionView<C, I, W> =
+    WrappedHashableContainerView<C, MerkleCollectionView<C, I, W>, HasherOutput>;
+
 #[cfg(with_graphql)]
 mod graphql {
     use std::borrow::Cow;
 
+    use async_graphql::connection::Connection;
+
     use super::{CollectionView, CustomCollectionView, ReadGuardedView};
     use crate::{
-        graphql::{hash_name, mangle, missing_key_error, Entry, MapFilters, MapInput},
+        common::CustomSerialize,
+        context::BaseKey,
+        graphql::{hash_name, mangle, missing_key_error, Entry, MapFilters, MapInput, PageQuery},
+        pagination::Page,
         views::View,
     };
 
@@ -1488,6 +1916,36 @@ mod graphql {
 
             Ok(values)
         }
+
+        #[graphql(name = "page")]
+        async fn page_(
+            &self,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<usize>,
+            last: Option<usize>,
+        ) -> Result<Connection<String, Entry<K, ReadGuardedView<V>>>, async_graphql::Error>
+        {
+            let query = PageQuery::new(after, before, first, last)?;
+            let page = self
+                .collection
+                .page(query.cursor.as_deref(), query.limit, query.order)
+                .await?;
+            let mut items = Vec::new();
+            for short_key in page.items {
+                let key = BaseKey::deserialize_value(&short_key)?;
+                let value = self
+                    .try_load_entry(&key)
+                    .await?
+                    .ok_or_else(|| missing_key_error(&key))?;
+                items.push((short_key, Entry { value, key }));
+            }
+            let page = Page {
+                items,
+                next_cursor: page.next_cursor,
+            };
+            Ok(query.connection(page))
+        }
     }
 
     impl<C: Send + Sync, K: async_graphql::InputType, V: async_graphql::OutputType>
@@ -1554,5 +2012,35 @@ mod graphql {
 
             Ok(values)
         }
+
+        #[graphql(name = "page")]
+        async fn page_(
+            &self,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<usize>,
+            last: Option<usize>,
+        ) -> Result<Connection<String, Entry<K, ReadGuardedView<V>>>, async_graphql::Error>
+        {
+            let query = PageQuery::new(after, before, first, last)?;
+            let page = self
+                .collection
+                .page(query.cursor.as_deref(), query.limit, query.order)
+                .await?;
+            let mut items = Vec::new();
+            for short_key in page.items {
+                let key = K::from_custom_bytes(&short_key)?;
+                let value = self
+                    .try_load_entry(&key)
+                    .await?
+                    .ok_or_else(|| missing_key_error(&key))?;
+                items.push((short_key, Entry { value, key }));
+            }
+            let page = Page {
+                items,
+                next_cursor: page.next_cursor,
+            };
+            Ok(query.connection(page))
+        }
     }
 }
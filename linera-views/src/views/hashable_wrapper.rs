@@ -194,6 +194,19 @@ where
     }
 }
 
+impl<C, W, O> WrappedHashableContainerView<C, W, O> {
+    /// Converts the inner view, e.g. into a view with the same storage layout but a
+    /// different hash. The memoized hash is discarded, so it is recomputed and saved again.
+    pub fn map_inner<W2>(self, f: impl FnOnce(W) -> W2) -> WrappedHashableContainerView<C, W2, O> {
+        WrappedHashableContainerView {
+            _phantom: PhantomData,
+            stored_hash: self.stored_hash,
+            hash: Mutex::new(None),
+            inner: f(self.inner),
+        }
+    }
+}
+
 impl<C, W, O> Deref for WrappedHashableContainerView<C, W, O> {
     type Target = W;
 
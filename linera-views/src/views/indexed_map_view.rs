@@ -0,0 +1,442 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{borrow::Borrow, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    batch::Batch,
+    common::{get_interval, HasherOutput},
+    context::{BaseKey, Context},
+    hashable_wrapper::WrappedHashableContainerView,
+    map_view::ByteMapView,
+    set_view::ByteSetView,
+    views::{ClonableView, HashableView, Hasher, ReplaceContext, View, ViewError, MIN_VIEW_TAG},
+};
+
+/// Key tags to create the sub-keys of an `IndexedMapView` on top of the base key.
+#[repr(u8)]
+enum KeyTag {
+    /// Prefix for the entries of the map.
+    Map = MIN_VIEW_TAG,
+    /// Prefix for the entries of the secondary indexes.
+    Index,
+}
+
+/// Values of an [`IndexedMapView`], which can be looked up by secondary indexes.
+pub trait IndexedValue {
+    /// The values of the secondary indexes. Several indexes can be declared by using an
+    /// enum with one variant for each of them.
+    type Index: Serialize;
+
+    /// Returns the values of the secondary indexes under which this value is found.
+    fn indexes(&self) -> Vec<Self::Index>;
+}
+
+/// A map view that also maintains secondary indexes of its values, as declared by
+/// [`IndexedValue`]. The indexes are updated together with the entries, so that they are
+/// written in the same batch. Since the previous value of an entry has to be removed
+/// from the indexes, inserting and removing entries reads it.
+#[derive(Debug)]
+pub struct IndexedMapView<C, K, V> {
+    map: ByteMapView<C, V>,
+    indexes: ByteSetView<C>,
+    _phantom: PhantomData<K>,
+}
+
+impl<C, K, V, C2> ReplaceContext<C2> for IndexedMapView<C, K, V>
+where
+    C: Context,
+    C2: Context,
+    K: Send + Sync,
+    V: Send + Sync + Serialize + Clone,
+{
+    type Target = IndexedMapView<C2, K, V>;
+
+    async fn with_context(
+        &mut self,
+        ctx: impl FnOnce(&Self::Context) -> C2 + Clone,
+    ) -> Self::Target {
+        IndexedMapView {
+            map: self.map.with_context(ctx.clone()).await,
+            indexes: self.indexes.with_context(ctx).await,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, K, V> View for IndexedMapView<C, K, V>
+where
+    C: Context,
+    K: Send + Sync,
+    V: Send + Sync + Serialize,
+{
+    const NUM_INIT_KEYS: usize =
+        ByteMapView::<C, V>::NUM_INIT_KEYS + ByteSetView::<C>::NUM_INIT_KEYS;
+
+    type Context = C;
+
+    fn context(&self) -> &C {
+        self.map.context()
+    }
+
+    fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
+        let (map_context, indexes_context) = Self::sub_contexts(context);
+        let mut keys = ByteMapView::<C, V>::pre_load(&map_context)?;
+        keys.extend(ByteSetView::<C>::pre_load(&indexes_context)?);
+        Ok(keys)
+    }
+
+    fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let (map_context, indexes_context) = Self::sub_contexts(&context);
+        let split = ByteMapView::<C, V>::NUM_INIT_KEYS;
+        let map = ByteMapView::post_load(
+            map_context,
+            values.get(..split).ok_or(ViewError::PostLoadValuesError)?,
+        )?;
+        let indexes = ByteSetView::post_load(
+            indexes_context,
+            values.get(split..).ok_or(ViewError::PostLoadValuesError)?,
+        )?;
+        Ok(Self {
+            map,
+            indexes,
+            _phantom: PhantomData,
+        })
+    }
+
+    async fn load(context: C) -> Result<Self, ViewError> {
+        Self::post_load(context, &[])
+    }
+
+    fn rollback(&mut self) {
+        self.map.rollback();
+        self.indexes.rollback();
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        self.map.has_pending_changes().await || self.indexes.has_pending_changes().await
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        let delete_map = self.map.flush(batch)?;
+        let delete_indexes = self.indexes.flush(batch)?;
+        Ok(delete_map && delete_indexes)
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.indexes.clear();
+    }
+}
+
+impl<C: Context, K, V> IndexedMapView<C, K, V> {
+    fn sub_contexts(context: &C) -> (C, C) {
+        let base_key = context.base_key();
+        (
+            context.clone_with_base_key(base_key.base_tag(KeyTag::Map as u8)),
+            context.clone_with_base_key(base_key.base_tag(KeyTag::Index as u8)),
+        )
+    }
+}
+
+impl<C, K, V> ClonableView for IndexedMapView<C, K, V>
+where
+    C: Context,
+    K: Send + Sync,
+    V: Clone + Send + Sync + Serialize,
+{
+    fn clone_unchecked(&mut self) -> Self {
+        IndexedMapView {
+            map: self.map.clone_unchecked(),
+            indexes: self.indexes.clone_unchecked(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, K, V> IndexedMapView<C, K, V>
+where
+    C: Context,
+    K: Serialize,
+    V: IndexedValue + Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Inserts or resets the value at a key, updating the secondary indexes.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::indexed_map_view::{IndexedMapView, IndexedValue};
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    /// struct Token {
+    ///     owner: String,
+    /// }
+    ///
+    /// impl IndexedValue for Token {
+    ///     type Index = String;
+    ///
+    ///     fn indexes(&self) -> Vec<String> {
+    ///         vec![self.owner.clone()]
+    ///     }
+    /// }
+    ///
+    /// let mut tokens: IndexedMapView<_, u32, Token> = IndexedMapView::load(context).await.unwrap();
+    /// let token = |owner: &str| Token {
+    ///     owner: owner.to_string(),
+    /// };
+    /// tokens.insert(&1, token("Alice")).await.unwrap();
+    /// tokens.insert(&2, token("Alice")).await.unwrap();
+    /// tokens.insert(&1, token("Bob")).await.unwrap();
+    /// let alice = "Alice".to_string();
+    /// assert_eq!(tokens.keys_by_index(&alice).await.unwrap(), vec![2]);
+    /// # })
+    /// ```
+    pub async fn insert<Q>(&mut self, key: &Q, value: V) -> Result<(), ViewError>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let short_key = BaseKey::derive_short_key(key)?;
+        if let Some(old_value) = self.map.get(&short_key).await? {
+            for index in old_value.indexes() {
+                self.indexes.remove(Self::index_key(&index, &short_key)?);
+            }
+        }
+        for index in value.indexes() {
+            self.indexes.insert(Self::index_key(&index, &short_key)?);
+        }
+        self.map.insert(short_key, value);
+        Ok(())
+    }
+
+    /// Removes the value at a key, if any, updating the secondary indexes.
+    pub async fn remove<Q>(&mut self, key: &Q) -> Result<(), ViewError>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let short_key = BaseKey::derive_short_key(key)?;
+        let Some(old_value) = self.map.get(&short_key).await? else {
+            return Ok(());
+        };
+        for index in old_value.indexes() {
+            self.indexes.remove(Self::index_key(&index, &short_key)?);
+        }
+        self.map.remove(short_key);
+        Ok(())
+    }
+
+    /// Reads the value at the given key, if any.
+    pub async fn get<Q>(&self, key: &Q) -> Result<Option<V>, ViewError>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let short_key = BaseKey::derive_short_key(key)?;
+        self.map.get(&short_key).await
+    }
+
+    /// Returns whether the map has a value at the given key.
+    pub async fn contains_key<Q>(&self, key: &Q) -> Result<bool, ViewError>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let short_key = BaseKey::derive_short_key(key)?;
+        self.map.contains_key(&short_key).await
+    }
+
+    /// Obtains the extra data.
+    pub fn extra(&self) -> &C::Extra {
+        self.map.extra()
+    }
+
+    // The BCS serialization of a given type is prefix-free, so the keys of an index
+    // value are exactly the ones starting with its serialization.
+    fn index_key(index: &V::Index, short_key: &[u8]) -> Result<Vec<u8>, ViewError> {
+        let mut key = BaseKey::derive_short_key(index)?;
+        key.extend_from_slice(short_key);
+        Ok(key)
+    }
+}
+
+impl<C, K, V> IndexedMapView<C, K, V>
+where
+    C: Context,
+    K: Send + DeserializeOwned,
+    V: IndexedValue + Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Returns the keys whose value has the given index value. The order is the one of
+    /// the serialization of the keys.
+    pub async fn keys_by_index(&self, index: &V::Index) -> Result<Vec<K>, ViewError> {
+        let prefix = BaseKey::derive_short_key(index)?;
+        let mut keys = Vec::new();
+        self.indexes
+            .for_each_key_in_range_while(
+                |key| {
+                    keys.push(BaseKey::deserialize_value(&key[prefix.len()..])?);
+                    Ok(true)
+                },
+                get_interval(prefix.clone()),
+            )
+            .await?;
+        Ok(keys)
+    }
+
+    /// Returns the keys and values whose value has the given index value. The order is
+    /// the one of the serialization of the keys.
+    pub async fn entries_by_index(&self, index: &V::Index) -> Result<Vec<(K, V)>, ViewError> {
+        let prefix = BaseKey::derive_short_key(index)?;
+        let mut short_keys = Vec::new();
+        self.indexes
+            .for_each_key_in_range_while(
+                |key| {
+                    short_keys.push(key[prefix.len()..].to_vec());
+                    Ok(true)
+                },
+                get_interval(prefix.clone()),
+            )
+            .await?;
+        let values = self.map.multi_get(short_keys.clone()).await?;
+        short_keys
+            .into_iter()
+            .zip(values)
+            .map(|(short_key, value)| {
+                let value = value.ok_or(ViewError::InconsistentEntries)?;
+                Ok((BaseKey::deserialize_value(&short_key)?, value))
+            })
+            .collect()
+    }
+
+    /// Returns the list of keys of the map. The order is the one of their serialization.
+    pub async fn keys(&self) -> Result<Vec<K>, ViewError> {
+        let mut keys = Vec::new();
+        self.map
+            .for_each_key(
+                |key| {
+                    keys.push(BaseKey::deserialize_value(key)?);
+                    Ok(())
+                },
+                Vec::new(),
+            )
+            .await?;
+        Ok(keys)
+    }
+
+    /// Returns the list of keys and values of the map. The order is the one of the
+    /// serialization of the keys.
+    pub async fn key_values(&self) -> Result<Vec<(K, V)>, ViewError> {
+        self.map
+            .key_values()
+            .await?
+            .into_iter()
+            .map(|(key, value)| Ok((BaseKey::deserialize_value(&key)?, value)))
+            .collect()
+    }
+}
+
+impl<C, K, V> HashableView for IndexedMapView<C, K, V>
+where
+    C: Context,
+    K: Send + Sync,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    type Hasher = <ByteMapView<C, V> as HashableView>::Hasher;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.map.hash_mut().await
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.map.hash().await
+    }
+}
+
+/// Type wrapping `IndexedMapView` while memoizing the hash.
+pub type HashedIndexedMapView<C, K, V> =
+    WrappedHashableContainerView<C, IndexedMapView<C, K, V>, HasherOutput>;
+
+#[cfg(with_graphql)]
+mod graphql {
+    use std::borrow::Cow;
+
+    use super::{IndexedMapView, IndexedValue};
+    use crate::{
+        context::Context,
+        graphql::{hash_name, mangle, Entry},
+    };
+
+    impl<C: Send + Sync, K: async_graphql::OutputType, V: async_graphql::OutputType>
+        async_graphql::TypeName for IndexedMapView<C, K, V>
+    {
+        fn type_name() -> Cow<'static, str> {
+            format!(
+                "IndexedMapView_{}_{}_{:08x}",
+                mangle(K::type_name()),
+                mangle(V::type_name()),
+                hash_name::<(K, V)>(),
+            )
+            .into()
+        }
+    }
+
+    #[async_graphql::Object(cache_control(no_cache), name_type)]
+    impl<C, K, V> IndexedMapView<C, K, V>
+    where
+        C: Context,
+        K: async_graphql::OutputType
+            + async_graphql::InputType
+            + serde::ser::Serialize
+            + serde::de::DeserializeOwned
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        V: async_graphql::OutputType
+            + IndexedValue<Index: async_graphql::InputType>
+            + serde::ser::Serialize
+            + serde::de::DeserializeOwned
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+    {
+        #[graphql(name = "keys")]
+        async fn keys_(&self, count: Option<usize>) -> Result<Vec<K>, async_graphql::Error> {
+            let keys = self.keys().await?;
+            let it = keys.into_iter();
+            Ok(if let Some(count) = count {
+                it.take(count).collect()
+            } else {
+                it.collect()
+            })
+        }
+
+        async fn entry(&self, key: K) -> Result<Entry<K, Option<V>>, async_graphql::Error> {
+            Ok(Entry {
+                value: self.get(&key).await?,
+                key,
+            })
+        }
+
+        #[graphql(name = "keysByIndex")]
+        async fn keys_by_index_(&self, index: V::Index) -> Result<Vec<K>, async_graphql::Error> {
+            Ok(self.keys_by_index(&index).await?)
+        }
+
+        #[graphql(name = "entriesByIndex")]
+        async fn entries_by_index_(
+            &self,
+            index: V::Index,
+        ) -> Result<Vec<Entry<K, V>>, async_graphql::Error> {
+            Ok(self
+                .entries_by_index(&index)
+                .await?
+                .into_iter()
+                .map(|(key, value)| Entry { key, value })
+                .collect())
+        }
+    }
+}
@@ -42,6 +42,7 @@ use std::{
     collections::{btree_map::Entry, BTreeMap},
     marker::PhantomData,
     mem,
+    ops::{Deref, DerefMut, RangeBounds},
 };
 
 use serde::{de::DeserializeOwned, Serialize};
@@ -54,6 +55,8 @@ use crate::{
     },
     context::{BaseKey, Context},
     hashable_wrapper::WrappedHashableContainerView,
+    pagination::{self, Order, Page, RangeScan},
+    proof::{self, EntryLocator, EntryProof, MerkleTreeBuilder},
     store::ReadableKeyValueStore as _,
     views::{ClonableView, HashableView, Hasher, ReplaceContext, View, ViewError},
 };
@@ -868,6 +871,144 @@ where
     }
 }
 
+impl<C, V> ByteMapView<C, V>
+where
+    C: Context,
+    V: Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Applies a function f on each key/value pair whose key is in the range. Keys and
+    /// values are visited in the lexicographic order. If the function returns false,
+    /// then the loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// map.insert(vec![1, 3], String::from("Hallo"));
+    /// let mut keys = Vec::new();
+    /// map.for_each_key_value_in_range_while(
+    ///     |key, _value| {
+    ///         keys.push(key.to_vec());
+    ///         Ok(true)
+    ///     },
+    ///     vec![0, 2]..vec![1, 3],
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(keys, vec![vec![1, 2]]);
+    /// # })
+    /// ```
+    pub async fn for_each_key_value_in_range_while<'a, F>(
+        &'a self,
+        mut f: F,
+        range: impl RangeBounds<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8], Cow<'a, V>) -> Result<bool, ViewError> + Send,
+    {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        let mut scan = RangeScan::new(range, Order::Ascending);
+        while let Some(prefix) = scan.next_prefix() {
+            let keys = scan.select(self.keys_by_prefix(prefix).await?);
+            let values = self.multi_get(keys.clone()).await?;
+            for (key, value) in keys.into_iter().zip(values) {
+                if let Some(value) = value {
+                    if !f(&key, Cow::Owned(value))? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the list of keys and values of the map whose key is in the range, in
+    /// lexicographic order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// assert_eq!(
+    ///     map.key_values_in_range(vec![1]..).await.unwrap(),
+    ///     vec![(vec![1, 2], String::from("Bonjour"))]
+    /// );
+    /// # })
+    /// ```
+    pub async fn key_values_in_range(
+        &self,
+        range: impl RangeBounds<Vec<u8>>,
+    ) -> Result<Vec<(Vec<u8>, V)>, ViewError> {
+        let mut key_values = Vec::new();
+        self.for_each_key_value_in_range_while(
+            |key, value| {
+                key_values.push((key.to_vec(), value.into_owned()));
+                Ok(true)
+            },
+            range,
+        )
+        .await?;
+        Ok(key_values)
+    }
+
+    /// Returns up to `limit` entries following the cursor, or preceding it in descending
+    /// order, starting from the first or last key if there is no cursor. The cursor of
+    /// the returned page resumes the iteration. Only the values of the page are read.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::pagination::Order;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// map.insert(vec![1, 3], String::from("Hallo"));
+    /// let page = map.page(None, 2, Order::Descending).await.unwrap();
+    /// assert_eq!(page.items.len(), 2);
+    /// assert_eq!(page.next_cursor, Some(vec![1, 2]));
+    /// let page = map
+    ///     .page(page.next_cursor.as_deref(), 2, Order::Descending)
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(page.items, vec![(vec![0, 1], String::from("Hello"))]);
+    /// assert_eq!(page.next_cursor, None);
+    /// # })
+    /// ```
+    pub async fn page(
+        &self,
+        cursor: Option<&[u8]>,
+        limit: usize,
+        order: Order,
+    ) -> Result<Page<(Vec<u8>, V)>, ViewError> {
+        let mut scan = RangeScan::new(pagination::cursor_range(cursor, order), order);
+        let mut keys = Vec::new();
+        while keys.len() <= limit {
+            let Some(prefix) = scan.next_prefix() else {
+                break;
+            };
+            keys.extend(scan.select(self.keys_by_prefix(prefix).await?));
+        }
+        keys.truncate(limit.saturating_add(1));
+        let values = self.multi_get(keys.clone()).await?;
+        let entries = keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| Some((key.clone(), (key, value?))))
+            .collect();
+        Ok(pagination::make_page(entries, limit))
+    }
+}
+
 impl<C, V> ByteMapView<C, V>
 where
     C: Context,
@@ -1446,6 +1587,98 @@ where
     }
 }
 
+impl<C, I, V> MapView<C, I, V>
+where
+    C: Context,
+    I: Send + Serialize + DeserializeOwned,
+    V: Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Returns the indices and values of the map whose index is in the range. The order
+    /// is the one of the BCS serialization of the indices, which is not the natural order
+    /// of integers for instance.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map: MapView<_, String, _> = MapView::load(context).await.unwrap();
+    /// map.insert("Italian", String::from("Ciao"));
+    /// map.insert("French", String::from("Bonjour"));
+    /// map.insert("German", String::from("Hallo"));
+    /// let range = "German".to_string()..="Italian".to_string();
+    /// let index_values = map.index_values_in_range(range).await.unwrap();
+    /// assert_eq!(
+    ///     index_values,
+    ///     vec![
+    ///         ("German".to_string(), "Hallo".to_string()),
+    ///         ("Italian".to_string(), "Ciao".to_string())
+    ///     ]
+    /// );
+    /// # })
+    /// ```
+    pub async fn index_values_in_range<Q>(
+        &self,
+        range: impl RangeBounds<Q>,
+    ) -> Result<Vec<(I, V)>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let range = pagination::serialize_range(range, BaseKey::derive_short_key)?;
+        let mut index_values = Vec::new();
+        self.map
+            .for_each_key_value_in_range_while(
+                |key, value| {
+                    let index = BaseKey::deserialize_value(key)?;
+                    index_values.push((index, value.into_owned()));
+                    Ok(true)
+                },
+                range,
+            )
+            .await?;
+        Ok(index_values)
+    }
+
+    /// Returns up to `limit` indices and values following the cursor, or preceding it in
+    /// descending order. The order is the one of the BCS serialization of the indices.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::pagination::Order;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map: MapView<_, String, _> = MapView::load(context).await.unwrap();
+    /// map.insert("Italian", String::from("Ciao"));
+    /// map.insert("French", String::from("Bonjour"));
+    /// let page = map.page(None, 1, Order::Ascending).await.unwrap();
+    /// assert_eq!(
+    ///     page.items,
+    ///     vec![("French".to_string(), "Bonjour".to_string())]
+    /// );
+    /// let cursor = page.next_cursor.unwrap();
+    /// let page = map.page(Some(&cursor), 1, Order::Ascending).await.unwrap();
+    /// assert_eq!(
+    ///     page.items,
+    ///     vec![("Italian".to_string(), "Ciao".to_string())]
+    /// );
+    /// assert_eq!(page.next_cursor, None);
+    /// # })
+    /// ```
+    pub async fn page(
+        &self,
+        cursor: Option<&[u8]>,
+        limit: usize,
+        order: Order,
+    ) -> Result<Page<(I, V)>, ViewError> {
+        self.map
+            .page(cursor, limit, order)
+            .await?
+            .try_map(|(key, value)| Ok((BaseKey::deserialize_value(&key)?, value)))
+    }
+}
+
 impl<C, I, V> HashableView for MapView<C, I, V>
 where
     Self: View,
@@ -1462,6 +1695,205 @@ where
     }
 }
 
+/// A `MapView` whose hash is the root of a Merkle tree over its entries, so that the
+/// presence or absence of an entry can be proven with an [`EntryProof`]. The hash is
+/// different from the one of the inner `MapView`.
+#[derive(Debug)]
+pub struct MerkleMapView<C, I, V> {
+    map: MapView<C, I, V>,
+}
+
+impl<C, C2, I, V> ReplaceContext<C2> for MerkleMapView<C, I, V>
+where
+    C: Context,
+    C2: Context,
+    I: Send + Sync,
+    V: Send + Sync + Serialize + Clone,
+{
+    type Target = MerkleMapView<C2, I, V>;
+
+    async fn with_context(
+        &mut self,
+        ctx: impl FnOnce(&Self::Context) -> C2 + Clone,
+    ) -> Self::Target {
+        MerkleMapView {
+            map: self.map.with_context(ctx).await,
+        }
+    }
+}
+
+impl<C, I, V> View for MerkleMapView<C, I, V>
+where
+    C: Context,
+    I: Send + Sync,
+    V: Send + Sync + Serialize,
+{
+    const NUM_INIT_KEYS: usize = MapView::<C, I, V>::NUM_INIT_KEYS;
+
+    type Context = C;
+
+    fn context(&self) -> &C {
+        self.map.context()
+    }
+
+    fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
+        MapView::<C, I, V>::pre_load(context)
+    }
+
+    fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let map = MapView::post_load(context, values)?;
+        Ok(MerkleMapView { map })
+    }
+
+    async fn load(context: C) -> Result<Self, ViewError> {
+        Self::post_load(context, &[])
+    }
+
+    fn rollback(&mut self) {
+        self.map.rollback()
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        self.map.has_pending_changes().await
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        self.map.flush(batch)
+    }
+
+    fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<C, I, V: Clone> ClonableView for MerkleMapView<C, I, V>
+where
+    Self: View,
+    MapView<C, I, V>: ClonableView,
+{
+    fn clone_unchecked(&mut self) -> Self {
+        MerkleMapView {
+            map: self.map.clone_unchecked(),
+        }
+    }
+}
+
+impl<C, I, V> Deref for MerkleMapView<C, I, V> {
+    type Target = MapView<C, I, V>;
+
+    fn deref(&self) -> &MapView<C, I, V> {
+        &self.map
+    }
+}
+
+impl<C, I, V> DerefMut for MerkleMapView<C, I, V> {
+    fn deref_mut(&mut self) -> &mut MapView<C, I, V> {
+        &mut self.map
+    }
+}
+
+/// The entries are stored in the same way, so an existing `MapView` can be converted
+/// without rewriting them. Only the hash changes.
+impl<C, I, V> From<MapView<C, I, V>> for MerkleMapView<C, I, V> {
+    fn from(map: MapView<C, I, V>) -> Self {
+        MerkleMapView { map }
+    }
+}
+
+impl<C, I, V> MerkleMapView<C, I, V> {
+    /// Returns the inner `MapView`, with its flat hash.
+    pub fn into_inner(self) -> MapView<C, I, V> {
+        self.map
+    }
+}
+
+impl<C, I, V> MerkleMapView<C, I, V>
+where
+    C: Context,
+    I: Send + Sync + Serialize,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Produces a proof that the entry at `index` is present with its current value, or
+    /// that it is absent. The proof is checked against the hash of the map with
+    /// [`EntryProof::map_root_hash`].
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::map_view::MerkleMapView;
+    /// # use linera_views::views::{HashableView, View};
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map: MerkleMapView<_, u32, _> = MerkleMapView::load(context).await.unwrap();
+    /// map.insert(&(37 as u32), String::from("Hello")).unwrap();
+    /// let hash = map.hash().await.unwrap();
+    /// let proof = map.prove(&(37 as u32)).await.unwrap();
+    /// let value = String::from("Hello");
+    /// assert_eq!(proof.map_root_hash(&37u32, Some(&value)).unwrap(), hash);
+    /// let proof = map.prove(&(34 as u32)).await.unwrap();
+    /// assert_eq!(
+    ///     proof.map_root_hash::<_, String>(&34u32, None).unwrap(),
+    ///     hash
+    /// );
+    /// # })
+    /// ```
+    pub async fn prove<Q>(&self, index: &Q) -> Result<EntryProof, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let short_key = BaseKey::derive_short_key(index)?;
+        let mut locator = EntryLocator::new(&short_key);
+        self.map
+            .map
+            .for_each_key(
+                |key| {
+                    locator.visit(key);
+                    Ok(())
+                },
+                Vec::new(),
+            )
+            .await?;
+        let mut builder = MerkleTreeBuilder::new(locator.tracked_positions());
+        self.push_entries(&mut builder).await?;
+        builder.into_proof(&locator)
+    }
+
+    /// Adds the entries of the map to the Merkle tree, in key order.
+    async fn push_entries(&self, builder: &mut MerkleTreeBuilder) -> Result<(), ViewError> {
+        self.map
+            .map
+            .for_each_key_value_or_bytes(
+                |key, value| {
+                    let value_hash = proof::bytes_hash(&value.into_bytes()?);
+                    builder.push(key, &value_hash)?;
+                    Ok(())
+                },
+                Vec::new(),
+            )
+            .await
+    }
+}
+
+impl<C, I, V> HashableView for MerkleMapView<C, I, V>
+where
+    C: Context,
+    I: Send + Sync + Serialize,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    type Hasher = sha3::Sha3_256;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.hash().await
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        #[cfg(with_metrics)]
+        let _hash_latency = metrics::MAP_VIEW_HASH_RUNTIME.measure_latency();
+        let mut builder = MerkleTreeBuilder::new([]);
+        self.push_entries(&mut builder).await?;
+        Ok(builder.finish()?)
+    }
+}
+
 /// A map view that uses custom serialization
 #[derive(Debug)]
 pub struct CustomMapView<C, I, V> {
@@ -1932,6 +2364,70 @@ where
     }
 }
 
+impl<C, I, V> CustomMapView<C, I, V>
+where
+    C: Context,
+    I: Send + CustomSerialize,
+    V: Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Returns the indices and values of the map whose index is in the range, in the
+    /// order of their custom serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::map_view::CustomMapView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut map: CustomMapView<MemoryContext<()>, u128, String> =
+    ///     CustomMapView::load(context).await.unwrap();
+    /// for i in 0..10 {
+    ///     map.insert(&(i as u128), i.to_string()).unwrap();
+    /// }
+    /// let index_values = map.index_values_in_range(3u128..5).await.unwrap();
+    /// assert_eq!(
+    ///     index_values,
+    ///     vec![(3, "3".to_string()), (4, "4".to_string())]
+    /// );
+    /// # })
+    /// ```
+    pub async fn index_values_in_range<Q>(
+        &self,
+        range: impl RangeBounds<Q>,
+    ) -> Result<Vec<(I, V)>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+    {
+        let range = pagination::serialize_range(range, Q::to_custom_bytes)?;
+        let mut index_values = Vec::new();
+        self.map
+            .for_each_key_value_in_range_while(
+                |key, value| {
+                    let index = I::from_custom_bytes(key)?;
+                    index_values.push((index, value.into_owned()));
+                    Ok(true)
+                },
+                range,
+            )
+            .await?;
+        Ok(index_values)
+    }
+
+    /// Returns up to `limit` indices and values following the cursor, or preceding it in
+    /// descending order, in the order of the custom serialization of the indices.
+    pub async fn page(
+        &self,
+        cursor: Option<&[u8]>,
+        limit: usize,
+        order: Order,
+    ) -> Result<Page<(I, V)>, ViewError> {
+        self.map
+            .page(cursor, limit, order)
+            .await?
+            .try_map(|(key, value)| Ok((I::from_custom_bytes(&key)?, value)))
+    }
+}
+
 impl<C, I, V> HashableView for CustomMapView<C, I, V>
 where
     C: Context,
@@ -1959,14 +2455,22 @@ pub type HashedMapView<C, I, V> = WrappedHashableContainerView<C, MapView<C, I,
 pub type HashedCustomMapView<C, I, V> =
     WrappedHashableContainerView<C, CustomMapView<C, I, V>, HasherOutput>;
 
+/// Type wrapping `MerkleMapView` while memoizing the hash.
+pub type HashedMerkleMapView<C, I, V> =
+    WrappedHashableContainerView<C, MerkleMapView<C, I, V>, HasherOutput>;
+
 #[cfg(with_graphql)]
 mod graphql {
     use std::borrow::Cow;
 
+    use async_graphql::connection::Connection;
+
     use super::{ByteMapView, CustomMapView, MapView};
     use crate::{
-        context::Context,
-        graphql::{hash_name, mangle, Entry, MapInput},
+        common::CustomSerialize,
+        context::{BaseKey, Context},
+        graphql::{hash_name, mangle, Entry, MapInput, PageQuery},
+        ViewError,
     };
 
     impl<C: Send + Sync, V: async_graphql::OutputType> async_graphql::TypeName for ByteMapView<C, V> {
@@ -2036,6 +2540,22 @@ mod graphql {
 
             Ok(entries)
         }
+
+        #[graphql(name = "page")]
+        async fn page_(
+            &self,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<usize>,
+            last: Option<usize>,
+        ) -> Result<Connection<String, Entry<Vec<u8>, V>>, async_graphql::Error> {
+            let query = PageQuery::new(after, before, first, last)?;
+            let page = self
+                .page(query.cursor.as_deref(), query.limit, query.order)
+                .await?
+                .try_map(|(key, value)| Ok::<_, ViewError>((key.clone(), Entry { key, value })))?;
+            Ok(query.connection(page))
+        }
     }
 
     impl<C: Send + Sync, I: async_graphql::OutputType, V: async_graphql::OutputType>
@@ -2113,6 +2633,26 @@ mod graphql {
 
             Ok(values)
         }
+
+        #[graphql(name = "page")]
+        async fn page_(
+            &self,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<usize>,
+            last: Option<usize>,
+        ) -> Result<Connection<String, Entry<I, V>>, async_graphql::Error> {
+            let query = PageQuery::new(after, before, first, last)?;
+            let page = self
+                .map
+                .page(query.cursor.as_deref(), query.limit, query.order)
+                .await?
+                .try_map(|(key, value)| {
+                    let index = BaseKey::deserialize_value(&key)?;
+                    Ok::<_, ViewError>((key, Entry { key: index, value }))
+                })?;
+            Ok(query.connection(page))
+        }
     }
 
     impl<C: Send + Sync, I: async_graphql::OutputType, V: async_graphql::OutputType>
@@ -2189,6 +2729,26 @@ mod graphql {
 
             Ok(values)
         }
+
+        #[graphql(name = "page")]
+        async fn page_(
+            &self,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<usize>,
+            last: Option<usize>,
+        ) -> Result<Connection<String, Entry<I, V>>, async_graphql::Error> {
+            let query = PageQuery::new(after, before, first, last)?;
+            let page = self
+                .map
+                .page(query.cursor.as_deref(), query.limit, query.order)
+                .await?
+                .try_map(|(key, value)| {
+                    let index = I::from_custom_bytes(&key)?;
+                    Ok::<_, ViewError>((key, Entry { key: index, value }))
+                })?;
+            Ok(query.connection(page))
+        }
     }
 }
 
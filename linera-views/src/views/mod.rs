@@ -9,7 +9,10 @@ pub use linera_views_derive::{
 };
 use serde::Serialize;
 
-use crate::{batch::Batch, common::HasherOutput, ViewError};
+use crate::{
+    batch::Batch, common::HasherOutput, context::Context, store::ReadableKeyValueStore as _,
+    ViewError,
+};
 
 #[cfg(test)]
 #[path = "unit_tests/views.rs"]
@@ -33,6 +36,18 @@ pub mod map_view;
 /// The `SetView` implements a set with ordered entries.
 pub mod set_view;
 
+/// The `AggregateMapView` implements a map maintaining the count and sum of its values.
+pub mod aggregate_map_view;
+
+/// The `IndexedMapView` implements a map whose values can be looked up by secondary indexes.
+pub mod indexed_map_view;
+
+/// The `SortedSetView` implements a set whose entries are sorted by their natural order.
+pub mod sorted_set_view;
+
+/// The `PriorityQueueView` implements a queue whose entries are sorted by priority.
+pub mod priority_queue_view;
+
 /// The `CollectionView` implements a map structure whose keys are ordered and the values are views.
 pub mod collection_view;
 
@@ -45,9 +60,19 @@ pub mod key_value_store_view;
 /// Wrapping a view to compute a hash.
 pub mod hashable_wrapper;
 
+/// Merkle proofs of the entries of hashable views.
+pub mod proof;
+
+/// Range scans and pagination over maps and collections.
+pub mod pagination;
+
 /// The minimum value for the view tags. Values in `0..MIN_VIEW_TAG` are used for other purposes.
 pub const MIN_VIEW_TAG: u8 = 1;
 
+/// The tag of the key storing the layout version of the views deriving `View` with
+/// `#[view(version = N)]`. Their fields are stored under the tag `MIN_VIEW_TAG`.
+pub const VERSION_TAG: u8 = MIN_VIEW_TAG + 1;
+
 /// A view gives exclusive access to read and write the data stored at an underlying
 /// address in storage.
 #[cfg_attr(not(web), trait_variant::make(Send + Sync))]
@@ -96,6 +121,47 @@ pub trait View: Sized {
     }
 }
 
+/// A view whose storage layout is versioned.
+///
+/// This is implemented by deriving `View` with `#[view(version = N)]`. The version is
+/// saved along with the view, and the views saved before the layout was versioned have
+/// version 0. A field whose type changed is marked with
+/// `#[view(migrate_from = OldType, migrate_with = path, changed_in = M)]`: when the
+/// stored version is lower than `M` (by default `N`), the field is loaded as `OldType`
+/// and `path(old, stored_version)` must return it in the new layout. Fields are
+/// identified by their position, so new fields must be added at the end.
+///
+/// Migrations run in `post_load`, so a view is migrated when it is loaded, including
+/// the views nested in collections, which are migrated one at a time as they are read.
+/// The version is updated when the view is saved.
+///
+/// A migration that needs to read from storage, e.g. to rewrite the entries of a
+/// `MapView` or a `CollectionView` whose value type changed, is declared with
+/// `#[view(migrate_from = OldType, migrate_async = path)]` instead: `path(old,
+/// stored_version)` is then awaited. It typically creates the new view with `View::new`
+/// on the context of the old one, which deletes the old entries when saved, and inserts
+/// the converted entries. These migrations only run in `View::load`, which is how root
+/// views and the entries of `CollectionView` are loaded. Loading a view that still needs
+/// such a migration with `post_load`, e.g. as a field of another view, fails with
+/// [`ViewError::MigrationRequiresLoad`].
+pub trait VersionedView: View {
+    /// The current version of the layout.
+    const VERSION: u32;
+}
+
+/// Returns the layout version saved for the versioned view with the given context.
+pub async fn stored_version<C: Context>(context: &C) -> Result<u32, ViewError> {
+    let key = context.base_key().base_tag(VERSION_TAG);
+    let value = context.store().read_value_bytes(&key).await?;
+    parse_version(&value)
+}
+
+/// Parses the value of the version key of a versioned view. Used by the derive macros.
+#[doc(hidden)]
+pub fn parse_version(value: &Option<Vec<u8>>) -> Result<u32, ViewError> {
+    Ok(crate::common::from_bytes_option(value)?.unwrap_or_default())
+}
+
 /// A view which can have its context replaced.
 pub trait ReplaceContext<C: crate::context::Context>: View {
     /// The type returned after replacing the context.
@@ -0,0 +1,311 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Range scans and cursor-based pagination over the keys of maps and collections.
+//!
+//! Keys are compared by their serialization, as for the other iterations over maps and
+//! collections. Since the key-value stores only list keys by prefix, a [`RangeScan`]
+//! lists the keys under longer prefixes of the bound it starts from first, so that a
+//! page only reads the keys close to its cursor, in either order. Values are only read
+//! for the keys of the page.
+
+use std::ops::{
+    Bound::{self, Excluded, Included, Unbounded},
+    RangeBounds,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A range of serialized keys.
+pub type KeyRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+/// The order in which the entries of a page are listed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Order {
+    /// By increasing keys.
+    #[default]
+    Ascending,
+    /// By decreasing keys.
+    Descending,
+}
+
+/// The largest number of entries in a page requested through GraphQL.
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// A page of entries, with the cursor to obtain the next one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<T> {
+    /// The entries of the page.
+    pub items: Vec<T>,
+    /// The serialized key of the last entry of the page, if there are more entries
+    /// after it. Passing it back resumes the iteration after that entry.
+    pub next_cursor: Option<Vec<u8>>,
+}
+
+impl<T> Page<T> {
+    /// Applies `f` to the entries of the page.
+    pub fn try_map<U, E>(self, f: impl FnMut(T) -> Result<U, E>) -> Result<Page<U>, E> {
+        Ok(Page {
+            items: self.items.into_iter().map(f).collect::<Result<_, _>>()?,
+            next_cursor: self.next_cursor,
+        })
+    }
+}
+
+/// Converts a range of keys into a range of serialized keys.
+pub(crate) fn serialize_range<Q, E>(
+    range: impl RangeBounds<Q>,
+    serialize: impl Fn(&Q) -> Result<Vec<u8>, E>,
+) -> Result<KeyRange, E>
+where
+    Q: ?Sized,
+{
+    let map = |bound: Bound<&Q>| match bound {
+        Included(key) => Ok(Included(serialize(key)?)),
+        Excluded(key) => Ok(Excluded(serialize(key)?)),
+        Unbounded => Ok(Unbounded),
+    };
+    Ok((map(range.start_bound())?, map(range.end_bound())?))
+}
+
+/// Returns the range of the keys after the cursor, or before it in descending order.
+pub(crate) fn cursor_range(cursor: Option<&[u8]>, order: Order) -> KeyRange {
+    let bound = cursor.map_or(Unbounded, |cursor| Excluded(cursor.to_vec()));
+    match order {
+        Order::Ascending => (bound, Unbounded),
+        Order::Descending => (Unbounded, bound),
+    }
+}
+
+/// Returns whether no key can belong to the range.
+pub(crate) fn is_empty(range: &KeyRange) -> bool {
+    match range {
+        (Included(start), Included(end)) => start > end,
+        (Included(start), Excluded(end))
+        | (Excluded(start), Included(end))
+        | (Excluded(start), Excluded(end)) => start >= end,
+        _ => false,
+    }
+}
+
+/// Returns the longest prefix shared by all the keys of the range.
+pub(crate) fn common_prefix(range: &KeyRange) -> Vec<u8> {
+    let (Included(start) | Excluded(start), Included(end) | Excluded(end)) = range else {
+        return Vec::new();
+    };
+    let length = start
+        .iter()
+        .zip(end)
+        .take_while(|(start, end)| start == end)
+        .count();
+    start[..length].to_vec()
+}
+
+/// Returns whether `key`, and thus all the keys after it, are after the end of the range.
+pub(crate) fn is_after_end(range: &KeyRange, key: &[u8]) -> bool {
+    match &range.1 {
+        Included(end) => key > end.as_slice(),
+        Excluded(end) => key >= end.as_slice(),
+        Unbounded => false,
+    }
+}
+
+/// Returns whether `key`, and thus all the keys before it, are before the start of the
+/// range.
+fn is_before_start(range: &KeyRange, key: &[u8]) -> bool {
+    match &range.0 {
+        Included(start) => key < start.as_slice(),
+        Excluded(start) => key <= start.as_slice(),
+        Unbounded => false,
+    }
+}
+
+/// The listings needed to visit the keys of a range in a given order.
+///
+/// The keys are listed under shorter and shorter prefixes of the bound the scan starts
+/// from, down to the common prefix of the bounds. Since the keys under a prefix are
+/// contiguous, each listing contributes the keys following, in the order of the scan,
+/// the ones of the previous listing. A scan that stops early thus only lists the keys
+/// sharing a long prefix with its starting bound, at the cost of one listing per byte of
+/// that bound.
+pub(crate) struct RangeScan {
+    range: KeyRange,
+    order: Order,
+    /// The bound the scan starts from, or the common prefix if it is unbounded.
+    bound: Vec<u8>,
+    /// The length of the common prefix of the bounds.
+    min_length: usize,
+    /// The length of the prefix of `bound` to list next, if the scan is not over.
+    length: Option<usize>,
+    /// The prefix of the keys that were already visited.
+    visited: Option<Vec<u8>>,
+}
+
+impl RangeScan {
+    /// Starts a scan of the range in the given order.
+    pub(crate) fn new(range: KeyRange, order: Order) -> Self {
+        let prefix = common_prefix(&range);
+        let start = match order {
+            Order::Ascending => &range.0,
+            Order::Descending => &range.1,
+        };
+        let bound = match start {
+            Included(bound) | Excluded(bound) => bound.clone(),
+            Unbounded => prefix.clone(),
+        };
+        let length = (!is_empty(&range)).then_some(bound.len());
+        RangeScan {
+            range,
+            order,
+            bound,
+            min_length: prefix.len(),
+            length,
+            visited: None,
+        }
+    }
+
+    /// Returns the prefix of the keys to list next, if the scan is not over.
+    pub(crate) fn next_prefix(&self) -> Option<Vec<u8>> {
+        self.length.map(|length| self.bound[..length].to_vec())
+    }
+
+    /// Takes the keys listed under the prefix returned by [`Self::next_prefix`], in
+    /// ascending order, and returns the ones to visit next, in the order of the scan.
+    pub(crate) fn select(&mut self, mut keys: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        let Some(length) = self.length else {
+            return Vec::new();
+        };
+        if self.order == Order::Descending {
+            keys.reverse();
+        }
+        let mut selected = Vec::new();
+        for key in keys {
+            if self
+                .visited
+                .as_ref()
+                .is_some_and(|visited| key.starts_with(visited))
+            {
+                continue;
+            }
+            let is_over = match self.order {
+                Order::Ascending => is_after_end(&self.range, &key),
+                Order::Descending => is_before_start(&self.range, &key),
+            };
+            if is_over {
+                self.length = None;
+                return selected;
+            }
+            if self.range.contains(&key) {
+                selected.push(key);
+            }
+        }
+        self.visited = Some(self.bound[..length].to_vec());
+        self.length = (length > self.min_length).then(|| length - 1);
+        selected
+    }
+}
+
+/// Builds a page from the first entries of a scan, listed in the order of the page. A
+/// page with a limit of zero is always empty.
+///
+/// One more entry than the limit is needed to know if there is a next page.
+pub(crate) fn make_page<T>(mut entries: Vec<(Vec<u8>, T)>, limit: usize) -> Page<T> {
+    let has_more = entries.len() > limit;
+    entries.truncate(limit);
+    let next_cursor = if has_more {
+        entries.last().map(|(key, _)| key.clone())
+    } else {
+        None
+    };
+    Page {
+        items: entries.into_iter().map(|(_, item)| item).collect(),
+        next_cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    use super::{common_prefix, cursor_range, is_empty, make_page, Order, RangeScan};
+
+    #[test]
+    fn test_range_helpers() {
+        let range = (Included(vec![1, 2, 3]), Excluded(vec![1, 2, 5, 0]));
+        assert_eq!(common_prefix(&range), vec![1, 2]);
+        assert!(!is_empty(&range));
+        assert_eq!(
+            common_prefix(&(Included(vec![1]), Unbounded)),
+            Vec::<u8>::new()
+        );
+        assert!(is_empty(&(Excluded(vec![1]), Excluded(vec![1]))));
+        assert!(is_empty(&(Included(vec![2]), Included(vec![1]))));
+        assert!(!is_empty(&(Included(vec![1]), Included(vec![1]))));
+    }
+
+    #[test]
+    fn test_make_page() {
+        let entries = (0..4u8).map(|i| (vec![i], i)).collect::<Vec<_>>();
+        let page = make_page(entries[..3].to_vec(), 2);
+        assert_eq!(page.items, vec![0, 1]);
+        assert_eq!(page.next_cursor, Some(vec![1]));
+        let page = make_page(entries[..2].to_vec(), 2);
+        assert_eq!(page.items, vec![0, 1]);
+        assert_eq!(page.next_cursor, None);
+        let page = make_page(entries, 0);
+        assert!(page.items.is_empty());
+        assert_eq!(page.next_cursor, None);
+    }
+
+    /// Runs a scan over `keys`, returning the visited keys and the listed prefixes.
+    fn scan(keys: &[Vec<u8>], mut scan: RangeScan, count: usize) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+        let mut visited = Vec::new();
+        let mut prefixes = Vec::new();
+        while visited.len() < count {
+            let Some(prefix) = scan.next_prefix() else {
+                break;
+            };
+            let listed = keys
+                .iter()
+                .filter(|key| key.starts_with(&prefix))
+                .cloned()
+                .collect();
+            visited.extend(scan.select(listed));
+            prefixes.push(prefix);
+        }
+        (visited, prefixes)
+    }
+
+    #[test]
+    fn test_range_scan() {
+        let keys = [[0, 0], [0, 5], [1, 1], [1, 2], [1, 3], [2, 0], [3, 0]]
+            .map(|key| key.to_vec())
+            .to_vec();
+        let range = cursor_range(Some(&[1, 2]), Order::Ascending);
+        let (visited, prefixes) = scan(&keys, RangeScan::new(range, Order::Ascending), 1);
+        assert_eq!(visited, vec![vec![1, 3]]);
+        assert_eq!(prefixes, vec![vec![1, 2], vec![1]]);
+
+        let range = cursor_range(Some(&[1, 2]), Order::Ascending);
+        let (visited, _) = scan(&keys, RangeScan::new(range, Order::Ascending), 10);
+        assert_eq!(visited, vec![vec![1, 3], vec![2, 0], vec![3, 0]]);
+
+        let range = cursor_range(Some(&[1, 2]), Order::Descending);
+        let (visited, _) = scan(&keys, RangeScan::new(range, Order::Descending), 10);
+        assert_eq!(visited, vec![vec![1, 1], vec![0, 5], vec![0, 0]]);
+
+        let range = (Excluded(vec![0, 0]), Included(vec![2, 0]));
+        let (visited, _) = scan(&keys, RangeScan::new(range.clone(), Order::Ascending), 10);
+        assert_eq!(visited, keys[1..6].to_vec());
+        let (visited, _) = scan(&keys, RangeScan::new(range, Order::Descending), 10);
+        assert_eq!(
+            visited,
+            keys[1..6].iter().rev().cloned().collect::<Vec<_>>()
+        );
+
+        let range = cursor_range(None, Order::Descending);
+        let (visited, prefixes) = scan(&keys, RangeScan::new(range, Order::Descending), 2);
+        assert_eq!(visited, keys.iter().rev().cloned().collect::<Vec<_>>());
+        assert_eq!(prefixes, vec![Vec::<u8>::new()]);
+    }
+}
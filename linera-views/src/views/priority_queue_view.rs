@@ -0,0 +1,470 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    batch::Batch,
+    common::{from_bytes_option_or_default, HasherOutput, OrderedSerialize},
+    context::Context,
+    hashable_wrapper::WrappedHashableContainerView,
+    map_view::ByteMapView,
+    pagination::{Order, RangeScan},
+    store::ReadableKeyValueStore as _,
+    views::{ClonableView, HashableView, Hasher, ReplaceContext, View, ViewError, MIN_VIEW_TAG},
+};
+
+/// Key tags to create the sub-keys of a `PriorityQueueView` on top of the base key.
+#[repr(u8)]
+enum KeyTag {
+    /// Prefix for the storing of the variable `stored_sequence`.
+    Sequence = MIN_VIEW_TAG,
+    /// Prefix for the entries of the queue.
+    Entry,
+}
+
+/// The serialization of the largest sequence number.
+const MAX_SEQUENCE_BYTES: [u8; 8] = [u8::MAX; 8];
+
+/// A view implementing a priority queue of values of type `T` ordered by priorities of
+/// type `P`. Entries of equal priority are ordered by insertion, so [`Self::pop_min`]
+/// returns the oldest entry of minimal priority while [`Self::pop_max`] returns the
+/// newest entry of maximal priority. Use [`std::cmp::Reverse`] priorities to obtain
+/// the oldest entry of maximal priority.
+#[derive(Debug)]
+pub struct PriorityQueueView<C, P, T> {
+    context: C,
+    delete_storage_first: bool,
+    stored_sequence: u64,
+    sequence: u64,
+    entries: ByteMapView<C, T>,
+    _phantom: PhantomData<P>,
+}
+
+impl<C, P, T, C2> ReplaceContext<C2> for PriorityQueueView<C, P, T>
+where
+    C: Context,
+    C2: Context,
+    P: Send + Sync,
+    T: Send + Sync + Serialize + Clone,
+{
+    type Target = PriorityQueueView<C2, P, T>;
+
+    async fn with_context(
+        &mut self,
+        ctx: impl FnOnce(&Self::Context) -> C2 + Clone,
+    ) -> Self::Target {
+        PriorityQueueView {
+            context: ctx.clone()(&self.context),
+            delete_storage_first: self.delete_storage_first,
+            stored_sequence: self.stored_sequence,
+            sequence: self.sequence,
+            entries: self.entries.with_context(ctx).await,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, P, T> View for PriorityQueueView<C, P, T>
+where
+    C: Context,
+    P: Send + Sync,
+    T: Send + Sync + Serialize,
+{
+    const NUM_INIT_KEYS: usize = 1 + ByteMapView::<C, T>::NUM_INIT_KEYS;
+
+    type Context = C;
+
+    fn context(&self) -> &C {
+        &self.context
+    }
+
+    fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = vec![context.base_key().base_tag(KeyTag::Sequence as u8)];
+        let base_key = context.base_key().base_tag(KeyTag::Entry as u8);
+        keys.extend(ByteMapView::<C, T>::pre_load(
+            &context.clone_with_base_key(base_key),
+        )?);
+        Ok(keys)
+    }
+
+    fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let stored_sequence =
+            from_bytes_option_or_default(values.first().ok_or(ViewError::PostLoadValuesError)?)?;
+        let base_key = context.base_key().base_tag(KeyTag::Entry as u8);
+        let entries = ByteMapView::post_load(
+            context.clone_with_base_key(base_key),
+            values.get(1..).ok_or(ViewError::PostLoadValuesError)?,
+        )?;
+        Ok(Self {
+            context,
+            delete_storage_first: false,
+            stored_sequence,
+            sequence: stored_sequence,
+            entries,
+            _phantom: PhantomData,
+        })
+    }
+
+    async fn load(context: C) -> Result<Self, ViewError> {
+        let keys = Self::pre_load(&context)?;
+        let values = context.store().read_multi_values_bytes(keys).await?;
+        Self::post_load(context, &values)
+    }
+
+    fn rollback(&mut self) {
+        self.delete_storage_first = false;
+        self.sequence = self.stored_sequence;
+        self.entries.rollback();
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        if self.delete_storage_first || self.sequence != self.stored_sequence {
+            return true;
+        }
+        self.entries.has_pending_changes().await
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        let mut delete_view = false;
+        if self.delete_storage_first {
+            batch.delete_key_prefix(self.context.base_key().bytes.clone());
+            self.stored_sequence = 0;
+            delete_view = true;
+        }
+        delete_view &= self.entries.flush(batch)?;
+        if self.sequence != self.stored_sequence {
+            let key = self.context.base_key().base_tag(KeyTag::Sequence as u8);
+            batch.put_key_value(key, &self.sequence)?;
+            self.stored_sequence = self.sequence;
+            delete_view = false;
+        }
+        self.delete_storage_first = false;
+        Ok(delete_view)
+    }
+
+    fn clear(&mut self) {
+        self.delete_storage_first = true;
+        self.sequence = 0;
+        self.entries.clear();
+    }
+}
+
+impl<C, P, T> ClonableView for PriorityQueueView<C, P, T>
+where
+    C: Context,
+    P: Send + Sync,
+    T: Clone + Send + Sync + Serialize,
+{
+    fn clone_unchecked(&mut self) -> Self {
+        PriorityQueueView {
+            context: self.context.clone(),
+            delete_storage_first: self.delete_storage_first,
+            stored_sequence: self.stored_sequence,
+            sequence: self.sequence,
+            entries: self.entries.clone_unchecked(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, P, T> PriorityQueueView<C, P, T>
+where
+    C: Context,
+    P: OrderedSerialize + Send,
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Inserts a value with the given priority.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::priority_queue_view::PriorityQueueView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut queue = PriorityQueueView::<_, u64, String>::load(context)
+    ///     .await
+    ///     .unwrap();
+    /// queue.push(&5, "five".to_string());
+    /// queue.push(&3, "three".to_string());
+    /// assert_eq!(queue.first().await.unwrap(), Some((3, "three".to_string())));
+    /// # })
+    /// ```
+    pub fn push(&mut self, priority: &P, value: T) {
+        let mut short_key = priority.to_ordered_bytes();
+        self.sequence.write_ordered_bytes(&mut short_key);
+        self.sequence += 1;
+        self.entries.insert(short_key, value);
+    }
+
+    /// Returns the number of entries in the queue.
+    pub async fn count(&self) -> Result<usize, ViewError> {
+        self.entries.count().await
+    }
+
+    /// Obtains the extra data.
+    pub fn extra(&self) -> &C::Extra {
+        self.context.extra()
+    }
+
+    /// Applies a function f on each entry whose priority is in the range, by increasing
+    /// priority. If the function returns false, then the loop ends prematurely.
+    pub async fn for_each_entry_in_range_while<F>(
+        &self,
+        mut f: F,
+        range: impl RangeBounds<P>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(P, T) -> Result<bool, ViewError> + Send,
+    {
+        // The keys of a priority are its serialization followed by exactly 8 bytes.
+        let start = match range.start_bound() {
+            Bound::Included(priority) => Bound::Included(priority.to_ordered_bytes()),
+            Bound::Excluded(priority) => {
+                let mut key = priority.to_ordered_bytes();
+                key.extend_from_slice(&MAX_SEQUENCE_BYTES);
+                Bound::Excluded(key)
+            }
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(priority) => {
+                let mut key = priority.to_ordered_bytes();
+                key.extend_from_slice(&MAX_SEQUENCE_BYTES);
+                Bound::Included(key)
+            }
+            Bound::Excluded(priority) => Bound::Excluded(priority.to_ordered_bytes()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.entries
+            .for_each_key_value_in_range_while(
+                |key, value| {
+                    let (priority, _) = <(P, u64)>::from_ordered_bytes(key)?;
+                    f(priority, value.into_owned())
+                },
+                (start, end),
+            )
+            .await
+    }
+
+    /// Returns the entries whose priority is in the range, by increasing priority.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::priority_queue_view::PriorityQueueView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut queue = PriorityQueueView::<_, i32, u8>::load(context)
+    ///     .await
+    ///     .unwrap();
+    /// queue.push(&-1, 0);
+    /// queue.push(&2, 1);
+    /// queue.push(&-1, 2);
+    /// queue.push(&3, 3);
+    /// assert_eq!(
+    ///     queue.range(-1..3).await.unwrap(),
+    ///     vec![(-1, 0), (-1, 2), (2, 1)]
+    /// );
+    /// # })
+    /// ```
+    pub async fn range(&self, range: impl RangeBounds<P>) -> Result<Vec<(P, T)>, ViewError> {
+        let mut entries = Vec::new();
+        self.for_each_entry_in_range_while(
+            |priority, value| {
+                entries.push((priority, value));
+                Ok(true)
+            },
+            range,
+        )
+        .await?;
+        Ok(entries)
+    }
+
+    /// Returns all the entries, by increasing priority.
+    pub async fn entries(&self) -> Result<Vec<(P, T)>, ViewError> {
+        self.range(..).await
+    }
+
+    /// Returns the key of the first entry in the given order, if any.
+    async fn first_key(&self, order: Order) -> Result<Option<Vec<u8>>, ViewError> {
+        let mut scan = RangeScan::new((Bound::Unbounded, Bound::Unbounded), order);
+        while let Some(prefix) = scan.next_prefix() {
+            let keys = scan.select(self.entries.keys_by_prefix(prefix).await?);
+            if let Some(key) = keys.into_iter().next() {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn read_entry(&self, key: Option<Vec<u8>>) -> Result<Option<(P, T)>, ViewError> {
+        let Some(key) = key else {
+            return Ok(None);
+        };
+        let (priority, _) = <(P, u64)>::from_ordered_bytes(&key)?;
+        let value = self
+            .entries
+            .get(&key)
+            .await?
+            .ok_or(ViewError::MissingEntries)?;
+        Ok(Some((priority, value)))
+    }
+
+    /// Returns the oldest entry of minimal priority, if any.
+    pub async fn first(&self) -> Result<Option<(P, T)>, ViewError> {
+        let key = self.first_key(Order::Ascending).await?;
+        self.read_entry(key).await
+    }
+
+    /// Returns the newest entry of maximal priority, if any.
+    pub async fn last(&self) -> Result<Option<(P, T)>, ViewError> {
+        let key = self.first_key(Order::Descending).await?;
+        self.read_entry(key).await
+    }
+
+    /// Removes and returns the oldest entry of minimal priority, if any.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::priority_queue_view::PriorityQueueView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut queue = PriorityQueueView::<_, u8, char>::load(context)
+    ///     .await
+    ///     .unwrap();
+    /// queue.push(&1, 'a');
+    /// queue.push(&1, 'b');
+    /// queue.push(&0, 'c');
+    /// assert_eq!(queue.pop_min().await.unwrap(), Some((0, 'c')));
+    /// assert_eq!(queue.pop_min().await.unwrap(), Some((1, 'a')));
+    /// assert_eq!(queue.pop_max().await.unwrap(), Some((1, 'b')));
+    /// assert_eq!(queue.pop_max().await.unwrap(), None);
+    /// # })
+    /// ```
+    pub async fn pop_min(&mut self) -> Result<Option<(P, T)>, ViewError> {
+        let key = self.first_key(Order::Ascending).await?;
+        self.pop_entry(key).await
+    }
+
+    /// Removes and returns the newest entry of maximal priority, if any.
+    pub async fn pop_max(&mut self) -> Result<Option<(P, T)>, ViewError> {
+        let key = self.first_key(Order::Descending).await?;
+        self.pop_entry(key).await
+    }
+
+    async fn pop_entry(&mut self, key: Option<Vec<u8>>) -> Result<Option<(P, T)>, ViewError> {
+        let entry = self.read_entry(key.clone()).await?;
+        if let Some(key) = key {
+            self.entries.remove(key);
+        }
+        Ok(entry)
+    }
+}
+
+impl<C, P, T> HashableView for PriorityQueueView<C, P, T>
+where
+    C: Context,
+    P: Send + Sync,
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    type Hasher = sha3::Sha3_256;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        let entries_hash = self.entries.hash_mut().await?;
+        let mut hasher = sha3::Sha3_256::default();
+        hasher.update_with_bcs_bytes(&self.sequence)?;
+        hasher.update_with_bytes(&entries_hash)?;
+        Ok(hasher.finalize())
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        let entries_hash = self.entries.hash().await?;
+        let mut hasher = sha3::Sha3_256::default();
+        hasher.update_with_bcs_bytes(&self.sequence)?;
+        hasher.update_with_bytes(&entries_hash)?;
+        Ok(hasher.finalize())
+    }
+}
+
+/// Type wrapping `PriorityQueueView` while memoizing the hash.
+pub type HashedPriorityQueueView<C, P, T> =
+    WrappedHashableContainerView<C, PriorityQueueView<C, P, T>, HasherOutput>;
+
+#[cfg(with_graphql)]
+mod graphql {
+    use std::borrow::Cow;
+
+    use super::PriorityQueueView;
+    use crate::{
+        common::OrderedSerialize,
+        context::Context,
+        graphql::{hash_name, mangle, Entry},
+    };
+
+    impl<C: Send + Sync, P: async_graphql::OutputType, T: async_graphql::OutputType>
+        async_graphql::TypeName for PriorityQueueView<C, P, T>
+    {
+        fn type_name() -> Cow<'static, str> {
+            format!(
+                "PriorityQueueView_{}_{}_{:08x}",
+                mangle(P::type_name()),
+                mangle(T::type_name()),
+                hash_name::<(P, T)>(),
+            )
+            .into()
+        }
+    }
+
+    #[async_graphql::Object(cache_control(no_cache), name_type)]
+    impl<C, P, T> PriorityQueueView<C, P, T>
+    where
+        C: Context,
+        P: async_graphql::OutputType + OrderedSerialize + Send + Sync,
+        T: async_graphql::OutputType
+            + serde::ser::Serialize
+            + serde::de::DeserializeOwned
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+    {
+        #[graphql(name = "count")]
+        async fn count_(&self) -> Result<usize, async_graphql::Error> {
+            Ok(self.count().await?)
+        }
+
+        #[graphql(name = "first")]
+        async fn first_(&self) -> Result<Option<Entry<P, T>>, async_graphql::Error> {
+            Ok(self.first().await?.map(|(key, value)| Entry { key, value }))
+        }
+
+        #[graphql(name = "last")]
+        async fn last_(&self) -> Result<Option<Entry<P, T>>, async_graphql::Error> {
+            Ok(self.last().await?.map(|(key, value)| Entry { key, value }))
+        }
+
+        #[graphql(name = "entries")]
+        async fn entries_(
+            &self,
+            count: Option<usize>,
+        ) -> Result<Vec<Entry<P, T>>, async_graphql::Error> {
+            let mut entries = Vec::new();
+            self.for_each_entry_in_range_while(
+                |key, value| {
+                    entries.push(Entry { key, value });
+                    Ok(count.is_none_or(|count| entries.len() < count))
+                },
+                ..,
+            )
+            .await?;
+            if let Some(count) = count {
+                entries.truncate(count);
+            }
+            Ok(entries)
+        }
+    }
+}
@@ -0,0 +1,637 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Merkle proofs for the entries of hashable containers.
+//!
+//! The hash of a [`MerkleMapView`][map] or of a [`MerkleCollectionView`][collection] is
+//! the root of a binary Merkle tree whose leaves are the entries of the container, in the
+//! lexicographic order of their short keys. This allows proving that an entry is present
+//! in the container, with a given value, or that it is absent, with a number of hashes
+//! that is logarithmic in the size of the container. The other containers keep their
+//! flat hashes, so using these views is opt-in.
+//!
+//! There are no proofs for the entries of a [`MapView`][map_view] or of a
+//! [`CollectionView`][collection_view]: their hash is computed sequentially over all the
+//! entries, so checking one of them would require all the others, and changing it would
+//! change the state hashes that are already committed. Since the Merkle views store their
+//! entries in the same way, existing state is migrated by changing the type of a field
+//! and converting the loaded view with `From`, without rewriting any entry: in a view
+//! deriving `View`, with `#[view(migrate_from = MapView<..>, migrate_with = ..)]` and a
+//! new layout version. A memoized hash, e.g. of a `HashedMapView`, is discarded with
+//! [`WrappedHashableContainerView::map_inner`][map_inner]. The hash of the migrated view
+//! changes, so the migration must happen at the same time on all the nodes computing it.
+//!
+//! An [`EntryProof`] recomputes the hash of the container it was produced from. For a
+//! root view, a [`FieldProof`] then recomputes the hash of the root view from the hash of
+//! one of its fields, and [`FieldProof::crypto_hash`] yields the value of
+//! [`CryptoHashView::crypto_hash`][crypto_hash]. The hash of a [`RegisterView`][register]
+//! is recomputed by [`register_hash`] from the value itself.
+//!
+//! [map]: crate::map_view::MerkleMapView
+//! [collection]: crate::collection_view::MerkleCollectionView
+//! [map_view]: crate::map_view::MapView
+//! [collection_view]: crate::collection_view::CollectionView
+//! [map_inner]: crate::hashable_wrapper::WrappedHashableContainerView::map_inner
+//! [register]: crate::register_view::RegisterView
+//! [crypto_hash]: crate::views::CryptoHashView::crypto_hash
+
+use linera_base::crypto::CryptoHash;
+use serde::{Deserialize, Serialize};
+use sha3::Digest as _;
+
+use crate::{common::HasherOutput, ViewError};
+
+/// The domain separator of the hash of a leaf.
+const LEAF_TAG: u8 = 0;
+
+/// The domain separator of the hash of an inner node.
+const NODE_TAG: u8 = 1;
+
+/// An error when verifying a proof.
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+    /// The proof shows that the entry is absent but a value was given, or conversely.
+    #[error("the proof does not match the presence or absence of the entry")]
+    UnexpectedMembership,
+
+    /// The Merkle path of a leaf does not match the size of the container.
+    #[error("invalid Merkle path for the leaf at position {0}")]
+    InvalidPath(u32),
+
+    /// The neighbors of an absent entry do not surround its key.
+    #[error("the neighbors in the proof do not surround the key")]
+    InvalidNeighbors,
+
+    /// The neighbors of an absent entry lead to different hashes.
+    #[error("the neighbors in the proof lead to different hashes")]
+    InconsistentNeighbors,
+
+    /// The position of the field is not smaller than the number of fields.
+    #[error("field {position} is out of range for a view with {count} fields")]
+    FieldOutOfRange {
+        /// The position of the field.
+        position: u32,
+        /// The number of fields.
+        count: usize,
+    },
+
+    /// BCS serialization error.
+    #[error(transparent)]
+    BcsError(#[from] bcs::Error),
+}
+
+/// A proof that an entry is present in, or absent from, a map or a collection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryProof {
+    /// The number of entries in the container.
+    count: u32,
+    /// The evidence for the entry.
+    kind: EntryProofKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum EntryProofKind {
+    /// The entry is the leaf at the given position.
+    Member {
+        position: u32,
+        siblings: Vec<HasherOutput>,
+    },
+    /// The entry is absent: the neighbors are the adjacent leaves surrounding its key,
+    /// or the first or last leaf, or nothing if the container is empty.
+    NonMember { neighbors: Vec<Neighbor> },
+}
+
+/// A leaf of the Merkle tree, revealed to prove the absence of another key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Neighbor {
+    position: u32,
+    key: Vec<u8>,
+    value_hash: Vec<u8>,
+    siblings: Vec<HasherOutput>,
+}
+
+impl EntryProof {
+    /// Returns the number of entries in the container the proof was produced from.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns whether this proves that the entry is present.
+    pub fn is_membership(&self) -> bool {
+        matches!(self.kind, EntryProofKind::Member { .. })
+    }
+
+    /// Recomputes the hash of the container, assuming that the entry with the given
+    /// short key has a value with the given hash, or is absent if `value_hash` is `None`.
+    ///
+    /// The proof is valid if the result is the expected hash of the container.
+    pub fn root_hash(
+        &self,
+        short_key: &[u8],
+        value_hash: Option<&[u8]>,
+    ) -> Result<HasherOutput, ProofError> {
+        let tree_root = match (&self.kind, value_hash) {
+            (EntryProofKind::Member { position, siblings }, Some(value_hash)) => {
+                let leaf = leaf_hash(short_key, value_hash)?;
+                Some(path_root(leaf, *position, self.count, siblings)?)
+            }
+            (EntryProofKind::NonMember { neighbors }, None) => {
+                self.check_neighbors(short_key, neighbors)?;
+                let mut tree_root = None;
+                for neighbor in neighbors {
+                    let leaf = leaf_hash(&neighbor.key, &neighbor.value_hash)?;
+                    let root = path_root(leaf, neighbor.position, self.count, &neighbor.siblings)?;
+                    if tree_root.is_some_and(|tree_root| tree_root != root) {
+                        return Err(ProofError::InconsistentNeighbors);
+                    }
+                    tree_root = Some(root);
+                }
+                tree_root
+            }
+            _ => return Err(ProofError::UnexpectedMembership),
+        };
+        Ok(finalize_root(self.count, tree_root.as_ref())?)
+    }
+
+    /// Recomputes the hash of a `MapView`, assuming that `index` has the given value, or
+    /// is absent if `value` is `None`.
+    pub fn map_root_hash<I, V>(
+        &self,
+        index: &I,
+        value: Option<&V>,
+    ) -> Result<HasherOutput, ProofError>
+    where
+        I: Serialize + ?Sized,
+        V: Serialize,
+    {
+        let short_key = bcs::to_bytes(index)?;
+        let value_hash = value
+            .map(|value| Ok::<_, ProofError>(bytes_hash(&bcs::to_bytes(value)?)))
+            .transpose()?;
+        self.root_hash(&short_key, value_hash.as_ref().map(AsRef::as_ref))
+    }
+
+    /// Recomputes the hash of a `CollectionView`, assuming that the subview at `index`
+    /// has the given hash, or is absent if `subview_hash` is `None`.
+    pub fn collection_root_hash<I>(
+        &self,
+        index: &I,
+        subview_hash: Option<&HasherOutput>,
+    ) -> Result<HasherOutput, ProofError>
+    where
+        I: Serialize + ?Sized,
+    {
+        let short_key = bcs::to_bytes(index)?;
+        self.root_hash(&short_key, subview_hash.map(AsRef::as_ref))
+    }
+
+    fn check_neighbors(&self, short_key: &[u8], neighbors: &[Neighbor]) -> Result<(), ProofError> {
+        let is_first = |next: &Neighbor| next.position == 0 && short_key < next.key.as_slice();
+        let is_last = |previous: &Neighbor| {
+            previous.position.checked_add(1) == Some(self.count)
+                && previous.key.as_slice() < short_key
+        };
+        let valid = match neighbors {
+            [] => self.count == 0,
+            [neighbor] => is_first(neighbor) || is_last(neighbor),
+            [previous, next] => {
+                previous.position.checked_add(1) == Some(next.position)
+                    && previous.key.as_slice() < short_key
+                    && short_key < next.key.as_slice()
+            }
+            _ => false,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(ProofError::InvalidNeighbors)
+        }
+    }
+}
+
+/// A proof that a view is a given field of a root view.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldProof {
+    /// The hashes of all the fields of the root view, in declaration order.
+    field_hashes: Vec<HasherOutput>,
+    /// The position of the proven field.
+    position: u32,
+}
+
+impl FieldProof {
+    /// Creates a proof for the field at `position`, given the hashes of all the fields of
+    /// the root view, as returned by their `HashableView::hash`.
+    pub fn new(field_hashes: Vec<HasherOutput>, position: u32) -> Self {
+        FieldProof {
+            field_hashes,
+            position,
+        }
+    }
+
+    /// Recomputes the `HashableView::hash` of the root view, assuming that the proven
+    /// field has the given hash.
+    pub fn root_hash(&self, field_hash: &HasherOutput) -> Result<HasherOutput, ProofError> {
+        let count = self.field_hashes.len();
+        let position = usize::try_from(self.position)
+            .ok()
+            .filter(|position| *position < count)
+            .ok_or(ProofError::FieldOutOfRange {
+                position: self.position,
+                count,
+            })?;
+        let mut hasher = sha3::Sha3_256::default();
+        for (i, hash) in self.field_hashes.iter().enumerate() {
+            let hash = if i == position { field_hash } else { hash };
+            hasher.update(hash);
+        }
+        Ok(hasher.finalize())
+    }
+
+    /// Recomputes the `CryptoHashView::crypto_hash` of the root view, assuming that the
+    /// proven field has the given hash. The `type_name` is the name of the type of the
+    /// root view.
+    pub fn crypto_hash(
+        &self,
+        type_name: &str,
+        field_hash: &HasherOutput,
+    ) -> Result<CryptoHash, ProofError> {
+        let hash = self.root_hash(field_hash)?;
+        // This matches the `BcsHashable` implementation generated by the derive macro.
+        let mut hasher = sha3::Keccak256::default();
+        hasher.update(type_name);
+        hasher.update("Hash::");
+        bcs::serialize_into(&mut hasher, &hash)?;
+        let bytes: [u8; 32] = hasher.finalize().into();
+        Ok(CryptoHash::from(bytes))
+    }
+}
+
+/// Computes the hash of a `RegisterView` holding the given value.
+pub fn register_hash<T: Serialize + ?Sized>(value: &T) -> Result<HasherOutput, ViewError> {
+    let mut hasher = sha3::Sha3_256::default();
+    bcs::serialize_into(&mut hasher, value)?;
+    Ok(hasher.finalize())
+}
+
+/// Computes the hash of the serialized value of a map entry.
+pub fn bytes_hash(bytes: &[u8]) -> HasherOutput {
+    let mut hasher = sha3::Sha3_256::default();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Computes the hash of the leaf for an entry.
+fn leaf_hash(short_key: &[u8], value_hash: &[u8]) -> Result<HasherOutput, bcs::Error> {
+    let mut hasher = sha3::Sha3_256::default();
+    hasher.update(&[LEAF_TAG]);
+    bcs::serialize_into(&mut hasher, short_key)?;
+    hasher.update(value_hash);
+    Ok(hasher.finalize())
+}
+
+fn node_hash(left: &HasherOutput, right: &HasherOutput) -> HasherOutput {
+    let mut hasher = sha3::Sha3_256::default();
+    hasher.update(&[NODE_TAG]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize()
+}
+
+/// Combines the number of entries and the root of the tree into the container hash.
+fn finalize_root(count: u32, tree_root: Option<&HasherOutput>) -> Result<HasherOutput, bcs::Error> {
+    let mut hasher = sha3::Sha3_256::default();
+    bcs::serialize_into(&mut hasher, &count)?;
+    if let Some(tree_root) = tree_root {
+        hasher.update(tree_root);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Recomputes the root of the tree from a leaf and its Merkle path.
+///
+/// At each level of the tree, the nodes are paired from the left and a node without a
+/// sibling is moved up unchanged.
+fn path_root(
+    leaf: HasherOutput,
+    position: u32,
+    count: u32,
+    siblings: &[HasherOutput],
+) -> Result<HasherOutput, ProofError> {
+    if position >= count {
+        return Err(ProofError::InvalidPath(position));
+    }
+    let mut siblings = siblings.iter();
+    let mut hash = leaf;
+    let (mut index, mut width) = (position, count);
+    while width > 1 {
+        if index % 2 == 1 {
+            let sibling = siblings.next().ok_or(ProofError::InvalidPath(position))?;
+            hash = node_hash(sibling, &hash);
+        } else if index + 1 < width {
+            let sibling = siblings.next().ok_or(ProofError::InvalidPath(position))?;
+            hash = node_hash(&hash, sibling);
+        }
+        index /= 2;
+        width = width.div_ceil(2);
+    }
+    if siblings.next().is_some() {
+        return Err(ProofError::InvalidPath(position));
+    }
+    Ok(hash)
+}
+
+/// The root of a complete subtree built by a [`MerkleTreeBuilder`].
+struct Peak {
+    height: u32,
+    /// The positions of the first leaf of the subtree and of the leaf after it.
+    start: u32,
+    end: u32,
+    hash: HasherOutput,
+}
+
+/// A leaf whose Merkle path is recorded while building the tree.
+struct TrackedLeaf {
+    position: u32,
+    key: Vec<u8>,
+    value_hash: Vec<u8>,
+    siblings: Vec<HasherOutput>,
+}
+
+impl TrackedLeaf {
+    fn is_in(&self, peak: &Peak) -> bool {
+        peak.start <= self.position && self.position < peak.end
+    }
+}
+
+/// Builds the Merkle tree of a container from its entries, given one at a time in key
+/// order, and records the Merkle paths of some of them.
+///
+/// Only the roots of the complete subtrees built so far are kept, so the memory used is
+/// logarithmic in the number of entries. Folding these roots from the right yields the
+/// same tree as pairing the nodes of each level from the left.
+pub(crate) struct MerkleTreeBuilder {
+    count: u32,
+    /// The roots of the complete subtrees, by decreasing height.
+    peaks: Vec<Peak>,
+    tracked: Vec<TrackedLeaf>,
+}
+
+impl MerkleTreeBuilder {
+    /// Creates a builder recording the Merkle paths of the leaves at the given positions.
+    pub(crate) fn new(tracked_positions: impl IntoIterator<Item = u32>) -> Self {
+        let tracked = tracked_positions
+            .into_iter()
+            .map(|position| TrackedLeaf {
+                position,
+                key: Vec::new(),
+                value_hash: Vec::new(),
+                siblings: Vec::new(),
+            })
+            .collect();
+        MerkleTreeBuilder {
+            count: 0,
+            peaks: Vec::new(),
+            tracked,
+        }
+    }
+
+    /// Adds the next entry, given by its short key and the hash of its value.
+    pub(crate) fn push(&mut self, short_key: &[u8], value_hash: &[u8]) -> Result<(), bcs::Error> {
+        let position = self.count;
+        for leaf in &mut self.tracked {
+            if leaf.position == position {
+                leaf.key = short_key.to_vec();
+                leaf.value_hash = value_hash.to_vec();
+            }
+        }
+        self.peaks.push(Peak {
+            height: 0,
+            start: position,
+            end: position + 1,
+            hash: leaf_hash(short_key, value_hash)?,
+        });
+        self.count += 1;
+        while let [.., left, right] = self.peaks.as_slice() {
+            if left.height != right.height {
+                break;
+            }
+            let right = self.peaks.pop().expect("two peaks");
+            let left = self.peaks.pop().expect("two peaks");
+            let peak = self.join(left, right);
+            self.peaks.push(peak);
+        }
+        Ok(())
+    }
+
+    /// Joins two adjacent subtrees, recording the siblings of the tracked leaves.
+    fn join(&mut self, left: Peak, right: Peak) -> Peak {
+        for leaf in &mut self.tracked {
+            if leaf.is_in(&left) {
+                leaf.siblings.push(right.hash);
+            } else if leaf.is_in(&right) {
+                leaf.siblings.push(left.hash);
+            }
+        }
+        Peak {
+            height: left.height.max(right.height) + 1,
+            start: left.start,
+            end: right.end,
+            hash: node_hash(&left.hash, &right.hash),
+        }
+    }
+
+    /// Returns the root of the tree, if the container is not empty.
+    fn tree_root(&mut self) -> Option<HasherOutput> {
+        let mut root = self.peaks.pop()?;
+        while let Some(peak) = self.peaks.pop() {
+            root = self.join(peak, root);
+        }
+        Some(root.hash)
+    }
+
+    /// Returns the hash of the container.
+    pub(crate) fn finish(mut self) -> Result<HasherOutput, bcs::Error> {
+        let tree_root = self.tree_root();
+        finalize_root(self.count, tree_root.as_ref())
+    }
+
+    /// Returns the proof for the entry located by `locator`. The builder must have been
+    /// created with the positions of [`EntryLocator::tracked_positions`].
+    pub(crate) fn into_proof(mut self, locator: &EntryLocator) -> Result<EntryProof, ViewError> {
+        // Joining the remaining subtrees completes the Merkle paths.
+        self.tree_root();
+        if self.count != locator.count {
+            return Err(ViewError::InconsistentEntries);
+        }
+        let count = self.count;
+        let kind = match locator.member {
+            Some(position) => {
+                let leaf = self
+                    .tracked
+                    .into_iter()
+                    .find(|leaf| leaf.position == position)
+                    .ok_or(ViewError::InconsistentEntries)?;
+                EntryProofKind::Member {
+                    position,
+                    siblings: leaf.siblings,
+                }
+            }
+            None => EntryProofKind::NonMember {
+                neighbors: self
+                    .tracked
+                    .into_iter()
+                    .map(|leaf| Neighbor {
+                        position: leaf.position,
+                        key: leaf.key,
+                        value_hash: leaf.value_hash,
+                        siblings: leaf.siblings,
+                    })
+                    .collect(),
+            },
+        };
+        Ok(EntryProof { count, kind })
+    }
+}
+
+/// Finds the position of an entry, or of its neighbors if it is absent, from the short
+/// keys of a container given one at a time in key order.
+pub(crate) struct EntryLocator<'a> {
+    short_key: &'a [u8],
+    count: u32,
+    member: Option<u32>,
+    /// The position of the first key after `short_key`.
+    successor: Option<u32>,
+}
+
+impl<'a> EntryLocator<'a> {
+    /// Creates a locator for the entry with the given short key.
+    pub(crate) fn new(short_key: &'a [u8]) -> Self {
+        EntryLocator {
+            short_key,
+            count: 0,
+            member: None,
+            successor: None,
+        }
+    }
+
+    /// Visits the next short key of the container.
+    pub(crate) fn visit(&mut self, key: &[u8]) {
+        if key == self.short_key {
+            self.member = Some(self.count);
+        } else if key > self.short_key && self.successor.is_none() {
+            self.successor = Some(self.count);
+        }
+        self.count += 1;
+    }
+
+    /// Returns the positions of the leaves whose Merkle paths make the proof.
+    pub(crate) fn tracked_positions(&self) -> Vec<u32> {
+        if let Some(position) = self.member {
+            return vec![position];
+        }
+        let successor = self.successor.unwrap_or(self.count);
+        let mut positions = Vec::new();
+        if let Some(predecessor) = successor.checked_sub(1) {
+            positions.push(predecessor);
+        }
+        if successor < self.count {
+            positions.push(successor);
+        }
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bytes_hash, finalize_root, leaf_hash, node_hash, EntryLocator, HasherOutput,
+        MerkleTreeBuilder, ProofError,
+    };
+
+    fn entries(keys: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        keys.iter()
+            .map(|key| (vec![*key], bytes_hash(&[*key, *key]).to_vec()))
+            .collect()
+    }
+
+    fn container_hash(entries: &[(Vec<u8>, Vec<u8>)]) -> anyhow::Result<HasherOutput> {
+        let mut builder = MerkleTreeBuilder::new([]);
+        for (key, value_hash) in entries {
+            builder.push(key, value_hash)?;
+        }
+        Ok(builder.finish()?)
+    }
+
+    fn prove(
+        entries: &[(Vec<u8>, Vec<u8>)],
+        short_key: &[u8],
+    ) -> anyhow::Result<super::EntryProof> {
+        let mut locator = EntryLocator::new(short_key);
+        for (key, _) in entries {
+            locator.visit(key);
+        }
+        let mut builder = MerkleTreeBuilder::new(locator.tracked_positions());
+        for (key, value_hash) in entries {
+            builder.push(key, value_hash)?;
+        }
+        Ok(builder.into_proof(&locator)?)
+    }
+
+    #[test]
+    fn test_streaming_tree_matches_levels() -> anyhow::Result<()> {
+        for size in 0..40u8 {
+            let entries = entries(&(0..size).collect::<Vec<_>>());
+            // Pair the nodes of each level from the left, moving up a node without sibling.
+            let mut level = entries
+                .iter()
+                .map(|(key, value_hash)| leaf_hash(key, value_hash))
+                .collect::<Result<Vec<_>, _>>()?;
+            while level.len() > 1 {
+                level = level
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [left, right] => node_hash(left, right),
+                        [single] => *single,
+                        _ => unreachable!(),
+                    })
+                    .collect();
+            }
+            let expected = finalize_root(u32::from(size), level.first())?;
+            assert_eq!(container_hash(&entries)?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_proofs() -> anyhow::Result<()> {
+        for size in 0..12u8 {
+            let keys = (0..size).map(|i| 2 * i + 1).collect::<Vec<_>>();
+            let entries = entries(&keys);
+            let hash = container_hash(&entries)?;
+            for key in 0..(2 * size + 2) {
+                let proof = prove(&entries, &[key])?;
+                if key % 2 == 1 {
+                    assert!(proof.is_membership());
+                    let value_hash = bytes_hash(&[key, key]);
+                    assert_eq!(proof.root_hash(&[key], Some(value_hash.as_slice()))?, hash);
+                    let wrong_hash = bytes_hash(&[key]);
+                    assert_ne!(proof.root_hash(&[key], Some(wrong_hash.as_slice()))?, hash);
+                    assert!(matches!(
+                        proof.root_hash(&[key], None),
+                        Err(ProofError::UnexpectedMembership)
+                    ));
+                } else {
+                    assert!(!proof.is_membership());
+                    assert_eq!(proof.root_hash(&[key], None)?, hash);
+                    // The same neighbors do not prove the absence of an existing key.
+                    for other in [key.wrapping_sub(1), key + 1] {
+                        if keys.contains(&other) {
+                            assert!(proof.root_hash(&[other], None).is_err());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{borrow::Borrow, collections::BTreeMap, marker::PhantomData, mem};
+use std::{borrow::Borrow, collections::BTreeMap, marker::PhantomData, mem, ops::RangeBounds};
 
 #[cfg(with_metrics)]
 use linera_base::prometheus_util::MeasureLatency as _;
@@ -12,6 +12,7 @@ use crate::{
     common::{CustomSerialize, HasherOutput, Update},
     context::{BaseKey, Context},
     hashable_wrapper::WrappedHashableContainerView,
+    pagination::{Order, RangeScan},
     store::ReadableKeyValueStore as _,
     views::{ClonableView, HashableView, Hasher, ReplaceContext, View, ViewError},
 };
@@ -273,47 +274,95 @@ impl<C: Context> ByteSetView<C> {
     /// assert_eq!(count, 2);
     /// # })
     /// ```
-    pub async fn for_each_key_while<F>(&self, mut f: F) -> Result<(), ViewError>
+    pub async fn for_each_key_while<F>(&self, f: F) -> Result<(), ViewError>
     where
         F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
     {
-        let mut updates = self.updates.iter();
-        let mut update = updates.next();
+        self.for_each_key_in_range_while(f, .., Order::Ascending)
+            .await
+    }
+
+    /// Applies a function f on each index (aka key) in the range. Keys are visited in
+    /// the lexicographic order, or in the reverse order if `order` is descending. If the
+    /// function returns false, then the loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::{context::MemoryContext, set_view::ByteSetView};
+    /// # use linera_views::pagination::Order;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut set = ByteSetView::load(context).await.unwrap();
+    /// set.insert(vec![0, 1]);
+    /// set.insert(vec![0, 2]);
+    /// set.insert(vec![3]);
+    /// let mut keys = Vec::new();
+    /// set.for_each_key_in_range_while(
+    ///     |key| {
+    ///         keys.push(key.to_vec());
+    ///         Ok(true)
+    ///     },
+    ///     vec![0, 2]..,
+    ///     Order::Descending,
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(keys, vec![vec![3], vec![0, 2]]);
+    /// # })
+    /// ```
+    pub async fn for_each_key_in_range_while<F>(
+        &self,
+        mut f: F,
+        range: impl RangeBounds<Vec<u8>>,
+        order: Order,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
+    {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        let mut scan = RangeScan::new(range, order);
+        while let Some(prefix) = scan.next_prefix() {
+            for key in scan.select(self.keys_by_prefix(prefix).await?) {
+                if !f(&key)? {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the keys starting with `prefix`, in the lexicographic order.
+    async fn keys_by_prefix(&self, prefix: Vec<u8>) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = Vec::new();
+        let mut updates = self
+            .updates
+            .range::<Vec<u8>, _>(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .peekable();
         if !self.delete_storage_first {
-            let base = &self.context.base_key().bytes;
-            for index in self.context.store().find_keys_by_prefix(base).await? {
-                loop {
-                    match update {
-                        Some((key, value)) if key <= &index => {
-                            if let Update::Set(_) = value {
-                                if !f(key)? {
-                                    return Ok(());
-                                }
-                            }
-                            update = updates.next();
-                            if key == &index {
-                                break;
-                            }
-                        }
-                        _ => {
-                            if !f(&index)? {
-                                return Ok(());
-                            }
-                            break;
-                        }
+            let base = self.context.base_key().base_index(&prefix);
+            for suffix in self.context.store().find_keys_by_prefix(&base).await? {
+                let key = [prefix.as_slice(), &suffix].concat();
+                let mut is_removed = false;
+                while let Some((update_key, update)) =
+                    updates.next_if(|(update_key, _)| **update_key <= key)
+                {
+                    if *update_key == key {
+                        is_removed = matches!(update, Update::Removed);
+                    } else if let Update::Set(_) = update {
+                        keys.push(update_key.clone());
                     }
                 }
+                if !is_removed {
+                    keys.push(key);
+                }
             }
         }
-        while let Some((key, value)) = update {
-            if let Update::Set(_) = value {
-                if !f(key)? {
-                    return Ok(());
-                }
+        for (key, update) in updates {
+            if let Update::Set(_) = update {
+                keys.push(key.clone());
             }
-            update = updates.next();
         }
-        Ok(())
+        Ok(keys)
     }
 
     /// Applies a function f on each serialized index (aka key). Keys are visited in a
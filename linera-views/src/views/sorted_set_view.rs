@@ -0,0 +1,335 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{marker::PhantomData, ops::RangeBounds};
+
+use crate::{
+    batch::Batch,
+    common::{HasherOutput, OrderedSerialize},
+    context::Context,
+    hashable_wrapper::WrappedHashableContainerView,
+    pagination::Order,
+    set_view::ByteSetView,
+    views::{ClonableView, HashableView, Hasher, ReplaceContext, View, ViewError},
+};
+
+/// A [`View`] implementing a set whose elements are sorted by their natural order,
+/// using the [`OrderedSerialize`] serialization of the elements as keys.
+#[derive(Debug)]
+pub struct SortedSetView<C, I> {
+    set: ByteSetView<C>,
+    _phantom: PhantomData<I>,
+}
+
+impl<C: Context, I: Send + Sync, C2: Context> ReplaceContext<C2> for SortedSetView<C, I> {
+    type Target = SortedSetView<C2, I>;
+
+    async fn with_context(
+        &mut self,
+        ctx: impl FnOnce(&Self::Context) -> C2 + Clone,
+    ) -> Self::Target {
+        SortedSetView {
+            set: self.set.with_context(ctx).await,
+            _phantom: self._phantom,
+        }
+    }
+}
+
+impl<C: Context, I: Send + Sync> View for SortedSetView<C, I> {
+    const NUM_INIT_KEYS: usize = ByteSetView::<C>::NUM_INIT_KEYS;
+
+    type Context = C;
+
+    fn context(&self) -> &C {
+        self.set.context()
+    }
+
+    fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
+        ByteSetView::<C>::pre_load(context)
+    }
+
+    fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let set = ByteSetView::post_load(context, values)?;
+        Ok(Self {
+            set,
+            _phantom: PhantomData,
+        })
+    }
+
+    async fn load(context: C) -> Result<Self, ViewError> {
+        Self::post_load(context, &[])
+    }
+
+    fn rollback(&mut self) {
+        self.set.rollback()
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        self.set.has_pending_changes().await
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        self.set.flush(batch)
+    }
+
+    fn clear(&mut self) {
+        self.set.clear()
+    }
+}
+
+impl<C: Context, I: Send + Sync> ClonableView for SortedSetView<C, I> {
+    fn clone_unchecked(&mut self) -> Self {
+        SortedSetView {
+            set: self.set.clone_unchecked(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Context, I: OrderedSerialize> SortedSetView<C, I> {
+    /// Inserts a value. If already present then no effect.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::sorted_set_view::SortedSetView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut set = SortedSetView::<_, i32>::load(context).await.unwrap();
+    /// set.insert(&-34);
+    /// assert_eq!(set.contains(&-34).await.unwrap(), true);
+    /// # })
+    /// ```
+    pub fn insert(&mut self, index: &I) {
+        self.set.insert(index.to_ordered_bytes());
+    }
+
+    /// Removes a value. If absent then nothing is done.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::sorted_set_view::SortedSetView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut set = SortedSetView::<_, i32>::load(context).await.unwrap();
+    /// set.insert(&-34);
+    /// set.remove(&-34);
+    /// assert_eq!(set.contains(&-34).await.unwrap(), false);
+    /// # })
+    /// ```
+    pub fn remove(&mut self, index: &I) {
+        self.set.remove(index.to_ordered_bytes());
+    }
+
+    /// Obtains the extra data.
+    pub fn extra(&self) -> &C::Extra {
+        self.set.extra()
+    }
+
+    /// Returns true if the given index exists in the set.
+    pub async fn contains(&self, index: &I) -> Result<bool, ViewError> {
+        self.set.contains(&index.to_ordered_bytes()).await
+    }
+}
+
+impl<C: Context, I: OrderedSerialize + Send> SortedSetView<C, I> {
+    /// Returns the list of indices in the set, in increasing order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::sorted_set_view::SortedSetView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut set = SortedSetView::<_, i32>::load(context).await.unwrap();
+    /// set.insert(&3);
+    /// set.insert(&-34);
+    /// assert_eq!(set.indices().await.unwrap(), vec![-34, 3]);
+    /// # })
+    /// ```
+    pub async fn indices(&self) -> Result<Vec<I>, ViewError> {
+        self.range(..).await
+    }
+
+    /// Returns the number of entries in the set.
+    pub async fn count(&self) -> Result<usize, ViewError> {
+        self.set.count().await
+    }
+
+    /// Applies a function f on each index in the range, in increasing order or in
+    /// decreasing order if `order` is descending. If the function returns false, then
+    /// the loop ends prematurely.
+    pub async fn for_each_index_in_range_while<F>(
+        &self,
+        mut f: F,
+        range: impl RangeBounds<I>,
+        order: Order,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(I) -> Result<bool, ViewError> + Send,
+    {
+        let range = (
+            range.start_bound().map(I::to_ordered_bytes),
+            range.end_bound().map(I::to_ordered_bytes),
+        );
+        self.set
+            .for_each_key_in_range_while(|key| f(I::from_ordered_bytes(key)?), range, order)
+            .await
+    }
+
+    /// Returns the indices of the set in the range, in increasing order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::sorted_set_view::SortedSetView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut set = SortedSetView::<_, u64>::load(context).await.unwrap();
+    /// for i in [1, 256, 3, 1000] {
+    ///     set.insert(&i);
+    /// }
+    /// assert_eq!(set.range(2..=1000).await.unwrap(), vec![3, 256, 1000]);
+    /// # })
+    /// ```
+    pub async fn range(&self, range: impl RangeBounds<I>) -> Result<Vec<I>, ViewError> {
+        let mut indices = Vec::new();
+        self.for_each_index_in_range_while(
+            |index| {
+                indices.push(index);
+                Ok(true)
+            },
+            range,
+            Order::Ascending,
+        )
+        .await?;
+        Ok(indices)
+    }
+
+    /// Returns the smallest index of the set, if any.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::sorted_set_view::SortedSetView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut set = SortedSetView::<_, String>::load(context).await.unwrap();
+    /// set.insert(&"b".to_string());
+    /// set.insert(&"ab".to_string());
+    /// assert_eq!(set.first().await.unwrap(), Some("ab".to_string()));
+    /// assert_eq!(set.last().await.unwrap(), Some("b".to_string()));
+    /// # })
+    /// ```
+    pub async fn first(&self) -> Result<Option<I>, ViewError> {
+        self.first_in_order(Order::Ascending).await
+    }
+
+    /// Returns the largest index of the set, if any.
+    pub async fn last(&self) -> Result<Option<I>, ViewError> {
+        self.first_in_order(Order::Descending).await
+    }
+
+    async fn first_in_order(&self, order: Order) -> Result<Option<I>, ViewError> {
+        let mut first = None;
+        self.for_each_index_in_range_while(
+            |index| {
+                first = Some(index);
+                Ok(false)
+            },
+            ..,
+            order,
+        )
+        .await?;
+        Ok(first)
+    }
+
+    /// Removes and returns the smallest index of the set, if any.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::sorted_set_view::SortedSetView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut set = SortedSetView::<_, u8>::load(context).await.unwrap();
+    /// set.insert(&7);
+    /// set.insert(&5);
+    /// set.insert(&6);
+    /// assert_eq!(set.pop_min().await.unwrap(), Some(5));
+    /// assert_eq!(set.pop_max().await.unwrap(), Some(7));
+    /// assert_eq!(set.indices().await.unwrap(), vec![6]);
+    /// # })
+    /// ```
+    pub async fn pop_min(&mut self) -> Result<Option<I>, ViewError> {
+        let first = self.first().await?;
+        if let Some(index) = &first {
+            self.remove(index);
+        }
+        Ok(first)
+    }
+
+    /// Removes and returns the largest index of the set, if any.
+    pub async fn pop_max(&mut self) -> Result<Option<I>, ViewError> {
+        let last = self.last().await?;
+        if let Some(index) = &last {
+            self.remove(index);
+        }
+        Ok(last)
+    }
+}
+
+impl<C, I> HashableView for SortedSetView<C, I>
+where
+    Self: View,
+    ByteSetView<C>: HashableView,
+{
+    type Hasher = <ByteSetView<C> as HashableView>::Hasher;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.set.hash_mut().await
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.set.hash().await
+    }
+}
+
+/// Type wrapping `SortedSetView` while memoizing the hash.
+pub type HashedSortedSetView<C, I> =
+    WrappedHashableContainerView<C, SortedSetView<C, I>, HasherOutput>;
+
+#[cfg(with_graphql)]
+mod graphql {
+    use std::borrow::Cow;
+
+    use super::SortedSetView;
+    use crate::{common::OrderedSerialize, context::Context};
+
+    impl<C: Context, I: async_graphql::OutputType> async_graphql::OutputType for SortedSetView<C, I>
+    where
+        I: OrderedSerialize + Clone + Send + Sync,
+    {
+        fn type_name() -> Cow<'static, str> {
+            format!("[{}]", I::qualified_type_name()).into()
+        }
+
+        fn qualified_type_name() -> String {
+            format!("[{}]!", I::qualified_type_name())
+        }
+
+        fn create_type_info(registry: &mut async_graphql::registry::Registry) -> String {
+            I::create_type_info(registry);
+            Self::qualified_type_name()
+        }
+
+        async fn resolve(
+            &self,
+            ctx: &async_graphql::ContextSelectionSet<'_>,
+            field: &async_graphql::Positioned<async_graphql::parser::types::Field>,
+        ) -> async_graphql::ServerResult<async_graphql::Value> {
+            let indices = self
+                .indices()
+                .await
+                .map_err(|e| async_graphql::Error::from(e).into_server_error(ctx.item.pos))?;
+            let indices_len = indices.len();
+            async_graphql::resolver_utils::resolve_list(ctx, field, indices, Some(indices_len))
+                .await
+        }
+    }
+}
@@ -5,6 +5,10 @@ use std::marker::PhantomData;
 
 #[cfg(with_dynamodb)]
 use linera_views::dynamo_db::DynamoDbDatabase;
+#[cfg(with_postgres)]
+use linera_views::postgres_db::PostgresDatabase;
+#[cfg(with_redb)]
+use linera_views::redb_db::RedbDatabase;
 #[cfg(with_rocksdb)]
 use linera_views::rocks_db::RocksDbDatabase;
 #[cfg(with_scylladb)]
@@ -18,8 +22,10 @@ use test_case::test_case;
 
 #[test_case(PhantomData::<MemoryDatabase>; "MemoryDatabase")]
 #[cfg_attr(with_rocksdb, test_case(PhantomData::<RocksDbDatabase>; "RocksDbDatabase"))]
+#[cfg_attr(with_redb, test_case(PhantomData::<RedbDatabase>; "RedbDatabase"))]
 #[cfg_attr(with_dynamodb, test_case(PhantomData::<DynamoDbDatabase>; "DynamoDbDatabase"))]
 #[cfg_attr(with_scylladb, test_case(PhantomData::<ScyllaDbDatabase>; "ScyllaDbDatabase"))]
+#[cfg_attr(with_postgres, test_case(PhantomData::<PostgresDatabase>; "PostgresDatabase"))]
 #[tokio::test]
 async fn namespace_admin_test_cases<K: TestKeyValueDatabase>(_view_type: PhantomData<K>)
 where
@@ -30,8 +36,10 @@ where
 
 #[test_case(PhantomData::<MemoryDatabase>; "MemoryDatabase")]
 #[cfg_attr(with_rocksdb, test_case(PhantomData::<RocksDbDatabase>; "RocksDbDatabase"))]
+#[cfg_attr(with_redb, test_case(PhantomData::<RedbDatabase>; "RedbDatabase"))]
 #[cfg_attr(with_dynamodb, test_case(PhantomData::<DynamoDbDatabase>; "DynamoDbDatabase"))]
 #[cfg_attr(with_scylladb, test_case(PhantomData::<ScyllaDbDatabase>; "ScyllaDbDatabase"))]
+#[cfg_attr(with_postgres, test_case(PhantomData::<PostgresDatabase>; "PostgresDatabase"))]
 #[tokio::test]
 async fn root_key_admin_test_cases<K: TestKeyValueDatabase>(_view_type: PhantomData<K>)
 where
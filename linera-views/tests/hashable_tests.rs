@@ -3,11 +3,15 @@
 
 use anyhow::Result;
 use linera_views::{
+    collection_view::MerkleCollectionView,
     common::HasherOutput,
-    context::MemoryContext,
+    context::{Context, MemoryContext},
     hashable_wrapper::WrappedHashableContainerView,
+    map_view::{HashedMapView, HashedMerkleMapView, MerkleMapView},
+    proof::{register_hash, FieldProof},
     register_view::{HashedRegisterView, RegisterView},
-    views::{HashableView, View},
+    views::{CryptoHashView, HashableView, RootView, View},
+    ViewError,
 };
 use linera_views_derive::CryptoHashRootView;
 
@@ -41,3 +45,115 @@ async fn check_hashable_hash() -> Result<()> {
     assert_eq!(hash0, view.hash().await?);
     Ok(())
 }
+
+#[derive(CryptoHashRootView)]
+struct ProvenState<C> {
+    pub owner: RegisterView<C, String>,
+    pub balances: MerkleMapView<C, u32, u64>,
+    pub accounts: MerkleCollectionView<C, u32, MerkleMapView<C, String, u64>>,
+}
+
+#[tokio::test]
+async fn check_entry_proofs() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut state = ProvenState::load(context).await?;
+    state.owner.set("Alice".to_string());
+    for i in 0..10u32 {
+        state.balances.insert(&(3 * i), u64::from(i) * 100)?;
+        let account = state.accounts.load_entry_mut(&(3 * i)).await?;
+        account.insert("savings", u64::from(i))?;
+    }
+    let crypto_hash = state.crypto_hash().await?;
+    let field_hashes = vec![
+        state.owner.hash().await?,
+        state.balances.hash().await?,
+        state.accounts.hash().await?,
+    ];
+
+    // The register is proven by its value.
+    let owner_proof = FieldProof::new(field_hashes.clone(), 0);
+    let owner_hash = register_hash("Alice")?;
+    assert_eq!(
+        owner_proof.crypto_hash("ProvenState", &owner_hash)?,
+        crypto_hash
+    );
+
+    // Present and absent entries of a map.
+    let balances_proof = FieldProof::new(field_hashes.clone(), 1);
+    let proof = state.balances.prove(&6).await?;
+    assert!(proof.is_membership());
+    let map_hash = proof.map_root_hash(&6u32, Some(&200u64))?;
+    assert_eq!(
+        balances_proof.crypto_hash("ProvenState", &map_hash)?,
+        crypto_hash
+    );
+    let wrong_hash = proof.map_root_hash(&6u32, Some(&300u64))?;
+    assert_ne!(
+        balances_proof.crypto_hash("ProvenState", &wrong_hash)?,
+        crypto_hash
+    );
+    let proof = state.balances.prove(&7).await?;
+    assert!(!proof.is_membership());
+    let map_hash = proof.map_root_hash::<_, u64>(&7u32, None)?;
+    assert_eq!(
+        balances_proof.crypto_hash("ProvenState", &map_hash)?,
+        crypto_hash
+    );
+    assert!(proof.map_root_hash(&7u32, Some(&0u64)).is_err());
+
+    // An entry of a map nested in a collection.
+    let accounts_proof = FieldProof::new(field_hashes, 2);
+    let account = state.accounts.try_load_entry(&9).await?.unwrap();
+    let entry_proof = account.prove("savings").await?;
+    let account_hash = entry_proof.map_root_hash("savings", Some(&3u64))?;
+    let proof = state.accounts.prove(&9).await?;
+    let accounts_hash = proof.collection_root_hash(&9u32, Some(&account_hash))?;
+    assert_eq!(
+        accounts_proof.crypto_hash("ProvenState", &accounts_hash)?,
+        crypto_hash
+    );
+    Ok(())
+}
+
+#[derive(CryptoHashRootView)]
+struct LegacyState<C> {
+    pub balances: HashedMapView<C, u32, u64>,
+}
+
+#[derive(CryptoHashRootView)]
+#[view(version = 1)]
+struct MigratedState<C> {
+    #[view(migrate_from = HashedMapView<C, u32, u64>, migrate_with = migrate_to_merkle)]
+    pub balances: HashedMerkleMapView<C, u32, u64>,
+}
+
+fn migrate_to_merkle<C: Context>(
+    balances: HashedMapView<C, u32, u64>,
+    _version: u32,
+) -> Result<HashedMerkleMapView<C, u32, u64>, ViewError> {
+    Ok(balances.map_inner(MerkleMapView::from))
+}
+
+#[tokio::test]
+async fn check_migration_to_merkle_views() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut state = LegacyState::load(context.clone()).await?;
+    for i in 0..5u32 {
+        state.balances.insert(&i, u64::from(i))?;
+    }
+    let legacy_hash = state.balances.hash().await?;
+    state.save().await?;
+
+    // The entries are kept, but the memoized hash is recomputed as a Merkle root.
+    let mut state = MigratedState::load(context.clone()).await?;
+    assert_eq!(state.balances.get(&3).await?, Some(3));
+    let hash = state.balances.hash().await?;
+    assert_ne!(hash, legacy_hash);
+    let proof = state.balances.prove(&3).await?;
+    assert_eq!(proof.map_root_hash(&3u32, Some(&3u64))?, hash);
+    state.save().await?;
+
+    let state = MigratedState::load(context).await?;
+    assert_eq!(state.balances.hash().await?, hash);
+    Ok(())
+}
@@ -0,0 +1,224 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use linera_views::{
+    collection_view::CollectionView,
+    context::{Context, MemoryContext},
+    map_view::MapView,
+    register_view::RegisterView,
+    store::ReadableKeyValueStore as _,
+    test_utils::fixtures::{check_fixture, read_fixture, view_fixture},
+    views::{stored_version, RootView, VersionedView, View},
+    ViewError,
+};
+
+const COUNTER_VIEW_V0: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/counter_view_v0.txt"
+);
+
+/// The layout of `CounterView` before it was versioned.
+#[derive(RootView)]
+pub struct CounterViewV0<C> {
+    pub count: RegisterView<C, u64>,
+}
+
+#[derive(RootView)]
+#[view(version = 1)]
+pub struct CounterView<C> {
+    #[view(migrate_from = RegisterView<C, u64>, migrate_with = migrate_count)]
+    pub count: RegisterView<C, u128>,
+    pub label: RegisterView<C, String>,
+}
+
+fn migrate_count<C: Context>(
+    old_count: RegisterView<C, u64>,
+    version: u32,
+) -> Result<RegisterView<C, u128>, ViewError> {
+    assert_eq!(version, 0);
+    let mut count = RegisterView::new(old_count.context().clone())?;
+    count.set(u128::from(*old_count.get()));
+    Ok(count)
+}
+
+/// `CounterViewV0` with a version, but without any change to the fields.
+#[derive(RootView)]
+#[view(version = 1)]
+pub struct CounterViewV1<C> {
+    pub count: RegisterView<C, u64>,
+}
+
+#[derive(RootView)]
+pub struct CountersV0<C> {
+    pub counters: CollectionView<C, u8, CounterViewV0<C>>,
+}
+
+#[derive(RootView)]
+pub struct Counters<C> {
+    pub counters: CollectionView<C, u8, CounterView<C>>,
+}
+
+#[derive(RootView)]
+pub struct BalancesV0<C> {
+    pub balances: MapView<C, u8, u64>,
+}
+
+#[derive(RootView)]
+#[view(version = 1)]
+pub struct Balances<C> {
+    #[view(migrate_from = MapView<C, u8, u64>, migrate_async = migrate_balances)]
+    pub balances: MapView<C, u8, u128>,
+}
+
+async fn migrate_balances<C: Context>(
+    old_balances: MapView<C, u8, u64>,
+    version: u32,
+) -> Result<MapView<C, u8, u128>, ViewError> {
+    assert_eq!(version, 0);
+    let mut balances = MapView::new(old_balances.context().clone())?;
+    old_balances
+        .for_each_index_value(|index, balance| balances.insert(&index, u128::from(*balance)))
+        .await?;
+    Ok(balances)
+}
+
+#[tokio::test]
+async fn test_counter_view_fixture() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut view = CounterViewV0::load(context).await?;
+    view.count.set(7);
+    assert_eq!(
+        view_fixture(&mut view).await?,
+        read_fixture(COUNTER_VIEW_V0)?
+    );
+
+    let view = check_fixture::<CounterView<_>>(read_fixture(COUNTER_VIEW_V0)?).await?;
+    assert_eq!(*view.count.get(), 7);
+    assert!(view.label.get().is_empty());
+    assert_eq!(
+        stored_version(view.context()).await?,
+        CounterView::<MemoryContext<()>>::VERSION
+    );
+
+    // Layouts saved by a newer version cannot be loaded.
+    let keys = CounterViewV1::pre_load(view.context())?;
+    let mut values = vec![None; keys.len()];
+    values[0] = Some(bcs::to_bytes(&2u32)?);
+    assert!(matches!(
+        CounterViewV1::post_load(view.context().clone(), &values),
+        Err(ViewError::IncompatibleVersion {
+            stored: 2,
+            current: 1
+        })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_versioned_view_lifecycle() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut view = CounterView::load(context.clone()).await?;
+    view.label.set("counter".to_string());
+    view.save().await?;
+    assert_eq!(stored_version(&context).await?, 1);
+
+    let mut view = CounterView::load(context.clone()).await?;
+    assert_eq!(view.label.get(), "counter");
+    view.clear();
+    view.save().await?;
+    assert_eq!(stored_version(&context).await?, 0);
+
+    let mut view = CounterView::new(context.clone())?;
+    view.count.set(3);
+    view.save().await?;
+    let view = CounterView::load(context).await?;
+    assert_eq!(*view.count.get(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_version_saved_without_changes() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut view = CounterViewV0::load(context.clone()).await?;
+    view.count.set(7);
+    view.save().await?;
+
+    let mut view = CounterViewV1::load(context.clone()).await?;
+    assert!(!view.has_pending_changes().await);
+    view.save().await?;
+    assert_eq!(stored_version(&context).await?, 1);
+    let view = CounterViewV1::load(context).await?;
+    assert_eq!(*view.count.get(), 7);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_nested_view_migration() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut view = CountersV0::load(context.clone()).await?;
+    for index in 0..3u8 {
+        view.counters
+            .load_entry_mut(&index)
+            .await?
+            .count
+            .set(index.into());
+    }
+    view.save().await?;
+
+    // Each entry is migrated when it is loaded.
+    let mut view = Counters::load(context.clone()).await?;
+    assert!(!view.has_pending_changes().await);
+    let counter = view.counters.load_entry_mut(&1).await?;
+    assert_eq!(*counter.count.get(), 1);
+    assert!(counter.has_pending_changes().await);
+    view.save().await?;
+
+    let view = Counters::load(context).await?;
+    for index in 0..3u8 {
+        let counter = view.counters.try_load_entry(&index).await?.unwrap();
+        assert_eq!(*counter.count.get(), u128::from(index));
+        assert_eq!(
+            stored_version(counter.context()).await?,
+            u32::from(index == 1)
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_map_value_migration() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut view = BalancesV0::load(context.clone()).await?;
+    for index in 0..3u8 {
+        view.balances.insert(&index, u64::MAX - u64::from(index))?;
+    }
+    view.save().await?;
+
+    // The entries can only be rewritten by loading the view asynchronously.
+    let keys = Balances::<MemoryContext<()>>::pre_load(&context)?;
+    let values = context.store().read_multi_values_bytes(keys).await?;
+    assert!(matches!(
+        Balances::post_load(context.clone(), &values),
+        Err(ViewError::MigrationRequiresLoad(0))
+    ));
+
+    let mut view = Balances::load(context.clone()).await?;
+    assert!(view.has_pending_changes().await);
+    assert_eq!(view.balances.get(&1).await?, Some(u128::from(u64::MAX - 1)));
+    view.balances.insert(&3, u128::from(u64::MAX) + 1)?;
+    view.save().await?;
+    assert_eq!(stored_version(&context).await?, 1);
+
+    let view = Balances::load(context).await?;
+    assert_eq!(
+        view.balances.index_values().await?,
+        vec![
+            (0, u128::from(u64::MAX)),
+            (1, u128::from(u64::MAX - 1)),
+            (2, u128::from(u64::MAX - 2)),
+            (3, u128::from(u64::MAX) + 1),
+        ]
+    );
+    Ok(())
+}
@@ -3,11 +3,19 @@
 
 use linera_views::{
     batch::Batch,
+    change_feed::ChangeFeedDatabase,
     context::{Context as _, MemoryContext},
     key_value_store_view::ViewContainer,
+    lru_caching::{
+        LruCachingConfig, LruCachingMemoryDatabase, StorageCacheConfig,
+        DEFAULT_STORAGE_CACHE_CONFIG,
+    },
     memory::MemoryDatabase,
-    random::make_deterministic_rng,
-    store::{ReadableKeyValueStore as _, TestKeyValueDatabase as _, WritableKeyValueStore as _},
+    random::{generate_test_namespace, make_deterministic_rng},
+    store::{
+        KeyValueDatabase as _, ReadableKeyValueStore as _, TestKeyValueDatabase as _,
+        WritableKeyValueStore as _,
+    },
     test_utils::{
         big_read_multi_values, get_random_test_scenarios, run_big_write_read, run_reads,
         run_writes_from_blank, run_writes_from_state,
@@ -20,7 +28,7 @@ use wasm_bindgen_test::wasm_bindgen_test;
 #[cfg(web)]
 wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 
-#[cfg(any(with_dynamodb, with_scylladb))]
+#[cfg(any(with_dynamodb, with_scylladb, with_postgres))]
 use linera_views::test_utils::access_admin_test;
 
 #[ignore]
@@ -75,6 +83,17 @@ async fn test_reads_rocks_db() {
     }
 }
 
+#[cfg(with_redb)]
+#[tokio::test]
+async fn test_reads_redb() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::redb_db::RedbDatabase::new_test_store()
+            .await
+            .unwrap();
+        run_reads(store, scenario).await;
+    }
+}
+
 #[cfg(with_dynamodb)]
 #[tokio::test]
 async fn test_reads_dynamo_db() {
@@ -103,6 +122,17 @@ async fn test_reads_scylla_db() {
     }
 }
 
+#[cfg(with_postgres)]
+#[tokio::test]
+async fn test_reads_postgres() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::postgres_db::PostgresDatabase::new_test_store()
+            .await
+            .unwrap();
+        run_reads(store, scenario).await;
+    }
+}
+
 #[cfg(with_scylladb)]
 #[tokio::test]
 async fn test_reads_scylla_db_no_root_key() {
@@ -156,6 +186,14 @@ async fn test_memory_writes_from_blank() {
     run_writes_from_blank(&store).await;
 }
 
+#[tokio::test]
+async fn test_change_feed_memory_writes_from_blank() {
+    let store = ChangeFeedDatabase::<MemoryDatabase>::new_test_store()
+        .await
+        .unwrap();
+    run_writes_from_blank(&store).await;
+}
+
 #[tokio::test]
 async fn test_key_value_store_view_memory_writes_from_blank() {
     let context = MemoryContext::new_for_testing(());
@@ -172,6 +210,15 @@ async fn test_rocks_db_writes_from_blank() {
     run_writes_from_blank(&store).await;
 }
 
+#[cfg(with_redb)]
+#[tokio::test]
+async fn test_redb_writes_from_blank() {
+    let store = linera_views::redb_db::RedbDatabase::new_test_store()
+        .await
+        .unwrap();
+    run_writes_from_blank(&store).await;
+}
+
 #[cfg(with_dynamodb)]
 #[tokio::test]
 async fn test_dynamo_db_writes_from_blank() {
@@ -190,6 +237,15 @@ async fn test_scylla_db_writes_from_blank() {
     run_writes_from_blank(&store).await;
 }
 
+#[cfg(with_postgres)]
+#[tokio::test]
+async fn test_postgres_writes_from_blank() {
+    let store = linera_views::postgres_db::PostgresDatabase::new_test_store()
+        .await
+        .unwrap();
+    run_writes_from_blank(&store).await;
+}
+
 #[cfg(with_indexeddb)]
 #[wasm_bindgen_test]
 async fn test_indexed_db_writes_from_blank() {
@@ -257,6 +313,17 @@ async fn test_scylla_db_big_write_read() {
     run_big_write_read(store, target_size, value_sizes).await;
 }
 
+#[cfg(with_postgres)]
+#[tokio::test]
+async fn test_postgres_big_write_read() {
+    let store = linera_views::postgres_db::PostgresDatabase::new_test_store()
+        .await
+        .unwrap();
+    let value_sizes = vec![100, 1000, 200000, 5000000];
+    let target_size = 20000000;
+    run_big_write_read(store, target_size, value_sizes).await;
+}
+
 #[tokio::test]
 async fn test_memory_big_write_read() {
     let store = MemoryDatabase::new_test_store().await.unwrap();
@@ -276,6 +343,17 @@ async fn test_rocks_db_big_write_read() {
     run_big_write_read(store, target_size, value_sizes).await;
 }
 
+#[cfg(with_redb)]
+#[tokio::test]
+async fn test_redb_big_write_read() {
+    let store = linera_views::redb_db::RedbDatabase::new_test_store()
+        .await
+        .unwrap();
+    let value_sizes = vec![100, 1000, 200000, 5000000];
+    let target_size = 20000000;
+    run_big_write_read(store, target_size, value_sizes).await;
+}
+
 #[cfg(with_indexeddb)]
 #[wasm_bindgen_test]
 async fn test_indexed_db_big_write_read() {
@@ -314,6 +392,15 @@ async fn test_rocks_db_writes_from_state() {
     run_writes_from_state(&store).await;
 }
 
+#[cfg(with_redb)]
+#[tokio::test]
+async fn test_redb_writes_from_state() {
+    let store = linera_views::redb_db::RedbDatabase::new_test_store()
+        .await
+        .unwrap();
+    run_writes_from_state(&store).await;
+}
+
 #[cfg(with_indexeddb)]
 #[wasm_bindgen_test]
 async fn test_indexed_db_writes_from_state() {
@@ -339,6 +426,21 @@ async fn test_scylla_db_writes_from_state() {
     run_writes_from_state(&store).await;
 }
 
+#[cfg(with_postgres)]
+#[tokio::test]
+async fn test_postgres_writes_from_state() {
+    let store = linera_views::postgres_db::PostgresDatabase::new_test_store()
+        .await
+        .unwrap();
+    run_writes_from_state(&store).await;
+}
+
+#[cfg(with_postgres)]
+#[tokio::test]
+async fn test_postgres_access() {
+    access_admin_test::<linera_views::postgres_db::PostgresDatabase>().await
+}
+
 #[cfg(with_scylladb)]
 #[tokio::test]
 async fn test_scylladb_access() {
@@ -350,3 +452,76 @@ async fn test_scylladb_access() {
 async fn test_dynamodb_access() {
     access_admin_test::<linera_views::dynamo_db::DynamoDbDatabase>().await
 }
+
+#[tokio::test]
+async fn test_lru_caching_shared_database() {
+    let config = LruCachingConfig {
+        inner_config: MemoryDatabase::new_test_config().await.unwrap(),
+        storage_cache_config: StorageCacheConfig {
+            shared_database: true,
+            ..DEFAULT_STORAGE_CACHE_CONFIG
+        },
+    };
+    let namespace = generate_test_namespace();
+    let database1 = LruCachingMemoryDatabase::recreate_and_connect(&config, &namespace)
+        .await
+        .unwrap();
+    let database2 = LruCachingMemoryDatabase::connect(&config, &namespace)
+        .await
+        .unwrap();
+    // The two stores stand for two processes, each with its own cache.
+    let store1 = database1.open_exclusive(&[1]).unwrap();
+    let store2 = database2.open_exclusive(&[1]).unwrap();
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2], vec![3]);
+    store1.write_batch(batch).await.unwrap();
+    assert_eq!(
+        store2.read_value_bytes(&[1, 2]).await.unwrap(),
+        Some(vec![3])
+    );
+    assert!(!store2.contains_key(&[1, 3]).await.unwrap());
+
+    // The entries cached by the second store are discarded after the first one writes.
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2], vec![4]);
+    batch.put_key_value_bytes(vec![1, 3], vec![5]);
+    store1.write_batch(batch).await.unwrap();
+    assert_eq!(
+        store2.read_value_bytes(&[1, 2]).await.unwrap(),
+        Some(vec![4])
+    );
+    assert!(store2.contains_key(&[1, 3]).await.unwrap());
+    assert_eq!(
+        store2
+            .read_multi_values_bytes(vec![vec![1, 2], vec![1, 3]])
+            .await
+            .unwrap(),
+        vec![Some(vec![4]), Some(vec![5])]
+    );
+
+    // The generation of the root key is not visible to the users of the stores.
+    assert_eq!(
+        store2.find_keys_by_prefix(&[]).await.unwrap(),
+        vec![vec![1, 2], vec![1, 3]]
+    );
+    assert_eq!(
+        store1.find_key_values_by_prefix(&[]).await.unwrap().len(),
+        2
+    );
+
+    // Deleting the root key also deletes its generation.
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![]);
+    store1.write_batch(batch).await.unwrap();
+    assert_eq!(store2.read_value_bytes(&[1, 2]).await.unwrap(), None);
+    let inner_database = MemoryDatabase::connect(&config.inner_config, &namespace)
+        .await
+        .unwrap();
+    let inner_store = inner_database.open_exclusive(&[1]).unwrap();
+    assert!(inner_store
+        .find_keys_by_prefix(&[])
+        .await
+        .unwrap()
+        .is_empty());
+}
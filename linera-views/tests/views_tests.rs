@@ -1,7 +1,11 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::BTreeSet;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+};
 
 use anyhow::Result;
 #[cfg(with_dynamodb)]
@@ -11,22 +15,27 @@ use linera_views::rocks_db::RocksDbDatabase;
 #[cfg(with_scylladb)]
 use linera_views::scylla_db::ScyllaDbDatabase;
 use linera_views::{
+    aggregate_map_view::{AggregateMapView, HashedAggregateMapView},
     batch::{
         Batch, WriteOperation,
         WriteOperation::{Delete, DeletePrefix, Put},
     },
     collection_view::HashedCollectionView,
     context::{Context, MemoryContext, ViewContext},
+    indexed_map_view::{HashedIndexedMapView, IndexedValue},
     key_value_store_view::{KeyValueStoreView, ViewContainer},
     log_view::HashedLogView,
     lru_caching::LruCachingMemoryDatabase,
     map_view::{ByteMapView, HashedMapView},
     memory::MemoryDatabase,
+    pagination::Order,
+    priority_queue_view::PriorityQueueView,
     queue_view::HashedQueueView,
     random::make_deterministic_rng,
     reentrant_collection_view::HashedReentrantCollectionView,
     register_view::HashedRegisterView,
     set_view::HashedSetView,
+    sorted_set_view::HashedSortedSetView,
     store::{KeyValueDatabase, TestKeyValueDatabase as _, WritableKeyValueStore as _},
     test_utils::{
         get_random_byte_vector, get_random_key_value_operations, get_random_key_values,
@@ -36,6 +45,7 @@ use linera_views::{
     ViewError,
 };
 use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
 
 #[derive(CryptoHashRootView)]
 pub struct StateView<C> {
@@ -646,6 +656,355 @@ async fn test_byte_map_view() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_byte_map_view_ranges_and_pages() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut model = BTreeMap::new();
+    {
+        let mut view = ByteMapStateView::load(context.clone()).await?;
+        for i in 0..40u8 {
+            view.map.insert(vec![i / 8, i % 8], i);
+            model.insert(vec![i / 8, i % 8], i);
+        }
+        view.save().await?;
+    }
+    // Mix the stored entries with staged insertions and removals.
+    let mut view = ByteMapStateView::load(context.clone()).await?;
+    view.map.remove_by_prefix(vec![2]);
+    model.retain(|key, _| key[0] != 2);
+    view.map.remove(vec![3, 3]);
+    model.remove(&vec![3, 3]);
+    for key in [vec![1, 4, 0], vec![2, 5], vec![7]] {
+        view.map.insert(key.clone(), 100);
+        model.insert(key, 100);
+    }
+
+    let ranges = [
+        (Bound::Unbounded, Bound::Unbounded),
+        (Bound::Included(vec![1, 4]), Bound::Excluded(vec![1, 6])),
+        (Bound::Excluded(vec![1, 4]), Bound::Included(vec![3, 5])),
+        (Bound::Included(vec![2]), Bound::Unbounded),
+        (Bound::Unbounded, Bound::Excluded(vec![0, 3])),
+        (Bound::Included(vec![4, 1]), Bound::Excluded(vec![4, 1])),
+    ];
+    for range in ranges {
+        let expected = model
+            .range::<Vec<u8>, _>(range.clone())
+            .map(|(key, value)| (key.clone(), *value))
+            .collect::<Vec<_>>();
+        assert_eq!(view.map.key_values_in_range(range).await?, expected);
+    }
+
+    for order in [Order::Ascending, Order::Descending] {
+        let mut expected = model
+            .iter()
+            .map(|(key, value)| (key.clone(), *value))
+            .collect::<Vec<_>>();
+        if order == Order::Descending {
+            expected.reverse();
+        }
+        let mut entries = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = view.map.page(cursor.as_deref(), 7, order).await?;
+            assert!(page.items.len() <= 7);
+            entries.extend(page.items);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(entries, expected);
+        let page = view.map.page(None, 0, order).await?;
+        assert!(page.items.is_empty());
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_collection_view_ranges_and_pages() -> Result<()> {
+    type EntryType = HashedRegisterView<MemoryContext<()>, u8>;
+    type CollectionViewType = HashedCollectionView<MemoryContext<()>, u8, EntryType>;
+
+    let context = MemoryContext::new_for_testing(());
+    let mut collection = CollectionViewType::load(context.clone()).await?;
+    for i in (0..30).step_by(2) {
+        collection.load_entry_mut(&i).await?.set(i);
+    }
+    let mut batch = Batch::new();
+    collection.flush(&mut batch)?;
+    collection.context().store().write_batch(batch).await?;
+
+    let mut collection = CollectionViewType::load(context.clone()).await?;
+    collection.remove_entry(&10)?;
+    collection.load_entry_mut(&11).await?.set(11);
+    assert_eq!(
+        collection.indices_in_range(8..=14).await?,
+        vec![8, 11, 12, 14]
+    );
+    assert_eq!(collection.indices_in_range(..3).await?, vec![0, 2]);
+    assert_eq!(collection.indices_in_range(27..).await?, vec![28]);
+
+    let mut indices = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = collection
+            .page(cursor.as_deref(), 4, Order::Descending)
+            .await?;
+        indices.extend(page.items);
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    let mut expected = collection.indices().await?;
+    expected.reverse();
+    assert_eq!(indices, expected);
+    Ok(())
+}
+
+#[derive(CryptoHashRootView)]
+pub struct OrderBookView<C> {
+    pub prices: HashedSortedSetView<C, i64>,
+    pub bids: PriorityQueueView<C, Reverse<u64>, String>,
+}
+
+#[tokio::test]
+async fn test_sorted_views() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    {
+        let mut view = OrderBookView::load(context.clone()).await?;
+        for price in [7, -300, 0, 256, -2] {
+            view.prices.insert(&price);
+        }
+        view.bids.push(&Reverse(10), "first at 10".to_string());
+        view.bids.push(&Reverse(12), "first at 12".to_string());
+        view.bids.push(&Reverse(10), "second at 10".to_string());
+        view.bids.push(&Reverse(8), "first at 8".to_string());
+        view.save().await?;
+    }
+    let mut view = OrderBookView::load(context.clone()).await?;
+    let hash = view.hash().await?;
+    view.prices.insert(&-1);
+    assert_eq!(view.prices.indices().await?, vec![-300, -2, -1, 0, 7, 256]);
+    assert_eq!(view.prices.range(-2..7).await?, vec![-2, -1, 0]);
+    let mut descending = Vec::new();
+    view.prices
+        .for_each_index_in_range_while(
+            |price| {
+                descending.push(price);
+                Ok(descending.len() < 3)
+            },
+            ..=0,
+            Order::Descending,
+        )
+        .await?;
+    assert_eq!(descending, vec![0, -1, -2]);
+    assert_eq!(view.prices.pop_min().await?, Some(-300));
+    assert_eq!(view.prices.pop_max().await?, Some(256));
+    assert_eq!(view.prices.first().await?, Some(-2));
+    assert_eq!(view.prices.last().await?, Some(7));
+
+    assert_eq!(view.bids.count().await?, 4);
+    let at_10 = view.bids.range(Reverse(10)..=Reverse(10)).await?;
+    assert_eq!(
+        at_10,
+        vec![
+            (Reverse(10), "first at 10".to_string()),
+            (Reverse(10), "second at 10".to_string()),
+        ]
+    );
+    let below_12 = view
+        .bids
+        .range((Bound::Excluded(Reverse(12)), Bound::Unbounded))
+        .await?;
+    assert_eq!(below_12.len(), 3);
+    assert_eq!(
+        view.bids.pop_min().await?,
+        Some((Reverse(12), "first at 12".to_string()))
+    );
+    assert_eq!(
+        view.bids.pop_min().await?,
+        Some((Reverse(10), "first at 10".to_string()))
+    );
+    view.bids.push(&Reverse(10), "third at 10".to_string());
+    assert_eq!(
+        view.bids.last().await?,
+        Some((Reverse(8), "first at 8".to_string()))
+    );
+    assert_ne!(view.hash().await?, hash);
+    view.save().await?;
+
+    let mut view = OrderBookView::load(context.clone()).await?;
+    assert_eq!(view.prices.indices().await?, vec![-2, -1, 0, 7]);
+    assert_eq!(
+        view.bids.entries().await?,
+        vec![
+            (Reverse(10), "second at 10".to_string()),
+            (Reverse(10), "third at 10".to_string()),
+            (Reverse(8), "first at 8".to_string()),
+        ]
+    );
+    view.clear();
+    view.save().await?;
+    let view = OrderBookView::load(context).await?;
+    assert_eq!(view.prices.count().await?, 0);
+    assert_eq!(view.bids.first().await?, None);
+    Ok(())
+}
+
+#[derive(CryptoHashRootView)]
+pub struct BalancesView<C> {
+    pub balances: HashedAggregateMapView<C, u32, u64>,
+}
+
+#[tokio::test]
+async fn test_aggregate_map_view() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut rng = make_deterministic_rng();
+    let mut model = BTreeMap::new();
+    for _ in 0..20 {
+        let mut view = BalancesView::load(context.clone()).await?;
+        for _ in 0..10 {
+            // Spread the indices over several bytes so that they are not ordered by
+            // their first byte only.
+            let index = rng.gen_range(0..30u32) * 100;
+            if rng.gen_bool(0.3) {
+                view.balances.remove(&index).await?;
+                model.remove(&index);
+            } else {
+                let value = rng.gen_range(0..1000u64);
+                view.balances.insert(&index, value).await?;
+                model.insert(index, value);
+            }
+        }
+        if rng.gen_bool(0.1) {
+            view.clear();
+            model.clear();
+        }
+        let aggregates = view.balances.aggregates().clone();
+        if rng.gen_bool(0.8) {
+            view.save().await?;
+        } else {
+            view.rollback();
+            model = view.balances.index_values().await?.into_iter().collect();
+            continue;
+        }
+        let view = BalancesView::load(context.clone()).await?;
+        assert_eq!(view.balances.aggregates(), &aggregates);
+        assert_eq!(view.balances.count(), model.len() as u64);
+        let sum = model.values().map(|value| u128::from(*value)).sum::<u128>();
+        assert_eq!(view.balances.sum(), sum);
+        assert_eq!(view.balances.min_index()?, model.keys().next().copied());
+        assert_eq!(
+            view.balances.max_index()?,
+            model.keys().next_back().copied()
+        );
+        let indices = view.balances.indices().await?;
+        assert_eq!(indices, model.keys().copied().collect::<Vec<_>>());
+        let index_values = view.balances.index_values().await?;
+        assert_eq!(index_values.into_iter().collect::<BTreeMap<_, _>>(), model);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aggregate_map_view_overflow() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut view = AggregateMapView::<_, u8, u128>::load(context).await?;
+    view.insert(&1, u128::MAX).await?;
+    assert!(view.insert(&2, 1).await.is_err());
+    // The failed insertion changes neither the entries nor the aggregates.
+    assert_eq!(view.count(), 1);
+    assert_eq!(view.max_index()?, Some(1));
+    assert_eq!(view.get(&2).await?, None);
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Token {
+    pub owner: u8,
+    pub tags: Vec<u8>,
+}
+
+#[derive(Serialize)]
+pub enum TokenIndex {
+    Owner(u8),
+    Tag(u8),
+}
+
+impl IndexedValue for Token {
+    type Index = TokenIndex;
+
+    fn indexes(&self) -> Vec<TokenIndex> {
+        let mut indexes = vec![TokenIndex::Owner(self.owner)];
+        indexes.extend(self.tags.iter().map(|tag| TokenIndex::Tag(*tag)));
+        indexes
+    }
+}
+
+#[derive(CryptoHashRootView)]
+pub struct TokensView<C> {
+    pub tokens: HashedIndexedMapView<C, u16, Token>,
+}
+
+#[tokio::test]
+async fn test_indexed_map_view() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut rng = make_deterministic_rng();
+    let mut model = BTreeMap::new();
+    for _ in 0..20 {
+        let mut view = TokensView::load(context.clone()).await?;
+        for _ in 0..10 {
+            let key = rng.gen_range(0..300u16);
+            if rng.gen_bool(0.3) {
+                view.tokens.remove(&key).await?;
+                model.remove(&key);
+            } else {
+                let token = Token {
+                    owner: rng.gen_range(0..4),
+                    tags: (0..rng.gen_range(0..3))
+                        .map(|_| rng.gen_range(0..4))
+                        .collect(),
+                };
+                view.tokens.insert(&key, token.clone()).await?;
+                model.insert(key, token);
+            }
+        }
+        if rng.gen_bool(0.1) {
+            view.clear();
+            model.clear();
+        }
+        view.save().await?;
+
+        let view = TokensView::load(context.clone()).await?;
+        let key_values = view.tokens.key_values().await?;
+        assert_eq!(key_values.into_iter().collect::<BTreeMap<_, _>>(), model);
+        for value in 0..4 {
+            let mut keys = view.tokens.keys_by_index(&TokenIndex::Owner(value)).await?;
+            keys.sort();
+            let expected = model
+                .iter()
+                .filter(|(_, token)| token.owner == value)
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>();
+            assert_eq!(keys, expected);
+            let mut entries = view
+                .tokens
+                .entries_by_index(&TokenIndex::Tag(value))
+                .await?;
+            entries.sort_by_key(|(key, _)| *key);
+            let expected = model
+                .iter()
+                .filter(|(_, token)| token.tags.contains(&value))
+                .map(|(key, token)| (*key, token.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(entries, expected);
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 async fn test_views_in_lru_memory_param(config: &TestConfig) -> Result<()> {
     tracing::warn!("Testing config {:?} with lru memory", config);
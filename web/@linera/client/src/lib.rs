@@ -93,6 +93,7 @@ pub const OPTIONS: ClientContextOptions = ClientContextOptions {
     // client
     wallet_state_path: None,
     keystore_path: None,
+    keystore_passphrase_file: None,
     with_wallet: None,
 };
 